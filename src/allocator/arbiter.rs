@@ -0,0 +1,121 @@
+/*!
+
+Arbiters selecting one among several requesters, to be used as the building blocks of the separable allocators.
+
+The requesters are given together with the priority of their request. Only the requesters with the best priority, the lowest value, are considered, and the arbiter breaks the ties among them.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/// An arbiter among a fixed number of requesters.
+pub trait Arbiter {
+    /// Chooses one of the `candidates`, given as pairs `(requester,priority)` with `priority` as returned by `priority_order`.
+    /// Returns the chosen requester, or `None` when there are no candidates.
+    fn arbitrate(&self, candidates: &[(usize, usize)]) -> Option<usize>;
+    /// Updates the state of the arbiter after the request of `winner` has been finally granted.
+    fn update(&mut self, winner: usize);
+}
+
+/// The candidates with the best priority.
+fn best_candidates(candidates: &[(usize, usize)]) -> impl Iterator<Item = usize> + '_ {
+    let best = candidates.iter().map(|&(_, priority)| priority).min().unwrap_or(0);
+    candidates.iter().filter(move |&&(_, priority)| priority == best).map(|&(requester, _)| requester)
+}
+
+/**
+An arbiter giving the highest priority to the requester after the last winner.
+```ignore
+RoundRobin{}
+```
+**/
+pub struct RoundRobinArbiter {
+    /// The requester with the highest priority.
+    pointer: usize,
+    /// The number of requesters.
+    size: usize,
+}
+
+impl Arbiter for RoundRobinArbiter {
+    fn arbitrate(&self, candidates: &[(usize, usize)]) -> Option<usize> {
+        let pointer = self.pointer;
+        let size = self.size;
+        best_candidates(candidates).min_by_key(|&requester| (requester + size - pointer) % size)
+    }
+    fn update(&mut self, winner: usize) {
+        self.pointer = (winner + 1) % self.size;
+    }
+}
+
+/**
+An arbiter keeping a matrix with the relative priority of each pair of requesters. The winner gets the lowest priority against every other requester, which makes it a least recently served arbiter.
+```ignore
+Matrix{}
+```
+**/
+pub struct MatrixArbiter {
+    /// `wins[a][b]` is true when `a` has priority over `b`.
+    wins: Vec<Vec<bool>>,
+}
+
+impl Arbiter for MatrixArbiter {
+    fn arbitrate(&self, candidates: &[(usize, usize)]) -> Option<usize> {
+        let best: Vec<usize> = best_candidates(candidates).collect();
+        best.iter().cloned().find(|&a| best.iter().all(|&b| a == b || self.wins[a][b]))
+    }
+    fn update(&mut self, winner: usize) {
+        for other in 0..self.wins.len() {
+            if other != winner {
+                self.wins[winner][other] = false;
+                self.wins[other][winner] = true;
+            }
+        }
+    }
+}
+
+/// Builds an arbiter among `size` requesters.
+/// # Panics
+/// If the configuration is not of a known arbiter.
+pub fn new_arbiter(cv: &ConfigurationValue, size: usize) -> Box<dyn Arbiter> {
+    if let ConfigurationValue::Object(ref name, _) = cv {
+        match name.as_ref() {
+            "RoundRobin" => {
+                match_object_panic!(cv, "RoundRobin", _value,);
+                Box::new(RoundRobinArbiter { pointer: 0, size })
+            },
+            "Matrix" => {
+                match_object_panic!(cv, "Matrix", _value,);
+                //Initially the lower indices have priority.
+                let wins = (0..size).map(|a| (0..size).map(|b| a < b).collect()).collect();
+                Box::new(MatrixArbiter { wins })
+            },
+            _ => panic!("Unknown arbiter: {}", name),
+        }
+    } else {
+        panic!("Trying to create an Arbiter from a non-Object");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn arbiters_rotate() {
+        for name in ["RoundRobin", "Matrix"] {
+            let mut arbiter = new_arbiter(&ConfigurationValue::Object(name.to_string(), vec![]), 4);
+            let candidates = [(1, 0), (2, 0), (3, 0)];
+            let mut winners = vec![];
+            for _ in 0..3 {
+                let winner = arbiter.arbitrate(&candidates).unwrap();
+                arbiter.update(winner);
+                winners.push(winner);
+            }
+            winners.sort_unstable();
+            assert_eq!(winners, vec![1, 2, 3], "{} must serve every requester", name);
+            //The priority of the request dominates the state of the arbiter.
+            assert_eq!(arbiter.arbitrate(&[(1, 5), (2, 3), (3, 5)]), Some(2));
+            assert_eq!(arbiter.arbitrate(&[]), None);
+        }
+    }
+}
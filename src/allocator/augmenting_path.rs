@@ -0,0 +1,110 @@
+/*!
+
+An allocator computing a maximum matching by augmenting paths, which is an upper bound on the number of grants of any allocator.
+
+\[Reference\] William J. Dally and Brian Towles, "Principles and Practices of Interconnection Networks," Morgan Kaufmann, 2004. Section 19.2.
+
+*/
+
+use rand::rngs::StdRng;
+
+use crate::allocator::{Allocator, AllocatorBuilderArgument, GrantedRequests, Request, priority_order};
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/**
+A maximum-size allocator. It first matches greedily by priority and then searches augmenting paths from each unmatched client, in order of priority of its best request, until the matching is maximum.
+An augmenting path may change the resource of a matched client but never unmatches it, so the clients and resources matched by the priority stage remain matched. Its `intransit_priority` is honoured in this sense.
+It is not intended to be implemented in hardware, but as a reference for the quality of the other allocators.
+```ignore
+AugmentingPath{}
+```
+**/
+pub struct AugmentingPathAllocator {
+    /// The number of inputs of the crossbar.
+    num_clients: usize,
+    /// The number of outputs of the crossbar.
+    num_resources: usize,
+    /// The requests to allocate.
+    requests: Vec<Request>,
+}
+
+impl AugmentingPathAllocator {
+    pub fn new(args: AllocatorBuilderArgument) -> AugmentingPathAllocator {
+        if args.num_clients == 0 || args.num_resources == 0 {
+            panic!("Invalid arguments for AugmentingPathAllocator");
+        }
+        match_object_panic!(args.cv, "AugmentingPath", _value);
+        AugmentingPathAllocator {
+            num_clients: args.num_clients,
+            num_resources: args.num_resources,
+            requests: Vec::new(),
+        }
+    }
+}
+
+/// Searches an alternating path from `client` to a free resource, updating the matching along it when found.
+/// `requests[client]` has the indices in `all_requests` of the requests of `client`.
+fn augment(client: usize, requests: &[Vec<usize>], all_requests: &[Request], resource_match: &mut [Option<usize>], visited: &mut [bool]) -> bool {
+    for &index in requests[client].iter() {
+        let resource = all_requests[index].resource;
+        if visited[resource] {
+            continue;
+        }
+        visited[resource] = true;
+        let free = match resource_match[resource] {
+            None => true,
+            Some(other) => augment(all_requests[other].client, requests, all_requests, resource_match, visited),
+        };
+        if free {
+            resource_match[resource] = Some(index);
+            return true;
+        }
+    }
+    false
+}
+
+impl Allocator for AugmentingPathAllocator {
+    fn add_request(&mut self, request: Request) {
+        if request.client >= self.num_clients || request.resource >= self.num_resources {
+            panic!("The request is not valid");
+        }
+        self.requests.push(request);
+    }
+
+    fn perform_allocation(&mut self, _rng: &mut StdRng) -> GrantedRequests {
+        self.requests.sort_by_key(priority_order);
+        // The indices of the requests of each client, best priority first.
+        let mut requests: Vec<Vec<usize>> = vec![vec![]; self.num_clients];
+        for (index, request) in self.requests.iter().enumerate() {
+            requests[request.client].push(index);
+        }
+        // The index of the request granted for each resource.
+        let mut resource_match: Vec<Option<usize>> = vec![None; self.num_resources];
+        let mut client_matched = vec![false; self.num_clients];
+        for (index, request) in self.requests.iter().enumerate() {
+            if !client_matched[request.client] && resource_match[request.resource].is_none() {
+                client_matched[request.client] = true;
+                resource_match[request.resource] = Some(index);
+            }
+        }
+        // Clients in the order of their best request.
+        let mut clients: Vec<usize> = (0..self.num_clients).filter(|&client| !client_matched[client] && !requests[client].is_empty()).collect();
+        clients.sort_by_key(|&client| requests[client][0]);
+        let mut visited = vec![false; self.num_resources];
+        for client in clients {
+            visited.iter_mut().for_each(|v| *v = false);
+            augment(client, &requests, &self.requests, &mut resource_match, &mut visited);
+        }
+        let mut gr = GrantedRequests::default();
+        for index in resource_match.into_iter().flatten() {
+            gr.add_granted_request(self.requests[index].clone());
+        }
+        self.requests.clear();
+        gr
+    }
+
+    fn support_intransit_priority(&self) -> bool {
+        true
+    }
+}
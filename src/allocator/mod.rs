@@ -10,7 +10,10 @@ pub mod random;
 pub mod random_priority;
 pub mod islip;
 mod label_reduction;
-//pub mod separable_input_first;
+pub mod arbiter;
+pub mod separable;
+pub mod wavefront;
+pub mod augmenting_path;
 
 use crate::Plugs;
 use crate::config_parser::ConfigurationValue;
@@ -19,6 +22,9 @@ use ::rand::rngs::StdRng;
 use random::RandomAllocator;
 use random_priority::RandomPriorityAllocator;
 use islip::ISLIPAllocator;
+use separable::SeparableAllocator;
+use wavefront::WavefrontAllocator;
+use augmenting_path::AugmentingPathAllocator;
 
 
 /// A request to a Virtual Channel Allocator.
@@ -79,6 +85,12 @@ impl Request {
 	}
 }
 
+/// The order in which the allocators honouring the priority consider a request: lower values first and requests without priority last.
+fn priority_order(request: &Request) -> usize
+{
+	request.priority.unwrap_or(usize::MAX)
+}
+
 /// A collection of granted requests
 #[derive(Default)]
 pub struct GrantedRequests {
@@ -186,6 +198,32 @@ Islip{
 	num_iter:2,
 }
 ```
+
+The separable allocators of Dally and Towles, with an arbitration stage at the inputs and another at the outputs, in either order. See [SeparableAllocator].
+```ignore
+SeparableInputFirst{
+	//Either RoundRobin or Matrix, for each stage. Both default to RoundRobin.
+	input_arbiter: RoundRobin,
+	output_arbiter: Matrix,
+	//Defaults to 1.
+	num_iter: 1,
+}
+SeparableOutputFirst{}
+```
+
+The wavefront allocator, granting the requests by diagonals of the request matrix. See [WavefrontAllocator].
+```ignore
+Wavefront{}
+```
+
+A maximum matching by augmenting paths, as an upper bound of the quality of the allocators. See [AugmentingPathAllocator].
+```ignore
+AugmentingPath{}
+```
+
+All of them honour the priority of the requests and support the `intransit_priority` option of the routers.
+
+The `InputOutput` router uses its `allocator` for the output virtual channels and, optionally, a second allocator `switch_allocator` whose clients and resources are the physical ports.
 **/
 pub fn new_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
 {
@@ -207,6 +245,9 @@ pub fn new_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
 				Box::new(ISLIPAllocator::new(alias))
 			}
 			"ISLIP" => Box::new(ISLIPAllocator::new(arg)),
+			"SeparableInputFirst" | "SeparableOutputFirst" => Box::new(SeparableAllocator::new(arg)),
+			"Wavefront" => Box::new(WavefrontAllocator::new(arg)),
+			"AugmentingPath" => Box::new(AugmentingPathAllocator::new(arg)),
 			_ => panic!("Unknown allocator: {}", cv_name),
 		}
 	}
@@ -215,3 +256,72 @@ pub fn new_allocator(arg:AllocatorBuilderArgument) -> Box<dyn Allocator>
 		panic!("Trying to create an Allocator from a non-Object");
 	}
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	fn allocator(cv:&ConfigurationValue, num_clients:usize, num_resources:usize, rng:&mut StdRng) -> Box<dyn Allocator>
+	{
+		new_allocator(AllocatorBuilderArgument{cv,num_resources,num_clients,plugs:&Plugs::default(),rng})
+	}
+	///The granted pairs, checking that they form a matching of the requests.
+	fn allocate(allocator:&mut dyn Allocator, requests:&[(usize,usize,Option<usize>)], rng:&mut StdRng) -> Vec<(usize,usize)>
+	{
+		for &(client,resource,priority) in requests
+		{
+			allocator.add_request(Request::new(client,resource,priority));
+		}
+		let granted:Vec<(usize,usize)> = allocator.perform_allocation(rng).into_iter().map(|request|(request.client,request.resource)).collect();
+		for (index,&(client,resource)) in granted.iter().enumerate()
+		{
+			assert!(requests.iter().any(|&(c,r,_)|c==client && r==resource),"granted a request not made");
+			assert!(granted[index+1..].iter().all(|&(c,r)|c!=client && r!=resource),"granted twice a client or resource");
+		}
+		granted
+	}
+	#[test]
+	fn matchings_and_priority()
+	{
+		let mut rng=StdRng::seed_from_u64(5);
+		let names=["SeparableInputFirst","SeparableOutputFirst","Wavefront","AugmentingPath"];
+		//A full request matrix can always be completely granted by a maximum matching.
+		let full:Vec<(usize,usize,Option<usize>)>=(0..4).flat_map(|c|(0..4).map(move|r|(c,r,Some(1)))).collect();
+		for name in names
+		{
+			for arbiter in ["RoundRobin","Matrix"]
+			{
+				let cv=ConfigurationValue::Object(name.to_string(),if name.starts_with("Separable") {vec![
+					("input_arbiter".to_string(),ConfigurationValue::Object(arbiter.to_string(),vec![])),
+					("output_arbiter".to_string(),ConfigurationValue::Object(arbiter.to_string(),vec![])),
+				]} else {vec![]});
+				let mut allocator=allocator(&cv,4,4,&mut rng);
+				assert!(allocator.support_intransit_priority());
+				for _ in 0..5
+				{
+					let granted=allocate(allocator.as_mut(),&full,&mut rng);
+					assert!(!granted.is_empty());
+				}
+				//Both clients want the resource 0. The client 1 has the better priority.
+				let granted=allocate(allocator.as_mut(),&[(0,0,Some(3)),(1,0,Some(0))],&mut rng);
+				assert_eq!(granted,vec![(1,0)],"{} does not honour the priority",name);
+			}
+		}
+	}
+	#[test]
+	fn augmenting_path_is_maximum()
+	{
+		let mut rng=StdRng::seed_from_u64(5);
+		let cv=ConfigurationValue::Object("AugmentingPath".to_string(),vec![]);
+		let mut allocator=allocator(&cv,3,3,&mut rng);
+		//The greedy stage would give resource 0 to client 0, leaving client 1 without resource.
+		let requests=[(0,0,Some(0)),(0,1,Some(1)),(1,0,Some(1)),(2,1,Some(2)),(2,2,Some(2))];
+		let mut granted=allocate(allocator.as_mut(),&requests,&mut rng);
+		granted.sort_unstable();
+		assert_eq!(granted.len(),3);
+		let wavefront=ConfigurationValue::Object("Wavefront".to_string(),vec![]);
+		let mut allocator=self::allocator(&wavefront,3,3,&mut rng);
+		assert!(allocate(allocator.as_mut(),&requests,&mut rng).len()<=3);
+	}
+}
@@ -0,0 +1,163 @@
+/*!
+
+The separable allocators of Dally and Towles, which perform the allocation by two stages of arbiters, one at the inputs and one at the outputs.
+
+\[Reference\] William J. Dally and Brian Towles, "Principles and Practices of Interconnection Networks," Morgan Kaufmann, 2004. Section 19.3.
+
+*/
+
+use rand::rngs::StdRng;
+
+use crate::allocator::{Allocator, AllocatorBuilderArgument, GrantedRequests, Request, priority_order};
+use crate::allocator::arbiter::{Arbiter, new_arbiter};
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/**
+A separable allocator. In the input-first variant each input arbiter selects one of the requests of its client and then each output arbiter selects one of the inputs that selected its resource. In the output-first variant each output arbiter selects one of the clients requesting its resource and then each input arbiter selects one of the resources that selected its client.
+Further iterations add matches among the clients and resources that remain free. The arbiters are only updated by the grants of the first iteration, as in iSLIP, to avoid starvation.
+The arbiters consider only the requests with the best priority, so the priority of the requests and the `intransit_priority` of the router are honoured by both stages.
+```ignore
+SeparableInputFirst{
+	//The arbiter at each input. Either RoundRobin or Matrix. Defaults to RoundRobin.
+	input_arbiter: RoundRobin,
+	//The arbiter at each output. Either RoundRobin or Matrix. Defaults to RoundRobin.
+	output_arbiter: Matrix,
+	//Number of iterations to perform. Defaults to 1.
+	num_iter: 1,
+}
+SeparableOutputFirst{}
+```
+**/
+pub struct SeparableAllocator {
+    /// Whether the input arbitration is performed first.
+    input_first: bool,
+    /// The number of iterations to perform.
+    num_iterations: usize,
+    /// The arbiter of each client, among resources.
+    input_arbiters: Vec<Box<dyn Arbiter>>,
+    /// The arbiter of each resource, among clients.
+    output_arbiters: Vec<Box<dyn Arbiter>>,
+    /// The requests of each client, as pairs `(resource,priority)`.
+    client_requests: Vec<Vec<(usize, usize)>>,
+    /// The original requests, by client and resource, to return them when granted.
+    requests: Vec<Request>,
+}
+
+impl SeparableAllocator {
+    pub fn new(args: AllocatorBuilderArgument) -> SeparableAllocator {
+        if args.num_clients == 0 || args.num_resources == 0 {
+            panic!("Invalid arguments for SeparableAllocator");
+        }
+        let round_robin = ConfigurationValue::Object("RoundRobin".to_string(), vec![]);
+        let mut input_arbiter = round_robin.clone();
+        let mut output_arbiter = round_robin;
+        let mut num_iterations = 1;
+        match_object_panic!(args.cv, ["SeparableInputFirst", "SeparableOutputFirst"], value,
+            "input_arbiter" => input_arbiter = value.clone(),
+            "output_arbiter" => output_arbiter = value.clone(),
+            "num_iter" => num_iterations = value.as_usize().expect("bad value for num_iter"),
+        );
+        let input_first = matches!(args.cv, ConfigurationValue::Object(name, _) if name == "SeparableInputFirst");
+        SeparableAllocator {
+            input_first,
+            num_iterations,
+            input_arbiters: (0..args.num_clients).map(|_| new_arbiter(&input_arbiter, args.num_resources)).collect(),
+            output_arbiters: (0..args.num_resources).map(|_| new_arbiter(&output_arbiter, args.num_clients)).collect(),
+            client_requests: vec![vec![]; args.num_clients],
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl Allocator for SeparableAllocator {
+    fn add_request(&mut self, request: Request) {
+        if request.client >= self.input_arbiters.len() || request.resource >= self.output_arbiters.len() {
+            panic!("The request is not valid");
+        }
+        self.client_requests[request.client].push((request.resource, priority_order(&request)));
+        self.requests.push(request);
+    }
+
+    fn perform_allocation(&mut self, _rng: &mut StdRng) -> GrantedRequests {
+        let num_clients = self.input_arbiters.len();
+        let num_resources = self.output_arbiters.len();
+        let mut client_match: Vec<Option<usize>> = vec![None; num_clients];
+        let mut resource_match: Vec<Option<usize>> = vec![None; num_resources];
+        for iteration in 0..self.num_iterations {
+            let mut new_matches = vec![];
+            if self.input_first {
+                // Each input selects one resource, then each output selects among its selectors.
+                let mut selected_by: Vec<Vec<(usize, usize)>> = vec![vec![]; num_resources];
+                for client in 0..num_clients {
+                    if client_match[client].is_some() {
+                        continue;
+                    }
+                    let candidates: Vec<(usize, usize)> = self.client_requests[client].iter().cloned().filter(|&(resource, _)| resource_match[resource].is_none()).collect();
+                    if let Some(resource) = self.input_arbiters[client].arbitrate(&candidates) {
+                        let priority = candidates.iter().find(|&&(r, _)| r == resource).unwrap().1;
+                        selected_by[resource].push((client, priority));
+                    }
+                }
+                for (resource, selectors) in selected_by.iter().enumerate() {
+                    if let Some(client) = self.output_arbiters[resource].arbitrate(selectors) {
+                        new_matches.push((client, resource));
+                    }
+                }
+            } else {
+                // Each output grants one client, then each input accepts one of its grants.
+                // The requests among free clients and resources, by resource as pairs `(client,priority)`.
+                let mut resource_requests: Vec<Vec<(usize, usize)>> = vec![vec![]; num_resources];
+                for (client, requests) in self.client_requests.iter().enumerate() {
+                    if client_match[client].is_some() {
+                        continue;
+                    }
+                    for &(resource, priority) in requests {
+                        if resource_match[resource].is_none() {
+                            resource_requests[resource].push((client, priority));
+                        }
+                    }
+                }
+                let mut granted_by: Vec<Vec<(usize, usize)>> = vec![vec![]; num_clients];
+                for (resource, requesters) in resource_requests.iter().enumerate() {
+                    if let Some(client) = self.output_arbiters[resource].arbitrate(requesters) {
+                        let priority = requesters.iter().find(|&&(c, _)| c == client).unwrap().1;
+                        granted_by[client].push((resource, priority));
+                    }
+                }
+                for (client, grants) in granted_by.iter().enumerate() {
+                    if let Some(resource) = self.input_arbiters[client].arbitrate(grants) {
+                        new_matches.push((client, resource));
+                    }
+                }
+            }
+            if new_matches.is_empty() {
+                break;
+            }
+            for (client, resource) in new_matches {
+                client_match[client] = Some(resource);
+                resource_match[resource] = Some(client);
+                if iteration == 0 {
+                    self.input_arbiters[client].update(resource);
+                    self.output_arbiters[resource].update(client);
+                }
+            }
+        }
+        let mut gr = GrantedRequests::default();
+        for request in self.requests.drain(..) {
+            if client_match[request.client] == Some(request.resource) {
+                // Avoid granting twice the same pair if it was requested twice.
+                client_match[request.client] = None;
+                gr.add_granted_request(request);
+            }
+        }
+        for requests in self.client_requests.iter_mut() {
+            requests.clear();
+        }
+        gr
+    }
+
+    fn support_intransit_priority(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,82 @@
+/*!
+
+The wavefront allocator by Tamir and Chi.
+
+\[Reference\] Yuval Tamir and Hsin-Chou Chi, "Symmetric Crossbar Arbiters for VLSI Communication Switches," IEEE Transactions on Parallel and Distributed Systems, vol. 4, no. 1, pp. 13-27, 1993.
+
+*/
+
+use rand::rngs::StdRng;
+
+use crate::allocator::{Allocator, AllocatorBuilderArgument, GrantedRequests, Request, priority_order};
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/**
+A wavefront allocator. The clients and resources form a square matrix, padded to the greater of both amounts, whose diagonals are processed in order starting from a priority diagonal. A request is granted when neither its client nor its resource have been granted in a previous diagonal. The priority diagonal rotates after each allocation.
+The requests are allocated by priority classes, first running the wavefront on the requests with the best priority and then on the following ones among the clients and resources that remain free. Thus it honours the `intransit_priority` of the router.
+```ignore
+Wavefront{}
+```
+**/
+pub struct WavefrontAllocator {
+    /// The number of inputs of the crossbar.
+    num_clients: usize,
+    /// The number of outputs of the crossbar.
+    num_resources: usize,
+    /// The diagonal processed first in the next allocation.
+    priority_diagonal: usize,
+    /// The requests to allocate.
+    requests: Vec<Request>,
+}
+
+impl WavefrontAllocator {
+    pub fn new(args: AllocatorBuilderArgument) -> WavefrontAllocator {
+        if args.num_clients == 0 || args.num_resources == 0 {
+            panic!("Invalid arguments for WavefrontAllocator");
+        }
+        match_object_panic!(args.cv, "Wavefront", _value);
+        WavefrontAllocator {
+            num_clients: args.num_clients,
+            num_resources: args.num_resources,
+            priority_diagonal: 0,
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl Allocator for WavefrontAllocator {
+    fn add_request(&mut self, request: Request) {
+        if request.client >= self.num_clients || request.resource >= self.num_resources {
+            panic!("The request is not valid");
+        }
+        self.requests.push(request);
+    }
+
+    fn perform_allocation(&mut self, _rng: &mut StdRng) -> GrantedRequests {
+        let size = usize::max(self.num_clients, self.num_resources);
+        let mut client_free = vec![true; self.num_clients];
+        let mut resource_free = vec![true; self.num_resources];
+        let mut gr = GrantedRequests::default();
+        // Sort by priority class and, inside it, by the order in which the diagonals are processed.
+        let priority_diagonal = self.priority_diagonal;
+        self.requests.sort_by_key(|request| {
+            let diagonal = (request.client + request.resource) % size;
+            (priority_order(request), (diagonal + size - priority_diagonal) % size)
+        });
+        // The cells in a diagonal never share client nor resource, so processing them in sequence is the same as in parallel.
+        for request in self.requests.drain(..) {
+            if client_free[request.client] && resource_free[request.resource] {
+                client_free[request.client] = false;
+                resource_free[request.resource] = false;
+                gr.add_granted_request(request);
+            }
+        }
+        self.priority_diagonal = (self.priority_diagonal + 1) % size;
+        gr
+    }
+
+    fn support_intransit_priority(&self) -> bool {
+        true
+    }
+}
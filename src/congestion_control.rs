@@ -0,0 +1,343 @@
+/*!
+
+End-to-end congestion control at the servers, reacting to the Explicit Congestion Notification (ECN) marks set by the routers.
+
+The routers mark the packets whose head arrives to an input buffer holding at least `ecn_threshold` phits, as configured in the [Basic](crate::router::basic::Basic) and [InputOutput](crate::router::input_output::InputOutput) routers. When a packet is consumed its mark is echoed to its source server, immediately and out of band. Each server then limits the injection of phits to its current rate, given as a fraction of the link bandwidth, according to the algorithm given in the optional `congestion_control` field of the `Configuration`. A single rate is kept per server, instead of per flow.
+
+```ignore
+congestion_control: DCQCN{
+	alpha_gain: 0.00390625,//Optional, the `g` of DCQCN. 1/256 by default.
+	alpha_update_period: 100,//Optional, cycles without notifications to decay alpha. 100 by default.
+	rate_increase_period: 100,//Optional, cycles between rate increases. 100 by default.
+	fast_recovery_steps: 5,//Optional, increases towards the target rate before increasing the target. 5 by default.
+	additive_increase: 0.05,//Optional, the increase of the target rate. 0.05 by default.
+	notification_interval: 50,//Optional, marks arriving this soon after the last reaction are ignored. 50 by default.
+	minimum_rate: 0.01,//Optional, 0.01 by default.
+},
+//A simpler additive increase, multiplicative decrease, updating on every echoed packet.
+congestion_control: AIMD{
+	additive_increase: 0.01,//Optional, added to the rate for each unmarked packet. 0.01 by default.
+	multiplicative_decrease: 0.5,//Optional, the factor of the rate for each marked packet. 0.5 by default.
+	notification_interval: 50,//Optional, 50 by default.
+	minimum_rate: 0.01,//Optional, 0.01 by default.
+},
+```
+
+The DCQCN algorithm follows the reaction point of Zhu et al. (2015), with the marked packets acting as congestion notifications and timers measured in cycles.
+
+When there is congestion control the results include a `congestion_control` object with the fields
+* `marked_packets` and `echoed_packets`, the consumed packets that were marked and the total of them, and `marking_rate`, their quotient, which is 0 when no packet has been echoed.
+* `average_rate`, the time average of the injection rate over all servers, and `minimum_average_rate`, the smallest of the per-server averages.
+* `server_rate`, an array with the average rate of each server.
+* `temporal_server_rate`, when `statistics_temporal_step` is non-zero, an array with an entry per temporal step holding the array of per-server average rates in that step.
+* `delay_percentile50`, `delay_percentile99` and `delay_percentile999`, percentiles of the delay of the consumed packets since the creation of their message, which includes the time waiting to be injected.
+
+*/
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+use crate::event::Time;
+use quantifiable_derive::Quantifiable;//the derive macro
+
+///The algorithm updating the injection rate of a server.
+#[derive(Clone,Debug,Quantifiable)]
+pub enum CongestionControlAlgorithm
+{
+	DCQCN{
+		alpha_gain: f64,
+		alpha_update_period: Time,
+		rate_increase_period: Time,
+		fast_recovery_steps: usize,
+		additive_increase: f64,
+	},
+	AIMD{
+		additive_increase: f64,
+		multiplicative_decrease: f64,
+	},
+}
+
+///The injection throttle of a server. See the [module documentation](self).
+#[derive(Clone,Debug,Quantifiable)]
+pub struct CongestionControl
+{
+	algorithm: CongestionControlAlgorithm,
+	///Marks arriving sooner than this after the last reduction are ignored.
+	notification_interval: Time,
+	///The rate is never reduced below this.
+	minimum_rate: f64,
+	///The current injection rate, in phits per cycle.
+	rate: f64,
+	///The rate to recover in DCQCN.
+	target_rate: f64,
+	///The estimation of the congestion in DCQCN.
+	alpha: f64,
+	///The phits that may be currently sent. At most 1.
+	credit: f64,
+	///The last cycle in which the rate was reduced.
+	last_reduction: Option<Time>,
+	///The last cycle in which alpha was updated.
+	last_alpha_update: Time,
+	///The last cycle in which the rate was increased.
+	last_increase: Time,
+	///The number of increases since the last reduction.
+	increase_steps: usize,
+	///The first cycle included in the statistics.
+	begin_cycle: Time,
+	///The sum of the rate in each cycle since `begin_cycle`.
+	accumulated_rate: f64,
+	///The sum of the rate in each cycle of the current temporal step.
+	temporal_accumulated_rate: f64,
+	///The average rate in each temporal step.
+	temporal_rate: Vec<f64>,
+	///The consumed packets sent by this server.
+	echoed_packets: usize,
+	///The consumed packets sent by this server which arrived marked.
+	marked_packets: usize,
+	///The delay of each consumed packet sent by this server since the creation of its message.
+	delays: Vec<Time>,
+}
+
+impl CongestionControl
+{
+	pub fn new(cv:&ConfigurationValue) -> CongestionControl
+	{
+		let mut alpha_gain=1.0/256.0;
+		let mut alpha_update_period=100;
+		let mut rate_increase_period=100;
+		let mut fast_recovery_steps=5;
+		let mut additive_increase=None;
+		let mut multiplicative_decrease=0.5;
+		let mut notification_interval=50;
+		let mut minimum_rate=0.01;
+		match_object_panic!(cv,["DCQCN","AIMD"],value,
+			"alpha_gain" => alpha_gain=value.as_f64().expect("bad value for alpha_gain"),
+			"alpha_update_period" => alpha_update_period=value.as_time().expect("bad value for alpha_update_period"),
+			"rate_increase_period" => rate_increase_period=value.as_time().expect("bad value for rate_increase_period"),
+			"fast_recovery_steps" => fast_recovery_steps=value.as_usize().expect("bad value for fast_recovery_steps"),
+			"additive_increase" => additive_increase=Some(value.as_f64().expect("bad value for additive_increase")),
+			"multiplicative_decrease" => multiplicative_decrease=value.as_f64().expect("bad value for multiplicative_decrease"),
+			"notification_interval" => notification_interval=value.as_time().expect("bad value for notification_interval"),
+			"minimum_rate" => minimum_rate=value.as_f64().expect("bad value for minimum_rate"),
+		);
+		if !(minimum_rate>0.0 && minimum_rate<=1.0)
+		{
+			panic!("the minimum_rate of the congestion control must be in (0,1]");
+		}
+		let algorithm=match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="DCQCN" => CongestionControlAlgorithm::DCQCN{
+				alpha_gain,
+				alpha_update_period,
+				rate_increase_period,
+				fast_recovery_steps,
+				additive_increase:additive_increase.unwrap_or(0.05),
+			},
+			_ => CongestionControlAlgorithm::AIMD{
+				additive_increase:additive_increase.unwrap_or(0.01),
+				multiplicative_decrease,
+			},
+		};
+		CongestionControl{
+			algorithm,
+			notification_interval,
+			minimum_rate,
+			rate:1.0,
+			target_rate:1.0,
+			alpha:1.0,
+			credit:1.0,
+			last_reduction:None,
+			last_alpha_update:0,
+			last_increase:0,
+			increase_steps:0,
+			begin_cycle:0,
+			accumulated_rate:0.0,
+			temporal_accumulated_rate:0.0,
+			temporal_rate:vec![],
+			echoed_packets:0,
+			marked_packets:0,
+			delays:vec![],
+		}
+	}
+	///The current injection rate, in phits per cycle.
+	pub fn rate(&self) -> f64
+	{
+		self.rate
+	}
+	///Whether the server may send a phit in this cycle.
+	pub fn can_send(&self) -> bool
+	{
+		//Allow some rounding error from the accumulation of the rate.
+		self.credit >= 1.0-1e-9
+	}
+	///Called when the server sends a phit.
+	pub fn track_sent_phit(&mut self)
+	{
+		self.credit-=1.0;
+	}
+	///Called at the beginning of each cycle, before sending, to update the timers and the credit.
+	///With a non-zero `temporal_step` the average rate of each step is recorded.
+	pub fn advance(&mut self, cycle:Time, temporal_step:Time)
+	{
+		if let CongestionControlAlgorithm::DCQCN{alpha_gain,alpha_update_period,rate_increase_period,fast_recovery_steps,additive_increase} = self.algorithm
+		{
+			if cycle >= self.last_alpha_update+alpha_update_period
+			{
+				self.alpha*=1.0-alpha_gain;
+				self.last_alpha_update=cycle;
+			}
+			if cycle >= self.last_increase+rate_increase_period
+			{
+				if self.increase_steps >= fast_recovery_steps
+				{
+					self.target_rate=f64::min(1.0,self.target_rate+additive_increase);
+				}
+				self.rate=(self.rate+self.target_rate)/2.0;
+				self.increase_steps+=1;
+				self.last_increase=cycle;
+			}
+		}
+		self.credit=f64::min(1.0,self.credit+self.rate);
+		self.accumulated_rate+=self.rate;
+		if temporal_step>0
+		{
+			self.temporal_accumulated_rate+=self.rate;
+			if (cycle+1-self.begin_cycle)%temporal_step==0
+			{
+				self.temporal_rate.push(self.temporal_accumulated_rate/temporal_step as f64);
+				self.temporal_accumulated_rate=0.0;
+			}
+		}
+	}
+	///Called when a packet sent by this server has been consumed at its destination, telling whether it was marked and its delay since the creation of its message.
+	pub fn notify(&mut self, cycle:Time, marked:bool, delay:Time)
+	{
+		self.echoed_packets+=1;
+		self.delays.push(delay);
+		if marked
+		{
+			self.marked_packets+=1;
+			if let Some(last) = self.last_reduction
+			{
+				if cycle < last+self.notification_interval
+				{
+					return;
+				}
+			}
+			self.last_reduction=Some(cycle);
+			match self.algorithm
+			{
+				CongestionControlAlgorithm::DCQCN{alpha_gain,..} =>
+				{
+					self.target_rate=self.rate;
+					self.rate*=1.0-self.alpha/2.0;
+					self.alpha=(1.0-alpha_gain)*self.alpha+alpha_gain;
+					self.increase_steps=0;
+					self.last_alpha_update=cycle;
+					self.last_increase=cycle;
+				},
+				CongestionControlAlgorithm::AIMD{multiplicative_decrease,..} => self.rate*=multiplicative_decrease,
+			}
+			self.rate=f64::max(self.rate,self.minimum_rate);
+		}
+		else if let CongestionControlAlgorithm::AIMD{additive_increase,..} = self.algorithm
+		{
+			self.rate=f64::min(1.0,self.rate+additive_increase);
+		}
+	}
+	///Forgets the statistics, which begin again at `next_cycle`.
+	pub fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.begin_cycle=next_cycle;
+		self.accumulated_rate=0.0;
+		self.temporal_accumulated_rate=0.0;
+		self.temporal_rate.clear();
+		self.echoed_packets=0;
+		self.marked_packets=0;
+		self.delays.clear();
+	}
+}
+
+///Builds the `congestion_control` result from the congestion control of each server.
+pub fn congestion_control_result<'a,I:Iterator<Item=&'a CongestionControl>>(controls:I, cycle:Time) -> ConfigurationValue
+{
+	let controls:Vec<&CongestionControl>=controls.collect();
+	let marked_packets:usize=controls.iter().map(|control|control.marked_packets).sum();
+	let echoed_packets:usize=controls.iter().map(|control|control.echoed_packets).sum();
+	let marking_rate = if echoed_packets>0 { marked_packets as f64/echoed_packets as f64 } else { 0.0 };
+	let server_rate:Vec<f64>=controls.iter().map(|control|{
+		let cycles=cycle-control.begin_cycle;
+		if cycles==0 { control.rate } else { control.accumulated_rate/cycles as f64 }
+	}).collect();
+	let average_rate=server_rate.iter().sum::<f64>()/server_rate.len() as f64;
+	let minimum_average_rate=server_rate.iter().cloned().fold(f64::INFINITY,f64::min);
+	let mut delays:Vec<Time>=controls.iter().flat_map(|control|control.delays.iter().cloned()).collect();
+	delays.sort_unstable();
+	let percentile=|per_thousand:usize|{
+		if delays.is_empty()
+		{
+			return ConfigurationValue::None;
+		}
+		let index=usize::min(delays.len()*per_thousand/1000,delays.len()-1);
+		ConfigurationValue::Number(delays[index] as f64)
+	};
+	let mut content=vec![
+		(String::from("marked_packets"),ConfigurationValue::Number(marked_packets as f64)),
+		(String::from("echoed_packets"),ConfigurationValue::Number(echoed_packets as f64)),
+		(String::from("marking_rate"),ConfigurationValue::Number(marking_rate)),
+		(String::from("average_rate"),ConfigurationValue::Number(average_rate)),
+		(String::from("minimum_average_rate"),ConfigurationValue::Number(minimum_average_rate)),
+		(String::from("server_rate"),ConfigurationValue::Array(server_rate.into_iter().map(ConfigurationValue::Number).collect())),
+		(String::from("delay_percentile50"),percentile(500)),
+		(String::from("delay_percentile99"),percentile(990)),
+		(String::from("delay_percentile999"),percentile(999)),
+	];
+	let steps=controls.iter().map(|control|control.temporal_rate.len()).min().unwrap_or(0);
+	if steps>0
+	{
+		let temporal=(0..steps).map(|step|ConfigurationValue::Array(controls.iter().map(|control|ConfigurationValue::Number(control.temporal_rate[step])).collect())).collect();
+		content.push((String::from("temporal_server_rate"),ConfigurationValue::Array(temporal)));
+	}
+	ConfigurationValue::Object(String::from("CongestionControl"),content)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	fn algorithm(name:&str) -> CongestionControl
+	{
+		CongestionControl::new(&ConfigurationValue::Object(name.to_string(),vec![]))
+	}
+	#[test]
+	fn throttle()
+	{
+		let mut control=algorithm("AIMD");
+		control.notify(10,true,0);
+		assert_eq!(control.rate(),0.5);
+		//Within the notification interval the marks are ignored.
+		control.notify(20,true,0);
+		assert_eq!(control.rate(),0.5);
+		let sent=(0..100).filter(|&cycle|{
+			control.advance(cycle,0);
+			let can=control.can_send();
+			if can { control.track_sent_phit(); }
+			can
+		}).count();
+		assert_eq!(sent,50);
+		control.notify(200,false,0);
+		assert!((control.rate()-0.51).abs()<1e-9);
+	}
+	#[test]
+	fn dcqcn_recovers()
+	{
+		let mut control=algorithm("DCQCN");
+		control.notify(10,true,0);
+		//The initial alpha is 1, so the rate halves.
+		assert_eq!(control.rate(),0.5);
+		for cycle in 11..20000
+		{
+			control.advance(cycle,0);
+		}
+		assert!(control.rate()>0.99);
+	}
+}
@@ -22,6 +22,8 @@ use crate::{Simulation,Plugs,source_location,error,match_object_panic};
 use crate::output::{create_output,OutputEnvironment,OutputEnvironmentEntry};
 use crate::config::{self,evaluate,flatten_configuration_value};
 use crate::error::{Error,ErrorKind,SourceLocation};
+use crate::topology::analysis::analyze_experiment;
+use crate::routing::deadlock::verify_experiment;
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 pub enum Action
@@ -52,6 +54,10 @@ pub enum Action
 	Discard,
 	///Executes a few cycles of each simulation, to detect possible runtime failures.
 	QuickTest,
+	///Analyzes the topology of each experiment without simulating it, storing the analysis in `local.analysis` and creating the output files from the analyses. See [topology_analysis](crate::topology::analysis::topology_analysis).
+	Analyze,
+	///Verifies that the routing and virtual channel policies of each experiment are free of deadlock without simulating it, storing the verification in `local.deadlock` and creating the output files from the verifications. See [verify_deadlock](crate::routing::deadlock::verify_deadlock).
+	VerifyDeadlock,
 }
 
 impl FromStr for Action
@@ -75,6 +81,8 @@ impl FromStr for Action
 			"pack" => Ok(Action::Pack),
 			"discard" => Ok(Action::Discard),
 			"quick_test" => Ok(Action::QuickTest),
+			"analyze" => Ok(Action::Analyze),
+			"verify_deadlock" => Ok(Action::VerifyDeadlock),
 			_ => Err(error!(bad_argument).with_message(format!("String {s} cannot be parsed as an Action."))),
 		}
 	}
@@ -93,6 +101,21 @@ impl fmt::Display for Action
 	}
 }
 
+impl Action
+{
+	///The file in each run directory where the action stores its outcome, for the actions that do not produce simulation results.
+	///These files are never packed into `binary.results`.
+	fn own_run_file(&self) -> Option<&'static str>
+	{
+		match *self
+		{
+			Action::Analyze => Some("local.analysis"),
+			Action::VerifyDeadlock => Some("local.deadlock"),
+			_ => None,
+		}
+	}
+}
+
 struct KeyboardInteraction;
 
 impl KeyboardInteraction
@@ -1307,6 +1330,10 @@ impl<'a> Experiment<'a>
 			Action::Pack => (),
 			Action::Discard => (),
 			Action::QuickTest => (),
+			Action::Analyze | Action::VerifyDeadlock =>
+			{
+				must_draw=true;
+			},
 		};
 
 		//Remove mutabiity to prevent mistakes.
@@ -1361,10 +1388,35 @@ impl<'a> Experiment<'a>
 				use Action::*;
 				match action
 				{
-					Local|LocalAndOutput|Slurm => fs::create_dir(&experiment_path).expect("Something went wrong when creating the run directory."),
+					Local|LocalAndOutput|Slurm|Analyze|VerifyDeadlock => fs::create_dir(&experiment_path).expect("Something went wrong when creating the run directory."),
 					_ => (),
 				}
 			}
+			if let Some(own_run_file) = action.own_run_file()
+			{
+				//These outcomes do not depend on the simulation results.
+				let own_path=experiment_path.join(own_run_file);
+				if !own_path.is_file() || own_path.metadata().unwrap().len()<5
+				{
+					let outcome = match action
+					{
+						Action::Analyze =>
+						{
+							println!("analyzing topology of experiment {} of {}",experiment_index,self.files.experiments.len());
+							analyze_experiment(experiment,self.plugs)
+						},
+						Action::VerifyDeadlock =>
+						{
+							println!("verifying deadlock freedom of experiment {} of {}",experiment_index,self.files.experiments.len());
+							verify_experiment(experiment,self.plugs)
+						},
+						_ => unreachable!(),
+					};
+					let mut own_file=File::create(&own_path).unwrap_or_else(|_|panic!("Could not create the file {:?}.",own_path));
+					writeln!(own_file,"{}",outcome).unwrap_or_else(|_|panic!("Could not write the file {:?}.",own_path));
+				}
+				continue;
+			}
 			let is_packed = if let ConfigurationValue::Experiments(ref a) = self.files.packed_results {
 				! matches!(a[experiment_index],ConfigurationValue::None)
 			} else {false};
@@ -1567,6 +1619,10 @@ impl<'a> Experiment<'a>
 					{
 						println!("experiment {} of {} is {}",experiment_index,self.files.experiments.len(),experiment.format_terminal());
 						let mut simulation=Simulation::new(experiment,self.plugs);
+						if let Some(watchdog) = simulation.watchdog.as_mut()
+						{
+							watchdog.set_directory(&experiment_path);
+						}
 						simulation.run();
 						simulation.write_result(&mut File::create(&result_path).expect("Could not create the result file."));
 					},
@@ -1728,7 +1784,7 @@ impl<'a> Experiment<'a>
 							simulation.advance();
 						}
 					},
-					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard =>
+					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard | Action::Analyze | Action::VerifyDeadlock =>
 					{
 					},
 				};
@@ -1765,10 +1821,11 @@ impl<'a> Experiment<'a>
 		if must_draw
 		{
 			results=Vec::with_capacity(self.files.experiments.len());
+			let own_run_file = action.own_run_file();
 			//for (experiment_index,experiment) in experiments.iter().enumerate()
 			for (experiment_index,experiment) in self.files.experiments.iter().enumerate().skip(start_index).take(end_index-start_index)
 			{
-				if let ConfigurationValue::Experiments(ref a) = self.files.packed_results
+				if let (ConfigurationValue::Experiments(ref a),None) = (&self.files.packed_results,own_run_file)
 				{
 					match &a[experiment_index]
 					{
@@ -1785,7 +1842,7 @@ impl<'a> Experiment<'a>
 					}
 				}
 				let experiment_path=runs_path.join(format!("run{}",experiment_index));
-				let result_path=experiment_path.join("local.result");
+				let result_path=experiment_path.join(own_run_file.unwrap_or("local.result"));
 				let mut result_file=match File::open(&result_path)
 				{
 					Ok(rf) => rf,
@@ -1807,7 +1864,7 @@ impl<'a> Experiment<'a>
 							config_parser::Token::Value(value) => value,
 							_ => panic!("wrong token"),
 						};
-						if let ConfigurationValue::Experiments(ref mut a) = self.files.packed_results
+						if let (ConfigurationValue::Experiments(ref mut a),None) = (&mut self.files.packed_results,own_run_file)
 						{
 							a[experiment_index] = result.clone();
 							added_packed_results+=1;
@@ -82,6 +82,8 @@ The `directory_main` function receives a `&Path` assumed to contain a `main.cfg`
 * `runs/job<action_index>/launch<experiment_index>-<slurm_index>.{out,err}` are the outputs from scripts launched to slurm. The `slurm_index` is the job id given by slurm.
 * `runs/run<experiment_index>/local.cfg` is the configuration exclusive to the simulation number `experiment_index`.
 * `runs/run<experiment_index>/local.result` will contain the result values of the simulation number `experiment_index` after a successful simulation.
+* `runs/run<experiment_index>/local.analysis` will contain the topology analysis of the experiment number `experiment_index` after an `analyze` action.
+* `runs/run<experiment_index>/local.deadlock` will contain the deadlock verification of the experiment number `experiment_index` after a `verify_deadlock` action.
 
 The `directory_main` receives also an `Action`. In the crate `caminos` this is done via its `--action=<method>` falg.
 * `local_and_output` runs all the remaining simulations locally and generates the outputs.
@@ -95,6 +97,8 @@ The `directory_main` receives also an `Action`. In the crate `caminos` this is d
 * `slurm_cancel` executes a `scancel` with the job ids found in the journal file.
 * `shell` creates the experiment folder with default configuration files. Alternatively, when receiving `--source=another_experiment` it copies the configuration of the other experiment into this one.
 * `pack` forces the creation of a binary.results file and erases the verbose raw results files. In some extreme cases it can reduce a decent amount of space and sped up computations.
+* `analyze` computes graph measures of the topology of each experiment, such as average distance, path diversity, bisection width and algebraic connectivity, instead of simulating it. The analysis is stored in `runs/run<experiment_index>/local.analysis`, apart from the simulation results in `local.result`. The outputs of this action are generated from the analyses, which take the place of the results in the output expressions, so `=result.average_distance` and similar can be used in the plots.
+* `verify_deadlock` builds the channel dependency graph of the routing and virtual channel policies of each remaining experiment, instead of simulating it, and stores whether it is `deadlock_free` in `runs/run<experiment_index>/local.deadlock`, together with a cycle and the routes creating it when there is one. The outputs are generated from these verifications. See the `routing::deadlock` module.
 
 
 # Configuration Syntax
@@ -192,6 +196,14 @@ Configuration
 		LinkClass { delay: 1},
 		//In a dragonfly topology we would have 0=routers from same group, 1=routers from different groups, and 2=from server
 	],
+	//Optionally, links may fail and recover during the simulation. See the link_fault module.
+	//link_fault_schedule: LinkFaultSchedule{events:[LinkFault{cycle:25000, router:0, port:0, state:Down}], policy:Hold},
+	//Optionally, stop the simulation when it makes no progress, writing a diagnostic. See the watchdog module.
+	//watchdog: Watchdog{cycles:2000},
+	//Optionally, servers retransmit the packets not acknowledged after this number of cycles, as required by lossy transmission mechanisms.
+	//retransmission_timeout: 1000,
+	//Optionally, servers throttle their injection when the routers mark congested packets. See the congestion_control module.
+	//congestion_control: DCQCN{},
 	launch_configurations: [
 		//We may put here options to send to the SLURM system.
 		Slurm
@@ -338,6 +350,9 @@ pub mod error;
 pub mod measures;
 pub mod allocator;
 pub mod packet;
+pub mod link_fault;
+pub mod watchdog;
+pub mod congestion_control;
 
 use std::rc::Rc;
 use std::boxed::Box;
@@ -346,7 +361,7 @@ use std::env;
 use std::fs::{self,File};
 use std::io::prelude::*;
 use std::io::{stdout};
-use std::collections::{VecDeque,BTreeMap};
+use std::collections::{VecDeque,BTreeMap,BTreeSet};
 use std::ops::DerefMut;
 use std::path::{Path};
 use std::mem::{size_of};
@@ -358,11 +373,11 @@ use rand::{rngs::StdRng,SeedableRng};
 
 use config_parser::{ConfigurationValue,Expr};
 use topology::{Topology,new_topology,TopologyBuilderArgument,Location,
-	multistage::{Stage,StageBuilderArgument}};
+	multistage::{Stage,StageBuilderArgument},export::{ExportFormat,export_topology}};
 use traffic::{Traffic,new_traffic,TrafficBuilderArgument,TrafficError};
-use router::{Router,new_router,RouterBuilderArgument};
+use router::{Router,new_router,RouterBuilderArgument,AcknowledgeMessage};
 use routing::{RoutingInfo,Routing,new_routing,RoutingBuilderArgument};
-use event::{EventQueue,Event,EventGeneration};
+use event::{EventQueue,Event,EventGeneration,CyclePosition};
 use quantify::Quantifiable;
 use experiments::{Experiment,Action,ExperimentOptions};
 use policies::{VirtualChannelPolicy,VCPolicyBuilderArgument};
@@ -371,6 +386,9 @@ use config::flatten_configuration_value;
 use measures::{Statistics,ServerStatistics};
 use error::{Error,SourceLocation};
 use allocator::{Allocator,AllocatorBuilderArgument};
+use link_fault::LinkFaultSchedule;
+use watchdog::Watchdog;
+use congestion_control::CongestionControl;
 pub use packet::{Phit,Packet,Message,PacketExtraInfo,PacketRef,AsMessage};
 pub use event::Time;
 
@@ -396,10 +414,59 @@ pub struct Server
 	consumed_phits: BTreeMap<*const Message,usize>,
 	///Statistics local to the server.
 	statistics: ServerStatistics,
+	///With retransmission, the packets not yet acknowledged by their destination, by message and index in the message.
+	///Each one has its message, its size and the cycle in which it was last sent, which is `None` while it waits to be sent.
+	unacknowledged_packets: BTreeMap<(*const Message,usize),(Rc<Message>,usize,Option<Time>)>,
+	///With retransmission, the packets arriving to this server that are being discarded as copies of already delivered ones.
+	duplicate_packets: BTreeSet<*const Packet>,
+	///The throttle of the injection rate, if there is congestion control.
+	congestion_control: Option<CongestionControl>,
 }
 
 impl Server
 {
+	///Builds the packet of index `index` of a message sent by this server.
+	fn build_packet(&self, message:&Rc<Message>, index:usize, size:usize) -> PacketRef
+	{
+		let mut routing_info = RoutingInfo::new();
+		routing_info.source_server = Some(self.index);
+		Packet{
+			size,
+			routing_info: RefCell::new(routing_info),
+			message:message.clone(),
+			index,
+			cycle_into_network:RefCell::new(0),
+			extra: RefCell::new(None),
+			congestion_experienced: RefCell::new(false),
+		}.into_ref()
+	}
+	///The packet has reached its destination and it will not be retransmitted. Returns false if it had already been acknowledged, being a duplicate.
+	fn acknowledge_packet(&mut self, packet:&Packet) -> bool
+	{
+		self.unacknowledged_packets.remove(&(packet.message.as_ref() as *const Message,packet.index)).is_some()
+	}
+	///Queues again, before any other packet, the packets sent at least `timeout` cycles ago and not acknowledged. Returns how many.
+	fn retransmit_expired_packets(&mut self, cycle:Time, timeout:Time) -> usize
+	{
+		let mut expired = vec![];
+		for (&(_,index),(message,size,sent)) in self.unacknowledged_packets.iter_mut()
+		{
+			if let Some(sent_cycle) = *sent
+			{
+				if sent_cycle+timeout <= cycle
+				{
+					*sent = None;
+					expired.push((message.clone(),index,*size));
+				}
+			}
+		}
+		for (message,index,size) in expired.iter().rev()
+		{
+			let packet = self.build_packet(message,*index,*size);
+			self.stored_packets.push_front(packet);
+		}
+		expired.len()
+	}
 	///Consumes a phit
 	fn consume(&mut self, phit:Rc<Phit>, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:Time, topology:&dyn Topology, rng: &mut StdRng)
 	{
@@ -751,6 +818,13 @@ pub struct Simulation<'a>
 	pub plugs: &'a Plugs,
 	///Number of cycles to wait between reports of memory usage.
 	pub memory_report_period: Option<Time>,
+	///Links that fail or recover during the simulation.
+	pub link_fault_schedule: Option<LinkFaultSchedule>,
+	///Detects when the simulation stops making progress.
+	pub watchdog: Option<Watchdog>,
+	///If given, the servers retransmit the packets not acknowledged after this number of cycles, to recover the packets discarded by lossy links.
+	///A packet is acknowledged as soon as its head reaches its destination server, and later copies of it are discarded there.
+	pub retransmission_timeout: Option<Time>,
 }
 
 impl<'a> Simulation<'a>
@@ -776,6 +850,10 @@ impl<'a> Simulation<'a>
 		let mut server_queue_size = None;
 		let mut memory_report_period = None;
 		let mut general_frequency_divisor = 1;
+		let mut link_fault_schedule = None;
+		let mut watchdog = None;
+		let mut retransmission_timeout = None;
+		let mut congestion_control = None;
 		match_object_panic!(cv,"Configuration",value,
 			"random_seed" => seed=Some(value.as_usize().expect("bad value for random_seed")),
 			"warmup" => warmup=Some(value.as_time().expect("bad value for warmup")),
@@ -889,6 +967,11 @@ impl<'a> Simulation<'a>
 
 			"memory_report_period" => memory_report_period=Some(value.as_time().expect("bad value for memory_report_period")),
 			"general_frequency_divisor" => general_frequency_divisor = value.as_time().expect("bad value for general_frequency_divisor"),
+			"link_fault_schedule" => link_fault_schedule = Some(LinkFaultSchedule::new(value)),
+			"watchdog" => watchdog = Some(Watchdog::new(value)),
+			"retransmission_timeout" => retransmission_timeout = Some(value.as_time().expect("bad value for retransmission_timeout")),
+			"congestion_control" => congestion_control = Some(CongestionControl::new(value)),
+			"deadlock_verification" => (),//Only read by the `verify_deadlock` action.
 		);
 		let seed=seed.expect("There were no random_seed");
 		let warmup=warmup.expect("There were no warmup");
@@ -916,6 +999,15 @@ impl<'a> Simulation<'a>
 			rng:&mut rng,
 		});
 		topology.check_adjacency_consistency(Some(link_classes.len()));
+		let unreachable_servers:Vec<usize> = (0..topology.num_servers()).filter(|&server|topology.is_server_unreachable(server)).collect();
+		if !unreachable_servers.is_empty()
+		{
+			println!("WARNING: the servers {:?} are attached to failed routers. They do not generate messages and the messages towards them are discarded.",unreachable_servers);
+		}
+		if let Some(schedule) = &link_fault_schedule
+		{
+			schedule.check(topology.as_ref());
+		}
 		routing.initialize(topology.as_ref(),&mut rng);
 		let num_routers=topology.num_routers();
 		let num_servers=topology.num_servers();
@@ -961,6 +1053,9 @@ impl<'a> Simulation<'a>
 				outcoming_virtual_channel: None,
 				consumed_phits: BTreeMap::new(),
 				statistics: ServerStatistics::new(statistics_temporal_step),
+				unacknowledged_packets: BTreeMap::new(),
+				duplicate_packets: BTreeSet::new(),
+				congestion_control: congestion_control.clone(),
 			}
 		}).collect();
 		let traffic=new_traffic(TrafficBuilderArgument{
@@ -1002,6 +1097,9 @@ impl<'a> Simulation<'a>
 			launch_configurations,
 			plugs,
 			memory_report_period,
+			link_fault_schedule,
+			watchdog,
+			retransmission_timeout,
 		}
 	}
 	///Run the simulations until it finishes.
@@ -1022,11 +1120,34 @@ impl<'a> Simulation<'a>
 				println!("Traffic consumed before cycle {}",self.shared.cycle);
 				break;
 			}
+			if let Some(watchdog) = self.watchdog.as_mut()
+			{
+				if watchdog.check(self.shared.cycle,&self.shared.network,self.shared.routing.as_ref(),&mut self.mutable.rng)
+				{
+					break;
+				}
+			}
 		}
 	}
 	///Execute a single cycle of the simulation.
 	fn advance(&mut self)
 	{
+		if let Some(schedule) = self.link_fault_schedule.as_mut()
+		{
+			for (end,other,available) in schedule.take_events(self.shared.cycle,self.shared.network.topology.as_ref())
+			{
+				for (router,port) in [end,other]
+				{
+					let mut brouter=self.shared.network.routers[router].borrow_mut();
+					for event in brouter.set_link_available(self.shared.cycle,port,available)
+					{
+						self.event_queue.enqueue(event);
+					}
+				}
+				self.shared.routing.link_status_change(self.shared.network.topology.as_ref(),end.0,end.1,available,&mut self.mutable.rng);
+			}
+		}
+		self.shared.routing.update_global_status(&self.shared.network.routers,self.shared.network.topology.as_ref(),self.shared.cycle);
 		let mut ievent=0;
 		//println!("Begin advance");
 		//while let Some(event) = self.event_queue.access_begin(ievent)
@@ -1054,6 +1175,10 @@ impl<'a> Simulation<'a>
 					ref new,
 				} =>
 				{
+					if let Some(watchdog) = self.watchdog.as_mut()
+					{
+						watchdog.track_movement(self.shared.cycle,matches!(previous,Location::ServerPort(_)));
+					}
 					let target_server = phit.packet.message.destination;
 					let (target_location,_link_class)=self.shared.network.topology.server_neighbour(target_server);
 					let target_router=match target_location
@@ -1061,6 +1186,57 @@ impl<'a> Simulation<'a>
 						Location::RouterPort{router_index,router_port:_} =>router_index,
 						_ => panic!("The server is not attached to a router"),
 					};
+					let dropped_link_class = match (new,self.link_fault_schedule.as_mut())
+					{
+						(&Location::RouterPort{router_index,router_port},Some(schedule)) => if schedule.must_drop(router_index,router_port,phit)
+						{
+							Some(self.shared.network.topology.neighbour(router_index,router_port).1)
+						}
+						else
+						{
+							None
+						},
+						_ => None,
+					};
+					if let Some(link_class) = dropped_link_class
+					{
+						//Return the credit of the discarded phit to the emitter.
+						let vc = phit.virtual_channel.borrow().expect("dropped phit without virtual channel");
+						self.event_queue.enqueue(EventGeneration{
+							delay: self.shared.link_classes[link_class].delay,
+							position: CyclePosition::Begin,
+							event: Event::Acknowledge{location:previous.clone(),message:AcknowledgeMessage::ack_phit_clear_from_virtual_channel(vc)},
+						});
+						self.statistics.track_dropped_phit(self.shared.cycle,phit);
+						if let Some(watchdog) = self.watchdog.as_mut()
+						{
+							watchdog.track_removal(self.shared.cycle,false);
+						}
+						if phit.is_end()
+						{
+							phit.packet.destroy();
+						}
+						ievent+=1;
+						continue;
+					}
+					if let &Location::RouterPort{router_index,router_port} = new
+					{
+						//Lossy links discard the packets for which there is no space.
+						if self.shared.network.routers[router_index].borrow_mut().must_drop(router_port,phit,&mut self.mutable.rng)
+						{
+							self.statistics.track_dropped_phit(self.shared.cycle,phit);
+							if let Some(watchdog) = self.watchdog.as_mut()
+							{
+								watchdog.track_removal(self.shared.cycle,false);
+							}
+							if phit.is_end()
+							{
+								phit.packet.destroy();
+							}
+							ievent+=1;
+							continue;
+						}
+					}
 					match new
 					{
 						&Location::RouterPort{router_index:router,router_port:port} =>
@@ -1110,7 +1286,70 @@ impl<'a> Simulation<'a>
 							{
 								panic!("Packet reached wrong server, {} instead of {}!\n",server,phit.packet.message.destination);
 							}
-							self.shared.network.servers[server].consume(phit.clone(),self.shared.traffic.deref_mut(),&mut self.statistics,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng);
+							//With retransmission the head acknowledges the packet, and the copies of acknowledged packets are discarded.
+							let duplicate = self.retransmission_timeout.is_some() && {
+								let packet = phit.packet.as_ref() as *const Packet;
+								let servers = &mut self.shared.network.servers;
+								let duplicate = if phit.is_begin()
+								{
+									!servers[phit.packet.message.origin].acknowledge_packet(&phit.packet)
+								}
+								else
+								{
+									servers[server].duplicate_packets.contains(&packet)
+								};
+								if duplicate
+								{
+									if phit.is_end()
+									{
+										servers[server].duplicate_packets.remove(&packet);
+									}
+									else
+									{
+										servers[server].duplicate_packets.insert(packet);
+									}
+								}
+								duplicate
+							};
+							if duplicate
+							{
+								if phit.is_begin()
+								{
+									self.statistics.track_duplicate_packet(self.shared.cycle);
+								}
+								if phit.is_end()
+								{
+									phit.packet.destroy();
+								}
+							}
+							else
+							{
+								//The ECN mark is echoed to the source when the packet is consumed, which destroys it.
+								let echo = if phit.is_end()
+								{
+									Some((phit.packet.message.origin,*phit.packet.congestion_experienced.borrow(),self.shared.cycle-phit.packet.message.creation_cycle))
+								}
+								else
+								{
+									None
+								};
+								self.shared.network.servers[server].consume(phit.clone(),self.shared.traffic.deref_mut(),&mut self.statistics,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng);
+								if let Some((origin,marked,delay)) = echo
+								{
+									if let Some(control) = self.shared.network.servers[origin].congestion_control.as_mut()
+									{
+										control.notify(self.shared.cycle,marked,delay);
+									}
+								}
+								if let Some(schedule) = self.link_fault_schedule.as_mut()
+								{
+									schedule.track_consumed_phit();
+								}
+							}
+							if let Some(watchdog) = self.watchdog.as_mut()
+							{
+								watchdog.track_removal(self.shared.cycle,!duplicate);
+							}
 						}
 						&Location::None => panic!("Phit went nowhere previous={:?}",previous),
 					};
@@ -1200,7 +1439,8 @@ impl<'a> Simulation<'a>
 			//println!("credits of {} = {}",iserver,server.credits);
 			if let (Location::RouterPort{router_index: index,router_port: port},link_class)=server.port
 			{
-				if self.shared.traffic.should_generate(iserver,self.shared.cycle,&mut self.mutable.rng)
+				//The servers attached to failed routers are excluded from the traffic.
+				if !self.shared.network.topology.is_server_unreachable(iserver) && self.shared.traffic.should_generate(iserver,self.shared.cycle,&mut self.mutable.rng)
 				{
 					if server.stored_messages.len()<self.server_queue_size {
 						match self.shared.traffic.generate_message(iserver,self.shared.cycle,Some(self.shared.network.topology.as_ref()),&mut self.mutable.rng)
@@ -1215,7 +1455,15 @@ impl<'a> Simulation<'a>
 								{
 									panic!("Generated message to self unexpectedly.");
 								}
-								server.stored_messages.push_back(message);
+								if self.shared.network.topology.is_server_unreachable(message.destination)
+								{
+									//It could not be delivered, so it is counted as not generated.
+									server.statistics.track_missed_generation(self.shared.cycle);
+								}
+								else
+								{
+									server.stored_messages.push_back(message);
+								}
 							},
 							Err(TrafficError::OriginOutsideTraffic) => (),
 							Err(TrafficError::SelfMessage) => (),
@@ -1241,23 +1489,33 @@ impl<'a> Simulation<'a>
 						{
 							size
 						};
-						let mut routing_info = RoutingInfo::new();
-						routing_info.source_server = Some(iserver);
-						server.stored_packets.push_back(Packet{
-							size:ps,
-							routing_info: RefCell::new(routing_info),
-							message:message.clone(),
-							index:index_packet,
-							cycle_into_network:RefCell::new(0),
-							extra: RefCell::new(None),
-						}.into_ref());
+						let packet = server.build_packet(&message,index_packet,ps);
+						server.stored_packets.push_back(packet);
+						if self.retransmission_timeout.is_some()
+						{
+							server.unacknowledged_packets.insert((message.as_ref() as *const Message,index_packet),(message.clone(),ps,None));
+						}
 						index_packet+=1;
 						size-=ps;
 					}
 				}
-				if server.stored_phits.is_empty() && !server.stored_packets.is_empty()
+				if let Some(timeout) = self.retransmission_timeout
+				{
+					for _ in 0..server.retransmit_expired_packets(self.shared.cycle,timeout)
+					{
+						self.statistics.track_retransmitted_packet(self.shared.cycle);
+					}
+				}
+				while server.stored_phits.is_empty() && !server.stored_packets.is_empty()
 				{
 					let packet=server.stored_packets.pop_front().expect("There are not packets in queue");
+					let key = (packet.message.as_ref() as *const Message,packet.index);
+					if self.retransmission_timeout.is_some() && !server.unacknowledged_packets.contains_key(&key)
+					{
+						//A retransmission of a packet that has been acknowledged meanwhile.
+						packet.destroy();
+						continue;
+					}
 					for index in 0..packet.size
 					{
 						server.stored_phits.push_back(Rc::new(Phit{
@@ -1280,6 +1538,10 @@ impl<'a> Simulation<'a>
 				//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
 				//	server.credits-=1;
 				//}
+				if let Some(control) = server.congestion_control.as_mut()
+				{
+					control.advance(self.shared.cycle,self.statistics.temporal_step);
+				}
 				if !server.stored_phits.is_empty()
 				{
 					//Do not extract the phit until we know whether we can transmit it.
@@ -1303,13 +1565,22 @@ impl<'a> Simulation<'a>
 					{
 						if let Some(vc) = server.outcoming_virtual_channel
 						{
-							if server.router_status.can_transmit(phit,vc)
+							if server.router_status.can_transmit(phit,vc) && server.congestion_control.as_ref().map_or(true,|control|control.can_send())
 							{
 								let phit=server.stored_phits.pop_front().expect("There are not phits");
+								if let Some(control) = server.congestion_control.as_mut()
+								{
+									control.track_sent_phit();
+								}
 								*phit.virtual_channel.borrow_mut() = Some(vc);
 								if phit.is_end()
 								{
 									server.outcoming_virtual_channel = None;
+									let key = (phit.packet.message.as_ref() as *const Message,phit.packet.index);
+									if let Some(entry) = server.unacknowledged_packets.get_mut(&key)
+									{
+										entry.2 = Some(self.shared.cycle);
+									}
 								}
 								let event=Event::PhitToLocation{
 									phit,
@@ -1350,6 +1621,11 @@ impl<'a> Simulation<'a>
 	///Get config value for the simulation results.
 	pub fn get_simulation_results(&self) -> ConfigurationValue
 	{
+		if let Some(report) = self.watchdog.as_ref().and_then(|watchdog|watchdog.report())
+		{
+			//The statistics of a stalled simulation are meaningless.
+			return report.clone();
+		}
 		// https://stackoverflow.com/questions/22355273/writing-to-a-file-or-stdout-in-rust
 		//output.write(b"Hello from the simulator\n").unwrap();
 		//Result
@@ -1401,6 +1677,10 @@ impl<'a> Simulation<'a>
 			(String::from("server_average_missed_generations"),ConfigurationValue::Number(server_average_missed_generations)),
 			(String::from("servers_with_missed_generations"),ConfigurationValue::Number(servers_with_missed_generations as f64)),
 			(String::from("virtual_channel_usage"),ConfigurationValue::Array(virtual_channel_usage)),
+			(String::from("dropped_phits"),ConfigurationValue::Number(measurement.dropped_phits as f64)),
+			(String::from("dropped_packets"),ConfigurationValue::Number(measurement.dropped_packets as f64)),
+			(String::from("retransmitted_packets"),ConfigurationValue::Number(measurement.retransmitted_packets as f64)),
+			(String::from("duplicate_packets"),ConfigurationValue::Number(measurement.duplicate_packets as f64)),
 			//(String::from("git_id"),ConfigurationValue::Literal(format!("\"{}\"",git_id))),
 			(String::from("git_id"),ConfigurationValue::Literal(git_id.to_string())),
 			(String::from("version_number"),ConfigurationValue::Literal(version_number.to_string())),
@@ -1409,6 +1689,15 @@ impl<'a> Simulation<'a>
 		{
 			result_content.push((String::from("routing_statistics"),content));
 		}
+		if self.shared.network.servers.iter().any(|server|server.congestion_control.is_some())
+		{
+			let controls = self.shared.network.servers.iter().filter_map(|server|server.congestion_control.as_ref());
+			result_content.push((String::from("congestion_control"),congestion_control::congestion_control_result(controls,self.shared.cycle)));
+		}
+		if let Some(schedule)=self.link_fault_schedule.as_ref()
+		{
+			result_content.push((String::from("link_faults"),schedule.result(self.shared.cycle,num_servers)));
+		}
 		if let Some(content) = self.shared.network.routers.iter().enumerate().fold(None,|maybe_stat,(index,router)|router.borrow().aggregate_statistics(maybe_stat,index,self.shared.network.routers.len(),self.shared.cycle))
 		{
 			result_content.push((String::from("router_aggregated_statistics"),content));
@@ -2253,6 +2542,23 @@ pub fn terminal_main_normal_opts(args:&[String], plugs:&Plugs, option_matches:ge
 	}
 }
 
+/**
+Write a topology into a file. The `args` are an `Export` object.
+```ignore
+Export{
+	topology: Dragonfly{global_ports_per_router:4, servers_per_router:4},
+	seed: 42,//optional, for randomized topologies
+	format: "DOT",//optional
+	filename: "dragonfly.dot",
+}
+```
+The `format` can be a number for the adjacency lists of [Topology::write_adjacencies_to_file] (the default `0`), or one of the literals
+* `"DOT"`, for Graphviz, colouring the links by their class.
+* `"GraphML"`, for Gephi and similar tools, with the ports and link classes as edge data.
+* `"JSON"`, with the neighbour and link class of each router port and the attachment of each server.
+
+The DOT and GraphML files can be loaded back with the `FileTopology` topology.
+**/
 pub fn special_export(args: &str, plugs:&Plugs)
 {
 	let topology_cfg = match config_parser::parse(args)
@@ -2290,7 +2596,8 @@ pub fn special_export(args: &str, plugs:&Plugs)
 				},
 				"format" => match value
 				{
-					&ConfigurationValue::Number(f) => format=Some(f as usize),
+					&ConfigurationValue::Number(f) => format=Some(Err(f as usize)),
+					ConfigurationValue::Literal(s) => format=Some(Ok(ExportFormat::from_name(s).unwrap_or_else(||panic!("Unknown export format {}",s)))),
 					_ => panic!("bad value for format"),
 				},
 				"filename" => match value
@@ -2308,7 +2615,7 @@ pub fn special_export(args: &str, plugs:&Plugs)
 	}
 	let seed=seed.unwrap_or(42);
 	let topology_cfg=topology.expect("There were no topology.");
-	let format=format.unwrap_or(0);
+	let format=format.unwrap_or(Err(0));
 	let filename=filename.expect("There were no filename.");
 	let mut rng=StdRng::from_seed({
 		//changed from rand-0.4 to rand-0.8
@@ -2321,7 +2628,11 @@ pub fn special_export(args: &str, plugs:&Plugs)
 	});
 	let topology = new_topology(TopologyBuilderArgument{cv:&topology_cfg,plugs,rng:&mut rng});
 	let mut topology_file=File::create(&filename).expect("Could not create topology file");
-	topology.write_adjacencies_to_file(&mut topology_file,format).expect("Failed writing topology to file");
+	match format
+	{
+		Ok(format) => export_topology(topology.as_ref(),format,&mut topology_file),
+		Err(format) => topology.write_adjacencies_to_file(&mut topology_file,format),
+	}.expect("Failed writing topology to file");
 }
 
 #[cfg(test)]
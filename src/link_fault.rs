@@ -0,0 +1,244 @@
+/*!
+
+Links that fail or recover while the simulation is running.
+
+A [LinkFaultSchedule] is given in the `link_fault_schedule` field of the `Configuration`. At the beginning of each cycle the [Simulation](crate::Simulation) applies the events of that cycle, marking both ends of the link as unavailable (or available again) in the routers, which stop (or resume) transmitting through it. The routing is told of every change through [Routing::link_status_change](crate::routing::Routing::link_status_change). `Shortest` then routes through the shortest paths without the failed links, while `TableRouting` and `UpDownStar` discard the candidates through failed links when they have others.
+
+```ignore
+link_fault_schedule: LinkFaultSchedule{
+	events: [
+		LinkFault{cycle: 2000, router: 3, port: 1, state: Down},
+		LinkFault{cycle: 6000, router: 3, port: 1, state: Up},
+	],
+	policy: Hold,//or Drop
+},
+```
+
+The `policy` decides what happens to the phits that were already on the wire when the link failed.
+* `Hold`, the default. The phits on the wire arrive normally, and the rest of the packet waits in the previous router until the link recovers.
+* `Drop`. The packets whose head phit arrives through a failed link are discarded, together with the rest of their phits, which are still discarded if they are sent after the recovery. The credits of the discarded phits are returned to the emitting router. The amount of discarded phits is reported as `dropped_phits`.
+
+The result of the simulation includes a `link_faults` object with `dropped_phits` and the list of `events`. Each event reports the `accepted_load` in the interval since the previous event (or the start of the simulation) as `accepted_load_before`, and in the interval until the next event (or the end of the simulation) as `accepted_load_after`.
+
+*/
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+use crate::event::Time;
+use crate::packet::{Phit,Packet};
+use crate::topology::{Topology,Location};
+
+///What to do with the phits on the wire of a link when it fails.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum InFlightPolicy
+{
+	///Let them arrive and hold the rest of the packet until the link recovers.
+	Hold,
+	///Discard the packets whose head arrives through a failed link.
+	Drop,
+}
+
+///A change of state of a link at some cycle.
+#[derive(Clone,Debug)]
+pub struct LinkFault
+{
+	pub cycle: Time,
+	pub router: usize,
+	pub port: usize,
+	///Whether the link fails (`false`) or recovers (`true`).
+	pub available: bool,
+}
+
+impl LinkFault
+{
+	pub fn new(cv:&ConfigurationValue) -> LinkFault
+	{
+		let mut cycle=None;
+		let mut router=None;
+		let mut port=None;
+		let mut available=None;
+		match_object_panic!(cv,"LinkFault",value,
+			"cycle" => cycle=Some(value.as_time().expect("bad value for cycle")),
+			"router" => router=Some(value.as_usize().expect("bad value for router")),
+			"port" => port=Some(value.as_usize().expect("bad value for port")),
+			"state" => available=Some(match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Down" => false,
+				ConfigurationValue::Object(name,_) if name=="Up" => true,
+				_ => panic!("bad value for state, it must be Down or Up"),
+			}),
+		);
+		LinkFault{
+			cycle: cycle.expect("There were no cycle"),
+			router: router.expect("There were no router"),
+			port: port.expect("There were no port"),
+			available: available.expect("There were no state"),
+		}
+	}
+}
+
+///The list of link faults of a simulation and the state of the faulty links.
+#[derive(Debug)]
+pub struct LinkFaultSchedule
+{
+	///The events sorted by cycle.
+	events: Vec<LinkFault>,
+	///Index of the first event not applied yet.
+	next_event: usize,
+	policy: InFlightPolicy,
+	///The ends `(router,port)` of the currently failed links.
+	failed_ends: HashSet<(usize,usize)>,
+	///Packets being discarded, identified by address. Removed when their last phit is discarded.
+	dropped_packets: HashSet<*const Packet>,
+	///Total of phits discarded by the `Drop` policy.
+	dropped_phits: usize,
+	///Total of phits consumed by servers since the beginning of the simulation.
+	consumed_phits: usize,
+	///`(cycle,consumed_phits)` when each applied event happened.
+	snapshots: Vec<(Time,usize)>,
+}
+
+impl LinkFaultSchedule
+{
+	pub fn new(cv:&ConfigurationValue) -> LinkFaultSchedule
+	{
+		let mut events=None;
+		let mut policy=InFlightPolicy::Hold;
+		match_object_panic!(cv,"LinkFaultSchedule",value,
+			"events" => events=Some(value.as_array().expect("bad value for events").iter().map(LinkFault::new).collect::<Vec<_>>()),
+			"policy" => policy=match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Hold" => InFlightPolicy::Hold,
+				ConfigurationValue::Object(name,_) if name=="Drop" => InFlightPolicy::Drop,
+				_ => panic!("bad value for policy, it must be Hold or Drop"),
+			},
+		);
+		let mut events=events.expect("There were no events");
+		//A stable sort keeps the given order of the events in the same cycle.
+		events.sort_by_key(|event|event.cycle);
+		LinkFaultSchedule{
+			events,
+			next_event: 0,
+			policy,
+			failed_ends: HashSet::new(),
+			dropped_packets: HashSet::new(),
+			dropped_phits: 0,
+			consumed_phits: 0,
+			snapshots: vec![],
+		}
+	}
+	///Check that every event refers to a link between routers.
+	pub fn check(&self, topology:&dyn Topology)
+	{
+		for event in self.events.iter()
+		{
+			if event.router>=topology.num_routers() || event.port>=topology.ports(event.router)
+			{
+				panic!("There is no port {} in router {} to fail",event.port,event.router);
+			}
+			if let (Location::RouterPort{..},_) = topology.neighbour(event.router,event.port) {} else
+			{
+				panic!("The port {} of router {} is not a link between routers",event.port,event.router);
+			}
+		}
+	}
+	///Take the events of the given cycle, updating the state of the links. Returns for each event its link ends `(router,port)` and whether it becomes available.
+	pub fn take_events(&mut self, cycle:Time, topology:&dyn Topology) -> Vec<((usize,usize),(usize,usize),bool)>
+	{
+		let mut changes=vec![];
+		while self.next_event<self.events.len() && self.events[self.next_event].cycle<=cycle
+		{
+			let event=&self.events[self.next_event];
+			let other = match topology.neighbour(event.router,event.port).0
+			{
+				Location::RouterPort{router_index,router_port} => (router_index,router_port),
+				_ => unreachable!(),
+			};
+			let end=(event.router,event.port);
+			if event.available
+			{
+				self.failed_ends.remove(&end);
+				self.failed_ends.remove(&other);
+			}
+			else
+			{
+				self.failed_ends.insert(end);
+				self.failed_ends.insert(other);
+			}
+			changes.push((end,other,event.available));
+			self.snapshots.push((cycle,self.consumed_phits));
+			self.next_event+=1;
+		}
+		changes
+	}
+	///Whether a phit arriving at the given router port must be discarded. When it returns true the phit is accounted as dropped.
+	pub fn must_drop(&mut self, router:usize, port:usize, phit:&Rc<Phit>) -> bool
+	{
+		if self.policy!=InFlightPolicy::Drop
+		{
+			return false;
+		}
+		let packet = phit.packet.as_ref() as *const Packet;
+		let drop = if phit.is_begin()
+		{
+			self.failed_ends.contains(&(router,port))
+		}
+		else
+		{
+			self.dropped_packets.contains(&packet)
+		};
+		if drop
+		{
+			self.dropped_phits+=1;
+			if phit.is_end()
+			{
+				self.dropped_packets.remove(&packet);
+			}
+			else
+			{
+				self.dropped_packets.insert(packet);
+			}
+		}
+		drop
+	}
+	///Account a phit consumed by a server.
+	pub fn track_consumed_phit(&mut self)
+	{
+		self.consumed_phits+=1;
+	}
+	///Build the `link_faults` result, given the current cycle and the number of servers.
+	pub fn result(&self, cycle:Time, num_servers:usize) -> ConfigurationValue
+	{
+		let load = |(begin_cycle,begin_phits):(Time,usize),(end_cycle,end_phits):(Time,usize)|
+		{
+			if end_cycle>begin_cycle
+			{
+				(end_phits-begin_phits) as f64/(end_cycle-begin_cycle) as f64/num_servers as f64
+			}
+			else
+			{
+				0.0
+			}
+		};
+		let events = self.snapshots.iter().enumerate().map(|(index,&snapshot)|{
+			let event=&self.events[index];
+			let previous = if index>0 { self.snapshots[index-1] } else { (0,0) };
+			let next = self.snapshots.get(index+1).copied().unwrap_or((cycle,self.consumed_phits));
+			ConfigurationValue::Object(String::from("LinkFault"),vec![
+				(String::from("cycle"),ConfigurationValue::Number(event.cycle as f64)),
+				(String::from("router"),ConfigurationValue::Number(event.router as f64)),
+				(String::from("port"),ConfigurationValue::Number(event.port as f64)),
+				(String::from("state"),ConfigurationValue::Literal(String::from(if event.available {"Up"} else {"Down"}))),
+				(String::from("accepted_load_before"),ConfigurationValue::Number(load(previous,snapshot))),
+				(String::from("accepted_load_after"),ConfigurationValue::Number(load(snapshot,next))),
+			])
+		}).collect();
+		ConfigurationValue::Object(String::from("LinkFaults"),vec![
+			(String::from("dropped_phits"),ConfigurationValue::Number(self.dropped_phits as f64)),
+			(String::from("events"),ConfigurationValue::Array(events)),
+		])
+	}
+}
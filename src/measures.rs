@@ -22,6 +22,8 @@ the message was created until the cycle in its consumption was completed. Note t
 * `server_average_missed_generations` counts the average of times a server has skipped generating a message because its internal queue is full. Under some assumptions a greater than 0 value means some flows have infinite latency. It may also mean that `server_queue_size` is not large enough.
 * `servers_with_missed_generations` counts the number of severs that have missed some generations. Under some assumptions this is couting the number of flows with infinite latency.
 * `virtual_channel_usage` is an array with the link utilization indexed by the virtual channel. This is, when a phit is transmitted by a link requesting a virtual channel `vc`, a `+1` is tracked into the index `vc`.
+* `dropped_phits` and `dropped_packets` count the phits and packets discarded by lossy links, such as the `LossyVirtualChannels` transmission mechanism. They are 0 in lossless networks.
+* `retransmitted_packets` counts the packets sent again by the servers after the `retransmission_timeout`, and `duplicate_packets` the copies discarded at their destination because the packet had already arrived.
 * `git_id` has an id of the CAMINOS binary, which is meaningful when building from a git repository.
 * `version_number` has the CAMINOS version as read from the Cargo.toml.
 
@@ -496,6 +498,14 @@ pub struct StatisticMeasurement
 	///For each virtual channel `vc`, `virtual_channel_usage[vc]` counts the total number of times
 	///a phit has advanced by any link using that virtual channel.
 	pub virtual_channel_usage: Vec<usize>,
+	///Number of phits discarded by lossy links.
+	pub dropped_phits: usize,
+	///Number of packets whose head has been discarded by lossy links.
+	pub dropped_packets: usize,
+	///Number of packets sent again by their server after the retransmission timeout.
+	pub retransmitted_packets: usize,
+	///Number of packets discarded at their destination server because a copy had already arrived.
+	pub duplicate_packets: usize,
 }
 
 //impl StatisticMeasurement
@@ -690,6 +700,10 @@ impl Statistics
 		for server in network.servers.iter_mut()
 		{
 			server.statistics.reset(next_cycle);
+			if let Some(control) = server.congestion_control.as_mut()
+			{
+				control.reset_statistics(next_cycle);
+			}
 		}
 		for router in network.routers.iter()
 		{
@@ -867,6 +881,36 @@ impl Statistics
 			m.virtual_channel_usage[vc]+=1;
 		}
 	}
+	/// Called when a lossy link discards a phit.
+	pub fn track_dropped_phit(&mut self, cycle: Time, phit:&Phit)
+	{
+		let packet = if phit.is_begin() {1} else {0};
+		self.current_measurement.dropped_phits+=1;
+		self.current_measurement.dropped_packets+=packet;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.dropped_phits+=1;
+			m.dropped_packets+=packet;
+		}
+	}
+	/// Called when a server sends again a packet that has not been acknowledged.
+	pub fn track_retransmitted_packet(&mut self, cycle: Time)
+	{
+		self.current_measurement.retransmitted_packets+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.retransmitted_packets+=1;
+		}
+	}
+	/// Called when a server discards the head of a packet already received.
+	pub fn track_duplicate_packet(&mut self, cycle: Time)
+	{
+		self.current_measurement.duplicate_packets+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.duplicate_packets+=1;
+		}
+	}
 	//fn track_packet_hops(&mut self, hops:usize, cycle: Time)
 	//{
 	//	self.current_measurement.total_packet_hops+=hops;
@@ -67,6 +67,8 @@ pub struct Packet
 	pub cycle_into_network: RefCell<Time>,
 	///Extra info tracked for some special statistics.
 	pub extra: RefCell<Option<PacketExtraInfo>>,
+	///Whether some router has marked the packet as having experienced congestion (ECN).
+	pub congestion_experienced: RefCell<bool>,
 }
 
 #[cfg(feature="slab_packet")]
@@ -76,6 +76,12 @@ pub trait VirtualChannelPolicy : Debug
 	fn need_server_ports(&self)->bool;
 	fn need_port_average_queue_length(&self)->bool;
 	fn need_port_last_transmission(&self)->bool;
+	///Whether the selection made by the policy depends on the current status of the router, as its occupation or a random choice, instead of only on the request. The deadlock verifier stops applying the policies when it finds one of these.
+	///It defaults to true, so that the verifier does not miss dependencies. Policies must only return false when their selection is a function of the candidates and the request.
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
 }
 
 #[derive(Debug)]
@@ -370,6 +376,11 @@ impl VirtualChannelPolicy for Identity
 	{
 		false
 	}
+
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
 }
 
 impl Identity
@@ -407,6 +418,11 @@ impl VirtualChannelPolicy for Random
 	{
 		false
 	}
+
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
 }
 
 impl Random
@@ -464,6 +480,11 @@ impl VirtualChannelPolicy for Shortest
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
+
 }
 
 impl Shortest
@@ -510,6 +531,11 @@ impl VirtualChannelPolicy for Hops
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl Hops
@@ -599,6 +625,11 @@ impl VirtualChannelPolicy for WideHops
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl WideHops
@@ -765,6 +796,11 @@ impl VirtualChannelPolicy for LowestSinghWeight
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
+
 }
 
 impl LowestSinghWeight
@@ -941,6 +977,11 @@ impl VirtualChannelPolicy for AverageOccupancyFunction
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
+
 }
 
 impl AverageOccupancyFunction
@@ -1063,6 +1104,11 @@ impl VirtualChannelPolicy for PortDiscardLabelThreshold
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.previous_policy.depends_on_status()
+	}
+
 }
 
 impl PortDiscardLabelThreshold
@@ -1152,6 +1198,11 @@ impl VirtualChannelPolicy for RRRate
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
+
 }
 
 impl RRRate
@@ -1259,6 +1310,11 @@ impl VirtualChannelPolicy for Minimal
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.policy.depends_on_status()
+	}
+
 }
 
 impl Minimal
@@ -1325,6 +1381,11 @@ impl VirtualChannelPolicy for LowestLabel
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl LowestLabel
@@ -1397,6 +1458,11 @@ impl VirtualChannelPolicy for LabelSaturate
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl LabelSaturate
@@ -1497,6 +1563,11 @@ impl VirtualChannelPolicy for LabelTransform
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl LabelTransform
@@ -1648,6 +1719,11 @@ impl VirtualChannelPolicy for OccupancyFunction
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		true
+	}
+
 }
 
 impl OccupancyFunction
@@ -1724,6 +1800,11 @@ impl VirtualChannelPolicy for NegateLabel
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl NegateLabel
@@ -1787,6 +1868,11 @@ impl VirtualChannelPolicy for VecLabel
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl VecLabel
@@ -1868,6 +1954,11 @@ impl VirtualChannelPolicy for MapLabel
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.label_to_policy.iter().chain([&self.below_policy,&self.above_policy]).any(|policy|policy.depends_on_status())
+	}
+
 }
 
 impl MapLabel
@@ -1952,6 +2043,11 @@ impl VirtualChannelPolicy for MapTrafficIndex
 	{
 		false
 	}
+
+	fn depends_on_status(&self)->bool
+	{
+		self.traffic_to_policy.iter().chain([&self.above_policy]).any(|policy|policy.depends_on_status())
+	}
 }
 
 impl MapTrafficIndex
@@ -2014,6 +2110,11 @@ impl VirtualChannelPolicy for ShiftEntryVC
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl ShiftEntryVC
@@ -2073,6 +2174,11 @@ impl VirtualChannelPolicy for MapHop
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.hop_to_policy.iter().chain([&self.above_policy]).any(|policy|policy.depends_on_status())
+	}
+
 }
 
 impl MapHop
@@ -2132,6 +2238,11 @@ impl VirtualChannelPolicy for ArgumentVC
 		false
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl ArgumentVC
@@ -2193,6 +2304,11 @@ impl VirtualChannelPolicy for Either
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.policies.iter().any(|policy|policy.depends_on_status())
+	}
+
 }
 
 impl Either
@@ -2260,6 +2376,11 @@ impl VirtualChannelPolicy for MapEntryVC
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.vc_to_policy.iter().chain([&self.above_policy]).any(|policy|policy.depends_on_status())
+	}
+
 }
 
 impl MapEntryVC
@@ -2330,6 +2451,11 @@ impl VirtualChannelPolicy for MapMessageSize
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		self.policies.iter().any(|policy|policy.depends_on_status())
+	}
+
 }
 
 impl MapMessageSize
@@ -2396,6 +2522,11 @@ impl VirtualChannelPolicy for Chain
 	{
 		true
 	}
+
+	fn depends_on_status(&self)->bool
+	{
+		self.policies.iter().any(|policy|policy.depends_on_status())
+	}
 }
 
 impl Chain
@@ -2491,6 +2622,11 @@ impl VirtualChannelPolicy for VDQ
 	{
 		true
 	}
+
+	fn depends_on_status(&self)->bool
+	{
+		self.policies_override.iter().any(|policy|policy.depends_on_status())
+	}
 }
 
 impl VDQ
@@ -2623,6 +2759,11 @@ impl VirtualChannelPolicy for CurrentLinkLabel
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl CurrentLinkLabel
@@ -2684,6 +2825,11 @@ impl VirtualChannelPolicy for NextLinkLabel
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl NextLinkLabel
@@ -2750,6 +2896,11 @@ impl VirtualChannelPolicy for ChannelHop
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl ChannelHop
@@ -2820,6 +2971,11 @@ impl VirtualChannelPolicy for ValiantIntermediate
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl ValiantIntermediate
@@ -2934,6 +3090,11 @@ impl VirtualChannelPolicy for ValiantLastRouterPalmTree
 		true
 	}
 
+	fn depends_on_status(&self)->bool
+	{
+		false
+	}
+
 }
 
 impl ValiantLastRouterPalmTree
@@ -6,7 +6,7 @@ use std::mem::{size_of};
 use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 use std::convert::TryInto;
 
-use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument};
+use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument,DropPolicy,DynamicThreshold,Switching,mark_congestion,input_phits};
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Location,Topology};
 use crate::routing::CandidateEgress;
@@ -60,6 +60,11 @@ pub struct Basic
 	///In particular, [EnforceFlowControl] will filter them out.
 	///Defaults to false.
 	neglect_busy_output: bool,
+	///If given, the packets whose head arrives to an input buffer holding at least this number of phits get an ECN mark.
+	///See the [congestion_control](crate::congestion_control) module.
+	ecn_threshold: Option<usize>,
+	///The switching technique, deciding when the head of a packet may advance. Defaults to `Wormhole`.
+	switching: Switching,
 	/// `transmission_port_status[port] = status`
 	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
 	/// `reception_port_space[port] = space`
@@ -104,6 +109,8 @@ pub struct BasicRouterMeasurement
 	pub output_buffer_occupation_per_vc: Vec<f64>,
 	///Accumulated over time, averaged per port.
 	pub reception_space_occupation_per_vc: Vec<f64>,
+	///Accumulated over time, averaged per port. Only reported when some reception space has shared space.
+	pub shared_space_occupation: f64,
 }
 
 impl BasicRouterMeasurement
@@ -115,6 +122,7 @@ impl BasicRouterMeasurement
 			begin_cycle: 0,
 			output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
 			reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+			shared_space_occupation: 0f64,
 		}
 	}
 	pub fn into_mul(mut self, factor:f64 ) -> BasicRouterMeasurement
@@ -132,6 +140,7 @@ impl BasicRouterMeasurement
 		{
 			*elem *= factor;
 		}
+		self.shared_space_occupation *= factor;
 		self
 	}
 }
@@ -140,7 +149,8 @@ impl Router for Basic
 {
 	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
 	{
-		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+		self.reception_port_space[port].insert(phit.clone(),rng).expect("there was some problem on the insertion");
+		mark_congestion(self.reception_port_space[port].as_ref(),&phit,self.ecn_threshold);
 		if let Some(event) = self.schedule(current_cycle,0) {
 			vec![event]
 		} else {
@@ -156,6 +166,15 @@ impl Router for Basic
 			vec![]
 		}
 	}
+	fn set_link_available(&mut self, current_cycle:Time, port:usize, available:bool) -> Vec<EventGeneration>
+	{
+		self.transmission_port_status[port].set_link_available(available);
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
 	fn num_virtual_channels(&self) -> usize
 	{
 		//self.virtual_ports[0].len()
@@ -171,6 +190,10 @@ impl Router for Basic
 		//Box::new(self.virtual_ports.iter().flat_map(|port|port.iter().flat_map(|vp|vp.iter_phits())).collect::<Vec<_>>().into_iter())
 		Box::new(self.reception_port_space.iter().flat_map(|space|space.iter_phits()).collect::<Vec<_>>().into_iter())
 	}
+	fn iter_input_phits(&self) -> Box<dyn Iterator<Item=(usize,Rc<Phit>,bool)>>
+	{
+		input_phits(&self.reception_port_space)
+	}
 	//fn get_virtual_port(&self, port:usize, virtual_channel:usize) -> Option<&VirtualPort>
 	//{
 	//	Some(&self.virtual_ports[port][virtual_channel])
@@ -179,9 +202,9 @@ impl Router for Basic
 	{
 		Some(&*self.transmission_port_status[port])
 	}
-	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
+	fn get_maximum_credits_towards(&self, port:usize, virtual_channel:usize) -> Option<usize>
 	{
-		Some(self.buffer_size)
+		Some(self.transmission_port_status[port].maximum_available_space_for_virtual_channel(virtual_channel).unwrap_or(self.buffer_size))
 	}
 	fn get_index(&self)->Option<usize>
 	{
@@ -198,6 +221,8 @@ impl Router for Basic
 		let cycle_span = cycle - self.principal_measurement.begin_cycle;
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
+		let has_shared_space = self.reception_port_space.iter().any(|space|space.occupied_shared_space().is_some());
+		let mut shared_space_occupation = self.principal_measurement.shared_space_occupation/cycle_span as f64;
 		let mut temporal_statistics: Vec<  BasicRouterMeasurement  > = self.temporal_statistics.iter().map(|m|m.clone().into_mul(1f64/self.statistics_temporal_step as f64)).collect();
 		if let Some(previous)=statistics
 		{
@@ -261,12 +286,18 @@ impl Router for Basic
 							}
 							_ => panic!("bad value for average_reception_space_occupation_per_vc"),
 						},
+						"average_shared_space_occupation" => match value
+						{
+							ConfigurationValue::Number(x) => shared_space_occupation += x,
+							_ => panic!("bad value for average_shared_space_occupation"),
+						},
 						"temporal_statistics" => match value
 						{
 							&ConfigurationValue::Object(_, ref prev_t_pairs) =>
 							{
 								let mut local_average_output_buffer_occupation_per_vc : Vec<Vec<f64>> = vec![];
 								let mut local_average_reception_space_occupation_per_vc : Vec<Vec<f64>> = vec![];
+								let mut local_average_shared_space_occupation : Vec<f64> = vec![];
 								for (ref t_name,ref t_value) in prev_t_pairs
 								{
 									match t_name.as_ref()
@@ -299,6 +330,17 @@ impl Router for Basic
 											}
 											_ => panic!("bad value for average_reception_space_occupation_per_vc"),
 										},
+										"average_shared_space_occupation" => match t_value
+										{
+											ConfigurationValue::Array(prev_a) =>
+											{
+												local_average_shared_space_occupation = prev_a.iter().map(|x|match x{
+													ConfigurationValue::Number(x) => *x,
+													_ => panic!(),
+												}).collect();
+											}
+											_ => panic!("bad value for average_shared_space_occupation"),
+										},
 										_ => panic!(),
 									}
 								}
@@ -315,6 +357,10 @@ impl Router for Basic
 								//println!("temporal_statistics.len()={}",temporal_statistics.len());
 								for temporal_index in 0..temporal_statistics.len()
 								{
+									if temporal_index<local_average_shared_space_occupation.len()
+									{
+										temporal_statistics[temporal_index].shared_space_occupation += local_average_shared_space_occupation[temporal_index];
+									}
 									for measurement_index in 0..temporal_statistics[temporal_index].output_buffer_occupation_per_vc.len()
 									{
 										if temporal_index<local_average_output_buffer_occupation_per_vc.len()
@@ -376,6 +422,14 @@ impl Router for Basic
 			}
 			result_content.push((String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(content.iter().map(|x|ConfigurationValue::Number(*x)).collect())));
 		}
+		if has_shared_space
+		{
+			if is_last
+			{
+				shared_space_occupation /= total_routers as f64;
+			}
+			result_content.push((String::from("average_shared_space_occupation"),ConfigurationValue::Number(shared_space_occupation)));
+		}
 		if !temporal_statistics.is_empty()
 		{
 			if is_last
@@ -386,10 +440,14 @@ impl Router for Basic
 					m.mul(factor);
 				}
 			}
-			let temporal_content = vec![
+			let mut temporal_content = vec![
 				(String::from("average_output_buffer_occupation_per_vc"),ConfigurationValue::Array(temporal_statistics.iter().map(|m|ConfigurationValue::Array(m.output_buffer_occupation_per_vc.iter().map(|x|ConfigurationValue::Number(*x)).collect())).collect())),
 				(String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(temporal_statistics.iter().map(|m|ConfigurationValue::Array(m.reception_space_occupation_per_vc.iter().map(|x|ConfigurationValue::Number(*x)).collect())).collect())),
 			];
+			if has_shared_space
+			{
+				temporal_content.push((String::from("average_shared_space_occupation"),ConfigurationValue::Array(temporal_statistics.iter().map(|m|ConfigurationValue::Number(m.shared_space_occupation)).collect())));
+			}
 			result_content.push((String::from("temporal_statistics"),ConfigurationValue::Object(String::from("TemporalStatistics"),temporal_content)));
 		}
 		Some(ConfigurationValue::Object(String::from("Basic"),result_content))
@@ -405,6 +463,7 @@ impl Router for Basic
 		{
 			*x=0f64;
 		}
+		self.principal_measurement.shared_space_occupation=0f64;
 	}
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
 	{
@@ -419,6 +478,10 @@ impl Router for Basic
 			unimplemented!()
 		}
 	}
+	fn must_drop(&mut self, port:usize, phit:&Rc<Phit>, rng: &mut StdRng) -> bool
+	{
+		self.reception_port_space[port].must_drop(phit,rng)
+	}
 }
 
 impl Basic
@@ -450,7 +513,11 @@ impl Basic
 		let mut transmission_mechanism=None;
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
+		let mut drop_policy=DropPolicy::TailDrop;
+		let mut shared_buffer_policy=DynamicThreshold::default();
+		let mut ecn_threshold=None;
 		let mut neglect_busy_output = false;
+		let mut switching = Switching::Wormhole;
 		match_object_panic!(cv,"Basic",value,
 			"virtual_channels" => match value
 			{
@@ -535,6 +602,10 @@ impl Basic
 				&ConfigurationValue::Literal(ref s) => from_server_mechanism = Some(s.to_string()),
 				_ => panic!("bad value for from_server_mechanism"),
 			},
+			"drop_policy" => drop_policy = DropPolicy::new(value),
+			"shared_buffer_policy" => shared_buffer_policy = DynamicThreshold::new(value),
+			"ecn_threshold" => ecn_threshold = Some(value.as_usize().expect("bad value for ecn_threshold")),
+			"switching" => switching = Switching::new(value),
 		);
 		//let sides=sides.expect("There were no sides");
 		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
@@ -547,6 +618,11 @@ impl Basic
 		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
 		let allow_request_busy_port=allow_request_busy_port.expect("There were no allow_request_busy_port");
 		let output_prioritize_lowest_label=output_prioritize_lowest_label.expect("There were no output_prioritize_lowest_label");
+		switching.check_buffer_size("buffer_size",buffer_size,maximum_packet_size);
+		if output_buffer_size>0
+		{
+			switching.check_buffer_size("output_buffer_size",output_buffer_size,maximum_packet_size);
+		}
 		let input_ports=topology.ports(router_index);
 		let selected_input=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|None).collect()
@@ -562,7 +638,7 @@ impl Basic
 		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
-		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size,drop_policy:&drop_policy,shared_buffer_policy:&shared_buffer_policy};
 		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
 		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
 		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
@@ -611,6 +687,8 @@ impl Basic
 			allow_request_busy_port,
 			output_prioritize_lowest_label,
 			neglect_busy_output,
+			ecn_threshold,
+			switching,
 			buffer_size,
 			transmission_port_status,
 			reception_port_space,
@@ -643,6 +721,7 @@ impl Basic
 	///bubble_in_use should be true only for leading phits that require the additional space.
 	fn can_phit_advance(&self, phit:&Rc<Phit>, exit_port:usize, exit_vc:usize, bubble_in_use:bool)->bool
 	{
+		let whole_packet = phit.is_begin() && self.switching.requires_whole_packet_space();
 		//if not internal output space
 		if self.output_buffer_size==0
 		{
@@ -661,7 +740,7 @@ impl Basic
 			}
 			else
 			{
-				self.transmission_port_status[exit_port].can_transmit(phit,exit_vc)
+				status.can_transmit(phit,exit_vc) && (!whole_packet || status.can_transmit_whole_packet(phit,exit_vc))
 			}
 		}
 		else
@@ -676,6 +755,10 @@ impl Basic
 				{
 					phit.packet.size + self.maximum_packet_size
 				}
+				else if whole_packet
+				{
+					phit.packet.size
+				}
 				else
 				{
 					self.flit_size
@@ -715,6 +798,12 @@ impl Basic
 					self.temporal_statistics[mindex].reception_space_occupation_per_vc[vc]+= increment;
 				}
 			}
+			let increment = (port_space.occupied_shared_space().unwrap_or(0)*cycles_span as usize) as f64 / self.reception_port_space.len() as f64;
+			self.principal_measurement.shared_space_occupation+= increment;
+			if let Some(mindex)=current_temporal_index
+			{
+				self.temporal_statistics[mindex].shared_space_occupation+= increment;
+			}
 		}
 		for output_port in self.output_buffers.iter()
 		{
@@ -908,6 +997,11 @@ impl Eventful for Basic
 					None =>
 					{
 						undecided_channels+=1;
+						if !self.switching.can_head_advance(self.reception_port_space[entry_port].as_ref(),&phit)
+						{
+							//The packet has not been completely received yet.
+							continue;
+						}
 						let target_server=phit.packet.message.destination;
 						let (target_location,_link_class)=topology.server_neighbour(target_server);
 						let target_router=match target_location
@@ -1218,7 +1312,7 @@ impl Eventful for Basic
 						}
 						else
 						{
-							status.can_transmit(&phit,exit_vc)
+							status.can_transmit(&phit,exit_vc) && (!(phit.is_begin() && self.switching.requires_whole_packet_space()) || status.can_transmit_whole_packet(&phit,exit_vc))
 						};
 						if can_transmit
 						{
@@ -1244,7 +1338,7 @@ impl Eventful for Basic
 						}
 						else
 						{
-							if 0<phit.index && phit.index<self.flit_size
+							if 0<phit.index && phit.index<self.flit_size && status.is_link_available()
 							{
 								panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
 							}
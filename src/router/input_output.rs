@@ -3,8 +3,8 @@ use std::rc::{Rc,Weak};
 use std::ops::Deref;
 use std::mem::size_of;
 use ::rand::{Rng,rngs::StdRng};
-use super::{Router,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage};
-use crate::allocator::{Allocator,VCARequest,AllocatorBuilderArgument, new_allocator};
+use super::{Router,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,DropPolicy,DynamicThreshold,Switching,mark_congestion,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,input_phits};
+use crate::allocator::{Allocator,Request,VCARequest,AllocatorBuilderArgument, new_allocator};
 use crate::config_parser::ConfigurationValue;
 use crate::router::RouterBuilderArgument;
 use crate::topology::{Location,Topology};
@@ -16,6 +16,9 @@ use crate::quantify::Quantifiable;
 use crate::match_object_panic;
 
 
+///The names in the statistics of the stalls at each stage of the pipeline, as counted in `statistics_pipeline_stalls`.
+const PIPELINE_STALL_NAMES: [&str;3] = ["average_routing_stalls","average_vc_allocation_stalls","average_switch_allocation_stalls"];
+
 ///Strategy for the arbitration of the output port.
 enum OutputArbiter
 {
@@ -25,6 +28,36 @@ enum OutputArbiter
 		port_token: Vec<usize>,
 	},
 }
+/**
+A router with input and output buffers joined by a crossbar.
+
+By default each packet at the front of an input buffer requests output virtual channels to the `allocator` and, once granted, its phits traverse the crossbar in the same cycle, all the virtual channels at once.
+The router can instead follow the pipeline of routing computation, virtual channel allocation, switch allocation and switch traversal, each with its own latency.
+```ignore
+InputOutput{
+	//...the usual router fields, with the allocator of output virtual channels...
+	allocator: Islip{},
+	//Cycles since the head reaches the front of its buffer until it requests output virtual channels. Defaults to 0.
+	routing_delay: 1,
+	//Cycles since an output virtual channel is granted until the packet may request the switch. Defaults to 0.
+	vc_allocation_delay: 1,
+	//Allocator of the crossbar among physical ports, granting a phit per input port and per output port each cycle.
+	//If omitted, every virtual channel holding an output virtual channel advances each cycle.
+	switch_allocator: SeparableInputFirst{},
+	//Whether the switch is allocated in the same cycle as the virtual channel, hiding the vc_allocation_delay.
+	//The speculative requests have lower priority than the others. Defaults to false.
+	speculative_switch_allocation: true,
+	//Cycles to traverse the crossbar. Defaults to 0.
+	crossbar_delay: 1,
+	//Number of switch allocations and traversals per cycle, so that each output port may receive this number of phits per cycle. Defaults to 1.
+	crossbar_speedup: 2,
+}
+```
+The statistics of the router include the average number per cycle of input virtual channels stalled at each stage.
+A head stalls in routing when there is no candidate after the routing and the virtual channel policies, in virtual channel allocation when its requests are not granted, and in switch allocation when it holds an output virtual channel and a phit ready but it is not granted the switch.
+
+The optional `switching`, one of `Wormhole` (the default), `VirtualCutThrough` or `StoreAndForward`, decides when the heads may leave the input and output buffers. See [Switching].
+**/
 pub struct InputOutput
 {
 	///Weak pointer to itself, see <https://users.rust-lang.org/t/making-a-rc-refcell-trait2-from-rc-refcell-trait1/16086/3>
@@ -55,6 +88,11 @@ pub struct InputOutput
 	///In particular, [EnforceFlowControl] will filter them out.
 	///Defaults to false.
 	neglect_busy_output: bool,
+	///If given, the packets whose head arrives to an input buffer holding at least this number of phits get an ECN mark.
+	///See the [congestion_control](crate::congestion_control) module.
+	ecn_threshold: Option<usize>,
+	///The switching technique, deciding when the head of a packet may advance. Defaults to `Wormhole`.
+	switching: Switching,
 	/// `transmission_port_status[port] = status`
 	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
 	/// `reception_port_space[port] = space`
@@ -87,6 +125,9 @@ pub struct InputOutput
 	///Divisor of the cycles in which the crossbar operates.
 	///Without other overrides, the quotient `general_frequency_divisor/crossbar_frequency_divisor` is the internal speedup.
 	crossbar_frequency_divisor: Time,
+	///Number of times that the switch allocation and traversal are performed in each crossbar cycle.
+	///With a `switch_allocator` it is the number of phits that each output port may receive per cycle.
+	crossbar_speedup: usize,
 
 	///Metrics
 	buffer_speed_metric: Option<Vec<Vec<TimeSegmentMetric>>>,
@@ -95,6 +136,20 @@ pub struct InputOutput
 	//allocator:
 	///The allocator for the croosbar.
 	crossbar_allocator: Box<dyn Allocator>,
+	///The allocator of the switch among physical ports, requested by the input virtual channels that hold an output virtual channel.
+	///If None, every virtual channel holding an output virtual channel advances.
+	switch_allocator: Option<Box<dyn Allocator>>,
+	///Cycles since the head of a packet reaches the front of its input buffer until it may request output virtual channels.
+	routing_delay: Time,
+	///Cycles since an output virtual channel is granted until the packet may perform the switch allocation.
+	vc_allocation_delay: Time,
+	///Whether the switch allocation is performed in the same cycle as the virtual channel allocation, with lower priority.
+	speculative_switch_allocation: bool,
+	///`pipeline_ready_cycle[port][vc]` is the cycle from which the packet at the front of the input virtual channel may perform its next stage.
+	///It is the virtual channel allocation while `selected_output` is None and the switch allocation afterwards.
+	pipeline_ready_cycle: Vec<Vec<Option<Time>>>,
+	///The virtual channel of each input port that was last granted the switch, to arbitrate among those requesting the same output port.
+	switch_vc_pointer: Vec<usize>,
 	//Use the labels provided by the routing to sort the petitions in the output arbiter.
 	//output_priorize_lowest_label: bool, // USE RandomPriorityAllocator instead of this parameter.
 
@@ -105,13 +160,18 @@ pub struct InputOutput
 	statistics_output_buffer_occupation_per_vc: Vec<f64>,
 	///Accumulated over time, averaged per port.
 	statistics_reception_space_occupation_per_vc: Vec<f64>,
+	///Accumulated over time, averaged per port. Only reported when some reception space has shared space.
+	statistics_shared_space_occupation: f64,
+	///Accumulated count of input virtual channels stalled at each pipeline stage, as `[routing, vc_allocation, switch_allocation]`.
+	statistics_pipeline_stalls: [usize;3],
 }
 
 impl Router for InputOutput
 {
 	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
 	{
-		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+		self.reception_port_space[port].insert(phit.clone(),rng).expect("there was some problem on the insertion");
+		mark_congestion(self.reception_port_space[port].as_ref(),&phit,self.ecn_threshold);
 		if let Some(event) = self.schedule(current_cycle,0) {
 			vec![event]
 		} else {
@@ -138,6 +198,18 @@ impl Router for InputOutput
 		}
 		events
 	}
+	fn set_link_available(&mut self, current_cycle:Time, port:usize, available:bool) -> Vec<EventGeneration>
+	{
+		self.transmission_port_status[port].set_link_available(available);
+		let mut events = vec![];
+		if let Some(event) = self.schedule(current_cycle,0) {
+			events.push(event);
+		}
+		if let Some(event) = self.output_schedulers[port].borrow_mut().schedule(current_cycle,0) {
+			events.push(event);
+		}
+		events
+	}
 	fn num_virtual_channels(&self) -> usize
 	{
 		//self.virtual_ports[0].len()
@@ -153,6 +225,10 @@ impl Router for InputOutput
 		//Box::new(self.virtual_ports.iter().flat_map(|port|port.iter().flat_map(|vp|vp.iter_phits())).collect::<Vec<_>>().into_iter())
 		Box::new(self.reception_port_space.iter().flat_map(|space|space.iter_phits()).collect::<Vec<_>>().into_iter())
 	}
+	fn iter_input_phits(&self) -> Box<dyn Iterator<Item=(usize,Rc<Phit>,bool)>>
+	{
+		input_phits(&self.reception_port_space)
+	}
 	//fn get_virtual_port(&self, port:usize, virtual_channel:usize) -> Option<&VirtualPort>
 	//{
 	//	Some(&self.virtual_ports[port][virtual_channel])
@@ -161,9 +237,9 @@ impl Router for InputOutput
 	{
 		Some(&*self.transmission_port_status[port])
 	}
-	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
+	fn get_maximum_credits_towards(&self, port:usize, virtual_channel:usize) -> Option<usize>
 	{
-		Some(self.buffer_size)
+		Some(self.transmission_port_status[port].maximum_available_space_for_virtual_channel(virtual_channel).unwrap_or(self.buffer_size))
 	}
 	fn get_index(&self)->Option<usize>
 	{
@@ -180,6 +256,9 @@ impl Router for InputOutput
 		let cycle_span = cycle - self.statistics_begin_cycle;
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
+		let has_shared_space = self.reception_port_space.iter().any(|space|space.occupied_shared_space().is_some());
+		let mut shared_space_occupation = self.statistics_shared_space_occupation/cycle_span as f64;
+		let mut pipeline_stalls:Vec<f64> = self.statistics_pipeline_stalls.iter().map(|&x|x as f64/cycle_span as f64).collect();
 		if let Some(previous)=statistics
 		{
 			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
@@ -242,6 +321,20 @@ impl Router for InputOutput
 							}
 							_ => panic!("bad value for average_output_buffer_occupation_per_vc"),
 						},
+						"average_shared_space_occupation" => match value
+						{
+							ConfigurationValue::Number(x) => shared_space_occupation += x,
+							_ => panic!("bad value for average_shared_space_occupation"),
+						},
+						stall_name if PIPELINE_STALL_NAMES.contains(&stall_name) => match value
+						{
+							ConfigurationValue::Number(x) =>
+							{
+								let stage = PIPELINE_STALL_NAMES.iter().position(|&n|n==stall_name).unwrap();
+								pipeline_stalls[stage] += x;
+							}
+							_ => panic!("bad value for {}",stall_name),
+						},
 						_ => panic!("Nothing to do with field {} in InputOutput statistics",name),
 					}
 				}
@@ -288,6 +381,23 @@ impl Router for InputOutput
 			}
 			result_content.push((String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(content.iter().map(|x|ConfigurationValue::Number(*x)).collect())));
 		}
+		if has_shared_space
+		{
+			if is_last
+			{
+				shared_space_occupation /= total_routers as f64;
+			}
+			result_content.push((String::from("average_shared_space_occupation"),ConfigurationValue::Number(shared_space_occupation)));
+		}
+		for (stage,stall_name) in PIPELINE_STALL_NAMES.iter().enumerate()
+		{
+			let mut stalls = pipeline_stalls[stage];
+			if is_last
+			{
+				stalls /= total_routers as f64;
+			}
+			result_content.push((String::from(*stall_name),ConfigurationValue::Number(stalls)));
+		}
 		Some(ConfigurationValue::Object(String::from("InputOutput"),result_content))
 	}
 
@@ -302,6 +412,8 @@ impl Router for InputOutput
 		{
 			*x=0f64;
 		}
+		self.statistics_shared_space_occupation=0f64;
+		self.statistics_pipeline_stalls=[0;3];
 	}
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
 	{
@@ -314,6 +426,10 @@ impl Router for InputOutput
 			unimplemented!()
 		}
 	}
+	fn must_drop(&mut self, port:usize, phit:&Rc<Phit>, rng: &mut StdRng) -> bool
+	{
+		self.reception_port_space[port].must_drop(phit,rng)
+	}
 }
 
 
@@ -344,12 +460,21 @@ impl InputOutput
 //		let mut output_priorize_lowest_label=None;
 		let mut output_buffer_size=None;
 		let mut allocator_value=None;
+		let mut switch_allocator_value=None;
+		let mut routing_delay: Time =0;
+		let mut vc_allocation_delay: Time =0;
+		let mut speculative_switch_allocation=false;
 		let mut transmission_mechanism=None;
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
+		let mut drop_policy=DropPolicy::TailDrop;
+		let mut shared_buffer_policy=DynamicThreshold::default();
+		let mut ecn_threshold=None;
+		let mut switching=Switching::Wormhole;
 		let mut crossbar_delay: Time =0;
 		let mut neglect_busy_output = false;
 		let mut crossbar_frequency_divisor = general_frequency_divisor;
+		let mut crossbar_speedup = 1;
 		let mut time_segment_metric_buffer_rate = None;
 
 		match_object_panic!(cv,["InputOutput","InputOutputMonocycle"],value,
@@ -432,9 +557,18 @@ impl InputOutput
 				&ConfigurationValue::Literal(ref s) => from_server_mechanism = Some(s.to_string()),
 				_ => panic!("bad value for from_server_mechanism"),
 			},
+			"drop_policy" => drop_policy = DropPolicy::new(value),
+			"shared_buffer_policy" => shared_buffer_policy = DynamicThreshold::new(value),
+			"ecn_threshold" => ecn_threshold = Some(value.as_usize().expect("bad value for ecn_threshold")),
+			"switching" => switching = Switching::new(value),
 			"time_segment_metric_buffer_rate" => time_segment_metric_buffer_rate = Some(value.as_usize().expect("bad value for time_segment_metric_buffer_rate")),
 			"allocator" => allocator_value=Some(value.clone()),
+			"switch_allocator" => switch_allocator_value=Some(value.clone()),
+			"routing_delay" => routing_delay = value.as_time().expect("bad value for routing_delay"),
+			"vc_allocation_delay" => vc_allocation_delay = value.as_time().expect("bad value for vc_allocation_delay"),
+			"speculative_switch_allocation" => speculative_switch_allocation = value.as_bool().expect("bad value for speculative_switch_allocation"),
 			"crossbar_frequency_divisor" => crossbar_frequency_divisor = value.as_time().expect("bad value for crossbar_frequency_divisor"),
+			"crossbar_speedup" => crossbar_speedup = value.as_usize().expect("bad value for crossbar_speedup"),
 		);
 		//let sides=sides.expect("There were no sides");
 		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
@@ -456,14 +590,27 @@ impl InputOutput
 		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
 		let allow_request_busy_port=allow_request_busy_port.expect("There were no allow_request_busy_port");
 //		let output_priorize_lowest_label=output_priorize_lowest_label.expect("There were no output_priorize_lowest_label");
+		switching.check_buffer_size("buffer_size",buffer_size,maximum_packet_size);
+		switching.check_buffer_size("output_buffer_size",output_buffer_size,maximum_packet_size);
+		if crossbar_speedup==0
+		{
+			panic!("crossbar_speedup must be at least 1");
+		}
 		let input_ports=topology.ports(router_index);
 		let allocator = new_allocator(AllocatorBuilderArgument{
 			cv:&allocator_value.expect("There were no allocator"),
 			num_clients:input_ports * virtual_channels,
 			num_resources:input_ports * virtual_channels,
 			plugs,
-			rng:arg.rng,
+			rng:&mut *arg.rng,
 		});
+		let switch_allocator = switch_allocator_value.map(|cv|new_allocator(AllocatorBuilderArgument{
+			cv:&cv,
+			num_clients:input_ports,
+			num_resources:input_ports,
+			plugs,
+			rng:arg.rng,
+		}));
 		let selected_input=(0..input_ports).map(|_|
 			(0..virtual_channels).map(|_|None).collect()
 		).collect();
@@ -478,7 +625,7 @@ impl InputOutput
 		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
-		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size,drop_policy:&drop_policy,shared_buffer_policy:&shared_buffer_policy};
 		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
 		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
 		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
@@ -533,6 +680,8 @@ impl InputOutput
 			allow_request_busy_port,
 //			output_priorize_lowest_label,
 			neglect_busy_output,
+			ecn_threshold,
+			switching,
 			buffer_size,
 			crossbar_delay,
 			transmission_port_status,
@@ -548,11 +697,20 @@ impl InputOutput
 			output_arbiter: OutputArbiter::Token{port_token: vec![0;input_ports]},
 			maximum_packet_size,
 			crossbar_frequency_divisor,
+			crossbar_speedup,
 			buffer_speed_metric,
 			crossbar_allocator: allocator,
+			switch_allocator,
+			routing_delay,
+			vc_allocation_delay,
+			speculative_switch_allocation,
+			pipeline_ready_cycle: vec![ vec![ None ; virtual_channels ] ; input_ports ],
+			switch_vc_pointer: vec![0;input_ports],
 			statistics_begin_cycle: 0,
 			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
 			statistics_reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+			statistics_shared_space_occupation: 0f64,
+			statistics_pipeline_stalls: [0;3],
 		}));
 		//r.borrow_mut().self_rc=r.downgrade();
 		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
@@ -576,6 +734,10 @@ impl InputOutput
 			{
 				phit.packet.size + self.maximum_packet_size
 			}
+			else if self.switching.requires_whole_packet_space()
+			{
+				phit.packet.size
+			}
 			else
 			{
 				self.flit_size
@@ -633,6 +795,7 @@ impl Eventful for InputOutput
 			{
 				self.statistics_reception_space_occupation_per_vc[vc]+=(port_space.occupied_dedicated_space(vc).unwrap_or(0)*cycles_span as usize) as f64 / self.reception_port_space.len() as f64;
 			}
+			self.statistics_shared_space_occupation+=(port_space.occupied_shared_space().unwrap_or(0)*cycles_span as usize) as f64 / self.reception_port_space.len() as f64;
 		}
 		for output_port in self.output_buffers.iter()
 		{
@@ -766,6 +929,19 @@ impl Eventful for InputOutput
 					None =>
 					{
 						undecided_channels+=1;
+						if !self.switching.can_head_advance(self.reception_port_space[entry_port].as_ref(),&phit)
+						{
+							//The packet has not been completely received yet.
+							self.time_at_input_head[entry_port][entry_vc]+=1;
+							continue;
+						}
+						//-- Routing computation. The head waits `routing_delay` cycles since it is first seen at the front.
+						let routing_ready = *self.pipeline_ready_cycle[entry_port][entry_vc].get_or_insert(simulation.cycle+self.routing_delay);
+						if routing_ready > simulation.cycle
+						{
+							self.time_at_input_head[entry_port][entry_vc]+=1;
+							continue;
+						}
 						let target_server=phit.packet.message.destination;
 						let (target_location,_link_class)=topology.server_neighbour(target_server);
 						let target_router=match target_location
@@ -782,6 +958,7 @@ impl Eventful for InputOutput
 								panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
 							}
 							//There are currently no good port choices, but there may be in the future.
+							self.statistics_pipeline_stalls[0]+=1;
 							continue;
 						}
 						let mut good_ports=routing_candidates.into_iter().filter_map(|candidate|{
@@ -843,6 +1020,7 @@ impl Eventful for InputOutput
 						}
 						if good_ports.len()==0
 						{
+							self.statistics_pipeline_stalls[0]+=1;
 							self.time_at_input_head[entry_port][entry_vc]+=1;
 							// if self.time_at_input_head[entry_port][entry_vc] > 25000
 							// {
@@ -909,7 +1087,9 @@ impl Eventful for InputOutput
 		}
 
 		// Add all the requests to the allocator.
+		let mut requesting_channels = vec![ vec![false;amount_virtual_channels] ; self.reception_port_space.len() ];
 		request.iter_mut().for_each(|pr| {
+			requesting_channels[pr.entry_port][pr.entry_vc]=true;
 			self.crossbar_allocator.add_request(pr.to_allocator_request(amount_virtual_channels));
 		});
 
@@ -924,90 +1104,153 @@ impl Eventful for InputOutput
 
 		//Complete the arbitration of the requests by writing the selected_input of the output virtual ports.
 		//let request=request_sequence.concat();
+		//The switch allocation of the packets granted in this cycle is speculative when `speculative_switch_allocation`.
+		let speculative = self.speculative_switch_allocation && self.switch_allocator.is_some();
+		let switch_delay = if speculative { 0 } else { self.vc_allocation_delay };
+		let mut granted_channels = vec![ vec![false;amount_virtual_channels] ; self.reception_port_space.len() ];
 		for VCARequest{entry_port,entry_vc,requested_port,requested_vc,..} in request_it
 		{
 			self.selected_input[requested_port][requested_vc]=Some((entry_port,entry_vc));
 			self.selected_output[entry_port][entry_vc]=Some((requested_port,requested_vc));
+			self.pipeline_ready_cycle[entry_port][entry_vc]=Some(simulation.cycle+switch_delay);
+			requesting_channels[entry_port][entry_vc]=false;
+			granted_channels[entry_port][entry_vc]=true;
 		}
+		self.statistics_pipeline_stalls[1]+=requesting_channels.iter().flatten().filter(|&&stalled|stalled).count();
 
-		//-- For each output port decide which input actually uses it this cycle.
+		//The switch allocation and traversal are repeated `crossbar_speedup` times per cycle.
 		let mut events=vec![];
-		for exit_port in 0..self.transmission_port_status.len()
+		let mut waiting_channels=0;
+		let mut total_switch_requests=0;
+		//The channels requesting the switch in some round of this cycle and the ones granted it in some round. The stalled ones are counted once per cycle.
+		let mut switch_requesting = vec![ vec![false;amount_virtual_channels] ; self.reception_port_space.len() ];
+		let mut switch_granted = vec![ vec![false;amount_virtual_channels] ; self.reception_port_space.len() ];
+		for _round in 0..self.crossbar_speedup
 		{
-			let nvc=amount_virtual_channels;
-			for exit_vc in 0..nvc
+			//-- Switch allocation among the input virtual channels that have completed the previous stages.
+			//Those still waiting the `vc_allocation_delay` are counted to recheck the crossbar.
+			let mut ready_channels = vec![];
+			for exit_port in 0..self.transmission_port_status.len()
 			{
-				if let Some((entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
+				for exit_vc in 0..amount_virtual_channels
 				{
-					//-- Move phits into the internal output space
-					//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
-					if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+					if let Some((entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 					{
-						// For the check with crossbar delay look into PhitToOutput::process.
-						if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+						//With speedup the output buffer could be filled faster than it is emptied.
+						let output_full = self.crossbar_speedup>1 && self.output_buffers[exit_port][exit_vc].len() + self.output_buffer_phits_traversing_crossbar[exit_port][exit_vc] >= self.output_buffer_size;
+						if output_full || self.pipeline_ready_cycle[entry_port][entry_vc].map_or(false,|ready|ready>simulation.cycle)
 						{
-							panic!("Trying to move into a full output buffer.");
-						}
-						moved_input_phits+=1;
-						self.time_at_input_head[entry_port][entry_vc]=0;
-						*phit.virtual_channel.borrow_mut()=Some(exit_vc);
-						if let Some(message)=ack_message
-						{
-							// If the crossbar operates at higher frequency (aka internal speedup) then it would send acks at greater rate than allowed.
-							// We allow sending several events in the same cycle of the link. Acks should have few bits and be possible to be aggregated.
-							let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
-							let event = Event::Acknowledge{location:previous_location,message};
-							events.push(simulation.schedule_link_arrival( previous_link_class, event ));
-						}
-						if phit.is_end()
-						{
-							self.selected_input[exit_port][exit_vc]=None;
-							self.selected_output[entry_port][entry_vc]=None;
+							waiting_channels+=1;
 						}
 						else
 						{
-							self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
-						}
-						if self.crossbar_delay==0 {
-							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
-							let mut output_scheduler = self.output_schedulers[exit_port].borrow_mut();
-							if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
-								events.push(event);
-							}
-						} else {
-							let event = Rc::<RefCell<internal::PhitToOutput>>::from(internal::PhitToOutputArgument{
-								//router: self.self_rc.upgrade().unwrap(),
-								router: self,
-								exit_port,
-								exit_vc,
-								entry_port,
-								entry_vc,
-								phit,
-							});
-							events.push(EventGeneration{
-								delay: self.crossbar_delay,
-								position:CyclePosition::Begin,
-								event: Event::Generic(event),
-							});
+							ready_channels.push((exit_port,exit_vc,entry_port,entry_vc));
 						}
 					}
+				}
+			}
+			if let Some(switch_allocator) = self.switch_allocator.as_mut()
+			{
+				//Only the channels with a phit to send compete for the switch.
+				let reception_port_space = &self.reception_port_space;
+				ready_channels.retain(|&(_,_,entry_port,entry_vc)|reception_port_space[entry_port].front_virtual_channel(entry_vc).is_some());
+				let switch_requests=ready_channels.len();
+				total_switch_requests+=switch_requests;
+				for &(exit_port,_exit_vc,entry_port,entry_vc) in ready_channels.iter()
+				{
+					switch_requesting[entry_port][entry_vc]=true;
+					let is_speculative = speculative && granted_channels[entry_port][entry_vc];
+					switch_allocator.add_request(Request::new(entry_port,exit_port,Some(if is_speculative {1} else {0})));
+				}
+				let granted : Vec<(usize,usize)> = switch_allocator.perform_allocation(&mut mutable.rng).into_iter().map(|gr|(gr.client,gr.resource)).collect();
+				//Among the virtual channels of the input port requesting the granted output port, the one following the last served.
+				let mut switch_winners = Vec::with_capacity(granted.len());
+				for (granted_entry_port,granted_exit_port) in granted
+				{
+					let pointer = self.switch_vc_pointer[granted_entry_port];
+					let winner = ready_channels.iter().filter(|&&(exit_port,_,entry_port,_)|exit_port==granted_exit_port && entry_port==granted_entry_port)
+						.min_by_key(|&&(_,_,_,entry_vc)|(entry_vc+amount_virtual_channels-pointer)%amount_virtual_channels)
+						.cloned().expect("the switch allocator granted a request not performed");
+					self.switch_vc_pointer[granted_entry_port]=(winner.3+1)%amount_virtual_channels;
+					switch_granted[winner.2][winner.3]=true;
+					switch_winners.push(winner);
+				}
+				ready_channels.retain(|channel|switch_winners.contains(channel));
+			}
+
+			//-- Move the phits of the channels that advance this cycle.
+			for (exit_port,exit_vc,entry_port,entry_vc) in ready_channels
+			{
+				//-- Move phits into the internal output space
+				//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
+				if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+				{
+					// For the check with crossbar delay look into PhitToOutput::process.
+					if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+					{
+						panic!("Trying to move into a full output buffer.");
+					}
+					moved_input_phits+=1;
+					self.time_at_input_head[entry_port][entry_vc]=0;
+					*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+					if let Some(message)=ack_message
+					{
+						// If the crossbar operates at higher frequency (aka internal speedup) then it would send acks at greater rate than allowed.
+						// We allow sending several events in the same cycle of the link. Acks should have few bits and be possible to be aggregated.
+						let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+						let event = Event::Acknowledge{location:previous_location,message};
+						events.push(simulation.schedule_link_arrival( previous_link_class, event ));
+					}
+					if phit.is_end()
+					{
+						self.selected_input[exit_port][exit_vc]=None;
+						self.selected_output[entry_port][entry_vc]=None;
+						self.pipeline_ready_cycle[entry_port][entry_vc]=None;
+					}
 					else
 					{
-						if self.flit_size>1
-						{
-							//XXX We seem to easily reach this region when using different frequencies.
-							//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
-							//println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
+						self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
+					}
+					if self.crossbar_delay==0 {
+						self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
+						let mut output_scheduler = self.output_schedulers[exit_port].borrow_mut();
+						if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
+							events.push(event);
 						}
+					} else {
+						let event = Rc::<RefCell<internal::PhitToOutput>>::from(internal::PhitToOutputArgument{
+							//router: self.self_rc.upgrade().unwrap(),
+							router: self,
+							exit_port,
+							exit_vc,
+							entry_port,
+							entry_vc,
+							phit,
+						});
+						events.push(EventGeneration{
+							delay: self.crossbar_delay,
+							position:CyclePosition::Begin,
+							event: Event::Generic(event),
+						});
+					}
+				}
+				else
+				{
+					if self.flit_size>1
+					{
+						//XXX We seem to easily reach this region when using different frequencies.
+						//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
+						//println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
 					}
 				}
 			}
 		}
+		self.statistics_pipeline_stalls[2]+=switch_requesting.iter().flatten().zip(switch_granted.iter().flatten()).filter(|&(&requesting,&granted)|requesting && !granted).count();
 		self.next_events.pop();//remove the event that was served.
 		//TODO: what to do with probabilistic requests???
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0 || request.len()>0
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0
-		let recheck_crossbar = undecided_channels>0 || moved_input_phits>0 || request.len()>0;//Needs to check the crossbar in its next slot.
+		let recheck_crossbar = undecided_channels>0 || moved_input_phits>0 || request.len()>0 || waiting_channels>0 || total_switch_requests>0;//Needs to check the crossbar in its next slot.
 		if recheck_crossbar {
 			let next_delay = event::round_to_multiple(simulation.cycle+1,self.crossbar_frequency_divisor) - simulation.cycle;
 			if let Some(event) = self.schedule(simulation.cycle,next_delay)
@@ -1265,7 +1508,7 @@ mod internal
 					}
 					else
 					{
-						status.can_transmit(&phit,exit_vc)
+						status.can_transmit(&phit,exit_vc) && (!(phit.is_begin() && router.switching.requires_whole_packet_space()) || status.can_transmit_whole_packet(&phit,exit_vc))
 					};
 					if can_transmit
 					{
@@ -1291,7 +1534,7 @@ mod internal
 					}
 					else
 					{
-						if 0<phit.index && phit.index<router.flit_size
+						if 0<phit.index && phit.index<router.flit_size && status.is_link_available()
 						{
 							panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,router.flit_size);
 						}
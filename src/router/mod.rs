@@ -1,19 +1,21 @@
 
 pub mod basic;
 pub mod input_output;
+pub mod output_queued;
 
 use std::rc::{Rc};
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::mem::{size_of};
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap,BTreeSet};
 use ::rand::{Rng,rngs::StdRng};
 
 use quantifiable_derive::Quantifiable;//the derive macro
 
-use crate::{Phit,Packet,Plugs,error,source_location};
+use crate::{Phit,Packet,Plugs,error,source_location,match_object_panic};
 use self::basic::Basic;
 use self::input_output::InputOutput;
+use self::output_queued::OutputQueued;
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Topology};
 use crate::event::{Eventful,Time,EventGeneration};
@@ -42,16 +44,25 @@ pub trait Router: Eventful + Quantifiable
 	///Receive the acknowledge of a phit clear. Generally to increase the credit count.
 	///Should return a list of events to push into the event queue. This may include to schedule itself or a subcomponent.
 	fn acknowledge(&mut self, current_cycle:Time, port:usize, ack_message:AcknowledgeMessage) -> Vec<EventGeneration>;
+	///Mark the link at a port as failed (`available=false`) or recovered. Nothing is transmitted through a failed link.
+	///Should return a list of events to push into the event queue. This may include to schedule itself or a subcomponent.
+	fn set_link_available(&mut self, current_cycle:Time, port:usize, available:bool) -> Vec<EventGeneration>;
 	///To get the number of virtual channels the router uses.
 	fn num_virtual_channels(&self) -> usize;
 	///Get the number of phits that fit inside the buffer of a port.
 	fn virtual_port_size(&self, port:usize, virtual_channel:usize) -> usize;
 	///To iterate over the phits managed by the router. Required to account memory.
 	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>;
+	///To iterate over the phits in the input buffers of the router as `(port,phit,at_front)`, where `at_front` tells whether the phit is the next to be processed in its virtual channel, which is stored in the phit.
+	///Used to diagnose deadlocks. Routers without it report no phits.
+	fn iter_input_phits(&self) -> Box<dyn Iterator<Item=(usize,Rc<Phit>,bool)>>
+	{
+		Box::new(std::iter::empty())
+	}
 	///Get a virtual port if any.
 	///To be used in some policies, e.g., VirtualChannelPolicy::Shortest.
 	fn get_status_at_emisor(&self, port:usize) -> Option<&dyn StatusAtEmissor>;
-	///Get the maximum number of credits towards the neighbour.
+	///Get the maximum number of credits towards the neighbour. With a shared buffer it includes the shared space that the virtual channel may take.
 	///To be used in policies such as VirtualChannelPolicy::LowestSinghWeight.
 	fn get_maximum_credits_towards(&self, port:usize, virtual_channel:usize) -> Option<usize>;
 	///Get the index of the router in the topology.
@@ -67,6 +78,12 @@ pub trait Router: Eventful + Quantifiable
 	///Build a status for an element that sends packets directly to the router ports.
 	///This is intended to build the status of the servers.
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>;
+	///Whether a phit arriving at a port is discarded instead of inserted, as decided by a lossy reception space such as the one of [LossyVirtualChannels].
+	///The discarded phits are not inserted. Routers without lossy spaces never discard.
+	fn must_drop(&mut self, _port:usize, _phit:&Rc<Phit>, _rng: &mut StdRng) -> bool
+	{
+		false
+	}
 }
 
 #[non_exhaustive]
@@ -107,6 +124,7 @@ pub fn new_router(arg:RouterBuilderArgument) -> Rc<RefCell<dyn Router>>
 			//"Basic" => Basic::<SimpleVirtualChannels>::new(arg.router_index, arg.cv, arg.plugs, arg.topology, arg.maximum_packet_size),
 			"Basic" => Basic::new(arg),
 			"InputOutput" | "InputOutputMonocycle" => InputOutput::new(arg),
+			"OutputQueued" => OutputQueued::new(arg),
 			_ => panic!("Unknown router {}",cv_name),
 		}
 	}
@@ -243,8 +261,18 @@ pub trait StatusAtEmissor : Quantifiable
 	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool;
 	///Consult available space. This includes dedicated and shared space.
 	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>;
+	///The largest value that `known_available_space_for_virtual_channel` can take, when the receptor is empty.
+	///None when it is the `buffer_size` of the router, as for spaces without shared space.
+	fn maximum_available_space_for_virtual_channel(&self,_virtual_channel:usize)->Option<usize>
+	{
+		None
+	}
 	///Get timestamp of last transmission.
 	fn get_last_transmission(&self)->Time;
+	///Mark the link as failed (`available=false`) or recovered. While failed nothing can be transmitted.
+	fn set_link_available(&mut self, available:bool);
+	///Whether the link is working. Only false after a `set_link_available(false)`.
+	fn is_link_available(&self)->bool;
 }
 
 ///A structure to store incoming phits.
@@ -264,6 +292,46 @@ pub trait SpaceAtReceptor
 	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
 	///Consult current number of phits in space dedicated to a virtual channel.
 	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
+	///Whether an arriving phit is discarded instead of inserted. It is called before `insert`, which is not called for the discarded phits.
+	///Packets are discarded whole, so the decision is taken at their head. Lossless spaces never discard.
+	fn must_drop(&mut self, _phit:&Rc<Phit>, _rng: &mut StdRng) -> bool
+	{
+		false
+	}
+	///Consult current number of phits in space shared among the virtual channels. None for spaces without shared space.
+	fn occupied_shared_space(&self) -> Option<usize>
+	{
+		None
+	}
+}
+
+///Sets the ECN mark of the packet of `phit` when it is a head just inserted into `space` and its virtual channel holds at least `threshold` phits.
+///See the [congestion_control](crate::congestion_control) module.
+pub fn mark_congestion(space:&dyn SpaceAtReceptor, phit:&Phit, threshold:Option<usize>)
+{
+	if let Some(threshold) = threshold
+	{
+		if phit.is_begin()
+		{
+			let vc = phit.virtual_channel.borrow().expect("the phit has no virtual channel");
+			if space.occupied_dedicated_space(vc).unwrap_or(0) >= threshold
+			{
+				*phit.packet.congestion_experienced.borrow_mut() = true;
+			}
+		}
+	}
+}
+
+///The phits in a list of input spaces indexed by port, as given by [Router::iter_input_phits].
+pub fn input_phits(spaces:&[Box<dyn SpaceAtReceptor>]) -> Box<dyn Iterator<Item=(usize,Rc<Phit>,bool)>>
+{
+	Box::new(spaces.iter().enumerate().flat_map(|(port,space)|{
+		let fronts:Vec<Rc<Phit>>=space.front_iter().collect();
+		space.iter_phits().map(move |phit|{
+			let at_front=fronts.iter().any(|front|Rc::ptr_eq(front,&phit));
+			(port,phit,at_front)
+		})
+	}).collect::<Vec<_>>().into_iter())
 }
 
 ///A message send from the receptor to the emissor when the receptor state changes.
@@ -298,6 +366,13 @@ impl AcknowledgeMessage
 			set_available_size: Some(amount),
 		}
 	}
+	pub fn ack_available_size_in_virtual_channel(virtual_channel:usize, amount:usize)->AcknowledgeMessage
+	{
+		AcknowledgeMessage{
+			virtual_channel: Some(virtual_channel),
+			set_available_size: Some(amount),
+		}
+	}
 }
 
 
@@ -344,6 +419,10 @@ pub struct TransmissionMechanismBuilderArgument<'a>
 	virtual_channels: usize,
 	buffer_size: usize,
 	size_to_send: usize,
+	///The policy of the mechanisms that discard packets.
+	drop_policy: &'a DropPolicy,
+	///The admission policy of the mechanisms with shared space.
+	shared_buffer_policy: &'a DynamicThreshold,
 }
 
 /// Creates a transmition mechanism.
@@ -372,6 +451,8 @@ pub fn new_transmission_mechanism(arg:TransmissionMechanismBuilderArgument) -> B
 		"SimpleVirtualChannels" => Box::new(SimpleVirtualChannels::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
 		"TransmissionToServer" => Box::new(TransmissionToServer() ),
 		"TransmissionFromOblivious" => Box::new(TransmissionFromOblivious::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
+		"LossyVirtualChannels" => Box::new(LossyVirtualChannels::new(arg.virtual_channels, arg.buffer_size, arg.drop_policy.clone())),
+		"SharedBuffer" => Box::new(SharedBuffer::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send, arg.shared_buffer_policy)),
 		x => panic!("Unknown transission mechanism {}",x),
 	}
 }
@@ -387,6 +468,8 @@ struct CreditCounterVector
 	last_transmission:Time,
 	///Credits required in the next router's virtual port to begin the transmission
 	flit_size: usize,
+	///Whether the link is working. It is false while it is failed.
+	link_available: bool,
 }
 
 impl StatusAtEmissor for CreditCounterVector
@@ -410,6 +493,10 @@ impl StatusAtEmissor for CreditCounterVector
 	
 	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
 	{
+		if !self.link_available
+		{
+			return false;
+		}
 		let mut necessary_credits=1;
 		if phit.is_begin()
 		{
@@ -421,7 +508,7 @@ impl StatusAtEmissor for CreditCounterVector
 	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
 	{
 		let necessary_credits=phit.packet.size - phit.index;
-		self.neighbour_credits[virtual_channel]>=necessary_credits
+		self.link_available && self.neighbour_credits[virtual_channel]>=necessary_credits
 	}
 	
 	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
@@ -433,6 +520,16 @@ impl StatusAtEmissor for CreditCounterVector
 	{
 		self.last_transmission
 	}
+
+	fn set_link_available(&mut self, available:bool)
+	{
+		self.link_available=available;
+	}
+
+	fn is_link_available(&self)->bool
+	{
+		self.link_available
+	}
 }
 
 ///A simple collection of buffers. Normally each being dedicated to a virtual channel.
@@ -569,6 +666,7 @@ impl TransmissionMechanism for SimpleVirtualChannels
 			neighbour_credits: vec![self.buffer_size;self.virtual_channels],
 			last_transmission: 0,
 			flit_size: self.flit_size,
+			link_available: true,
 		}
 	}
 
@@ -583,7 +681,11 @@ impl TransmissionMechanism for SimpleVirtualChannels
 
 ///For senders that not care about the receptor or phantom senders that do not actually send anything.
 #[derive(Quantifiable)]
-struct EmptyStatus();
+struct EmptyStatus
+{
+	///Whether the link is working.
+	link_available: bool,
+}
 
 ///For receptors that do not require space, let it be because they consume it immediately or because they do not actually receive anything.
 struct NoSpace();
@@ -606,12 +708,12 @@ impl StatusAtEmissor for EmptyStatus
 
 	fn can_transmit(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
 	{
-		true
+		self.link_available
 	}
 	
 	fn can_transmit_whole_packet(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
 	{
-		true
+		self.link_available
 	}
 
 	fn known_available_space_for_virtual_channel(&self,_virtual_channel:usize)->Option<usize>
@@ -625,6 +727,16 @@ impl StatusAtEmissor for EmptyStatus
 		//FIXME: this is not true, but is only used for servers...
 		0
 	}
+
+	fn set_link_available(&mut self, available:bool)
+	{
+		self.link_available=available;
+	}
+
+	fn is_link_available(&self)->bool
+	{
+		self.link_available
+	}
 }
 
 impl SpaceAtReceptor for NoSpace
@@ -682,7 +794,7 @@ impl TransmissionMechanism for TransmissionToServer
 	
 	fn new_status_at_emissor(&self)-> EmptyStatus
 	{
-		EmptyStatus()
+		EmptyStatus{link_available:true}
 	}
 
 	fn new_space_at_receptor(&self)-> NoSpace
@@ -699,6 +811,8 @@ pub struct StatusAtServer
 	//buffer_size: usize,
 	available_size: usize,
 	size_to_send: usize,
+	///Whether the link is working.
+	link_available: bool,
 }
 
 impl StatusAtEmissor for StatusAtServer
@@ -729,7 +843,11 @@ impl StatusAtEmissor for StatusAtServer
 	fn can_transmit(&self, phit:&Rc<Phit>, _virtual_channel:usize)->bool
 	{
 		//if self.available_size <= 25 { dbg!("can_transmit",self.available_size, phit.index, phit.packet.size); }
-		if phit.is_begin()
+		if !self.link_available
+		{
+			false
+		}
+		else if phit.is_begin()
 		{
 			self.available_size>=self.size_to_send
 		}
@@ -753,6 +871,16 @@ impl StatusAtEmissor for StatusAtServer
 	{
 		unimplemented!()
 	}
+
+	fn set_link_available(&mut self, available:bool)
+	{
+		self.link_available=available;
+	}
+
+	fn is_link_available(&self)->bool
+	{
+		self.link_available
+	}
 }
 
 /// A mechanism to receive phits from a very simple component that does not keep track of things such as virtual channels.
@@ -886,6 +1014,7 @@ impl TransmissionMechanism for TransmissionFromOblivious
 		StatusAtServer{
 			available_size: self.buffer_size,
 			size_to_send: self.size_to_send,
+			link_available: true,
 		}
 	}
 
@@ -900,3 +1029,574 @@ impl TransmissionMechanism for TransmissionFromOblivious
 }
 
 
+
+///How a lossy reception space decides to discard a packet arriving by a link.
+#[derive(Clone,Debug)]
+pub enum DropPolicy
+{
+	///Discard the packets that do not fit in the buffer of their virtual channel.
+	TailDrop,
+	///Random Early Detection of Floyd and Jacobson. An average of the occupation of the buffer is updated with `weight` at each arriving packet. Below `minimum_threshold` phits no packet is discarded, above `maximum_threshold` all of them are, and in between with a probability growing linearly up to `maximum_probability`. Packets that do not fit are always discarded.
+	RandomEarlyDetection{
+		minimum_threshold: f64,
+		maximum_threshold: f64,
+		maximum_probability: f64,
+		weight: f64,
+	},
+}
+
+impl DropPolicy
+{
+	pub fn new(cv:&ConfigurationValue) -> DropPolicy
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="TailDrop" => DropPolicy::TailDrop,
+			_ =>
+			{
+				let mut minimum_threshold=None;
+				let mut maximum_threshold=None;
+				let mut maximum_probability=0.1;
+				let mut weight=0.002;
+				match_object_panic!(cv,"RandomEarlyDetection",value,
+					"minimum_threshold" => minimum_threshold=Some(value.as_f64().expect("bad value for minimum_threshold")),
+					"maximum_threshold" => maximum_threshold=Some(value.as_f64().expect("bad value for maximum_threshold")),
+					"maximum_probability" => maximum_probability=value.as_f64().expect("bad value for maximum_probability"),
+					"weight" => weight=value.as_f64().expect("bad value for weight"),
+				);
+				let minimum_threshold=minimum_threshold.expect("There were no minimum_threshold");
+				let maximum_threshold=maximum_threshold.expect("There were no maximum_threshold");
+				if minimum_threshold>=maximum_threshold
+				{
+					panic!("The minimum_threshold ({}) must be lower than the maximum_threshold ({})",minimum_threshold,maximum_threshold);
+				}
+				DropPolicy::RandomEarlyDetection{minimum_threshold,maximum_threshold,maximum_probability,weight}
+			},
+		}
+	}
+}
+
+///The switching technique of a router, given by `switching`. It decides when the head of a packet may leave its input buffer.
+///```ignore
+///switching: VirtualCutThrough,
+///```
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Switching
+{
+	///The head advances as soon as the next buffer has `flit_size` free phits, so a blocked packet may span several routers. The default.
+	Wormhole,
+	///The head advances only when the next buffer has room for the whole packet, as given by [StatusAtEmissor::can_transmit_whole_packet].
+	VirtualCutThrough,
+	///As virtual cut-through, but the head also waits until the whole packet has been received into its input buffer.
+	StoreAndForward,
+}
+
+impl Switching
+{
+	pub fn new(cv:&ConfigurationValue) -> Switching
+	{
+		match_object_panic!(cv,["Wormhole","VirtualCutThrough","StoreAndForward"],_value,);
+		match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="VirtualCutThrough" => Switching::VirtualCutThrough,
+			ConfigurationValue::Object(name,_) if name=="StoreAndForward" => Switching::StoreAndForward,
+			_ => Switching::Wormhole,
+		}
+	}
+	///Whether the head of a packet requires room for the whole packet in the next buffer.
+	pub fn requires_whole_packet_space(&self) -> bool
+	{
+		*self!=Switching::Wormhole
+	}
+	///Whether the `phit` at the front of a virtual channel of `space` may start to advance.
+	///Only the heads in store-and-forward must wait, until all the phits of their packet are in `space`.
+	pub fn can_head_advance(&self, space:&dyn SpaceAtReceptor, phit:&Phit) -> bool
+	{
+		if *self!=Switching::StoreAndForward || !phit.is_begin()
+		{
+			return true;
+		}
+		let packet:&Packet = phit.packet.as_ref();
+		space.iter_phits().filter(|other|std::ptr::eq(other.packet.as_ref(),packet)).count() >= phit.packet.size
+	}
+	///Checks that buffers of `buffer_size` phits can hold the whole packets required by the switching.
+	pub fn check_buffer_size(&self, name:&str, buffer_size:usize, maximum_packet_size:usize)
+	{
+		if self.requires_whole_packet_space() && buffer_size<maximum_packet_size
+		{
+			panic!("The {} ({}) must hold the largest packet ({} phits) with {:?} switching.",name,buffer_size,maximum_packet_size,self);
+		}
+	}
+}
+
+///The status of a lossy link. It never prevents transmitting, but it keeps the space last reported by the receptor, minus the phits sent since then, for the policies that consult it.
+#[derive(Quantifiable)]
+pub struct LossyStatus
+{
+	///The estimated available space in each virtual channel of the receptor.
+	known_space: Vec<usize>,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: Time,
+	///Whether the link is working. It is false while it is failed.
+	link_available: bool,
+}
+
+impl StatusAtEmissor for LossyStatus
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.known_space.len()
+	}
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		let virtual_channel = message.virtual_channel.expect("there is no virtual channel in the message");
+		self.known_space[virtual_channel] = message.set_available_size.expect("there is no set_available_size in the message");
+	}
+	fn notify_outcoming_phit(&mut self, virtual_channel: usize, cycle:Time)
+	{
+		self.known_space[virtual_channel] = self.known_space[virtual_channel].saturating_sub(1);
+		self.last_transmission=cycle;
+	}
+	fn can_transmit(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
+	{
+		self.link_available
+	}
+	fn can_transmit_whole_packet(&self, _phit:&Rc<Phit>, _virtual_channel:usize)->bool
+	{
+		self.link_available
+	}
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(self.known_space[virtual_channel])
+	}
+	fn get_last_transmission(&self)->Time
+	{
+		self.last_transmission
+	}
+	fn set_link_available(&mut self, available:bool)
+	{
+		self.link_available=available;
+	}
+	fn is_link_available(&self)->bool
+	{
+		self.link_available
+	}
+}
+
+///A buffer for each virtual channel that discards the arriving packets according to a [DropPolicy].
+pub struct LossyBuffers
+{
+	buffers: Vec<Buffer>,
+	///The size of each buffer.
+	buffer_size: usize,
+	policy: DropPolicy,
+	///The average occupation of each buffer, for `RandomEarlyDetection`.
+	average_occupation: Vec<f64>,
+	///Packets being discarded, identified by address. Removed when their last phit is discarded.
+	dropped_packets: BTreeSet<*const Packet>,
+}
+
+impl SpaceAtReceptor for LossyBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, _rng: &mut StdRng) -> Result<(),Error>
+	{
+		let vc = phit.virtual_channel.borrow().expect("a lossy link requires the emissor to select the virtual channel");
+		self.buffers[vc].push(phit);
+		Ok(())
+	}
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().filter_map(|b|b.front()).collect::<Vec<_>>().into_iter())
+	}
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.buffers[virtual_channel].front()
+	}
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),Error>
+	{
+		match self.buffers[virtual_channel].pop()
+		{
+			Some(phit) =>
+			{
+				let available = self.buffer_size - self.buffers[virtual_channel].len();
+				Ok((phit,Some(AcknowledgeMessage::ack_available_size_in_virtual_channel(virtual_channel,available))))
+			},
+			_ => Err(error!(undetermined)),
+		}
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().flat_map(|buffer|buffer.iter_phits()).collect::<Vec<_>>().into_iter())
+	}
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size - self.buffers[virtual_channel].len())
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffers[virtual_channel].len())
+	}
+	fn must_drop(&mut self, phit:&Rc<Phit>, rng: &mut StdRng) -> bool
+	{
+		let packet = phit.packet.as_ref() as *const Packet;
+		let drop = if phit.is_begin()
+		{
+			let vc = phit.virtual_channel.borrow().expect("a lossy link requires the emissor to select the virtual channel");
+			let occupation = self.buffers[vc].len();
+			//The emissor does not stop in the middle of a packet, so it must fit whole.
+			let fits = occupation + phit.packet.size <= self.buffer_size;
+			match self.policy
+			{
+				DropPolicy::TailDrop => !fits,
+				DropPolicy::RandomEarlyDetection{minimum_threshold,maximum_threshold,maximum_probability,weight} =>
+				{
+					let average = &mut self.average_occupation[vc];
+					*average = (1.0-weight) * *average + weight * occupation as f64;
+					if !fits || *average>=maximum_threshold
+					{
+						true
+					}
+					else if *average<minimum_threshold
+					{
+						false
+					}
+					else
+					{
+						let probability = maximum_probability * (*average-minimum_threshold) / (maximum_threshold-minimum_threshold);
+						rng.gen_bool(probability)
+					}
+				},
+			}
+		}
+		else
+		{
+			self.dropped_packets.contains(&packet)
+		};
+		if drop
+		{
+			if phit.is_end()
+			{
+				self.dropped_packets.remove(&packet);
+			}
+			else
+			{
+				self.dropped_packets.insert(packet);
+			}
+		}
+		drop
+	}
+}
+
+/**
+A lossy link, as in Ethernet without priority flow control. The emissor transmits without waiting for credits and the receptor has a buffer of `buffer_size` phits for each virtual channel, discarding the arriving packets according to the `drop_policy` of the router. The receptor still reports its available space when extracting phits, which the emissor uses as an estimation for the policies.
+
+It is selected in the `Basic` and `InputOutput` routers by `transmission_mechanism: "LossyVirtualChannels"`, and it can also be used from the servers by `from_server_mechanism`.
+```ignore
+Basic{
+	transmission_mechanism: "LossyVirtualChannels",
+	drop_policy: RandomEarlyDetection{minimum_threshold:16, maximum_threshold:48, maximum_probability:0.1, weight:0.002},//or TailDrop, the default
+	//...
+}
+```
+The discarded phits are accounted in the `dropped_phits` and `dropped_packets` results. To recover the discarded packets the servers may retransmit them, see `retransmission_timeout` in the [Simulation](crate::Simulation).
+**/
+pub struct LossyVirtualChannels
+{
+	virtual_channels: usize,
+	buffer_size: usize,
+	drop_policy: DropPolicy,
+}
+
+impl LossyVirtualChannels
+{
+	pub fn new(virtual_channels:usize, buffer_size:usize, drop_policy:DropPolicy) -> LossyVirtualChannels
+	{
+		LossyVirtualChannels{virtual_channels, buffer_size, drop_policy}
+	}
+}
+
+impl TransmissionMechanism for LossyVirtualChannels
+{
+	type StatusAtEmissor = LossyStatus;
+	type SpaceAtReceptor = LossyBuffers;
+
+	fn new_status_at_emissor(&self)-> LossyStatus
+	{
+		LossyStatus{
+			known_space: vec![self.buffer_size;self.virtual_channels],
+			last_transmission: 0,
+			link_available: true,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> LossyBuffers
+	{
+		LossyBuffers{
+			buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+			buffer_size: self.buffer_size,
+			policy: self.drop_policy.clone(),
+			average_occupation: vec![0.0;self.virtual_channels],
+			dropped_packets: BTreeSet::new(),
+		}
+	}
+}
+
+///The admission policy of a [SharedBuffer], given in the routers by `shared_buffer_policy`.
+///Each virtual channel has `reserved_size` phits of its own, defaulting to the `flit_size` of the router, and the remaining space is shared.
+///A virtual channel may hold in the shared region at most `alpha` times the free shared space, the dynamic threshold of Choudhury and Hahne.
+///```ignore
+///shared_buffer_policy: DynamicThreshold{reserved_size:16, alpha:1.0},
+///```
+#[derive(Clone,Debug)]
+pub struct DynamicThreshold
+{
+	reserved_size: Option<usize>,
+	alpha: f64,
+}
+
+impl Default for DynamicThreshold
+{
+	fn default() -> DynamicThreshold
+	{
+		DynamicThreshold{reserved_size:None, alpha:1.0}
+	}
+}
+
+impl DynamicThreshold
+{
+	pub fn new(cv:&ConfigurationValue) -> DynamicThreshold
+	{
+		let mut policy = DynamicThreshold::default();
+		match_object_panic!(cv,"DynamicThreshold",value,
+			"reserved_size" => policy.reserved_size=Some(value.as_usize().expect("bad value for reserved_size")),
+			"alpha" => policy.alpha=value.as_f64().expect("bad value for alpha"),
+		);
+		if policy.alpha<=0.0
+		{
+			panic!("The alpha ({}) of the DynamicThreshold must be positive",policy.alpha);
+		}
+		policy
+	}
+}
+
+///The space available to `virtual_channel` in a shared buffer of `capacity` phits holding `occupation[vc]` phits of each virtual channel.
+///It is the free reserved space plus the amount of shared space that keeps the virtual channel below the dynamic threshold.
+fn shared_buffer_available_space(occupation:&[usize], virtual_channel:usize, capacity:usize, reserved_size:usize, alpha:f64) -> usize
+{
+	let reserved_free = reserved_size.saturating_sub(occupation[virtual_channel]);
+	let shared_capacity = capacity - reserved_size*occupation.len();
+	let shared_occupied : usize = occupation.iter().map(|&o|o.saturating_sub(reserved_size)).sum();
+	let shared_free = shared_capacity - shared_occupied;
+	let own_shared = occupation[virtual_channel].saturating_sub(reserved_size);
+	//Admitting `x` more phits requires `own_shared+x <= alpha*(shared_free-x)`.
+	let threshold = ((alpha*shared_free as f64 - own_shared as f64)/(1.0+alpha)).floor();
+	let shared_available = if threshold>0.0 { (threshold as usize).min(shared_free) } else { 0 };
+	reserved_free + shared_available
+}
+
+///The status of a link towards a [SharedBuffer]. It counts the phits of each virtual channel in the receptor, including those in flight, and applies the same admission as the receptor, so it never sends a phit that the receptor could not store.
+#[derive(Quantifiable)]
+pub struct SharedBufferStatus
+{
+	///The phits sent by each virtual channel not yet acknowledged.
+	occupation: Vec<usize>,
+	///The total space of the receptor.
+	capacity: usize,
+	reserved_size: usize,
+	alpha: f64,
+	///Credits required in the receptor to begin the transmission of a packet.
+	flit_size: usize,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: Time,
+	///Whether the link is working. It is false while it is failed.
+	link_available: bool,
+}
+
+impl SharedBufferStatus
+{
+	fn available(&self, virtual_channel:usize) -> usize
+	{
+		shared_buffer_available_space(&self.occupation,virtual_channel,self.capacity,self.reserved_size,self.alpha)
+	}
+}
+
+impl StatusAtEmissor for SharedBufferStatus
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.occupation.len()
+	}
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		self.occupation[message.virtual_channel.expect("there is no virtual channel in the message")]-=1;
+	}
+	fn notify_outcoming_phit(&mut self, virtual_channel: usize, cycle:Time)
+	{
+		self.occupation[virtual_channel]+=1;
+		self.last_transmission=cycle;
+	}
+	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		let necessary_credits = if phit.is_begin() { self.flit_size } else { 1 };
+		self.link_available && self.available(virtual_channel)>=necessary_credits
+	}
+	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		self.link_available && self.available(virtual_channel)>=phit.packet.size - phit.index
+	}
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(self.available(virtual_channel))
+	}
+	///The reserved space of the virtual channel plus the part of the shared space that the dynamic threshold allows it to take.
+	fn maximum_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		let empty=vec![0;self.occupation.len()];
+		Some(shared_buffer_available_space(&empty,virtual_channel,self.capacity,self.reserved_size,self.alpha))
+	}
+	fn get_last_transmission(&self)->Time
+	{
+		self.last_transmission
+	}
+	fn set_link_available(&mut self, available:bool)
+	{
+		self.link_available=available;
+	}
+	fn is_link_available(&self)->bool
+	{
+		self.link_available
+	}
+}
+
+///A queue for each virtual channel stored in a common memory, with the reserved and shared regions of a [SharedBuffer].
+pub struct SharedBuffers
+{
+	buffers: Vec<Buffer>,
+	///The total space, for all the virtual channels.
+	capacity: usize,
+	reserved_size: usize,
+	alpha: f64,
+}
+
+impl SpaceAtReceptor for SharedBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, _rng: &mut StdRng) -> Result<(),Error>
+	{
+		let vc = phit.virtual_channel.borrow().expect("a shared buffer requires the emissor to select the virtual channel");
+		if self.total_available_space()==0
+		{
+			return Err(error!(undetermined));
+		}
+		self.buffers[vc].push(phit);
+		Ok(())
+	}
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().filter_map(|b|b.front()).collect::<Vec<_>>().into_iter())
+	}
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.buffers[virtual_channel].front()
+	}
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),Error>
+	{
+		match self.buffers[virtual_channel].pop()
+		{
+			Some(phit) => Ok((phit,Some(AcknowledgeMessage::ack_phit_clear_from_virtual_channel(virtual_channel)))),
+			_ => Err(error!(undetermined)),
+		}
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().flat_map(|buffer|buffer.iter_phits()).collect::<Vec<_>>().into_iter())
+	}
+	///The space that the virtual channel may still use, both reserved and under the dynamic threshold.
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		let occupation : Vec<usize> = self.buffers.iter().map(|b|b.len()).collect();
+		Some(shared_buffer_available_space(&occupation,virtual_channel,self.capacity,self.reserved_size,self.alpha))
+	}
+	///The phits of the virtual channel, either in its reserved region or in the shared one.
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffers[virtual_channel].len())
+	}
+	fn occupied_shared_space(&self) -> Option<usize>
+	{
+		Some(self.buffers.iter().map(|b|b.len().saturating_sub(self.reserved_size)).sum())
+	}
+}
+
+impl SharedBuffers
+{
+	fn total_available_space(&self) -> usize
+	{
+		self.capacity - self.buffers.iter().map(|b|b.len()).sum::<usize>()
+	}
+}
+
+/**
+A dynamically allocated multi-queue (DAMQ) receptor, as the shared memory of commodity switches. The `virtual_channels*buffer_size` phits of the port are a common pool, in which each virtual channel has a reserved region and may take space from the shared region under a dynamic threshold, see [DynamicThreshold].
+The emissor keeps the occupation of each virtual channel in the receptor and applies the same admission rule, so its view of the available space is never above the real one.
+
+It is selected in the `Basic` and `InputOutput` routers by `transmission_mechanism: "SharedBuffer"`, and it can also be used from the servers by `from_server_mechanism`.
+```ignore
+InputOutput{
+	transmission_mechanism: "SharedBuffer",
+	shared_buffer_policy: DynamicThreshold{reserved_size:16, alpha:2.0},//alpha defaults to 1 and reserved_size to flit_size
+	//...
+}
+```
+The routers then report the `average_shared_space_occupation` of their ports.
+**/
+pub struct SharedBuffer
+{
+	virtual_channels: usize,
+	///The size of the pool is `virtual_channels*buffer_size`.
+	buffer_size: usize,
+	reserved_size: usize,
+	alpha: f64,
+	flit_size: usize,
+}
+
+impl SharedBuffer
+{
+	pub fn new(virtual_channels:usize, buffer_size:usize, flit_size:usize, policy:&DynamicThreshold) -> SharedBuffer
+	{
+		let reserved_size = policy.reserved_size.unwrap_or(flit_size);
+		if reserved_size > buffer_size
+		{
+			panic!("The reserved_size ({}) of the shared buffer cannot exceed the buffer_size ({})",reserved_size,buffer_size);
+		}
+		SharedBuffer{virtual_channels, buffer_size, reserved_size, alpha:policy.alpha, flit_size}
+	}
+}
+
+impl TransmissionMechanism for SharedBuffer
+{
+	type StatusAtEmissor = SharedBufferStatus;
+	type SpaceAtReceptor = SharedBuffers;
+
+	fn new_status_at_emissor(&self)-> SharedBufferStatus
+	{
+		SharedBufferStatus{
+			occupation: vec![0;self.virtual_channels],
+			capacity: self.virtual_channels*self.buffer_size,
+			reserved_size: self.reserved_size,
+			alpha: self.alpha,
+			flit_size: self.flit_size,
+			last_transmission: 0,
+			link_available: true,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> SharedBuffers
+	{
+		SharedBuffers{
+			buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+			capacity: self.virtual_channels*self.buffer_size,
+			reserved_size: self.reserved_size,
+			alpha: self.alpha,
+		}
+	}
+}
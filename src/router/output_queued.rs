@@ -0,0 +1,614 @@
+
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+use std::ops::Deref;
+use std::mem::size_of;
+use std::collections::VecDeque;
+use ::rand::{rngs::StdRng,prelude::SliceRandom};
+
+use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument,DropPolicy,DynamicThreshold,Switching,mark_congestion,input_phits};
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Location,Topology};
+use crate::routing::CandidateEgress;
+use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::event::{Event,Eventful,EventGeneration,CyclePosition,Time};
+use crate::{Phit,SimulationShared,SimulationMut};
+use crate::quantify::Quantifiable;
+use crate::packet::PacketRef;
+use crate::match_object_panic;
+
+///A packet admitted into an output queue, with the phits that have already crossed the switch.
+struct OutputPacket
+{
+	packet: PacketRef,
+	///The input port from which the packet comes.
+	entry_port: usize,
+	///The input virtual channel from which the packet comes.
+	entry_vc: usize,
+	///The phits of the packet stored in the output queue and not yet transmitted.
+	phits: VecDeque<Rc<Phit>>,
+}
+
+/**
+An ideal output-queued router. Every phit at the front of an input virtual channel crosses the switch in the cycle it arrives, as with an unbounded internal speedup, so the packets only contend for the output ports.
+It is the classical upper bound of the throughput and the lower bound of the latency of the input-queued routers, as [Basic](super::basic::Basic) and [InputOutput](super::input_output::InputOutput), to evaluate allocators and buffer sizes.
+
+A packet is admitted into an output virtual channel when there is room in its queue for the whole packet, which is then reserved.
+The phits of packets from different inputs never interleave in a queue, as each packet keeps its own phits until it reaches the front of the queue.
+```ignore
+OutputQueued{
+	virtual_channels: 4,
+	virtual_channel_policies: [EnforceFlowControl,Random],
+	//The input buffer size, in phits.
+	buffer_size: 64,
+	//The size of each output queue, in phits. It must be enough for the largest packet.
+	output_buffer_size: 64,
+	flit_size: 16,
+	//Whether packets from other routers are admitted into the output queues before those from servers. Defaults to false.
+	intransit_priority: false,
+	//The optional transmission_mechanism, to_server_mechanism, from_server_mechanism, drop_policy, shared_buffer_policy, ecn_threshold and switching are as in the Basic router.
+	//With StoreAndForward a packet is admitted into an output queue once completely received.
+}
+```
+**/
+pub struct OutputQueued
+{
+	///Weak pointer to itself, see <https://users.rust-lang.org/t/making-a-rc-refcell-trait2-from-rc-refcell-trait1/16086/3>
+	self_rc: Weak<RefCell<OutputQueued>>,
+	///When is the next scheduled event. Stack with the soonner event the last.
+	next_events: Vec<Time>,
+	///The cycle number of the last time OutputQueued::process was called. Only for debugging/assertion purposes.
+	last_process_at_cycle: Option<Time>,
+	///Its index in the topology
+	router_index: usize,
+	///The mechanism to select virtual channels
+	virtual_channel_policies: Vec<Box<dyn VirtualChannelPolicy>>,
+	///Size of each input buffer.
+	buffer_size: usize,
+	///Admit in-transit packets into the output queues before packets in injection queues.
+	intransit_priority: bool,
+	///If given, the packets whose head arrives to an input buffer holding at least this number of phits get an ECN mark.
+	ecn_threshold: Option<usize>,
+	///The switching technique, deciding when the head of a packet may advance. Defaults to `Wormhole`.
+	switching: Switching,
+	/// `transmission_port_status[port] = status`
+	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	/// `reception_port_space[port] = space`
+	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
+	/// The server to router mechanism employed.
+	/// This will be used to build the status at the servers.
+	from_server_mechanism: Box<dyn AbstractTransmissionMechanism>,
+	///The size of each output queue.
+	output_buffer_size: usize,
+	///The packets in the output queues, indexed as `[output_port][output_vc]`.
+	output_queues: Vec<Vec<VecDeque<OutputPacket>>>,
+	///The space of each output queue reserved by its packets, including the phits that have not yet crossed the switch.
+	output_reserved: Vec<Vec<usize>>,
+	///If not None then the output port+virtual_channel into which the packet at the input port+virtual_channel is being moved.
+	selected_output: Vec<Vec<Option<(usize,usize)>>>,
+	///Number of cycles that the current phit, if any, in the head of a given (port,virtual channel) input buffer the phit has been waiting.
+	time_at_input_head: Vec<Vec<usize>>,
+	///The virtual channel of each output port with the highest priority, as in the `Token` arbiter of the Basic router.
+	port_token: Vec<usize>,
+
+	//statistics:
+	statistics_begin_cycle: Time,
+	///Accumulated over time, averaged per port.
+	statistics_output_buffer_occupation_per_vc: Vec<f64>,
+	///Accumulated over time, averaged per port.
+	statistics_reception_space_occupation_per_vc: Vec<f64>,
+}
+
+impl Router for OutputQueued
+{
+	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
+	{
+		self.reception_port_space[port].insert(phit.clone(),rng).expect("there was some problem on the insertion");
+		mark_congestion(self.reception_port_space[port].as_ref(),&phit,self.ecn_threshold);
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
+	fn acknowledge(&mut self, current_cycle:Time, port:usize, ack_message:AcknowledgeMessage) -> Vec<EventGeneration>
+	{
+		self.transmission_port_status[port].acknowledge(ack_message);
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
+	fn set_link_available(&mut self, current_cycle:Time, port:usize, available:bool) -> Vec<EventGeneration>
+	{
+		self.transmission_port_status[port].set_link_available(available);
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
+	fn num_virtual_channels(&self) -> usize
+	{
+		self.transmission_port_status[0].num_virtual_channels()
+	}
+	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
+	{
+		self.buffer_size
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		let input = self.reception_port_space.iter().flat_map(|space|space.iter_phits());
+		let output = self.output_queues.iter().flatten().flatten().flat_map(|output_packet|output_packet.phits.iter().cloned());
+		Box::new(input.chain(output).collect::<Vec<_>>().into_iter())
+	}
+	fn iter_input_phits(&self) -> Box<dyn Iterator<Item=(usize,Rc<Phit>,bool)>>
+	{
+		input_phits(&self.reception_port_space)
+	}
+	fn get_status_at_emisor(&self, port:usize) -> Option<&dyn StatusAtEmissor>
+	{
+		Some(&*self.transmission_port_status[port])
+	}
+	fn get_maximum_credits_towards(&self, port:usize, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.transmission_port_status[port].maximum_available_space_for_virtual_channel(virtual_channel).unwrap_or(self.buffer_size))
+	}
+	fn get_index(&self)->Option<usize>
+	{
+		Some(self.router_index)
+	}
+	fn aggregate_statistics(&self, statistics:Option<ConfigurationValue>, router_index:usize, total_routers:usize, cycle:Time) -> Option<ConfigurationValue>
+	{
+		let cycle_span = (cycle - self.statistics_begin_cycle) as f64;
+		let mut output_buffer_occupation_per_vc:Vec<f64> = self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span).collect();
+		let mut reception_space_occupation_per_vc:Vec<f64> = self.statistics_reception_space_occupation_per_vc.iter().map(|x|x/cycle_span).collect();
+		if let Some(previous)=statistics
+		{
+			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
+			{
+				if cv_name!="OutputQueued"
+				{
+					panic!("incompatible statistics, should be `OutputQueued` object not `{}`",cv_name);
+				}
+				for (name,value) in previous_pairs
+				{
+					let current = match name.as_ref()
+					{
+						"average_output_buffer_occupation_per_vc" => &mut output_buffer_occupation_per_vc,
+						"average_reception_space_occupation_per_vc" => &mut reception_space_occupation_per_vc,
+						_ => panic!("Nothing to do with field {} in OutputQueued statistics",name),
+					};
+					let previous = value.as_array().unwrap_or_else(|_|panic!("bad value for {}",name));
+					for (c,p) in current.iter_mut().zip(previous.iter())
+					{
+						*c += p.as_f64().unwrap_or_else(|_|panic!("The non-number {:?} cannot be added",p));
+					}
+				}
+			}
+			else
+			{
+				panic!("received incompatible statistics");
+			}
+		}
+		if router_index+1==total_routers
+		{
+			let factor=1f64 / total_routers as f64;
+			for x in output_buffer_occupation_per_vc.iter_mut().chain(reception_space_occupation_per_vc.iter_mut())
+			{
+				*x *= factor;
+			}
+		}
+		let result_content = vec![
+			(String::from("average_output_buffer_occupation_per_vc"),ConfigurationValue::Array(output_buffer_occupation_per_vc.into_iter().map(ConfigurationValue::Number).collect())),
+			(String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(reception_space_occupation_per_vc.into_iter().map(ConfigurationValue::Number).collect())),
+		];
+		Some(ConfigurationValue::Object(String::from("OutputQueued"),result_content))
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.statistics_begin_cycle=next_cycle;
+		for x in self.statistics_output_buffer_occupation_per_vc.iter_mut().chain(self.statistics_reception_space_occupation_per_vc.iter_mut())
+		{
+			*x=0f64;
+		}
+	}
+	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
+	{
+		if let (Location::ServerPort(_server),_link_class)=topology.neighbour(self.router_index,port)
+		{
+			self.from_server_mechanism.new_status_at_emissor()
+		}
+		else
+		{
+			unimplemented!()
+		}
+	}
+	fn must_drop(&mut self, port:usize, phit:&Rc<Phit>, rng: &mut StdRng) -> bool
+	{
+		self.reception_port_space[port].must_drop(phit,rng)
+	}
+}
+
+impl OutputQueued
+{
+	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<OutputQueued>>
+	{
+		let RouterBuilderArgument{
+			router_index,
+			cv,
+			plugs,
+			topology,
+			maximum_packet_size,
+			..
+		} = arg;
+		let mut virtual_channels=None;
+		let mut buffer_size=None;
+		let mut virtual_channel_policies=None;
+		let mut flit_size=None;
+		let mut intransit_priority=false;
+		let mut output_buffer_size=None;
+		let mut transmission_mechanism=None;
+		let mut to_server_mechanism=None;
+		let mut from_server_mechanism=None;
+		let mut drop_policy=DropPolicy::TailDrop;
+		let mut shared_buffer_policy=DynamicThreshold::default();
+		let mut ecn_threshold=None;
+		let mut switching=Switching::Wormhole;
+		match_object_panic!(cv,"OutputQueued",value,
+			"virtual_channels" => virtual_channels = Some(value.as_usize().expect("bad value for virtual_channels")),
+			"virtual_channel_policies" => virtual_channel_policies = Some(value.as_array().expect("bad value for virtual_channel_policies").iter().map(
+				|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{
+				cv,
+				plugs
+			})).collect::<Vec<_>>()),
+			"delay" => (),
+			"buffer_size" => buffer_size = Some(value.as_usize().expect("bad value for buffer_size")),
+			"output_buffer_size" => output_buffer_size = Some(value.as_usize().expect("bad value for output_buffer_size")),
+			"flit_size" => flit_size = Some(value.as_usize().expect("bad value for flit_size")),
+			"intransit_priority" => intransit_priority = value.as_bool().expect("bad value for intransit_priority"),
+			"transmission_mechanism" => transmission_mechanism = Some(value.as_str().expect("bad value for transmission_mechanism").to_string()),
+			"to_server_mechanism" => to_server_mechanism = Some(value.as_str().expect("bad value for to_server_mechanism").to_string()),
+			"from_server_mechanism" => from_server_mechanism = Some(value.as_str().expect("bad value for from_server_mechanism").to_string()),
+			"drop_policy" => drop_policy = DropPolicy::new(value),
+			"shared_buffer_policy" => shared_buffer_policy = DynamicThreshold::new(value),
+			"ecn_threshold" => ecn_threshold = Some(value.as_usize().expect("bad value for ecn_threshold")),
+			"switching" => switching = Switching::new(value),
+		);
+		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
+		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let buffer_size=buffer_size.expect("There were no buffer_size");
+		let output_buffer_size=output_buffer_size.expect("There were no output_buffer_size");
+		let flit_size=flit_size.expect("There were no flit_size");
+		if output_buffer_size<maximum_packet_size
+		{
+			panic!("The output_buffer_size ({}) of the OutputQueued router must hold the largest packet ({} phits).",output_buffer_size,maximum_packet_size);
+		}
+		switching.check_buffer_size("buffer_size",buffer_size,maximum_packet_size);
+		let ports=topology.ports(router_index);
+		let transmission_mechanism = transmission_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
+		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
+		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size,drop_policy:&drop_policy,shared_buffer_policy:&shared_buffer_policy};
+		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
+		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
+		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,..transmission_builder_argument});
+		let is_server_port = |p|matches!(topology.neighbour(router_index,p), (Location::ServerPort(_server),_link_class));
+		let transmission_port_status:Vec<Box<dyn StatusAtEmissor>> = (0..ports).map(|p|
+			if is_server_port(p) { to_server_mechanism.new_status_at_emissor() } else { transmission_mechanism.new_status_at_emissor() }
+		).collect();
+		let reception_port_space:Vec<Box<dyn SpaceAtReceptor>> = (0..ports).map(|p|
+			if is_server_port(p) { from_server_mechanism.new_space_at_receptor() } else { transmission_mechanism.new_space_at_receptor() }
+		).collect();
+		let r=Rc::new(RefCell::new(OutputQueued{
+			self_rc: Weak::new(),
+			next_events: vec![],
+			last_process_at_cycle: None,
+			router_index,
+			virtual_channel_policies,
+			buffer_size,
+			intransit_priority,
+			ecn_threshold,
+			switching,
+			transmission_port_status,
+			reception_port_space,
+			from_server_mechanism,
+			output_buffer_size,
+			output_queues: (0..ports).map(|_|(0..virtual_channels).map(|_|VecDeque::new()).collect()).collect(),
+			output_reserved: vec![vec![0;virtual_channels];ports],
+			selected_output: vec![vec![None;virtual_channels];ports],
+			time_at_input_head: vec![vec![0;virtual_channels];ports],
+			port_token: vec![0;ports],
+			statistics_begin_cycle: 0,
+			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
+			statistics_reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+		}));
+		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
+		r
+	}
+	fn gather_cycle_statistics(&mut self, cycles_span:Time)
+	{
+		let amount_virtual_channels=self.num_virtual_channels();
+		let ports = self.reception_port_space.len() as f64;
+		for vc in 0..amount_virtual_channels
+		{
+			let reception:usize = self.reception_port_space.iter().map(|space|space.occupied_dedicated_space(vc).unwrap_or(0)).sum();
+			let output:usize = self.output_queues.iter().map(|port|port[vc].iter().map(|output_packet|output_packet.phits.len()).sum::<usize>()).sum();
+			self.statistics_reception_space_occupation_per_vc[vc] += (reception*cycles_span as usize) as f64 / ports;
+			self.statistics_output_buffer_occupation_per_vc[vc] += (output*cycles_span as usize) as f64 / ports;
+		}
+	}
+}
+
+impl Eventful for OutputQueued
+{
+	///main routine of the router. Do all things that must be done in a cycle, if any.
+	fn process(&mut self, simulation:&SimulationShared, mutable:&mut SimulationMut) -> Vec<EventGeneration>
+	{
+		let mut cycles_span = 1;//cycles since last checked
+		if let Some(ref last)=self.last_process_at_cycle
+		{
+			cycles_span = simulation.cycle - *last;
+			if *last >= simulation.cycle
+			{
+				panic!("Trying to process at cycle {} a router::OutputQueued already processed at {}",simulation.cycle,last);
+			}
+		}
+		self.last_process_at_cycle = Some(simulation.cycle);
+		let topology = simulation.network.topology.as_ref();
+		let amount_virtual_channels=self.num_virtual_channels();
+		self.gather_cycle_statistics(cycles_span);
+
+		//-- Precompute whatever polcies ask for.
+		let server_ports : Option<Vec<usize>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_server_ports())
+		{
+			Some((0..topology.ports(self.router_index)).filter(|&p|
+				matches!(topology.neighbour(self.router_index,p), (Location::ServerPort(_server),_link_class))
+			).collect())
+		}
+		else
+		{
+			None
+		};
+		let port_last_transmission:Option<Vec<Time>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_port_last_transmission())
+		{
+			Some(self.transmission_port_status.iter().map(|p|p.get_last_transmission()).collect())
+		}
+		else
+		{
+			None
+		};
+
+		//-- Move every phit at the front of the input buffers into its output queue.
+		//The heads are considered in random order, the in-transit ones first if `intransit_priority`, as they compete for the space in the output queues.
+		let mut fronts:Vec<(usize,usize)> = (0..self.reception_port_space.len()).flat_map(|entry_port|
+			self.reception_port_space[entry_port].front_iter().map(move|phit|(entry_port,phit.virtual_channel.borrow().expect("it should have an associated virtual channel")))
+		).collect();
+		fronts.shuffle(&mut mutable.rng);
+		if self.intransit_priority
+		{
+			fronts.sort_by_key(|&(entry_port,_)|matches!(topology.neighbour(self.router_index,entry_port), (Location::ServerPort(_),_)));
+		}
+		let mut undecided_channels=0;//just as indicator if the router has pending work.
+		let mut moved_phits=0;//another indicator of pending work.
+		let mut events=vec![];
+		for (entry_port,entry_vc) in fronts
+		{
+			if self.selected_output[entry_port][entry_vc].is_none()
+			{
+				let phit = self.reception_port_space[entry_port].front_virtual_channel(entry_vc).expect("the front phit has vanished");
+				self.time_at_input_head[entry_port][entry_vc]+=1;
+				undecided_channels+=1;
+				if !self.switching.can_head_advance(self.reception_port_space[entry_port].as_ref(),&phit)
+				{
+					//The packet has not been completely received yet.
+					continue;
+				}
+				let target_server=phit.packet.message.destination;
+				let (target_location,_link_class)=topology.server_neighbour(target_server);
+				let target_router=match target_location
+				{
+					Location::RouterPort{router_index,router_port:_} =>router_index,
+					_ => panic!("The server is not attached to a router"),
+				};
+				let routing_candidates=simulation.routing.next(phit.packet.routing_info.borrow().deref(),topology,self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+				if routing_candidates.len()==0
+				{
+					if routing_candidates.idempotent
+					{
+						panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
+					}
+					continue;
+				}
+				//A candidate is allowed when its output queue has room for the whole packet.
+				let mut good_ports=routing_candidates.into_iter().map(|candidate|{
+					let allowed = self.output_buffer_size - self.output_reserved[candidate.port][candidate.virtual_channel] >= phit.packet.size;
+					CandidateEgress{router_allows:Some(allowed), ..candidate}
+				}).collect::<Vec<_>>();
+				let port_occupied_output_space:Vec<usize> = self.output_reserved.iter().map(|p|p.iter().sum()).collect();
+				let port_available_output_space:Vec<usize> = self.output_reserved.iter().map(|p|p.iter().map(|r|self.output_buffer_size-r).sum()).collect();
+				let virtual_channel_occupied_output_space:Vec<Vec<usize>> = self.output_reserved.clone();
+				let virtual_channel_available_output_space:Vec<Vec<usize>> = self.output_reserved.iter().map(|p|p.iter().map(|r|self.output_buffer_size-r).collect()).collect();
+				let request_info=RequestInfo{
+					target_router_index: target_router,
+					entry_port,
+					entry_virtual_channel: entry_vc,
+					performed_hops: phit.packet.routing_info.borrow().hops,
+					server_ports: server_ports.as_ref(),
+					port_average_neighbour_queue_length: None,
+					port_last_transmission: port_last_transmission.as_ref(),
+					port_occupied_output_space: Some(&port_occupied_output_space),
+					port_available_output_space: Some(&port_available_output_space),
+					virtual_channel_occupied_output_space: Some(&virtual_channel_occupied_output_space),
+					virtual_channel_available_output_space: Some(&virtual_channel_available_output_space),
+					time_at_front: Some(self.time_at_input_head[entry_port][entry_vc]),
+					current_cycle: simulation.cycle,
+					phit: phit.clone(),
+				};
+				for vcp in self.virtual_channel_policies.iter()
+				{
+					good_ports=vcp.filter(good_ports,self,&request_info,topology,&mut mutable.rng);
+					if good_ports.is_empty()
+					{
+						break;//No need to check other policies.
+					}
+				}
+				//The first allowed candidate remaining after the policies is admitted.
+				let candidate = match good_ports.into_iter().find(|candidate|candidate.router_allows!=Some(false))
+				{
+					Some(candidate) => candidate,
+					None => continue,//There is no room satisfying the policies. Hopefully there will in the future.
+				};
+				simulation.routing.performed_request(&candidate,&phit.packet.routing_info,topology,self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng);
+				let CandidateEgress{port:exit_port,virtual_channel:exit_vc,..} = candidate;
+				self.output_reserved[exit_port][exit_vc]+=phit.packet.size;
+				self.output_queues[exit_port][exit_vc].push_back(OutputPacket{packet:phit.packet.clone(),entry_port,entry_vc,phits:VecDeque::new()});
+				self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
+				undecided_channels-=1;
+			}
+			let (exit_port,exit_vc) = self.selected_output[entry_port][entry_vc].expect("the packet should have an output");
+			if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+			{
+				moved_phits+=1;
+				self.time_at_input_head[entry_port][entry_vc]=0;
+				*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+				if let Some(message)=ack_message
+				{
+					let (previous_location,previous_link_class)=topology.neighbour(self.router_index,entry_port);
+					events.push(EventGeneration{
+						delay: simulation.link_classes[previous_link_class].delay,
+						position:CyclePosition::Begin,
+						event:Event::Acknowledge{location:previous_location,message},
+					});
+				}
+				if phit.is_end()
+				{
+					self.selected_output[entry_port][entry_vc]=None;
+				}
+				//The packet being received from this input is the latest admitted from it.
+				let output_packet = self.output_queues[exit_port][exit_vc].iter_mut().rev()
+					.find(|output_packet|output_packet.entry_port==entry_port && output_packet.entry_vc==entry_vc)
+					.expect("the packet is not in its output queue");
+				if !std::ptr::eq(output_packet.packet.as_ref(),phit.packet.as_ref())
+				{
+					panic!("Mismatch between the phit extracted from input ({},{}) and the packet in the output queue ({},{}).",entry_port,entry_vc,exit_port,exit_vc);
+				}
+				output_packet.phits.push_back(phit);
+			}
+		}
+
+		//-- For each output port decide which virtual channel transmits this cycle.
+		for exit_port in 0..self.transmission_port_status.len()
+		{
+			let status=&self.transmission_port_status[exit_port];
+			//Gather the virtual channels that can advance, preferring those in the middle of a packet.
+			let mut cand=Vec::with_capacity(amount_virtual_channels);
+			let mut cand_in_transit=false;
+			for exit_vc in 0..amount_virtual_channels
+			{
+				if let Some(phit) = self.output_queues[exit_port][exit_vc].front().and_then(|output_packet|output_packet.phits.front())
+				{
+					let whole_packet = phit.is_begin() && self.switching.requires_whole_packet_space();
+					if status.can_transmit(phit,exit_vc) && (!whole_packet || status.can_transmit_whole_packet(phit,exit_vc))
+					{
+						if phit.is_begin()
+						{
+							if !cand_in_transit
+							{
+								cand.push(exit_vc);
+							}
+						}
+						else if cand_in_transit
+						{
+							cand.push(exit_vc);
+						}
+						else
+						{
+							cand=vec![exit_vc];
+							cand_in_transit=true;
+						}
+					}
+				}
+			}
+			if cand.is_empty()
+			{
+				continue;
+			}
+			//The candidate following the token.
+			let token=self.port_token[exit_port];
+			let selected_virtual_channel = cand.into_iter().min_by_key(|&vc|(vc+amount_virtual_channels-token)%amount_virtual_channels).unwrap();
+			self.port_token[exit_port]=selected_virtual_channel;
+			let queue = &mut self.output_queues[exit_port][selected_virtual_channel];
+			let output_packet = queue.front_mut().unwrap();
+			let phit = output_packet.phits.pop_front().unwrap();
+			let original_port = output_packet.entry_port;
+			self.output_reserved[exit_port][selected_virtual_channel]-=1;
+			if phit.is_end()
+			{
+				queue.pop_front();
+				self.port_token[exit_port]=(self.port_token[exit_port]+1)%amount_virtual_channels;
+			}
+			let (new_location,link_class)=topology.neighbour(self.router_index,exit_port);
+			//Send the phit to the other link endpoint.
+			events.push(EventGeneration{
+				delay: simulation.link_classes[link_class].delay,
+				position:CyclePosition::Begin,
+				event:Event::PhitToLocation{
+					phit,
+					previous: Location::RouterPort{
+						router_index: self.router_index,
+						router_port: original_port,
+					},
+					new: new_location,
+				},
+			});
+			self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+		}
+		self.next_events.pop();//remove the event that was served.
+		if undecided_channels>0 || moved_phits>0 || !events.is_empty()
+		{
+			//Repeat at next cycle
+			if let Some(event) = self.schedule(simulation.cycle,1)
+			{
+				events.push(event);
+			}
+		}
+		events
+	}
+	fn as_eventful(&self)->Weak<RefCell<dyn Eventful>>
+	{
+		self.self_rc.clone()
+	}
+	fn schedule(&mut self, current_cycle:Time, delay:Time) -> Option<EventGeneration>
+	{
+		let target = current_cycle+delay;
+		if self.next_events.is_empty() || target<*self.next_events.last().unwrap() {
+			self.next_events.push(target);
+			let event = Event::Generic(self.as_eventful().upgrade().expect("missing component"));
+			Some(EventGeneration{
+				delay: target-current_cycle,
+				position: CyclePosition::End,
+				event,
+			})
+		} else {
+			None
+		}
+	}
+}
+
+impl Quantifiable for OutputQueued
+{
+	fn total_memory(&self) -> usize
+	{
+		size_of::<OutputQueued>()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
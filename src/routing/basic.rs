@@ -12,6 +12,7 @@ Implementation of basic routing algorithms.
 
 use crate::general_pattern::pattern::Pattern;
 use std::cell::RefCell;
+use std::rc::Rc;
 use ::rand::{rngs::StdRng,Rng};
 
 use crate::{match_object_panic};
@@ -19,12 +20,15 @@ use crate::config_parser::ConfigurationValue;
 use crate::routing::prelude::*;
 use crate::topology::{Topology, Location};
 use crate::matrix::Matrix;
+use crate::router::Router;
 use crate::general_pattern::prelude::*;
 
 ///Use the shortest path from origin to destination
+///Once some link fails during the simulation it uses the shortest paths in the network without the failed links.
 #[derive(Debug)]
 pub struct Shortest
 {
+	failed_links: FailedLinks,
 }
 
 impl Routing for Shortest
@@ -56,6 +60,12 @@ impl Routing for Shortest
 			}
 			unreachable!();
 		}
+		//Avoid the failed links while they do not disconnect the routers. Otherwise wait for them to recover.
+		let (distance,avoid_failed) = match self.failed_links.distance(topology,current_router,target_router)
+		{
+			Some(distance) => (distance,true),
+			None => (distance,false),
+		};
 		let num_ports=topology.ports(current_router);
 		let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
 		for i in 0..num_ports
@@ -63,7 +73,15 @@ impl Routing for Shortest
 			//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
 			if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
 			{
-				if distance-1==topology.distance(router_index,target_router)
+				let good = if avoid_failed
+				{
+					!self.failed_links.is_failed(current_router,i) && self.failed_links.distance(topology,router_index,target_router)==Some(distance-1)
+				}
+				else
+				{
+					distance-1==topology.distance(router_index,target_router)
+				};
+				if good
 				{
 					//r.extend((0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)));
 					r.extend((0..num_virtual_channels).map(|vc|{
@@ -77,6 +95,10 @@ impl Routing for Shortest
 		//println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
 		Ok(RoutingNextCandidates{candidates:r,idempotent:true})
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, _rng: &mut StdRng)
+	{
+		self.failed_links.update(topology,router,port,available);
+	}
 }
 
 impl Shortest
@@ -85,6 +107,7 @@ impl Shortest
 	{
 		match_object_panic!(arg.cv,"Shortest",_value);
 		Shortest{
+			failed_links: FailedLinks::default(),
 		}
 	}
 }
@@ -332,6 +355,16 @@ impl Routing for Valiant
 			pattern.initialize(size,size,Some(topology),rng);
 		}
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.first.link_status_change(topology,router,port,available,rng);
+		self.second.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.first.update_global_status(routers,topology,cycle);
+		self.second.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
 		//TODO: recurse over routings
@@ -10,6 +10,7 @@ Implementations of operations on routings that modify the virtual channel to use
 */
 
 use std::cell::RefCell;
+use std::rc::Rc;
 
 use ::rand::{rngs::StdRng};
 use rand::SeedableRng;
@@ -19,6 +20,7 @@ use crate::config_parser::ConfigurationValue;
 use crate::general_pattern::{new_pattern, GeneralPatternBuilderArgument};
 use crate::topology::{new_topology, Topology, TopologyBuilderArgument};
 use crate::routing::prelude::*;
+use crate::router::Router;
 
 ///Set the virtual channels to use in each hop.
 ///Sometimes the same can be achieved by the router policy `Hops`.
@@ -55,6 +57,14 @@ impl Routing for ChannelsPerHop
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -171,6 +181,14 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -287,6 +305,14 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -366,6 +392,14 @@ impl Routing for ChannelMap
 
 		self.routing.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,self.map.len(),rng);
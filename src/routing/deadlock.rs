@@ -0,0 +1,502 @@
+/*!
+
+Offline verification of the deadlock freedom of a routing together with the virtual channel policies of the router.
+
+[verify_deadlock] walks the routes that the routing offers between pairs of servers, without simulating any traffic, and builds the channel dependency graph, in which each channel is a virtual channel of a link between routers. There is a dependency from a channel to another when a packet holding the first one may request the second one. If that graph is acyclic the combination is free of deadlock. Otherwise a cycle is reported together with the routes that create each of its dependencies.
+
+[verify_experiment] does the same for the configuration of an experiment, so that the `verify_deadlock` action can store it in the `local.deadlock` file of each experiment. The verification can be tuned by an optional `deadlock_verification` field in the configuration.
+
+```ignore
+deadlock_verification: DeadlockVerification{
+	samples: 4,//Number of walks with different random seeds for each pair of servers. Defaults to 4.
+	maximum_hops: 100,//Routes are not followed beyond this length. Defaults to 100.
+	all_servers: false,//Whether to use every server or just the first server of each router. Defaults to false.
+},
+```
+
+The routing is walked from each source to each destination. At each router the candidates returned by [Routing::next](crate::routing::Routing::next) are filtered by the `virtual_channel_policies` of the router, as in an idle network. The policies whose decision depends on the status of the router, such as `Random`, `EnforceFlowControl` or `LowestSinghWeight`, are those with [depends_on_status](crate::policies::VirtualChannelPolicy::depends_on_status) returning true, which is the default. The policies are applied until the first of them is found, since the ones after it would see a set of candidates that depends on the status. This keeps every candidate that the policies could select, so reported dependencies may include some that never happen in a simulation, but none is missed among the explored routing states. Thus the policies that break the cycles, such as `Hops`, are only taken into account when placed before `EnforceFlowControl`. Each remaining candidate is followed, calling [Routing::performed_request](crate::routing::Routing::performed_request) for it and [Routing::update_routing_info](crate::routing::Routing::update_routing_info) at the next router. The routing states already explored, identified by the router, the entry channel, the destination and the `RoutingInfo`, are not walked again. The opaque `auxiliar` field of the `RoutingInfo` is not part of that identification.
+
+Each walk is forked at every candidate, so the routes are followed in time proportional to their length. The routings storing state in the `auxiliar` field cannot be forked, and their walks are replayed from the injection instead.
+
+Random choices of the routing, as the intermediate of a Valiant routing, are covered by repeating the walks with different seeds, so a large enough `samples` is needed when the routing makes many random choices.
+
+The result is an object with the following fields.
+* `deadlock_free`, whether no cycle was found.
+* `explored_states`, `channels` and `dependencies`, the sizes of the exploration and of the graph.
+* `truncated_routes`, the number of routes that reached `maximum_hops`.
+* `dead_ends`, the number of routing states with no candidate left out of the destination router.
+* `cycle`, the list of channels `Channel{router,port,virtual_channel}` in a cycle of the graph, empty when `deadlock_free`.
+* `examples`, one `Route{source_server,destination_server,channels}` for each dependency in `cycle`, with the channels a packet crosses up to that dependency.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::{HashMap,HashSet,BTreeMap};
+use std::rc::Rc;
+
+use ::rand::{rngs::StdRng,SeedableRng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::prelude::*;
+use crate::topology::{Topology,Location};
+use crate::router::Router;
+use crate::policies::{VirtualChannelPolicy,RequestInfo,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::packet::{Packet,PacketRef,Phit,Message};
+use crate::{Plugs,Simulation};
+
+///The parameters of the deadlock verification.
+#[derive(Clone,Debug)]
+pub struct DeadlockVerificationOptions
+{
+	///Number of walks with different random seeds for each pair of servers.
+	pub samples: usize,
+	///Routes are not followed beyond this number of hops.
+	pub maximum_hops: usize,
+	///Whether to use every server instead of just the first server of each router.
+	pub all_servers: bool,
+}
+
+impl Default for DeadlockVerificationOptions
+{
+	fn default() -> DeadlockVerificationOptions
+	{
+		DeadlockVerificationOptions{
+			samples: 4,
+			maximum_hops: 100,
+			all_servers: false,
+		}
+	}
+}
+
+impl DeadlockVerificationOptions
+{
+	pub fn new(cv:&ConfigurationValue) -> DeadlockVerificationOptions
+	{
+		let mut options=DeadlockVerificationOptions::default();
+		match_object_panic!(cv,"DeadlockVerification",value,
+			"samples" => options.samples=value.as_usize().expect("bad value for samples"),
+			"maximum_hops" => options.maximum_hops=value.as_usize().expect("bad value for maximum_hops"),
+			"all_servers" => options.all_servers=value.as_bool().expect("bad value for all_servers"),
+		);
+		options
+	}
+}
+
+///A virtual channel of a link between routers, given by the router sending through it.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash,PartialOrd,Ord)]
+struct Channel
+{
+	router: usize,
+	port: usize,
+	virtual_channel: usize,
+}
+
+impl Channel
+{
+	fn to_configuration(self) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(String::from("Channel"),vec![
+			(String::from("router"),ConfigurationValue::Number(self.router as f64)),
+			(String::from("port"),ConfigurationValue::Number(self.port as f64)),
+			(String::from("virtual_channel"),ConfigurationValue::Number(self.virtual_channel as f64)),
+		])
+	}
+}
+
+///A route that creates a dependency, to be given as example.
+#[derive(Clone,Debug)]
+struct Route
+{
+	source_server: usize,
+	destination_server: usize,
+	channels: Vec<Channel>,
+}
+
+///The fields of a `RoutingInfo` that identify a routing state. The `auxiliar` field is opaque and it is not included.
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+struct RoutingInfoKey
+{
+	hops: usize,
+	routing_record: Option<Vec<i32>>,
+	selected_path: Option<Vec<usize>>,
+	selections: Option<Vec<i32>>,
+	visited_routers: Option<Vec<usize>>,
+	meta: Option<Vec<RoutingInfoKey>>,
+	source_server: Option<usize>,
+}
+
+impl RoutingInfoKey
+{
+	fn new(info:&RoutingInfo) -> RoutingInfoKey
+	{
+		RoutingInfoKey{
+			hops: info.hops,
+			routing_record: info.routing_record.clone(),
+			selected_path: info.selected_path.clone(),
+			selections: info.selections.clone(),
+			visited_routers: info.visited_routers.clone(),
+			meta: info.meta.as_ref().map(|list|list.iter().map(|meta|RoutingInfoKey::new(&meta.borrow())).collect()),
+			source_server: info.source_server,
+		}
+	}
+	///Whether the routing info can be rebuilt from its key, which requires every `auxiliar` to be empty.
+	fn can_rebuild(info:&RoutingInfo) -> bool
+	{
+		info.auxiliar.borrow().is_none() && info.meta.as_ref().map(|list|list.iter().all(|meta|RoutingInfoKey::can_rebuild(&meta.borrow()))).unwrap_or(true)
+	}
+	fn to_routing_info(&self) -> RoutingInfo
+	{
+		RoutingInfo{
+			hops: self.hops,
+			routing_record: self.routing_record.clone(),
+			selected_path: self.selected_path.clone(),
+			selections: self.selections.clone(),
+			visited_routers: self.visited_routers.clone(),
+			meta: self.meta.as_ref().map(|list|list.iter().map(|meta|RefCell::new(meta.to_routing_info())).collect()),
+			auxiliar: RefCell::new(None),
+			source_server: self.source_server,
+		}
+	}
+}
+
+///The position of a packet being walked through the network.
+struct Walk
+{
+	packet: PacketRef,
+	router: usize,
+	entry_port: usize,
+	entry_virtual_channel: usize,
+	rng: StdRng,
+}
+
+///The elements of the network required to walk the routes.
+struct Verifier<'a>
+{
+	topology: &'a dyn Topology,
+	routers: &'a [Rc<RefCell<dyn Router>>],
+	routing: &'a dyn Routing,
+	policies: &'a [Box<dyn VirtualChannelPolicy>],
+	packet_size: usize,
+}
+
+impl Verifier<'_>
+{
+	///A packet of the given message, as it would be in the network.
+	fn packet(&self, message:Rc<Message>, routing_info:RoutingInfo) -> PacketRef
+	{
+		Packet{
+			size: self.packet_size,
+			routing_info: RefCell::new(routing_info),
+			message,
+			index: 0,
+			cycle_into_network: RefCell::new(0),
+			extra: RefCell::new(None),
+			congestion_experienced: RefCell::new(false),
+		}.into_ref()
+	}
+	///Put a new packet from `source` to `destination` in its first router and follow the given choices of candidates.
+	fn replay(&self, source:usize, destination:usize, seed:u64, choices:&[usize]) -> Walk
+	{
+		let mut rng=StdRng::seed_from_u64(seed);
+		let message=Rc::new(Message{
+			origin: source,
+			destination,
+			size: self.packet_size,
+			creation_cycle: 0,
+			payload: vec![],
+			id_traffic: None,
+		});
+		let mut routing_info=RoutingInfo::new();
+		routing_info.source_server=Some(source);
+		let packet=self.packet(message,routing_info);
+		let (router,entry_port) = match self.topology.server_neighbour(source).0
+		{
+			Location::RouterPort{router_index,router_port} => (router_index,router_port),
+			_ => panic!("The server {} is not attached to a router",source),
+		};
+		let target_router=self.target_router(destination);
+		self.routing.initialize_routing_info(&packet.routing_info,self.topology,router,target_router,Some(destination),&mut rng);
+		let mut walk=Walk{packet,router,entry_port,entry_virtual_channel:0,rng};
+		for &choice in choices
+		{
+			let candidate=self.candidates(&mut walk,destination).swap_remove(choice);
+			self.advance(&mut walk,destination,&candidate);
+		}
+		walk
+	}
+	///A copy of the walk that can be advanced independently, or None when its routing info cannot be copied.
+	fn fork(&self, walk:&Walk) -> Option<Walk>
+	{
+		let routing_info=walk.packet.routing_info.borrow();
+		if !RoutingInfoKey::can_rebuild(&routing_info)
+		{
+			return None;
+		}
+		Some(Walk{
+			packet: self.packet(walk.packet.message.clone(),RoutingInfoKey::new(&routing_info).to_routing_info()),
+			router: walk.router,
+			entry_port: walk.entry_port,
+			entry_virtual_channel: walk.entry_virtual_channel,
+			rng: walk.rng.clone(),
+		})
+	}
+	///Move the packet through the given candidate, which must go to another router.
+	fn advance(&self, walk:&mut Walk, destination:usize, candidate:&CandidateEgress)
+	{
+		let target_router=self.target_router(destination);
+		let num_virtual_channels=self.routers[walk.router].borrow().num_virtual_channels();
+		self.routing.performed_request(candidate,&walk.packet.routing_info,self.topology,walk.router,target_router,Some(destination),num_virtual_channels,&mut walk.rng);
+		let (router,port) = match self.topology.neighbour(walk.router,candidate.port).0
+		{
+			Location::RouterPort{router_index,router_port} => (router_index,router_port),
+			_ => unreachable!(),
+		};
+		walk.packet.routing_info.borrow_mut().hops+=1;
+		self.routing.update_routing_info(&walk.packet.routing_info,self.topology,router,port,target_router,Some(destination),&mut walk.rng);
+		walk.router=router;
+		walk.entry_port=port;
+		walk.entry_virtual_channel=candidate.virtual_channel;
+	}
+	///The candidates offered by the routing at the current router of the walk that the policies could select.
+	fn candidates(&self, walk:&mut Walk, destination:usize) -> Vec<CandidateEgress>
+	{
+		let target_router=self.target_router(destination);
+		let router=self.routers[walk.router].borrow();
+		let num_virtual_channels=router.num_virtual_channels();
+		let candidates=match self.routing.next(&walk.packet.routing_info.borrow(),self.topology,walk.router,target_router,Some(destination),num_virtual_channels,&mut walk.rng)
+		{
+			Ok(candidates) => candidates,
+			Err(error) => panic!("The routing failed at router {} towards server {}: {}",walk.router,destination,error),
+		};
+		let mut candidates:Vec<CandidateEgress>=candidates.into_iter().map(|candidate|CandidateEgress{router_allows:Some(true),..candidate}).collect();
+		let server_ports:Vec<usize>=(0..self.topology.ports(walk.router)).filter(|&port|matches!(self.topology.neighbour(walk.router,port).0,Location::ServerPort(_))).collect();
+		let request_info=RequestInfo{
+			target_router_index: target_router,
+			entry_port: walk.entry_port,
+			entry_virtual_channel: walk.entry_virtual_channel,
+			performed_hops: walk.packet.routing_info.borrow().hops,
+			server_ports: Some(&server_ports),
+			port_average_neighbour_queue_length: None,
+			port_last_transmission: None,
+			port_occupied_output_space: None,
+			port_available_output_space: None,
+			virtual_channel_occupied_output_space: None,
+			virtual_channel_available_output_space: None,
+			time_at_front: Some(0),
+			current_cycle: 0,
+			phit: Rc::new(Phit{
+				packet: walk.packet.clone(),
+				index: 0,
+				virtual_channel: RefCell::new(Some(walk.entry_virtual_channel)),
+			}),
+		};
+		for policy in self.policies.iter()
+		{
+			if policy.depends_on_status() || candidates.is_empty()
+			{
+				break;
+			}
+			candidates=policy.filter(candidates,&*router,&request_info,self.topology,&mut walk.rng);
+		}
+		candidates
+	}
+	fn target_router(&self, server:usize) -> usize
+	{
+		match self.topology.server_neighbour(server).0
+		{
+			Location::RouterPort{router_index,..} => router_index,
+			_ => panic!("The server {} is not attached to a router",server),
+		}
+	}
+}
+
+/**
+Verify whether the routing with the given virtual channel policies is free of deadlock in the given network, returning a `DeadlockVerification` object as described in the [module documentation](self).
+The `routers` are only used to query the number of virtual channels and as idle routers for the policies. The `packet_size` is given to the packets being walked and the `seed` is combined with each pair of servers and sample to seed the walks.
+**/
+pub fn verify_deadlock(topology:&dyn Topology, routers:&[Rc<RefCell<dyn Router>>], routing:&dyn Routing, policies:&[Box<dyn VirtualChannelPolicy>], packet_size:usize, seed:u64, options:&DeadlockVerificationOptions) -> ConfigurationValue
+{
+	let verifier=Verifier{topology,routers,routing,policies,packet_size};
+	let servers:Vec<usize> = if options.all_servers
+	{
+		(0..topology.num_servers()).collect()
+	}
+	else
+	{
+		let mut seen=HashSet::new();
+		(0..topology.num_servers()).filter(|&server|seen.insert(verifier.target_router(server))).collect()
+	};
+	let mut channel_index:HashMap<Channel,usize>=HashMap::new();
+	let mut channels:Vec<Channel>=vec![];
+	//For each channel the channels it depends on, with a route creating the dependency.
+	let mut dependencies:Vec<BTreeMap<usize,Route>>=vec![];
+	let mut explored=HashSet::new();
+	let mut truncated_routes=0usize;
+	let mut dead_ends=0usize;
+	for &source in servers.iter()
+	{
+		for &destination in servers.iter()
+		{
+			if source==destination
+			{
+				continue;
+			}
+			for sample in 0..options.samples
+			{
+				let walk_seed=seed.wrapping_add((((source*topology.num_servers()+destination)*options.samples+sample) as u64).wrapping_mul(0x9e3779b97f4a7c15));
+				//Each pending state is given by the walk, the choices made since the injection to replay it if it cannot be forked, and the channels crossed.
+				let mut pending:Vec<(Walk,Vec<usize>,Vec<Channel>)>=vec![(verifier.replay(source,destination,walk_seed,&[]),vec![],vec![])];
+				while let Some((mut walk,choices,crossed))=pending.pop()
+				{
+					let candidates=verifier.candidates(&mut walk,destination);
+					if candidates.is_empty() && walk.router!=verifier.target_router(destination)
+					{
+						dead_ends+=1;
+					}
+					for (choice,candidate) in candidates.iter().enumerate()
+					{
+						if let Location::RouterPort{..}=topology.neighbour(walk.router,candidate.port).0 {} else
+						{
+							continue;
+						}
+						let channel=Channel{router:walk.router,port:candidate.port,virtual_channel:candidate.virtual_channel};
+						let index=*channel_index.entry(channel).or_insert_with(||{
+							channels.push(channel);
+							dependencies.push(BTreeMap::new());
+							channels.len()-1
+						});
+						let mut next_crossed=crossed.clone();
+						next_crossed.push(channel);
+						if let Some(previous)=crossed.last()
+						{
+							dependencies[channel_index[previous]].entry(index).or_insert_with(||Route{
+								source_server:source,
+								destination_server:destination,
+								channels:next_crossed.clone(),
+							});
+						}
+						if next_crossed.len()>=options.maximum_hops
+						{
+							truncated_routes+=1;
+							continue;
+						}
+						let mut next_choices=choices.clone();
+						next_choices.push(choice);
+						let next=match verifier.fork(&walk)
+						{
+							Some(mut next) =>
+							{
+								verifier.advance(&mut next,destination,candidate);
+								next
+							},
+							None => verifier.replay(source,destination,walk_seed,&next_choices),
+						};
+						let key=(next.router,next.entry_port,next.entry_virtual_channel,destination,RoutingInfoKey::new(&next.packet.routing_info.borrow()));
+						if explored.insert(key)
+						{
+							pending.push((next,next_choices,next_crossed));
+						}
+					}
+				}
+			}
+		}
+	}
+	let cycle=find_cycle(&dependencies.iter().map(|list|list.keys().copied().collect::<Vec<usize>>()).collect::<Vec<_>>());
+	let examples=cycle.iter().enumerate().map(|(position,&from)|{
+		let to=cycle[(position+1)%cycle.len()];
+		let route=&dependencies[from][&to];
+		ConfigurationValue::Object(String::from("Route"),vec![
+			(String::from("source_server"),ConfigurationValue::Number(route.source_server as f64)),
+			(String::from("destination_server"),ConfigurationValue::Number(route.destination_server as f64)),
+			(String::from("channels"),ConfigurationValue::Array(route.channels.iter().map(|channel|channel.to_configuration()).collect())),
+		])
+	}).collect();
+	ConfigurationValue::Object(String::from("DeadlockVerification"),vec![
+		(String::from("deadlock_free"),if cycle.is_empty() {ConfigurationValue::True} else {ConfigurationValue::False}),
+		(String::from("explored_states"),ConfigurationValue::Number(explored.len() as f64)),
+		(String::from("channels"),ConfigurationValue::Number(channels.len() as f64)),
+		(String::from("dependencies"),ConfigurationValue::Number(dependencies.iter().map(|list|list.len()).sum::<usize>() as f64)),
+		(String::from("truncated_routes"),ConfigurationValue::Number(truncated_routes as f64)),
+		(String::from("dead_ends"),ConfigurationValue::Number(dead_ends as f64)),
+		(String::from("cycle"),ConfigurationValue::Array(cycle.iter().map(|&index|channels[index].to_configuration()).collect())),
+		(String::from("examples"),ConfigurationValue::Array(examples)),
+	])
+}
+
+///Find a cycle in a directed graph, given as the list of successors of each vertex, by a depth-first search, returning its vertices in order. Returns an empty vector if the graph is acyclic.
+pub(crate) fn find_cycle(successors:&[Vec<usize>]) -> Vec<usize>
+{
+	//0 for not visited, 1 for being in the current search path, 2 for finished.
+	let mut state=vec![0u8;successors.len()];
+	for root in 0..successors.len()
+	{
+		if state[root]!=0
+		{
+			continue;
+		}
+		let mut path:Vec<(usize,Vec<usize>)>=vec![(root,successors[root].clone())];
+		state[root]=1;
+		while let Some((vertex,remaining))=path.last_mut()
+		{
+			match remaining.pop()
+			{
+				Some(next) => match state[next]
+				{
+					0 =>
+					{
+						state[next]=1;
+						path.push((next,successors[next].clone()));
+					},
+					1 =>
+					{
+						let start=path.iter().position(|(other,_)|*other==next).expect("vertex in path");
+						return path[start..].iter().map(|(other,_)|*other).collect();
+					},
+					_ => (),
+				},
+				None =>
+				{
+					state[*vertex]=2;
+					path.pop();
+				},
+			}
+		}
+	}
+	vec![]
+}
+
+/**
+Verify the deadlock freedom of the routing and virtual channel policies of an experiment configuration. The network is built as for a simulation, and the `deadlock_verification` field is read if present.
+**/
+pub fn verify_experiment(configuration:&ConfigurationValue, plugs:&Plugs) -> ConfigurationValue
+{
+	let mut options=DeadlockVerificationOptions::default();
+	let mut policies:Vec<Box<dyn VirtualChannelPolicy>>=vec![];
+	if let ConfigurationValue::Object(_,pairs)=configuration
+	{
+		for (name,value) in pairs.iter()
+		{
+			match name.as_ref()
+			{
+				"deadlock_verification" => options=DeadlockVerificationOptions::new(value),
+				"router" => if let ConfigurationValue::Object(_,router_pairs)=value
+				{
+					if let Some((_,list))=router_pairs.iter().find(|(router_name,_)|router_name=="virtual_channel_policies")
+					{
+						policies=list.as_array().expect("bad value for virtual_channel_policies").iter().map(|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{cv,plugs})).collect();
+					}
+				},
+				_ => (),
+			}
+		}
+	}
+	else
+	{
+		panic!("Trying to verify an experiment from a non-Object");
+	}
+	let simulation=Simulation::new(configuration,plugs);
+	let network=&simulation.shared.network;
+	verify_deadlock(network.topology.as_ref(),&network.routers,simulation.shared.routing.as_ref(),&policies,simulation.shared.maximum_packet_size,simulation.seed as u64,&options)
+}
+
@@ -12,6 +12,7 @@ Extra implementations of routing operations
 
 use std::default::Default;
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::collections::HashSet;
 use std::convert::{TryFrom};
 use std::ops::Deref;
@@ -27,6 +28,7 @@ use crate::general_pattern::many_to_many_pattern::{ManyToManyParam, ManyToManyPa
 use crate::general_pattern::many_to_many_pattern::filters::{RandomFilter};
 use crate::general_pattern::pattern::Pattern;
 use crate::routing::*;
+use crate::router::Router;
 use crate::topology::prelude::*;
 //use crate::topology::{Topology,Location};
 
@@ -276,6 +278,16 @@ impl Routing for SumRouting
 		self.routing[0].initialize(topology,rng);
 		self.routing[1].initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing[0].link_status_change(topology,router,port,available,rng);
+		self.routing[1].link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing[0].update_global_status(routers,topology,cycle);
+		self.routing[1].update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		use sum_routing_internal::{SumRoutingSelection,SumRoutingCase::*};
@@ -504,6 +516,14 @@ impl Routing for Stubborn
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let &CandidateEgress{port,virtual_channel,ref annotation,..} = requested;
@@ -750,6 +770,18 @@ impl Routing for AdaptiveStart
 			}
 		}
 	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.routing.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.routing.update_global_status(routers,topology,cycle);
+	}
 }
 
 impl AdaptiveStart
@@ -966,6 +998,17 @@ impl Routing for SubTopologyRouting
 		}
 	}
 
+	fn link_status_change(&mut self, topology: &dyn Topology, router: usize, port: usize, available: bool, rng: &mut StdRng) {
+		//Only the links of the logical topology matter to the logical routing.
+		let Location::RouterPort {router_index: neighbour, router_port:_} = topology.neighbour(router, port).0 else { return };
+		let logical_router = self.physical_to_logical[router];
+		let logical_neighbour = self.physical_to_logical[neighbour];
+		if let Some(item) = self.logical_topology.neighbour_router_iter(logical_router).find(|item| item.neighbour_router == logical_neighbour)
+		{
+			self.logical_routing.link_status_change(self.logical_topology.as_ref(), logical_router, item.port_index, available, rng);
+		}
+	}
+
 	fn initialize(&mut self, topology: &dyn Topology, rng: &mut StdRng) {
 
 		self.map.initialize(self.logical_topology.num_routers(), self.logical_topology.num_routers(), Some(self.logical_topology.as_ref()), rng);
@@ -1236,6 +1279,30 @@ impl Routing for RegionRouting
 		}
 	}
 
+	fn link_status_change(&mut self, topology: &dyn Topology, router: usize, port: usize, available: bool, rng: &mut StdRng) {
+		self.default_routing.link_status_change(topology, router, port, available, rng);
+		//Tell the routings of the regions containing the link, with the link in their logical topology.
+		let Location::RouterPort {router_index: neighbour, router_port:_} = topology.neighbour(router, port).0 else { return };
+		for (i, routing) in self.routings.iter_mut().enumerate()
+		{
+			let (ptlv, ltpv) = (&self.physical_to_logical_vector[i], &self.logical_to_physical_vector[i]);
+			if ltpv[ptlv[router]] == router && ltpv[ptlv[neighbour]] == neighbour
+			{
+				let logical_router = ptlv[router];
+				let logical_neighbour = ptlv[neighbour];
+				if let Some(item) = self.region_logical_topology[i].neighbour_router_iter(logical_router).find(|item| item.neighbour_router == logical_neighbour)
+				{
+					routing.link_status_change(self.region_logical_topology[i].as_ref(), logical_router, item.port_index, available, rng);
+				}
+			}
+		}
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.default_routing.update_global_status(routers,topology,cycle);
+		//The routings of the regions work on their logical topologies, whose ports do not match those of the routers.
+	}
+
 	fn initialize(&mut self, topology: &dyn Topology, rng: &mut StdRng) {
 		for (i, pat) in self.physical_to_logical.iter_mut().enumerate() {
 			pat.initialize(topology.num_routers(), self.selected_region_size[i], Some(topology), rng);
@@ -1989,6 +2056,38 @@ impl Routing for RoutingOperations
 			routing.initialize(topology, rng);
 		}
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.principal_routing.link_status_change(topology,router,port,available,rng);
+		for routing in self.intersection_routings.iter_mut()
+		{
+			routing.link_status_change(topology,router,port,available,rng);
+		}
+		for routing in self.difference_routings.iter_mut()
+		{
+			routing.link_status_change(topology,router,port,available,rng);
+		}
+		for routing in self.sum_routings.iter_mut()
+		{
+			routing.link_status_change(topology,router,port,available,rng);
+		}
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.principal_routing.update_global_status(routers,topology,cycle);
+		for routing in self.intersection_routings.iter_mut()
+		{
+			routing.update_global_status(routers,topology,cycle);
+		}
+		for routing in self.difference_routings.iter_mut()
+		{
+			routing.update_global_status(routers,topology,cycle);
+		}
+		for routing in self.sum_routings.iter_mut()
+		{
+			routing.update_global_status(routers,topology,cycle);
+		}
+	}
 }
 
 impl RoutingOperations
@@ -0,0 +1,437 @@
+/*!
+
+Source routing keeping the packets of each flow in the same path, changing it only between flowlets.
+
+* FlowletRouting
+
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ::rand::{rngs::StdRng,Rng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::router::Router;
+use crate::routing::prelude::*;
+use crate::routing::{read_port_occupation,flow_hash};
+use crate::topology::{Topology,Location};
+
+///How `FlowletRouting` chooses the path of a new flowlet.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum FlowletSelection
+{
+	///By a hash of the flow and the number of the flowlet, as ECMP.
+	Hash,
+	///The path with the least phits in the queues of its ports, as read from the status of every router.
+	LeastLoaded,
+}
+
+///The state of a flow, that is, of the packets from a source server to a target server.
+#[derive(Debug)]
+struct Flow
+{
+	///The routers of the path of the current flowlet.
+	path: Vec<usize>,
+	///The cycle in which the last packet of the flow was injected.
+	last_cycle: Time,
+	///Number of flowlets of the flow.
+	flowlets: usize,
+	///Sequence number to give to the next packet.
+	next_sequence: usize,
+	///Largest sequence number of the packets arrived to the target router.
+	arrived_sequence: Option<usize>,
+}
+
+///Counters reported by `FlowletRouting::statistics`.
+#[derive(Debug,Default)]
+struct FlowletCounters
+{
+	packets: usize,
+	flowlets: usize,
+	path_changes: usize,
+	out_of_order_packets: usize,
+}
+
+/**
+Source routing for flows, in which the packets from a source server to a target server follow the same path while they are sent close in time. When a packet is injected after more than `flowlet_timeout` cycles since the previous packet of its flow, a new flowlet begins and its path is chosen again among up to `paths` minimal paths between the routers, by a hash of the flow and flowlet (`Hash`, as in ECMP) or by the least occupied queues along the path (`LeastLoaded`). The occupation is read from the status of all the routers every `update_period` cycles. The flows are tracked in a table by the origin and destination servers of the messages.
+
+```ignore
+FlowletRouting{
+	paths: 8,//optional, defaults to 8
+	flowlet_timeout: 100,
+	selection: Hash,//or LeastLoaded
+	//update_period: 1,//optional, for LeastLoaded
+	legend_name: "flowlet switching",
+}
+```
+
+The paths are not changed to avoid deadlock, so a suitable virtual channel management should be added. The results include a `FlowletStatistics` object with the number of `flows`, injected `packets`, `flowlets`, `path_changes` among the flowlets, and `out_of_order_packets` reaching their target router after a later packet of their flow, together with their fraction `out_of_order_rate`.
+*/
+#[derive(Debug)]
+pub struct FlowletRouting
+{
+	///Maximum number of minimal paths considered between each pair of routers.
+	paths: usize,
+	///Idle cycles of a flow after which its path may change.
+	flowlet_timeout: Time,
+	selection: FlowletSelection,
+	///Cycles between reads of the routers status for `LeastLoaded`.
+	update_period: Time,
+	///The current cycle, as given by `update_global_status`.
+	cycle: Time,
+	///For `LeastLoaded`, the phits occupying the queues beyond each port, as `occupation[router][port]`.
+	occupation: Vec<Vec<usize>>,
+	///Seed of the `Hash` selection, drawn in `initialize`.
+	hash_seed: u64,
+	///The flows indexed by `(source_server,target_server)`.
+	flows: RefCell<HashMap<(usize,usize),Flow>>,
+	counters: RefCell<FlowletCounters>,
+}
+
+//routing_info.selected_path has the routers of the path.
+//routing_info.selections has the sequence number of the packet in its flow.
+impl Routing for FlowletRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for port in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,port)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(port,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let path=routing_info.selected_path.as_ref().expect("FlowletRouting requires a selected path");
+		let next_router=path[routing_info.hops+1];
+		let remain=path.len()-1-routing_info.hops;
+		let mut candidates=vec![];
+		for port in 0..topology.ports(current_router)
+		{
+			if let (Location::RouterPort{router_index,..},_link_class)=topology.neighbour(current_router,port)
+			{
+				if router_index==next_router
+				{
+					candidates.extend((0..num_virtual_channels).map(|vc|{
+						let mut egress=CandidateEgress::new(port,vc);
+						egress.estimated_remaining_hops=Some(remain);
+						egress
+					}));
+				}
+			}
+		}
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if current_router==target_router
+		{
+			return;
+		}
+		let mut bri=routing_info.borrow_mut();
+		let source_server=bri.source_server.expect("FlowletRouting requires the source server");
+		let target_server=target_server.expect("FlowletRouting requires the target server");
+		let mut flows=self.flows.borrow_mut();
+		let mut counters=self.counters.borrow_mut();
+		counters.packets+=1;
+		let flow=flows.entry((source_server,target_server)).or_insert_with(||Flow{
+			path: vec![],
+			last_cycle: 0,
+			flowlets: 0,
+			next_sequence: 0,
+			arrived_sequence: None,
+		});
+		if flow.path.is_empty() || self.cycle-flow.last_cycle>self.flowlet_timeout
+		{
+			let candidates=minimal_paths(topology,current_router,target_router,self.paths);
+			let start=(flow_hash(self.hash_seed,&[source_server,target_server,flow.flowlets]) % candidates.len() as u64) as usize;
+			let chosen=match self.selection
+			{
+				FlowletSelection::Hash => start,
+				FlowletSelection::LeastLoaded => (0..candidates.len()).map(|offset|(start+offset)%candidates.len()).min_by_key(|&index|self.path_load(topology,&candidates[index])).expect("there are no paths"),
+			};
+			counters.flowlets+=1;
+			if !flow.path.is_empty() && flow.path!=candidates[chosen]
+			{
+				counters.path_changes+=1;
+			}
+			flow.path=candidates[chosen].clone();
+			flow.flowlets+=1;
+		}
+		flow.last_cycle=self.cycle;
+		bri.selected_path=Some(flow.path.clone());
+		bri.selections=Some(vec![flow.next_sequence as i32]);
+		flow.next_sequence+=1;
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, _current_port:usize, target_router:usize, target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if current_router!=target_router
+		{
+			return;
+		}
+		let bri=routing_info.borrow();
+		if let (Some(source_server),Some(target_server),Some(selections))=(bri.source_server,target_server,bri.selections.as_ref())
+		{
+			let sequence=selections[0] as usize;
+			if let Some(flow)=self.flows.borrow_mut().get_mut(&(source_server,target_server))
+			{
+				match flow.arrived_sequence
+				{
+					Some(arrived) if arrived>sequence => self.counters.borrow_mut().out_of_order_packets+=1,
+					_ => flow.arrived_sequence=Some(sequence),
+				}
+			}
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.hash_seed=rng.gen();
+		if self.selection==FlowletSelection::LeastLoaded
+		{
+			self.occupation=(0..topology.num_routers()).map(|router|vec![0;topology.ports(router)]).collect();
+		}
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.cycle=cycle;
+		if self.selection!=FlowletSelection::LeastLoaded || cycle%self.update_period!=0
+		{
+			return;
+		}
+		read_port_occupation(&mut self.occupation,routers,topology);
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let counters=self.counters.borrow();
+		let out_of_order_rate = if counters.packets>0 { counters.out_of_order_packets as f64/counters.packets as f64 } else { 0.0 };
+		Some(ConfigurationValue::Object(String::from("FlowletStatistics"),vec![
+			(String::from("flows"),ConfigurationValue::Number(self.flows.borrow().len() as f64)),
+			(String::from("packets"),ConfigurationValue::Number(counters.packets as f64)),
+			(String::from("flowlets"),ConfigurationValue::Number(counters.flowlets as f64)),
+			(String::from("path_changes"),ConfigurationValue::Number(counters.path_changes as f64)),
+			(String::from("out_of_order_packets"),ConfigurationValue::Number(counters.out_of_order_packets as f64)),
+			(String::from("out_of_order_rate"),ConfigurationValue::Number(out_of_order_rate)),
+		]))
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.counters.borrow_mut()=FlowletCounters::default();
+	}
+}
+
+impl FlowletRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> FlowletRouting
+	{
+		let mut paths=8;
+		let mut flowlet_timeout=None;
+		let mut selection=None;
+		let mut update_period=1;
+		match_object_panic!(arg.cv,"FlowletRouting",value,
+			"paths" => paths=value.as_usize().expect("bad value for paths"),
+			"flowlet_timeout" => flowlet_timeout=Some(value.as_time().expect("bad value for flowlet_timeout")),
+			"selection" => selection=Some(match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Hash" => FlowletSelection::Hash,
+				ConfigurationValue::Object(name,_) if name=="LeastLoaded" => FlowletSelection::LeastLoaded,
+				_ => panic!("bad value for selection, it must be Hash or LeastLoaded"),
+			}),
+			"update_period" => update_period=value.as_time().expect("bad value for update_period"),
+		);
+		if paths==0
+		{
+			panic!("FlowletRouting requires some path");
+		}
+		if update_period==0
+		{
+			panic!("update_period must be positive");
+		}
+		FlowletRouting{
+			paths,
+			flowlet_timeout: flowlet_timeout.expect("There were no flowlet_timeout"),
+			selection: selection.expect("There were no selection"),
+			update_period,
+			cycle: 0,
+			occupation: vec![],
+			hash_seed: 0,
+			flows: RefCell::new(HashMap::new()),
+			counters: RefCell::new(FlowletCounters::default()),
+		}
+	}
+	///The phits in the queues of the ports along a path.
+	fn path_load(&self, topology:&dyn Topology, path:&[usize]) -> usize
+	{
+		path.windows(2).map(|pair|{
+			(0..topology.ports(pair[0])).find(|&port|matches!(topology.neighbour(pair[0],port).0,Location::RouterPort{router_index,..} if router_index==pair[1])).map(|port|self.occupation[pair[0]][port]).unwrap_or(0)
+		}).sum()
+	}
+}
+
+///Up to `amount` minimal paths from `source` to `target`, as lists of routers, in depth-first order.
+pub fn minimal_paths(topology:&dyn Topology, source:usize, target:usize, amount:usize) -> Vec<Vec<usize>>
+{
+	let mut paths=vec![];
+	let mut path=vec![source];
+	//The neighbours still to be tried at each position of the path.
+	let mut pending:Vec<Vec<usize>>=vec![closer_neighbours(topology,source,target)];
+	while let Some(options)=pending.last_mut()
+	{
+		let current=*path.last().unwrap();
+		if current==target
+		{
+			paths.push(path.clone());
+			if paths.len()==amount
+			{
+				break;
+			}
+			pending.pop();
+			path.pop();
+			continue;
+		}
+		match options.pop()
+		{
+			Some(next) =>
+			{
+				path.push(next);
+				pending.push(closer_neighbours(topology,next,target));
+			},
+			None =>
+			{
+				pending.pop();
+				path.pop();
+			},
+		}
+	}
+	paths
+}
+
+///The neighbours of `router` one hop closer to `target`, in reverse port order, to be popped.
+fn closer_neighbours(topology:&dyn Topology, router:usize, target:usize) -> Vec<usize>
+{
+	let distance=topology.distance(router,target);
+	let mut neighbours:Vec<usize>=topology.neighbour_router_iter(router).map(|item|item.neighbour_router).filter(|&neighbour|distance>0 && topology.distance(neighbour,target)+1==distance).collect();
+	neighbours.dedup();
+	neighbours.reverse();
+	neighbours
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::Plugs;
+	use rand::SeedableRng;
+	use crate::topology::cartesian::Hamming;
+	fn flowlet(selection:&str) -> FlowletRouting
+	{
+		let plugs=Plugs::default();
+		let cv=ConfigurationValue::Object(String::from("FlowletRouting"),vec![
+			(String::from("flowlet_timeout"),ConfigurationValue::Number(10.0)),
+			(String::from("selection"),ConfigurationValue::Object(selection.to_string(),vec![])),
+		]);
+		FlowletRouting::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs})
+	}
+	fn hamming() -> Hamming
+	{
+		let cv=ConfigurationValue::Object(String::from("Hamming"),vec![
+			(String::from("sides"),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			(String::from("servers_per_router"),ConfigurationValue::Number(1.0)),
+		]);
+		Hamming::new(&cv)
+	}
+	///Inject a packet from server/router 0 to server/router 5, returning its routing info.
+	fn inject(routing:&FlowletRouting, topology:&Hamming, rng:&mut StdRng) -> RefCell<RoutingInfo>
+	{
+		let mut info=RoutingInfo::new();
+		info.source_server=Some(0);
+		let info=RefCell::new(info);
+		routing.initialize_routing_info(&info,topology,0,5,Some(5),rng);
+		info
+	}
+	#[test]
+	fn minimal_paths_hamming()
+	{
+		let topology=hamming();
+		//Routers 0 and 5 differ in both coordinates, so there are two minimal paths.
+		let paths=minimal_paths(&topology,0,5,8);
+		assert_eq!(paths.len(),2);
+		for path in paths.iter()
+		{
+			assert_eq!(path.len(),3);
+			assert_eq!((path[0],path[2]),(0,5));
+		}
+		assert_eq!(minimal_paths(&topology,0,5,1).len(),1);
+	}
+	#[test]
+	fn flowlets_and_reordering()
+	{
+		let topology=hamming();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=flowlet("Hash");
+		routing.initialize(&topology,&mut rng);
+		//Packets close in time keep the path.
+		let first=inject(&routing,&topology,&mut rng);
+		routing.cycle=5;
+		let second=inject(&routing,&topology,&mut rng);
+		assert_eq!(first.borrow().selected_path,second.borrow().selected_path);
+		//After the timeout a new flowlet begins.
+		routing.cycle=100;
+		let third=inject(&routing,&topology,&mut rng);
+		let counters=routing.counters.borrow();
+		assert_eq!((counters.packets,counters.flowlets),(3,2));
+		drop(counters);
+		//The second packet arrives before the first one.
+		for info in [&second,&first,&third]
+		{
+			let path=info.borrow().selected_path.clone().unwrap();
+			info.borrow_mut().hops=2;
+			routing.update_routing_info(info,&topology,path[2],0,5,Some(5),&mut rng);
+		}
+		assert_eq!(routing.counters.borrow().out_of_order_packets,1);
+	}
+	#[test]
+	fn hash_depends_on_seed()
+	{
+		let topology=hamming();
+		//The same seed gives the same path.
+		let paths:Vec<_>=[10u64,10].iter().map(|&seed|{
+			let mut rng=StdRng::seed_from_u64(seed);
+			let mut routing=flowlet("Hash");
+			routing.initialize(&topology,&mut rng);
+			inject(&routing,&topology,&mut rng).borrow().selected_path.clone()
+		}).collect();
+		assert_eq!(paths[0],paths[1]);
+		//Some seed changes the path among the two minimal ones.
+		let seeds:Vec<u64>=(0..16u64).map(|seed|{
+			let mut rng=StdRng::seed_from_u64(seed);
+			let mut routing=flowlet("Hash");
+			routing.initialize(&topology,&mut rng);
+			routing.hash_seed
+		}).collect();
+		assert!(seeds.iter().any(|&seed|flow_hash(seed,&[0,5,0])%2 != flow_hash(seeds[0],&[0,5,0])%2));
+	}
+	#[test]
+	fn least_loaded_avoids_occupied_path()
+	{
+		let topology=hamming();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=flowlet("LeastLoaded");
+		routing.initialize(&topology,&mut rng);
+		let paths=minimal_paths(&topology,0,5,8);
+		//Congest the first hop of the first path.
+		let port=(0..topology.ports(0)).find(|&port|matches!(topology.neighbour(0,port).0,Location::RouterPort{router_index,..} if router_index==paths[0][1])).unwrap();
+		routing.occupation[0][port]=50;
+		let info=inject(&routing,&topology,&mut rng);
+		assert_eq!(info.borrow().selected_path.as_ref(),Some(&paths[1]));
+	}
+}
@@ -13,16 +13,29 @@ pub mod basic;
 pub mod extra;
 /// Contains ChannelsPerHop, ChannelsPerHopPerLinkClass, ChannelMap, AscendantChannelsWithLinkClass.
 pub mod channel_operations;
-/// Contains UpDown, UpDownStar.
+/// Contains UpDown, UpDownStar, MultipleUpDown, FatTreeRouting.
 pub mod updown;
 ///Contains polarized routing.
 pub mod polarized;
+///Contains UGAL.
+pub mod ugal;
+///Contains TableRouting.
+pub mod table;
+///Contains FlowletRouting.
+pub mod flowlet;
+///Contains the turn model routings WestFirst, NorthLast, NegativeFirst and OddEven.
+pub mod turn_model;
+///Contains SegmentBasedRouting.
+pub mod segment;
+pub mod deadlock;
 
 use crate::topology::cartesian::GeneralDOR;
 use crate::topology::dragonfly::DragonflyDirect;
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::fmt::Debug;
 use std::convert::TryFrom;
+use std::collections::{BTreeSet,VecDeque};
 
 use ::rand::{prelude::SliceRandom, rngs::StdRng, Rng};
 
@@ -30,12 +43,14 @@ use crate::config_parser::ConfigurationValue;
 use crate::topology::cartesian::{AdaptiveValiantClos, DimWAR, OmniDimensionalDeroute, Valiant4Hamming, ValiantDOR, DOR, GENERALTURN, O1TURN};
 use crate::topology::dragonfly::{Valiant4Dragonfly, PAR};
 use crate::topology::{Location, Topology};
+use crate::matrix::Matrix;
 pub use crate::event::Time;
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::{Plugs};
 pub use crate::error::Error;
 use crate::general_pattern::{new_many_to_many_pattern};
 use crate::topology::megafly::MegaflyAD;
+use crate::router::Router;
 use crate::topology::multistage::UpDownDerouting;
 
 pub use self::basic::*;
@@ -43,10 +58,15 @@ pub use self::extra::*;
 pub use self::channel_operations::*;
 pub use self::updown::*;
 pub use self::polarized::Polarized;
+pub use self::ugal::UGAL;
+pub use self::table::TableRouting;
+pub use self::flowlet::FlowletRouting;
+pub use self::turn_model::TurnModel;
+pub use self::segment::SegmentBasedRouting;
 
 pub mod prelude
 {
-	pub use super::{new_routing, CandidateEgress, Error, Routing, RoutingBuilderArgument, RoutingInfo, RoutingNextCandidates, Time};
+	pub use super::{new_routing, CandidateEgress, Error, FailedLinks, Routing, RoutingBuilderArgument, RoutingInfo, RoutingNextCandidates, Time};
 }
 
 ///Information stored in the packet for the `Routing` algorithms to operate.
@@ -197,6 +217,11 @@ pub trait Routing : Debug
 	fn update_routing_info(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _current_port:usize, _target_router:usize, _target_server:Option<usize>,_rng: &mut StdRng) {}
 	///Prepares the routing to be utilized. Perhaps by precomputing routing tables.
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng) {}
+	///Notifies that the link at `port` of `router` has failed (`available=false`) or recovered during the simulation. Routings with precomputed tables may update them here.
+	///Routings built over other routings must forward it to them.
+	fn link_status_change(&mut self, _topology:&dyn Topology, _router:usize, _port:usize, _available:bool, _rng: &mut StdRng) {}
+	///Receives the routers at the beginning of each cycle, for routings that assume global knowledge of the status of the network.
+	fn update_global_status(&mut self, _routers:&[Rc<RefCell<dyn Router>>], _topology:&dyn Topology, _cycle:Time) {}
 	///To be called by the router when one of the candidates is requested.
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng) {}
 	///To optionally write routing statistics into the simulation output.
@@ -215,6 +240,134 @@ pub struct RoutingBuilderArgument<'a>
 	pub plugs: &'a Plugs,
 }
 
+///The links that have failed during the simulation, as notified through [Routing::link_status_change].
+///Routings keep one to avoid the failed links, either by the distances in the network without them or by discarding candidates through them.
+#[derive(Debug,Default)]
+pub struct FailedLinks
+{
+	///Both ends `(router,port)` of every failed link.
+	ends: BTreeSet<(usize,usize)>,
+	///The distances between routers without using the failed links. None while there are no failed links.
+	distances: Option<Matrix<Option<usize>>>,
+}
+
+impl FailedLinks
+{
+	///Records the new status of the link at `port` of `router`, and of its other end.
+	pub fn update(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool)
+	{
+		let mut ends=vec![(router,port)];
+		if let (Location::RouterPort{router_index,router_port},_)=topology.neighbour(router,port)
+		{
+			ends.push((router_index,router_port));
+		}
+		for end in ends
+		{
+			if available
+			{
+				self.ends.remove(&end);
+			}
+			else
+			{
+				self.ends.insert(end);
+			}
+		}
+		self.distances = if self.ends.is_empty() { None } else { Some(self.compute_distances(topology)) };
+	}
+	pub fn is_failed(&self, router:usize, port:usize) -> bool
+	{
+		self.ends.contains(&(router,port))
+	}
+	///The distance from `origin` to `target` without using the failed links, or None if the failed links disconnect them.
+	pub fn distance(&self, topology:&dyn Topology, origin:usize, target:usize) -> Option<usize>
+	{
+		match self.distances
+		{
+			Some(ref distances) => *distances.get(origin,target),
+			None => Some(topology.distance(origin,target)),
+		}
+	}
+	///Removes the candidates going through failed links. When all of them go through failed links they are all kept, so the packet waits for some link to recover.
+	pub fn filter(&self, router:usize, candidates:Vec<CandidateEgress>) -> Vec<CandidateEgress>
+	{
+		if self.ends.is_empty() || candidates.iter().all(|candidate|self.is_failed(router,candidate.port))
+		{
+			return candidates;
+		}
+		candidates.into_iter().filter(|candidate|!self.is_failed(router,candidate.port)).collect()
+	}
+	///A BFS from each router through the links that have not failed.
+	fn compute_distances(&self, topology:&dyn Topology) -> Matrix<Option<usize>>
+	{
+		let n=topology.num_routers();
+		let mut distances=Matrix::constant(None,n,n);
+		for origin in 0..n
+		{
+			*distances.get_mut(origin,origin)=Some(0);
+			let mut queue=VecDeque::from(vec![origin]);
+			while let Some(current)=queue.pop_front()
+			{
+				let distance=distances.get(origin,current).expect("queued routers have a distance");
+				for port in 0..topology.ports(current)
+				{
+					if let (Location::RouterPort{router_index,..},_)=topology.neighbour(current,port)
+					{
+						if !self.is_failed(current,port) && distances.get(origin,router_index).is_none()
+						{
+							*distances.get_mut(origin,router_index)=Some(distance+1);
+							queue.push_back(router_index);
+						}
+					}
+				}
+			}
+		}
+		distances
+	}
+}
+
+///Reads the phits occupying the queues beyond each router port, as `occupation[router][port]`, from the credits known by the routers.
+///To be used by routings in `update_global_status`. The ports towards servers are left unchanged.
+pub fn read_port_occupation(occupation:&mut [Vec<usize>], routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology)
+{
+	for (index,router) in routers.iter().enumerate()
+	{
+		let router=router.borrow();
+		for port in 0..topology.ports(index)
+		{
+			if let (Location::RouterPort{..},_) = topology.neighbour(index,port)
+			{
+				if let Some(status) = router.get_status_at_emisor(port)
+				{
+					occupation[index][port] = (0..status.num_virtual_channels()).map(|vc|{
+						let maximum=router.get_maximum_credits_towards(port,vc).expect("reading the occupation requires routers with maximum credits");
+						let available=status.known_available_space_for_virtual_channel(vc).expect("reading the occupation requires knowing the available space");
+						maximum.saturating_sub(available)
+					}).sum();
+				}
+			}
+		}
+	}
+}
+
+///Hashes the given values, as the fields of a flow, with a fixed function that does not depend on the build.
+///The `seed` is to be taken from the simulation rng in `initialize`, so that the hash changes with the random seed of the simulation.
+pub fn flow_hash(seed:u64, values:&[usize]) -> u64
+{
+	//FNV-1a over the bytes of the seed and the values, followed by a final mix to spread the low bits.
+	let mut hash:u64=0xcbf29ce484222325;
+	for value in std::iter::once(seed).chain(values.iter().map(|&value|value as u64))
+	{
+		for byte in value.to_le_bytes()
+		{
+			hash^=byte as u64;
+			hash=hash.wrapping_mul(0x100000001b3);
+		}
+	}
+	hash^=hash>>33;
+	hash=hash.wrapping_mul(0xff51afd7ed558ccd);
+	hash^hash>>33
+}
+
 /**Build a new routing.
 
 ## Generic routings
@@ -268,6 +421,19 @@ Polarized{
 }
 ```
 
+To choose adaptively at injection between the minimal route and some Valiant routes there is UGAL, which works on any topology. In its `Local` variant the router policies select among the routes offered at the source, usually by `LowestSinghWeight{extra_congestion:1,use_estimation:true}`. In the `Global` variant the route with least queued phits along the whole path is chosen.
+
+- Singh, A. (2005). Load-balanced routing in interconnection networks (Doctoral dissertation, Stanford University).
+
+```ignore
+UGAL{
+	nonminimal: Valiant{first:Shortest,second:Shortest,first_reserved_virtual_channels:[1],second_reserved_virtual_channels:[0]},
+	intermediates: 4,
+	variant: Local,//or Global
+	legend_name: "UGAL-L",
+}
+```
+
 For topologies that define global links:
 ```ignore
 WeighedShortest{
@@ -282,6 +448,44 @@ UpDown{
 	legend_name: "up/down routing",
 }
 ```
+To select the up port by the occupation of the local queues, or deterministically by d-mod-k, there is FatTreeRouting.
+```ignore
+FatTreeRouting{
+	selection: Adaptive,//or DModK
+	legend_name: "adaptive fat-tree",
+}
+```
+
+For irregular networks, UpDownStar builds a spanning tree from a root, which can be chosen by `root_selection: MinimumEccentricity` or `MaximumDegree`, and with `tree: DFS` instead of the default breadth-first tree. MultipleUpDown combines several of these trees, each on its own virtual channels, and SegmentBasedRouting places the turn restrictions inside segments of the network. All of them report the fraction of pairs routed by minimal paths.
+```ignore
+MultipleUpDown{
+	trees: [UpDownStar{root_selection:MinimumEccentricity},UpDownStar{root_selection:MaximumDegree,tree:DFS}],
+	virtual_channels: [[0],[1]],
+	legend_name: "two up/down trees",
+}
+SegmentBasedRouting{
+	legend_name: "segment-based routing",
+}
+```
+
+To reproduce the forwarding tables of a real fabric, such as those computed by an InfiniBand subnet manager, they can be read from a file. See [TableRouting](table::TableRouting) for the format.
+```ignore
+TableRouting{
+	filename: "/path/to/tables.txt",
+	flow_hashing: true,//select a single entry per flow, with probability proportional to its weight
+	legend_name: "subnet manager tables",
+}
+```
+
+To keep the packets of each flow in order while balancing multiple paths, FlowletRouting pins each flow to a minimal path and only chooses again after the flow has been idle for `flowlet_timeout` cycles. It reports reordering statistics in its results.
+```ignore
+FlowletRouting{
+	paths: 8,
+	flowlet_timeout: 100,
+	selection: Hash,//or LeastLoaded
+	legend_name: "flowlet switching",
+}
+```
 
 There is a `Mindless` routing without parameters that includes all neighbours as candidates until reaching destination. Can be though as a random walk, if additionally the router would make its decisions randomly.
 
@@ -367,6 +571,7 @@ The dimensional ordered routing. Packets will go minimal along the first dimensi
 ```ignore
 DOR{
 	order: [0,1],
+	//express: true,//optional, to also use links of other classes along the dimension, as in the ExpressCube topology
 	legend_name: "dimension ordered routing, 0 before 1",
 }
 ```
@@ -379,10 +584,21 @@ O1TURN is a pair of DOR to balance the usage of the links.
 O1TURN{
 	reserved_virtual_channels_order01: [0],
 	reserved_virtual_channels_order10: [1],
+	//express: true,//optional, as in DOR
 	legend_name: "O1TURN",
 }
 ```
 
+### Turn model
+
+The partially adaptive routings for meshes of the turn model, which prohibit some turns to be deadlock-free with a single virtual channel. They offer every productive port allowed, to be selected by the router policies. `WestFirst`, `NorthLast` and `NegativeFirst` work on meshes of any dimension, while `OddEven` requires two dimensions. See [TurnModel](turn_model::TurnModel) for the naming of the directions.
+
+```ignore
+WestFirst{
+	legend_name: "west-first",
+}
+```
+
 ### OmniDimensional
 
 McDonal OmniDimensional routing for HyperX. it is a shortest with some allowed deroutes. It does not allow deroutes on unaligned dimensions.
@@ -424,6 +640,7 @@ pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 			"DOR" => Box::new(DOR::new(arg)),
 			"O1TURN" => Box::new(O1TURN::new(arg)),
 			"GeneralTurn" => Box::new(GENERALTURN::new(arg)),
+			"WestFirst" | "NorthLast" | "NegativeFirst" | "OddEven" => Box::new(TurnModel::new(arg)),
 			"OmniDimensionalDeroute" => Box::new(OmniDimensionalDeroute::new(arg)),
 			"DimWAR" => Box::new(DimWAR::new(arg)),
 			"Valiant4Hamming" => Box::new(Valiant4Hamming::new(arg)),
@@ -434,12 +651,18 @@ pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 			"Valiant" => Box::new(Valiant::new(arg)),
 			"ValiantDOR" => Box::new(ValiantDOR::new(arg)),
 			"Polarized" => Box::new(Polarized::new(arg)),
+			"UGAL" => Box::new(UGAL::new(arg)),
+			"TableRouting" => Box::new(TableRouting::new(arg)),
+			"FlowletRouting" => Box::new(FlowletRouting::new(arg)),
 			"Sum" => Box::new(SumRouting::new(arg)),
 			"Mindless" => Box::new(Mindless::new(arg)),
 			"WeighedShortest" => Box::new(WeighedShortest::new(arg)),
 			"Stubborn" => Box::new(Stubborn::new(arg)),
 			"UpDown" => Box::new(UpDown::new(arg)),
 			"UpDownStar" => Box::new(ExplicitUpDown::new(arg)),
+			"MultipleUpDown" => Box::new(MultipleUpDown::new(arg)),
+			"FatTreeRouting" => Box::new(FatTreeRouting::new(arg)),
+			"SegmentBasedRouting" => Box::new(SegmentBasedRouting::new(arg)),
 			"ChannelsPerHop" => Box::new(ChannelsPerHop::new(arg)),
 			"ChannelsPerHopPerLinkClass" => Box::new(ChannelsPerHopPerLinkClass::new(arg)),
 			"AscendantChannelsWithLinkClass" => Box::new(AscendantChannelsWithLinkClass::new(arg)),
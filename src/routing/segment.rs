@@ -0,0 +1,412 @@
+/*!
+
+Segment-based routing (SR) for irregular networks.
+
+* SegmentBasedRouting
+
+Reference: Mejia, A., Flich, J., Duato, J., Reinemo, S. A., & Skeie, T. (2006). Segment-based routing: an efficient fault-tolerant routing algorithm for meshes and tori. In Proceedings 20th IEEE International Parallel & Distributed Processing Symposium.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use ::rand::{rngs::StdRng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::prelude::*;
+use crate::routing::updown::{minimal_fraction,RootSelection};
+use crate::topology::{Topology,Location,NeighbourRouterIteratorItem};
+
+/**
+Segment-based routing. The network is split into segments, and a bidirectional turn restriction is placed inside each segment instead of globally as in the up/down routings. The segments are built from a `start` router, which defaults to the one of minimum eccentricity.
+* A regular segment is a path starting and ending at routers already in some segment and going through new routers. The first one is a cycle from the start router. Its restriction forbids the turn between the two links of the segment at its middle router.
+* A unitary segment is a link between two routers already in some segment. Its restriction forbids at one end the turns between the link and the links previously in segments.
+* Links towards routers that cannot close a segment are added without restriction, as they cannot be part of any cycle when added.
+
+Each cycle in the network includes the last segment added of those it uses, and it must cross the restriction of that segment, so the routing is deadlock-free with a single virtual channel. The packets follow the shortest paths that do not use forbidden turns, offering all the minimal candidates at each hop.
+
+```ignore
+SegmentBasedRouting{
+	//start: 0,//optional
+	legend_name: "segment-based routing",
+}
+```
+The routing statistics include the number of `segments`, of `unitary_segments` and of `forbidden_turns`, together with the fraction of pairs of routers whose route is as short as their distance in the topology, as `minimal_fraction`.
+**/
+#[derive(Debug)]
+pub struct SegmentBasedRouting
+{
+	start: Option<usize>,
+	///`forbidden[router][input_port][output_port]` tells whether the turn is forbidden.
+	forbidden: Vec<Vec<Vec<bool>>>,
+	///`distances[target][router][input_port]` is the length of the route towards `target` of a packet that entered `router` by `input_port`. The index `ports(router)` is for packets being injected.
+	distances: Vec<Vec<Vec<Option<usize>>>>,
+	segments: usize,
+	unitary_segments: usize,
+	minimal_fraction: f64,
+}
+
+//routing_info.selections has the port by which the packet entered the current router, or -1 at the router of injection.
+impl Routing for SegmentBasedRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let input = self.input_index(routing_info,topology,current_router);
+		let distance = self.distances[target_router][current_router][input].unwrap_or_else(||panic!("There is no route from {} to {}",current_router,target_router));
+		let mut candidates = vec![];
+		for NeighbourRouterIteratorItem{port_index,neighbour_router,neighbour_port,..} in topology.neighbour_router_iter(current_router)
+		{
+			if self.allowed(topology,current_router,input,port_index) && self.distances[target_router][neighbour_router][neighbour_port]==Some(distance-1)
+			{
+				candidates.extend((0..num_virtual_channels).map(|vc|{
+					let mut egress = CandidateEgress::new(port_index,vc);
+					egress.estimated_remaining_hops = Some(distance);
+					egress
+				}));
+			}
+		}
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections=Some(vec![-1]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections=Some(vec![current_port as i32]);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let start = self.start.unwrap_or_else(||RootSelection::MinimumEccentricity.select(topology));
+		self.build_segments(topology,start);
+		self.compute_distances(topology);
+		let distances = &self.distances;
+		self.minimal_fraction = minimal_fraction(topology,|origin,destination|distances[destination][origin][topology.ports(origin)]);
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let forbidden_turns:usize = self.forbidden.iter().map(|router|router.iter().map(|turns|turns.iter().filter(|&&forbidden|forbidden).count()).sum::<usize>()).sum();
+		Some(ConfigurationValue::Object(String::from("SegmentBasedRoutingStatistics"),vec![
+			(String::from("segments"),ConfigurationValue::Number(self.segments as f64)),
+			(String::from("unitary_segments"),ConfigurationValue::Number(self.unitary_segments as f64)),
+			(String::from("forbidden_turns"),ConfigurationValue::Number(forbidden_turns as f64)),
+			(String::from("minimal_fraction"),ConfigurationValue::Number(self.minimal_fraction)),
+		]))
+	}
+}
+
+impl SegmentBasedRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> SegmentBasedRouting
+	{
+		let mut start = None;
+		match_object_panic!(arg.cv,"SegmentBasedRouting",value,
+			"start" => start=Some(value.as_usize().expect("bad value for start")),
+		);
+		SegmentBasedRouting{
+			start,
+			forbidden: vec![],
+			distances: vec![],
+			segments: 0,
+			unitary_segments: 0,
+			minimal_fraction: 0.0,
+		}
+	}
+	///The index in the tables of the port by which the packet entered `router`.
+	fn input_index(&self, routing_info:&RoutingInfo, topology:&dyn Topology, router:usize) -> usize
+	{
+		let input = routing_info.selections.as_ref().expect("SegmentBasedRouting requires the input port")[0];
+		if input<0 { topology.ports(router) } else { input as usize }
+	}
+	///Whether a packet that entered `router` by the port of index `input` may leave by `output`.
+	fn allowed(&self, topology:&dyn Topology, router:usize, input:usize, output:usize) -> bool
+	{
+		input==topology.ports(router) || (input!=output && !self.forbidden[router][input][output])
+	}
+	///Forbids the turns between the ports `a` and `b` of `router` in both directions.
+	fn forbid(&mut self, router:usize, a:usize, b:usize)
+	{
+		self.forbidden[router][a][b]=true;
+		self.forbidden[router][b][a]=true;
+	}
+	///Splits the network into segments, placing the turn restrictions.
+	fn build_segments(&mut self, topology:&dyn Topology, start:usize)
+	{
+		let n = topology.num_routers();
+		self.forbidden = (0..n).map(|router|vec![vec![false;topology.ports(router)];topology.ports(router)]).collect();
+		self.segments = 0;
+		self.unitary_segments = 0;
+		let mut tagged_router = vec![false;n];
+		let mut tagged_link:Vec<Vec<bool>> = (0..n).map(|router|vec![false;topology.ports(router)]).collect();
+		//The tagged routers in the order they were tagged.
+		let mut order = vec![start];
+		tagged_router[start] = true;
+		let tag_link = |tagged_link:&mut Vec<Vec<bool>>, router:usize, port:usize|{
+			tagged_link[router][port] = true;
+			if let (Location::RouterPort{router_index,router_port},_) = topology.neighbour(router,port)
+			{
+				tagged_link[router_index][router_port] = true;
+			}
+		};
+		loop
+		{
+			if let Some(segment) = order.iter().find_map(|&router|regular_segment(topology,&tagged_router,&tagged_link,router))
+			{
+				//The segment is a list of hops (router,port). Its internal routers are those reached by all hops except the last one.
+				let internal:Vec<(usize,usize,usize)> = segment.windows(2).map(|hops|{
+					let (previous,port) = hops[0];
+					match topology.neighbour(previous,port).0
+					{
+						Location::RouterPort{router_index,router_port} => (router_index,router_port,hops[1].1),
+						_ => unreachable!(),
+					}
+				}).collect();
+				let (middle,input,output) = internal[(internal.len()-1)/2];
+				self.forbid(middle,input,output);
+				for &(router,port) in segment.iter()
+				{
+					tag_link(&mut tagged_link,router,port);
+				}
+				for &(router,_,_) in internal.iter()
+				{
+					tagged_router[router] = true;
+					order.push(router);
+				}
+				self.segments += 1;
+				continue;
+			}
+			let mut unitary = None;
+			let mut pendant = None;
+			for &router in order.iter()
+			{
+				for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(router)
+				{
+					if !tagged_link[router][port_index]
+					{
+						if tagged_router[neighbour_router]
+						{
+							unitary = unitary.or(Some((router,port_index)));
+						}
+						else
+						{
+							pendant = pendant.or(Some((router,port_index,neighbour_router)));
+						}
+					}
+				}
+				if unitary.is_some()
+				{
+					break;
+				}
+			}
+			if let Some((router,port)) = unitary
+			{
+				for other in 0..topology.ports(router)
+				{
+					if other!=port && tagged_link[router][other]
+					{
+						self.forbid(router,port,other);
+					}
+				}
+				tag_link(&mut tagged_link,router,port);
+				self.segments += 1;
+				self.unitary_segments += 1;
+			}
+			else if let Some((router,port,neighbour)) = pendant
+			{
+				tag_link(&mut tagged_link,router,port);
+				tagged_router[neighbour] = true;
+				order.push(neighbour);
+			}
+			else
+			{
+				break;
+			}
+		}
+		if let Some(router) = (0..n).find(|&router|!tagged_router[router])
+		{
+			panic!("The router {} is not connected to the start router {}",router,start);
+		}
+	}
+	///Computes the length of the shortest routes not using forbidden turns, by a BFS backwards from each target.
+	fn compute_distances(&mut self, topology:&dyn Topology)
+	{
+		let n = topology.num_routers();
+		let mut distances = Vec::with_capacity(n);
+		for target in 0..n
+		{
+			let mut table:Vec<Vec<Option<usize>>> = (0..n).map(|router|vec![None;topology.ports(router)+1]).collect();
+			let mut queue = VecDeque::new();
+			for input in 0..=topology.ports(target)
+			{
+				table[target][input] = Some(0);
+				queue.push_back((target,input));
+			}
+			while let Some((router,input)) = queue.pop_front()
+			{
+				let distance = table[router][input].unwrap();
+				//The states from which a hop enters `router` by `input`.
+				if let (Location::RouterPort{router_index:previous,router_port:output},_) = topology.neighbour(router,input)
+				{
+					for previous_input in 0..=topology.ports(previous)
+					{
+						if table[previous][previous_input].is_none() && self.allowed(topology,previous,previous_input,output)
+						{
+							table[previous][previous_input] = Some(distance+1);
+							queue.push_back((previous,previous_input));
+						}
+					}
+				}
+			}
+			for origin in 0..n
+			{
+				if table[origin][topology.ports(origin)].is_none()
+				{
+					panic!("SegmentBasedRouting has no route from {} to {}",origin,target);
+				}
+			}
+			distances.push(table);
+		}
+		self.distances = distances;
+	}
+}
+
+///Searches the shortest regular segment from the tagged router `origin`. It is returned as the list of hops `(router,port)`, where the intermediate routers are not tagged and the last hop reaches a tagged router.
+fn regular_segment(topology:&dyn Topology, tagged_router:&[bool], tagged_link:&[Vec<bool>], origin:usize) -> Option<Vec<(usize,usize)>>
+{
+	//For each reached untagged router, the hop by which it was reached.
+	let mut parent:Vec<Option<(usize,usize)>> = vec![None;tagged_router.len()];
+	let mut queue = VecDeque::new();
+	for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(origin)
+	{
+		if !tagged_link[origin][port_index] && !tagged_router[neighbour_router] && parent[neighbour_router].is_none()
+		{
+			parent[neighbour_router] = Some((origin,port_index));
+			queue.push_back(neighbour_router);
+		}
+	}
+	while let Some(current) = queue.pop_front()
+	{
+		let (previous,previous_port) = parent[current].unwrap();
+		for NeighbourRouterIteratorItem{port_index,neighbour_router,neighbour_port,..} in topology.neighbour_router_iter(current)
+		{
+			if tagged_link[current][port_index] || (neighbour_router==previous && neighbour_port==previous_port)
+			{
+				continue;
+			}
+			if tagged_router[neighbour_router]
+			{
+				let mut segment = vec![(current,port_index)];
+				let mut router = current;
+				while let Some((previous,port)) = parent[router]
+				{
+					segment.push((previous,port));
+					if previous==origin
+					{
+						break;
+					}
+					router = previous;
+				}
+				segment.reverse();
+				return Some(segment);
+			}
+			if parent[neighbour_router].is_none()
+			{
+				parent[neighbour_router] = Some((current,port_index));
+				queue.push_back(neighbour_router);
+			}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::Plugs;
+	use rand::SeedableRng;
+	use crate::topology::cartesian::{Mesh,Hamming};
+	fn sides_cv(name:&str, sides:&[usize]) -> ConfigurationValue
+	{
+		ConfigurationValue::Object(name.to_string(),vec![
+			("sides".to_string(),ConfigurationValue::Array(sides.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect())),
+			("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+		])
+	}
+	fn segment_routing(topology:&dyn Topology) -> SegmentBasedRouting
+	{
+		let plugs = Plugs::default();
+		let cv = ConfigurationValue::Object("SegmentBasedRouting".to_string(),vec![]);
+		let mut routing = SegmentBasedRouting::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs});
+		routing.initialize(topology,&mut StdRng::seed_from_u64(10u64));
+		routing
+	}
+	#[test]
+	fn mesh_segments()
+	{
+		let topology = Mesh::new(&sides_cv("Mesh",&[4,4]));
+		let routing = segment_routing(&topology);
+		// A 4x4 mesh has 24 links among 16 routers, so 9 independent cycles, each closed by a segment with its own restriction.
+		assert_eq!(routing.segments,9);
+		let n = topology.num_routers();
+		for origin in 0..n
+		{
+			for target in 0..n
+			{
+				let length = routing.distances[target][origin][topology.ports(origin)].unwrap();
+				assert!(length >= topology.distance(origin,target));
+			}
+		}
+		assert!(routing.minimal_fraction>0.5 && routing.minimal_fraction<=1.0,"minimal_fraction={}",routing.minimal_fraction);
+	}
+	#[test]
+	fn walks_reach_target()
+	{
+		let topology = Hamming::new(&sides_cv("Hamming",&[3,3]));
+		let routing = segment_routing(&topology);
+		let mut rng = StdRng::seed_from_u64(10u64);
+		let n = topology.num_routers();
+		for origin in 0..n
+		{
+			for target in 0..n
+			{
+				// Follow the first candidate at each hop, which must reduce the remaining length by one.
+				let info = RefCell::new(RoutingInfo::new());
+				routing.initialize_routing_info(&info,&topology,origin,target,Some(target),&mut rng);
+				let mut current = origin;
+				let mut hops = 0;
+				while current!=target
+				{
+					let next = routing.next(&info.borrow(),&topology,current,target,Some(target),1,&mut rng).unwrap();
+					let port = next.candidates[0].port;
+					let (router,input) = match topology.neighbour(current,port).0
+					{
+						Location::RouterPort{router_index,router_port} => (router_index,router_port),
+						_ => panic!("the route leaves the network"),
+					};
+					routing.update_routing_info(&info,&topology,router,input,target,Some(target),&mut rng);
+					current = router;
+					hops += 1;
+				}
+				assert_eq!(hops,routing.distances[target][origin][topology.ports(origin)].unwrap());
+			}
+		}
+	}
+}
@@ -0,0 +1,442 @@
+/*!
+
+Routing by forwarding tables read from files.
+
+* TableRouting
+
+*/
+
+use std::collections::HashMap;
+
+use ::rand::{rngs::StdRng,Rng};
+
+use crate::{match_object_panic,error,source_location};
+use crate::error::SourceLocation;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::prelude::*;
+use crate::routing::flow_hash;
+use crate::topology::{Topology,Location};
+
+///An output of a forwarding table.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct TableEntry
+{
+	pub port: usize,
+	///The virtual channels allowed through the port. `None` allows all.
+	pub virtual_channels: Option<Vec<usize>>,
+	///The relative frequency with which the entry is selected when hashing flows.
+	pub weight: usize,
+}
+
+///The forwarding tables of all the routers.
+#[derive(Clone,Debug,Default)]
+pub struct ForwardingTables
+{
+	///The entries of `(router,destination_router)`.
+	pub router_entries: HashMap<(usize,usize),Vec<TableEntry>>,
+	///The entries of `(router,destination_server)`. They take precedence over the entries for the router of the server.
+	pub server_entries: HashMap<(usize,usize),Vec<TableEntry>>,
+}
+
+///Parse forwarding tables. Each non-empty line not starting by `#` is a router, a destination and its entries.
+///The destination is a router index, optionally prefixed by `r`, or a server index prefixed by `s`.
+///Each entry is a port, optionally followed by `:` and a comma separated list of virtual channels, and optionally by `*` and a weight.
+pub fn parse_forwarding_tables(text:&str) -> ForwardingTables
+{
+	let mut tables=ForwardingTables::default();
+	for (line_number,line) in text.lines().enumerate()
+	{
+		let line=line.trim();
+		if line.is_empty() || line.starts_with('#')
+		{
+			continue;
+		}
+		let fields:Vec<&str>=line.split_whitespace().collect();
+		if fields.len()<3
+		{
+			panic!("line {} of the forwarding tables needs a router, a destination and at least one entry",line_number+1);
+		}
+		let router:usize=fields[0].parse().unwrap_or_else(|_|panic!("bad router {} in line {} of the forwarding tables",fields[0],line_number+1));
+		let parse_index = |text:&str| -> usize { text.parse().unwrap_or_else(|_|panic!("bad destination {} in line {} of the forwarding tables",fields[1],line_number+1)) };
+		let entries:Vec<TableEntry>=fields[2..].iter().map(|&text|parse_entry(text).unwrap_or_else(||panic!("bad entry {} in line {} of the forwarding tables",text,line_number+1))).collect();
+		let previous = if let Some(server)=fields[1].strip_prefix('s')
+		{
+			tables.server_entries.insert((router,parse_index(server)),entries)
+		}
+		else
+		{
+			let destination=fields[1].strip_prefix('r').unwrap_or(fields[1]);
+			tables.router_entries.insert((router,parse_index(destination)),entries)
+		};
+		if previous.is_some()
+		{
+			panic!("line {} of the forwarding tables repeats the router {} and destination {}",line_number+1,router,fields[1]);
+		}
+	}
+	tables
+}
+
+///Parse an entry `port[:vc,vc...][*weight]`.
+fn parse_entry(text:&str) -> Option<TableEntry>
+{
+	let (text,weight) = match text.split_once('*')
+	{
+		Some((text,weight)) => (text,weight.parse().ok()?),
+		None => (text,1),
+	};
+	let (port,virtual_channels) = match text.split_once(':')
+	{
+		Some((port,channels)) => (port,Some(channels.split(',').map(|vc|vc.parse().ok()).collect::<Option<Vec<usize>>>()?)),
+		None => (text,None),
+	};
+	Some(TableEntry{port:port.parse().ok()?,virtual_channels,weight})
+}
+
+/**
+Routing by forwarding tables, as produced by the subnet managers of InfiniBand or the ECMP tables of Ethernet fabrics.
+
+The tables are read from `filename` (see [parse_forwarding_tables] for the format). Each router forwards the packets to the entries of the destination server if there are any, or else to the entries of the router of the destination server. Once at that router the packet goes to its server.
+```text
+# router destination entries
+0 3 1 2        # from router 0 towards router 3 use ports 1 and 2
+0 s7 2:0,1*3   # towards server 7 use port 2 with virtual channels 0 and 1, and weight 3
+```
+When `flow_hashing` is true each router selects a single entry by hashing the source server, the destination and the router, with probability proportional to the weights, as ECMP does. The hash is seeded from the random seed of the simulation. Otherwise all the entries are candidates and the weights are ignored. Entries through links that fail during the simulation are discarded before the selection, while any other entry remains.
+
+When initialized it checks that the entries exist and are links between routers, that every router has entries towards every other router, and that following the tables there is no loop.
+With `allow_sparse` the routers may lack entries towards some destinations. Then a packet reaching a router without entries towards its destination, or whose entries allow none of the virtual channels, makes the routing return an error.
+
+```ignore
+TableRouting{
+	filename: "/path/to/tables.txt",
+	flow_hashing: false,//optional, defaults to false
+	allow_sparse: false,//optional, defaults to false
+	legend_name: "subnet manager tables",
+}
+```
+*/
+#[derive(Debug)]
+pub struct TableRouting
+{
+	tables: ForwardingTables,
+	flow_hashing: bool,
+	///Seed of the flow hashing, drawn in `initialize`.
+	hash_seed: u64,
+	///Whether the routers may lack entries towards some destinations.
+	allow_sparse: bool,
+	///The links that have failed during the simulation. Their entries are avoided while there are others.
+	failed_links: FailedLinks,
+}
+
+impl Routing for TableRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let entries = match self.entries(current_router,target_router,target_server)
+		{
+			Some(entries) => entries,
+			None => return Err(error!(undetermined).with_message(format!("There are no entries in router {} towards router {}",current_router,target_router))),
+		};
+		//Avoid the entries through failed links, unless all of them have failed.
+		let mut entries:Vec<&TableEntry>=entries.iter().collect();
+		if entries.iter().any(|entry|!self.failed_links.is_failed(current_router,entry.port))
+		{
+			entries.retain(|entry|!self.failed_links.is_failed(current_router,entry.port));
+		}
+		if self.flow_hashing
+		{
+			let flow=[routing_info.source_server.unwrap_or(usize::MAX),target_router,target_server.unwrap_or(usize::MAX),current_router];
+			let total:usize=entries.iter().map(|entry|entry.weight).sum();
+			let mut value=(flow_hash(self.hash_seed,&flow) % total as u64) as usize;
+			let index=entries.iter().position(|entry|if value<entry.weight { true } else { value-=entry.weight; false }).unwrap();
+			entries=vec![entries[index]];
+		}
+		let mut candidates=vec![];
+		for entry in entries
+		{
+			match entry.virtual_channels
+			{
+				Some(ref channels) => candidates.extend(channels.iter().filter(|&&vc|vc<num_virtual_channels).map(|&vc|CandidateEgress::new(entry.port,vc))),
+				None => candidates.extend((0..num_virtual_channels).map(|vc|CandidateEgress::new(entry.port,vc))),
+			}
+		}
+		if candidates.is_empty()
+		{
+			return Err(error!(undetermined).with_message(format!("The entries in router {} towards router {} allow none of the {} virtual channels",current_router,target_router,num_virtual_channels)));
+		}
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, _rng: &mut StdRng)
+	{
+		self.failed_links.update(topology,router,port,available);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.hash_seed=rng.gen();
+		let n=topology.num_routers();
+		for (&(router,destination),entries) in self.tables.router_entries.iter().chain(self.tables.server_entries.iter())
+		{
+			if router>=n
+			{
+				panic!("There is no router {} for the forwarding tables",router);
+			}
+			for entry in entries
+			{
+				if entry.port>=topology.ports(router)
+				{
+					panic!("There is no port {} in router {} for the forwarding tables",entry.port,router);
+				}
+				if let (Location::RouterPort{..},_)=topology.neighbour(router,entry.port) {} else
+				{
+					panic!("The port {} of router {} in the entries towards {} is not a link between routers",entry.port,router,destination);
+				}
+				if entry.weight==0
+				{
+					panic!("The entries in router {} towards {} have a null weight",router,destination);
+				}
+			}
+		}
+		for &(_,server) in self.tables.server_entries.keys()
+		{
+			if server>=topology.num_servers()
+			{
+				panic!("There is no server {} for the forwarding tables",server);
+			}
+		}
+		//Check every destination router, and every server with its own entries.
+		let mut servers:Vec<usize>=self.tables.server_entries.keys().map(|&(_,server)|server).collect();
+		servers.sort_unstable();
+		servers.dedup();
+		let destinations=(0..n).map(|router|(router,None)).chain(servers.into_iter().map(|server|{
+			match topology.server_neighbour(server).0
+			{
+				Location::RouterPort{router_index,..} => (router_index,Some(server)),
+				_ => panic!("The server {} is not attached to a router",server),
+			}
+		}));
+		for (target_router,target_server) in destinations
+		{
+			self.check_destination(topology,target_router,target_server);
+		}
+	}
+}
+
+impl TableRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> TableRouting
+	{
+		let mut filename=None;
+		let mut flow_hashing=false;
+		let mut allow_sparse=false;
+		match_object_panic!(arg.cv,"TableRouting",value,
+			"filename" => filename=Some(value.as_str().expect("bad value for filename").to_string()),
+			"flow_hashing" => flow_hashing=value.as_bool().expect("bad value for flow_hashing"),
+			"allow_sparse" => allow_sparse=value.as_bool().expect("bad value for allow_sparse"),
+		);
+		let filename=filename.expect("There were no filename");
+		let contents=std::fs::read_to_string(&filename).unwrap_or_else(|err|panic!("could not read forwarding tables {}: {}",filename,err));
+		TableRouting::from_tables(parse_forwarding_tables(&contents),flow_hashing,allow_sparse)
+	}
+	pub fn from_tables(tables:ForwardingTables, flow_hashing:bool, allow_sparse:bool) -> TableRouting
+	{
+		TableRouting{
+			tables,
+			flow_hashing,
+			hash_seed: 0,
+			allow_sparse,
+			failed_links: FailedLinks::default(),
+		}
+	}
+	///The entries at `router` towards the server, if given and it has specific entries, or else towards `target_router`.
+	fn entries(&self, router:usize, target_router:usize, target_server:Option<usize>) -> Option<&Vec<TableEntry>>
+	{
+		target_server.and_then(|server|self.tables.server_entries.get(&(router,server))).or_else(||self.tables.router_entries.get(&(router,target_router)))
+	}
+	///Panics if some router cannot reach the destination following the tables or there is a loop. Without entries at some router it only panics when not `allow_sparse`.
+	fn check_destination(&self, topology:&dyn Topology, target_router:usize, target_server:Option<usize>)
+	{
+		let n=topology.num_routers();
+		//0 not visited, 1 in the current path, 2 known to reach the destination.
+		let mut state=vec![0u8;n];
+		state[target_router]=2;
+		let destination_name = match target_server
+		{
+			Some(server) => format!("server {}",server),
+			None => format!("router {}",target_router),
+		};
+		for source in 0..n
+		{
+			if state[source]!=0
+			{
+				continue;
+			}
+			//Depth first search with a stack of (router,index of the next entry to explore).
+			let mut stack=vec![(source,0)];
+			state[source]=1;
+			while let Some(&mut (router,ref mut index))=stack.last_mut()
+			{
+				let entries = match self.entries(router,target_router,target_server)
+				{
+					Some(entries) => &entries[..],
+					None if self.allow_sparse => &[],
+					None => panic!("There are no entries in router {} towards {}",router,destination_name),
+				};
+				if *index<entries.len()
+				{
+					let next = match topology.neighbour(router,entries[*index].port).0
+					{
+						Location::RouterPort{router_index,..} => router_index,
+						_ => unreachable!(),
+					};
+					*index+=1;
+					match state[next]
+					{
+						0 =>
+						{
+							state[next]=1;
+							stack.push((next,0));
+						},
+						1 => panic!("The forwarding tables towards {} have a loop through router {}",destination_name,next),
+						_ => (),
+					}
+				}
+				else
+				{
+					state[router]=2;
+					stack.pop();
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::topology::cartesian::Hamming;
+	fn complete_graph() -> Hamming
+	{
+		let cv=ConfigurationValue::Object(String::from("Hamming"),vec![
+			(String::from("sides"),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0)])),
+			(String::from("servers_per_router"),ConfigurationValue::Number(1.0)),
+		]);
+		Hamming::new(&cv)
+	}
+	///The port of `router` towards `neighbour`.
+	fn port(topology:&dyn Topology, router:usize, neighbour:usize) -> usize
+	{
+		(0..topology.ports(router)).find(|&port|matches!(topology.neighbour(router,port).0,Location::RouterPort{router_index,..} if router_index==neighbour)).unwrap()
+	}
+	///Tables that go around the ring 0,1,2,3 until reaching the destination.
+	fn ring_tables(topology:&dyn Topology) -> String
+	{
+		let mut text=String::from("# ring\n");
+		for router in 0..4
+		{
+			for destination in 0..4
+			{
+				if router!=destination
+				{
+					text+=&format!("{} r{} {}\n",router,destination,port(topology,router,(router+1)%4));
+				}
+			}
+		}
+		text
+	}
+	#[test]
+	fn ring()
+	{
+		let topology=complete_graph();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=TableRouting::from_tables(parse_forwarding_tables(&ring_tables(&topology)),false,false);
+		routing.initialize(&topology,&mut rng);
+		let info=RoutingInfo::new();
+		let next=routing.next(&info,&topology,3,1,Some(1),2,&mut rng).unwrap();
+		assert!(next.idempotent);
+		assert_eq!(next.candidates.iter().map(|c|(c.port,c.virtual_channel)).collect::<Vec<_>>(),vec![(port(&topology,3,0),0),(port(&topology,3,0),1)]);
+	}
+	#[test]
+	fn server_entries_and_hashing()
+	{
+		let topology=complete_graph();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut text=ring_tables(&topology);
+		//Towards server 2 go directly from 0, using only the virtual channel 1, or through 1 with more weight.
+		text+=&format!("0 s2 {}:1*1 {}*3\n",port(&topology,0,2),port(&topology,0,1));
+		let mut routing=TableRouting::from_tables(parse_forwarding_tables(&text),true,false);
+		routing.initialize(&topology,&mut rng);
+		let mut counts=[0,0];
+		for source in 0..200
+		{
+			let mut info=RoutingInfo::new();
+			info.source_server=Some(source);
+			let next=routing.next(&info,&topology,0,2,Some(2),2,&mut rng).unwrap();
+			if next.candidates[0].port==port(&topology,0,2)
+			{
+				assert_eq!(next.candidates.len(),1);
+				assert_eq!(next.candidates[0].virtual_channel,1);
+				counts[0]+=1;
+			}
+			else
+			{
+				assert_eq!(next.candidates.len(),2);
+				counts[1]+=1;
+			}
+		}
+		assert!(counts[0]>0 && counts[1]>counts[0],"unexpected selections {:?}",counts);
+	}
+	#[test]
+	#[should_panic(expected="loop")]
+	fn loop_detected()
+	{
+		let topology=complete_graph();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut text=ring_tables(&topology);
+		text+=&format!("1 s0 {}\n",port(&topology,1,3));
+		text+=&format!("3 s0 {}\n",port(&topology,3,1));
+		let mut routing=TableRouting::from_tables(parse_forwarding_tables(&text),false,false);
+		routing.initialize(&topology,&mut rng);
+	}
+	#[test]
+	#[should_panic(expected="There are no entries")]
+	fn missing_entries()
+	{
+		let topology=complete_graph();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=TableRouting::from_tables(parse_forwarding_tables(&format!("0 1 {}\n",port(&topology,0,1))),false,false);
+		routing.initialize(&topology,&mut rng);
+	}
+	#[test]
+	fn sparse_tables()
+	{
+		let topology=complete_graph();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let text=format!("0 1 {}\n0 2 {}:3\n",port(&topology,0,1),port(&topology,0,2));
+		let mut routing=TableRouting::from_tables(parse_forwarding_tables(&text),false,true);
+		routing.initialize(&topology,&mut rng);
+		let info=RoutingInfo::new();
+		assert_eq!(routing.next(&info,&topology,0,1,Some(1),2,&mut rng).unwrap().candidates.len(),2);
+		let error=routing.next(&info,&topology,0,3,Some(3),2,&mut rng).expect_err("there should be no entries towards router 3");
+		assert!(format!("{}",error).contains("There are no entries in router 0 towards router 3"),"unexpected error {}",error);
+		//The only entry towards router 2 uses a virtual channel that does not exist.
+		let error=routing.next(&info,&topology,0,2,Some(2),2,&mut rng).expect_err("there should be no virtual channel towards router 2");
+		assert!(format!("{}",error).contains("in router 0 towards router 2"),"unexpected error {}",error);
+	}
+}
@@ -0,0 +1,271 @@
+/*!
+
+The partially adaptive routings of the turn model for meshes.
+
+* WestFirst
+* NorthLast
+* NegativeFirst
+* OddEven
+
+All of them are implemented by the struct [TurnModel].
+
+*/
+
+use std::cell::RefCell;
+
+use ::rand::{rngs::StdRng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::prelude::*;
+use crate::topology::{Topology,Location};
+
+///The rules of a [TurnModel], which determine the prohibited turns.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum TurnModelKind
+{
+	///First go west, then adaptively to the remaining directions. In n dimensions it is the all-but-one-negative-first of Glass and Ni: first the negative directions of all dimensions except the last one, then the rest.
+	WestFirst,
+	///First go adaptively to any direction except north, then north. In n dimensions it is the all-but-one-positive-last of Glass and Ni: first the negative directions and the positive direction of the dimension 0, then the rest of positive directions.
+	NorthLast,
+	///First the negative directions, then the positive ones. Valid for any number of dimensions.
+	NegativeFirst,
+	///The Odd-Even model of Chiu, which forbids the east to north and east to south turns at even columns and the north to west and south to west turns at odd columns. Only for two dimensions.
+	OddEven,
+}
+
+/**
+Minimal partially adaptive routing for the [Mesh](crate::topology::cartesian::Mesh) following the turn model. The dimension 0 is the horizontal, with west being the negative direction and east the positive one. The dimension 1 is the vertical, with south the negative direction and north the positive one.
+Every productive port allowed by the model is offered, with all the virtual channels, so that the router policies can select adaptively among them. All the models are deadlock-free with a single virtual channel.
+
+```ignore
+WestFirst{
+	legend_name: "west-first",
+}
+NorthLast{}
+NegativeFirst{}
+OddEven{}
+```
+
+The ports are assumed to be arranged as in a Mesh, the port `2d` going to the negative direction of the dimension `d` and the port `2d+1` to the positive direction.
+**/
+#[derive(Debug)]
+pub struct TurnModel
+{
+	kind: TurnModelKind,
+}
+
+//routing_info.selections has the column of the source router in OddEven.
+impl Routing for TurnModel
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let cartesian_data=topology.cartesian_data().expect("the turn model requires a Cartesian topology");
+		let current=cartesian_data.unpack(current_router);
+		let target=cartesian_data.unpack(target_router);
+		let offsets:Vec<i32>=current.iter().zip(target.iter()).map(|(&c,&t)|t as i32-c as i32).collect();
+		let n=offsets.len();
+		//The productive directions as pairs (dimension,positive).
+		let productive:Vec<(usize,bool)>=offsets.iter().enumerate().filter(|(_,&offset)|offset!=0).map(|(dimension,&offset)|(dimension,offset>0)).collect();
+		let first_phase=|allowed:&dyn Fn(usize,bool)->bool|{
+			let first:Vec<(usize,bool)>=productive.iter().cloned().filter(|&(dimension,positive)|allowed(dimension,positive)).collect();
+			if first.is_empty() { productive.clone() } else { first }
+		};
+		let directions=match self.kind
+		{
+			TurnModelKind::WestFirst => first_phase(&|dimension,positive|!positive && dimension+1<n),
+			TurnModelKind::NorthLast => first_phase(&|dimension,positive|!positive || dimension==0),
+			TurnModelKind::NegativeFirst => first_phase(&|_dimension,positive|!positive),
+			TurnModelKind::OddEven =>
+			{
+				let source_column=routing_info.selections.as_ref().expect("OddEven requires the source column")[0] as usize;
+				odd_even_directions(current[0],source_column,target[0],offsets[0],offsets[1])
+			},
+		};
+		let candidates=directions.into_iter().flat_map(|(dimension,positive)|{
+			let port=2*dimension+if positive {1} else {0};
+			(0..num_virtual_channels).map(move|vc|CandidateEgress::new(port,vc))
+		}).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if self.kind==TurnModelKind::OddEven
+		{
+			let cartesian_data=topology.cartesian_data().expect("the turn model requires a Cartesian topology");
+			routing_info.borrow_mut().selections=Some(vec![cartesian_data.unpack(current_router)[0] as i32]);
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let cartesian_data=topology.cartesian_data().expect("the turn model requires a Cartesian topology");
+		if self.kind==TurnModelKind::OddEven && cartesian_data.sides.len()!=2
+		{
+			panic!("OddEven only works for bidimensional meshes");
+		}
+	}
+}
+
+///The directions allowed by the Odd-Even turn model, as pairs `(dimension,positive)`, for a packet at column `current_column` that started at `source_column` towards `target_column`, with remaining offsets `dx` and `dy`.
+fn odd_even_directions(current_column:usize, source_column:usize, target_column:usize, dx:i32, dy:i32) -> Vec<(usize,bool)>
+{
+	let vertical=(1,dy>0);
+	if dx==0
+	{
+		return vec![vertical];
+	}
+	if dx>0
+	{
+		if dy==0
+		{
+			return vec![(0,true)];
+		}
+		let mut directions=vec![];
+		//Turning from east to vertical is only allowed at odd columns, so go vertical at odd columns or before starting to go east.
+		if current_column%2==1 || current_column==source_column
+		{
+			directions.push(vertical);
+		}
+		//Keep going east unless the target column is even and it is the next one, since there the turn to vertical would be forbidden.
+		if target_column%2==1 || dx!=1
+		{
+			directions.push((0,true));
+		}
+		directions
+	}
+	else
+	{
+		let mut directions=vec![(0,false)];
+		//Turning from vertical to west is only allowed at even columns.
+		if dy!=0 && current_column%2==0
+		{
+			directions.push(vertical);
+		}
+		directions
+	}
+}
+
+impl TurnModel
+{
+	pub fn new(arg: RoutingBuilderArgument) -> TurnModel
+	{
+		match_object_panic!(arg.cv,["WestFirst","NorthLast","NegativeFirst","OddEven"],_value,);
+		let kind=match arg.cv
+		{
+			ConfigurationValue::Object(name,_) if name=="WestFirst" => TurnModelKind::WestFirst,
+			ConfigurationValue::Object(name,_) if name=="NorthLast" => TurnModelKind::NorthLast,
+			ConfigurationValue::Object(name,_) if name=="NegativeFirst" => TurnModelKind::NegativeFirst,
+			_ => TurnModelKind::OddEven,
+		};
+		TurnModel{
+			kind,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::Plugs;
+	use rand::SeedableRng;
+	use crate::topology::cartesian::Mesh;
+	fn mesh(sides:&[usize]) -> Mesh
+	{
+		let cv=ConfigurationValue::Object("Mesh".to_string(),vec![
+			("sides".to_string(),ConfigurationValue::Array(sides.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect())),
+			("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+		]);
+		Mesh::new(&cv)
+	}
+	///The ports offered from `origin` to `target` with one virtual channel.
+	fn ports(routing:&TurnModel, topology:&Mesh, origin:usize, source:usize, target:usize, rng:&mut StdRng) -> Vec<usize>
+	{
+		let info=RefCell::new(RoutingInfo::new());
+		routing.initialize_routing_info(&info,topology,source,target,Some(target),rng);
+		let next=routing.next(&info.borrow(),topology,origin,target,Some(target),1,rng).unwrap();
+		next.candidates.iter().map(|candidate|candidate.port).collect()
+	}
+	#[test]
+	fn productive_and_adaptive()
+	{
+		let plugs=Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		for sides in [vec![5,4],vec![3,3,3]]
+		{
+			let topology=mesh(&sides);
+			for name in ["WestFirst","NorthLast","NegativeFirst","OddEven"]
+			{
+				if name=="OddEven" && sides.len()!=2
+				{
+					continue;
+				}
+				let cv=ConfigurationValue::Object(name.to_string(),vec![]);
+				let mut routing=TurnModel::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs});
+				routing.initialize(&topology,&mut rng);
+				for source in 0..topology.num_routers()
+				{
+					for target in 0..topology.num_routers()
+					{
+						//Walk every allowed path, checking each offered port is productive.
+						let mut pending=vec![source];
+						while let Some(current)=pending.pop()
+						{
+							if current==target
+							{
+								continue;
+							}
+							let offered=ports(&routing,&topology,current,source,target,&mut rng);
+							assert!(!offered.is_empty(),"{} offers nothing from {} to {}",name,current,target);
+							for port in offered
+							{
+								let next=match topology.neighbour(current,port).0
+								{
+									Location::RouterPort{router_index,..} => router_index,
+									_ => panic!("{} goes out of the mesh",name),
+								};
+								assert_eq!(topology.distance(next,target)+1,topology.distance(current,target));
+								pending.push(next);
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+	#[test]
+	fn allowed_turns()
+	{
+		let plugs=Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let topology=mesh(&[4,4]);
+		let routing=|name:&str|TurnModel::new(RoutingBuilderArgument{cv:&ConfigurationValue::Object(name.to_string(),vec![]),plugs:&plugs});
+		//Router 5 is at (1,1). Router 15 is at (3,3), to the north-east. Router 12 is at (0,3), to the north-west.
+		let mut west_first=ports(&routing("WestFirst"),&topology,5,5,12,&mut rng);
+		assert_eq!(west_first,vec![0]);
+		west_first=ports(&routing("WestFirst"),&topology,5,5,15,&mut rng);
+		assert_eq!(west_first,vec![1,3]);
+		assert_eq!(ports(&routing("NorthLast"),&topology,5,5,15,&mut rng),vec![1]);
+		assert_eq!(ports(&routing("NorthLast"),&topology,5,5,12,&mut rng),vec![0]);
+		assert_eq!(ports(&routing("NegativeFirst"),&topology,5,5,15,&mut rng),vec![1,3]);
+		//Router 0 is at (0,0) in an even column. Going east towards router 11 at (3,2) it can also go north, since it is the source column.
+		assert_eq!(ports(&routing("OddEven"),&topology,0,0,11,&mut rng),vec![3,1]);
+		//At router 2, column 2, having come from column 0, it cannot turn north.
+		assert_eq!(ports(&routing("OddEven"),&topology,2,0,11,&mut rng),vec![1]);
+	}
+}
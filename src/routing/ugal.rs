@@ -0,0 +1,356 @@
+/*!
+
+Universal Globally-Adaptive Load-balanced routing (UGAL), for any topology.
+
+* UGAL
+
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ::rand::{rngs::StdRng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::router::Router;
+use crate::routing::prelude::*;
+use crate::routing::read_port_occupation;
+use crate::routing::RoutingAnnotation;
+use crate::topology::{Topology,Location};
+
+///How `UGAL` compares the minimal route against the Valiant routes.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum UGALVariant
+{
+	///UGAL-L. All the routes are offered as candidates at the source router, where the router policies select among them by the local queues. The route of the requested candidate is kept.
+	Local,
+	///UGAL-G. The route is chosen at injection by the queues along the whole path, as read from the status of every router.
+	Global,
+}
+
+/**
+Universal Globally-Adaptive Load-balanced routing. At injection the packet compares the `minimal` route against the `nonminimal` routes through `intermediates` random Valiant intermediates, and follows the best one until its destination.
+
+In the `Local` variant all the routes are offered at the source router, each candidate with its `estimated_remaining_hops`. The router policies must make the selection, usually with `LowestSinghWeight{extra_congestion:1,use_estimation:true}`, which compares the occupation of the queues of the current router times the hops to destination. The route of the first requested candidate is kept.
+
+In the `Global` variant the route is chosen when the packet is injected. Each route is walked following the least occupied minimal port at each router, and its cost is the number of hops plus the phits in the queues of the traversed ports. The occupation is read from the status of all the routers every `update_period` cycles. A route through an intermediate is only taken if its cost plus the `threshold` is lower than the cost of the minimal route.
+
+Only the estimations of distance provided by the `Topology` are used, so it can be applied to any topology. The `nonminimal` routing must be a `Valiant`, whose intermediate is taken from its routing info.
+
+```ignore
+UGAL{
+	minimal: Shortest,//optional, defaults to Shortest
+	nonminimal: Valiant{first:Shortest,second:Shortest,first_reserved_virtual_channels:[1],second_reserved_virtual_channels:[0]},
+	intermediates: 4,//optional, defaults to 1
+	variant: Local,//or Global
+	//update_period: 1,//optional, for Global
+	//threshold: 0,//optional, for Global
+	legend_name: "UGAL-L with 4 intermediates",
+}
+```
+
+The results include a `UGALStatistics{minimal_decisions,nonminimal_decisions}` with the number of packets that chose each kind of route.
+*/
+#[derive(Debug)]
+pub struct UGAL
+{
+	minimal: Box<dyn Routing>,
+	nonminimal: Box<dyn Routing>,
+	///Number of Valiant intermediates compared against the minimal route.
+	intermediates: usize,
+	variant: UGALVariant,
+	///Cycles between reads of the routers status in the `Global` variant.
+	update_period: Time,
+	///Cost added to the nonminimal routes in the `Global` variant.
+	threshold: usize,
+	///For the `Global` variant, the phits occupying the queues beyond each port, as `occupation[router][port]`.
+	occupation: Vec<Vec<usize>>,
+	///Packets that have chosen the minimal and a nonminimal route, respectively.
+	decisions: RefCell<[usize;2]>,
+}
+
+//routing_info.meta has the routing info of the minimal route followed by those of the `intermediates` nonminimal routes.
+//routing_info.selections has the indices of the routes still being considered. A single one once decided.
+impl Routing for UGAL
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let meta=routing_info.meta.as_ref().expect("UGAL requires meta");
+		let selections=routing_info.selections.as_ref().expect("UGAL requires selections");
+		let mut candidates=vec![];
+		let mut idempotent=true;
+		for &s in selections.iter()
+		{
+			let routing = if s==0 { &self.minimal } else { &self.nonminimal };
+			let base=routing.next(&meta[s as usize].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+			idempotent = idempotent && base.idempotent;
+			candidates.extend(base.into_iter().map(|candidate|CandidateEgress{annotation:Some(RoutingAnnotation{values:vec![s],meta:vec![candidate.annotation]}),..candidate}));
+		}
+		Ok(RoutingNextCandidates{candidates,idempotent:idempotent && selections.len()==1})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri=routing_info.borrow_mut();
+		let meta:Vec<RefCell<RoutingInfo>> = (0..=self.intermediates).map(|index|{
+			let mut info=RoutingInfo::new();
+			info.source_server=bri.source_server;
+			let info=RefCell::new(info);
+			let routing = if index==0 { &self.minimal } else { &self.nonminimal };
+			routing.initialize_routing_info(&info,topology,current_router,target_router,target_server,rng);
+			info
+		}).collect();
+		let selections = if current_router==target_router
+		{
+			vec![0]
+		}
+		else
+		{
+			match self.variant
+			{
+				UGALVariant::Local => (0..=self.intermediates as i32).collect(),
+				UGALVariant::Global =>
+				{
+					let minimal_cost=self.route_cost(topology,current_router,target_router);
+					let mut best=(minimal_cost,0);
+					for (index,info) in meta.iter().enumerate().skip(1)
+					{
+						let cost = match intermediate(&info.borrow())
+						{
+							Some(middle) => self.route_cost(topology,current_router,middle) + self.route_cost(topology,middle,target_router),
+							None => minimal_cost,
+						} + self.threshold;
+						if cost<best.0
+						{
+							best=(cost,index);
+						}
+					}
+					self.decisions.borrow_mut()[if best.1==0 {0} else {1}]+=1;
+					vec![best.1 as i32]
+				},
+			}
+		};
+		bri.meta=Some(meta);
+		bri.selections=Some(selections);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let bri=routing_info.borrow();
+		let meta=bri.meta.as_ref().expect("UGAL requires meta");
+		for &s in bri.selections.as_ref().expect("UGAL requires selections").iter()
+		{
+			let routing = if s==0 { &self.minimal } else { &self.nonminimal };
+			meta[s as usize].borrow_mut().hops+=1;
+			routing.update_routing_info(&meta[s as usize],topology,current_router,current_port,target_router,target_server,rng);
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.minimal.initialize(topology,rng);
+		self.nonminimal.initialize(topology,rng);
+		if self.variant==UGALVariant::Global
+		{
+			self.occupation=(0..topology.num_routers()).map(|router|vec![0;topology.ports(router)]).collect();
+		}
+	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.minimal.link_status_change(topology,router,port,available,rng);
+		self.nonminimal.link_status_change(topology,router,port,available,rng);
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		let annotation=requested.annotation.as_ref().expect("UGAL candidate without annotation");
+		let s=annotation.values[0];
+		let mut bri=routing_info.borrow_mut();
+		if bri.selections.as_ref().map(|selections|selections.len()>1).unwrap_or(false)
+		{
+			bri.selections=Some(vec![s]);
+			self.decisions.borrow_mut()[if s==0 {0} else {1}]+=1;
+		}
+		let mut sub_requested=requested.clone();
+		sub_requested.annotation=annotation.meta[0].clone();
+		let routing = if s==0 { &self.minimal } else { &self.nonminimal };
+		routing.performed_request(&sub_requested,&bri.meta.as_ref().expect("UGAL requires meta")[s as usize],topology,current_router,target_router,target_server,num_virtual_channels,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.minimal.update_global_status(routers,topology,cycle);
+		self.nonminimal.update_global_status(routers,topology,cycle);
+		if self.variant!=UGALVariant::Global || cycle%self.update_period!=0
+		{
+			return;
+		}
+		read_port_occupation(&mut self.occupation,routers,topology);
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let decisions=self.decisions.borrow();
+		Some(ConfigurationValue::Object(String::from("UGALStatistics"),vec![
+			(String::from("minimal_decisions"),ConfigurationValue::Number(decisions[0] as f64)),
+			(String::from("nonminimal_decisions"),ConfigurationValue::Number(decisions[1] as f64)),
+		]))
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.decisions.borrow_mut()=[0,0];
+	}
+}
+
+///The intermediate router selected by a `Valiant` in its routing info, if it has still to be reached.
+fn intermediate(info:&RoutingInfo) -> Option<usize>
+{
+	info.selections.as_ref().map(|selections|selections[0] as usize)
+}
+
+impl UGAL
+{
+	pub fn new(arg: RoutingBuilderArgument) -> UGAL
+	{
+		let mut minimal=None;
+		let mut nonminimal=None;
+		let mut intermediates=1;
+		let mut variant=None;
+		let mut update_period=1;
+		let mut threshold=0;
+		match_object_panic!(arg.cv,"UGAL",value,
+			"minimal" => minimal=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"nonminimal" => nonminimal=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"intermediates" => intermediates=value.as_usize().expect("bad value for intermediates"),
+			"variant" => variant=Some(match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Local" => UGALVariant::Local,
+				ConfigurationValue::Object(name,_) if name=="Global" => UGALVariant::Global,
+				_ => panic!("bad value for variant, it must be Local or Global"),
+			}),
+			"update_period" => update_period=value.as_time().expect("bad value for update_period"),
+			"threshold" => threshold=value.as_usize().expect("bad value for threshold"),
+		);
+		let minimal=minimal.unwrap_or_else(||new_routing(RoutingBuilderArgument{cv:&ConfigurationValue::Object(String::from("Shortest"),vec![]),..arg}));
+		let nonminimal=nonminimal.expect("There were no nonminimal");
+		let variant=variant.expect("There were no variant");
+		if intermediates==0
+		{
+			panic!("UGAL requires at least one intermediate");
+		}
+		if update_period==0
+		{
+			panic!("update_period must be positive");
+		}
+		UGAL{
+			minimal,
+			nonminimal,
+			intermediates,
+			variant,
+			update_period,
+			threshold,
+			occupation: vec![],
+			decisions: RefCell::new([0,0]),
+		}
+	}
+	///The cost of going from `origin` to `destination`, following at each router the least occupied port that reduces the distance. It is the number of hops plus the occupation of the traversed ports.
+	fn route_cost(&self, topology:&dyn Topology, origin:usize, destination:usize) -> usize
+	{
+		let mut cost=0;
+		let mut current=origin;
+		while current!=destination
+		{
+			let distance=topology.distance(current,destination);
+			let (occupation,next) = (0..topology.ports(current)).filter_map(|port|{
+				match topology.neighbour(current,port)
+				{
+					(Location::RouterPort{router_index,..},_) if topology.distance(router_index,destination)+1==distance => Some((self.occupation[current][port],router_index)),
+					_ => None,
+				}
+			}).min().expect("there is no port towards destination");
+			cost+=1+occupation;
+			current=next;
+		}
+		cost
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::Plugs;
+	use rand::SeedableRng;
+	use crate::topology::cartesian::Hamming;
+	fn ugal(variant:&str) -> UGAL
+	{
+		let plugs=Plugs::default();
+		let cv=ConfigurationValue::Object(String::from("UGAL"),vec![
+			(String::from("nonminimal"),ConfigurationValue::Object(String::from("Valiant"),vec![
+				(String::from("first"),ConfigurationValue::Object(String::from("Shortest"),vec![])),
+				(String::from("second"),ConfigurationValue::Object(String::from("Shortest"),vec![])),
+			])),
+			(String::from("intermediates"),ConfigurationValue::Number(3.0)),
+			(String::from("variant"),ConfigurationValue::Object(variant.to_string(),vec![])),
+		]);
+		UGAL::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs})
+	}
+	fn hamming() -> Hamming
+	{
+		let cv=ConfigurationValue::Object(String::from("Hamming"),vec![
+			(String::from("sides"),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			(String::from("servers_per_router"),ConfigurationValue::Number(1.0)),
+		]);
+		Hamming::new(&cv)
+	}
+	#[test]
+	fn local_offers_all_routes()
+	{
+		let topology=hamming();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=ugal("Local");
+		routing.initialize(&topology,&mut rng);
+		let info=RefCell::new(RoutingInfo::new());
+		routing.initialize_routing_info(&info,&topology,0,5,Some(5),&mut rng);
+		let candidates=routing.next(&info.borrow(),&topology,0,5,Some(5),1,&mut rng).expect("no candidates").candidates;
+		assert!(candidates.iter().any(|c|c.annotation.as_ref().unwrap().values[0]==0),"missing the minimal route");
+		for candidate in candidates.iter()
+		{
+			assert!(candidate.estimated_remaining_hops.is_some());
+		}
+		//Requesting a nonminimal candidate keeps only its route.
+		let chosen=candidates.iter().find(|c|c.annotation.as_ref().unwrap().values[0]!=0).expect("no nonminimal candidate").clone();
+		routing.performed_request(&chosen,&info,&topology,0,5,Some(5),1,&mut rng);
+		assert_eq!(info.borrow().selections,Some(vec![chosen.annotation.as_ref().unwrap().values[0]]));
+		let after=routing.next(&info.borrow(),&topology,0,5,Some(5),1,&mut rng).expect("no candidates").candidates;
+		assert!(after.iter().all(|c|c.annotation.as_ref().unwrap().values==chosen.annotation.as_ref().unwrap().values));
+	}
+	#[test]
+	fn global_avoids_congestion()
+	{
+		let topology=hamming();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mut routing=ugal("Global");
+		routing.initialize(&topology,&mut rng);
+		//Without congestion the minimal route is always the best.
+		let info=RefCell::new(RoutingInfo::new());
+		routing.initialize_routing_info(&info,&topology,0,1,Some(1),&mut rng);
+		assert_eq!(info.borrow().selections,Some(vec![0]));
+		//Heavily congest the minimal ports from router 0 to router 1.
+		for port in 0..topology.ports(0)
+		{
+			if let (Location::RouterPort{router_index:1,..},_) = topology.neighbour(0,port)
+			{
+				routing.occupation[0][port]=1000;
+			}
+		}
+		let mut nonminimal=0;
+		for _ in 0..20
+		{
+			let info=RefCell::new(RoutingInfo::new());
+			routing.initialize_routing_info(&info,&topology,0,1,Some(1),&mut rng);
+			if info.borrow().selections!=Some(vec![0])
+			{
+				nonminimal+=1;
+			}
+		}
+		assert!(nonminimal>0,"UGAL-G never avoided the congested link");
+		let decisions=*routing.decisions.borrow();
+		assert_eq!(decisions[0]+decisions[1],21);
+	}
+}
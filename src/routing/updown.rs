@@ -4,15 +4,24 @@ Implementation of general Up/Down-like routings.
 
 * UpDown
 * UpDownStar (struct ExplicitUpDown)
+* MultipleUpDown
+* FatTreeRouting
 
 */
 
-use ::rand::{rngs::StdRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use ::rand::{prelude::SliceRandom,rngs::StdRng};
 use crate::general_pattern::{new_pattern};
 use crate::general_pattern::GeneralPatternBuilderArgument;
 use crate::match_object_panic;
 use crate::config_parser::ConfigurationValue;
 use crate::routing::prelude::*;
+use crate::routing::read_port_occupation;
+use crate::router::Router;
 use crate::topology::{Topology,NeighbourRouterIteratorItem,Location};
 use crate::matrix::Matrix;
 use crate::general_pattern::pattern::Pattern;
@@ -207,6 +216,76 @@ impl UpDown
 	}
 }
 
+///How to select the root of the spanning tree of an [ExplicitUpDown].
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum RootSelection
+{
+	///The router with the least eccentricity, this is, the least maximum distance to other routers. Ties go to the router of largest degree.
+	MinimumEccentricity,
+	///The router with the largest degree. Ties go to the router of least eccentricity.
+	MaximumDegree,
+}
+
+impl RootSelection
+{
+	pub fn new(cv:&ConfigurationValue) -> RootSelection
+	{
+		match cv
+		{
+			ConfigurationValue::Object(name,_) if name=="MinimumEccentricity" => RootSelection::MinimumEccentricity,
+			ConfigurationValue::Object(name,_) if name=="MaximumDegree" => RootSelection::MaximumDegree,
+			_ => panic!("bad value for a root selection, it must be MinimumEccentricity or MaximumDegree"),
+		}
+	}
+	///The selected router. Remaining ties are broken by the lowest index.
+	pub fn select(&self, topology:&dyn Topology) -> usize
+	{
+		let n = topology.num_routers();
+		let eccentricity = |router:usize| (0..n).map(|other|topology.distance(router,other)).max().unwrap_or(0);
+		(0..n).min_by_key(|&router|{
+			let degree = topology.degree(router);
+			let eccentricity = eccentricity(router);
+			match self
+			{
+				RootSelection::MinimumEccentricity => (eccentricity, usize::MAX-degree),
+				RootSelection::MaximumDegree => (usize::MAX-degree, eccentricity),
+			}
+		}).expect("there are no routers")
+	}
+}
+
+///The kind of spanning tree from which an [ExplicitUpDown] gives direction to the links.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum SpanningTree
+{
+	///Breadth-first. Each router is ranked by its distance to the root, so the links between routers at the same distance are neither up nor down.
+	BFS,
+	///Depth-first. Each router is ranked by the order in which it is first visited, so every link is either up or down.
+	DFS,
+}
+
+///The fraction of the pairs of different routers whose route length, as given by `length`, is the distance in the topology.
+pub fn minimal_fraction(topology:&dyn Topology, length:impl Fn(usize,usize)->Option<usize>) -> f64
+{
+	let n = topology.num_routers();
+	if n<2
+	{
+		return 1.0;
+	}
+	let mut minimal = 0;
+	for origin in 0..n
+	{
+		for destination in 0..n
+		{
+			if origin!=destination && length(origin,destination)==Some(topology.distance(origin,destination))
+			{
+				minimal += 1;
+			}
+		}
+	}
+	minimal as f64 / (n*(n-1)) as f64
+}
+
 ///Use a shortest up/down path from origin to destination.
 ///But in contrast with UpDown this uses explicit table instead of querying the topology.
 ///Used to define Up*/Down* (UpDownStar), see Autonet, where it is build from some spanning tree.
@@ -215,6 +294,10 @@ impl UpDown
 UpDownStar{
 	///The switch to select as root.
 	root: 0,
+	///Alternatively, a heuristic to select the root: MinimumEccentricity or MaximumDegree.
+	//root_selection: MinimumEccentricity,
+	///The spanning tree, BFS or DFS. Defaults to BFS.
+	//tree: DFS,
 	///Whether to allow travelling horizontal cross-branch links that reduce the up/down distance. Defaults to false.
 	branch_crossing:true,
 }
@@ -222,16 +305,27 @@ UpDownStar{
 Note how the `branch_crossing` option would cause deadlock if it were allowed to use down-links. Consider three flows, each flow having
 a unique posible last (down-link) hop. If this down-link could be used as a cross-branch by the next flow then that flow could block the former.
 If this were to happen simultaneously with the three flows it would create a deadlock.
+
+With a `DFS` tree the routers are ranked by the order of a depth-first search from the root, so there are no horizontal links and more links can be used, which usually preserves more minimal paths in irregular networks.
+The routing statistics include the fraction of pairs of routers whose up/down distance is the distance in the topology, as `minimal_fraction`.
+
+A packet keeps going up while that gives a shorter route than going only down, so it always follows a shortest up/down path. Once it takes a down-link it is marked in its routing info and only goes down from there on.
+The links that fail during the simulation are avoided while there are other candidates.
 **/
 #[derive(Debug)]
 pub struct ExplicitUpDown
 {
 	//defining factors to be kept up to initialization
 	pub root: Option<usize>,
+	pub root_selection: Option<RootSelection>,
+	pub tree: SpanningTree,
 	//computed at initialization
-	pub up_down_distances: Matrix<Option<u8>>,
-	pub down_distances: Matrix<Option<u8>>,
+	pub up_down_distances: Matrix<Option<usize>>,
+	pub down_distances: Matrix<Option<usize>>,
 	pub distance_to_root: Vec<u8>,
+	///The rank of each router in the spanning tree, a link towards a lower rank being an up-link. For BFS trees it is the distance to the root.
+	pub rank: Vec<usize>,
+	pub minimal_fraction: f64,
 	//other options
 	pub branch_crossings_downwards: bool,
 	pub branch_crossings_upwards: bool,
@@ -239,11 +333,14 @@ pub struct ExplicitUpDown
 	pub label_down: i32,
 	pub label_horizontal_vec: Vec<i32>,
 	pub label_horizontal_otherwise: i32,
+	///The links that have failed during the simulation, avoided while there are other up/down candidates.
+	pub failed_links: FailedLinks,
 }
 
+//routing_info.selections has 1 once the packet has taken a down-link, and 0 before.
 impl Routing for ExplicitUpDown
 {
-	fn next(&self, _routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//let (target_location,_link_class)=topology.server_neighbour(target_server);
 		//let target_router=match target_location
@@ -271,6 +368,12 @@ impl Routing for ExplicitUpDown
 		}
 		let up_down_distance = self.up_down_distances.get(current_router,target_router).unwrap_or_else(||panic!("Missing up/down path from {} to {}",current_router,target_router));
 		let down_distance = self.down_distances.get(current_router,target_router);
+		let gone_down = routing_info.selections.as_ref().expect("UpDownStar requires its routing info to be initialized")[0]==1;
+		//Going only down is chosen when it is a shortest up/down path, or when the packet has already gone down.
+		let down_distance = match down_distance {
+			&Some(down_distance) if gone_down || down_distance==up_down_distance => Some(down_distance),
+			_ => None,
+		};
 		let num_ports=topology.ports(current_router);
 		let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
 		for i in 0..num_ports
@@ -280,24 +383,25 @@ impl Routing for ExplicitUpDown
 			{
 				let mut label = 0i32;
 				let mut new_hops = 0usize;
-				let good = if let &Some(down_distance) = down_distance {
+				let good = if let Some(down_distance) = down_distance {
 					//We can already go down
 					let mut good = if let &Some(new_down) = self.down_distances.get(router_index,target_router) {
 						label = self.label_down;
-						new_hops = new_down.into();
-						new_down < down_distance
+						new_hops = new_down;
+						//With ranks other than the BFS levels a lower router may also have a shorter down path, so check the link goes down.
+						new_down < down_distance && self.rank[router_index]>self.rank[current_router]
 					} else {
 						false
 					};
 					//or there is some shortcut between branches
-					if !good && self.branch_crossings_downwards && self.distance_to_root[router_index]==self.distance_to_root[current_router] {
+					if !good && self.branch_crossings_downwards && self.rank[router_index]==self.rank[current_router] {
 						if let &Some(new_up_down) = self.up_down_distances.get(router_index,target_router)
 						{
 							if new_up_down < down_distance
 							{
 								good = true;
-								new_hops = new_up_down.into();
-								let delta = (down_distance-1-new_up_down) as usize;
+								new_hops = new_up_down;
+								let delta = down_distance-1-new_up_down;
 								if let Some(&x) = self.label_horizontal_vec.get(delta) {
 									label = x;
 								} else {
@@ -321,11 +425,11 @@ impl Routing for ExplicitUpDown
 						//}
 						if new_up_down < up_down_distance {
 							label = self.label_up;
-							new_hops = new_up_down.into();
-							let mut good = self.distance_to_root[router_index]<self.distance_to_root[current_router];
-							if !good && self.branch_crossings_upwards && self.distance_to_root[router_index]==self.distance_to_root[current_router] {
+							new_hops = new_up_down;
+							let mut good = self.rank[router_index]<self.rank[current_router];
+							if !good && self.branch_crossings_upwards && self.rank[router_index]==self.rank[current_router] {
 								good = true;
-								let delta = (up_down_distance-1-new_up_down) as usize;
+								let delta = up_down_distance-1-new_up_down;
 								if let Some(&x) = self.label_horizontal_vec.get(delta) {
 									label = x;
 								} else {
@@ -350,142 +454,251 @@ impl Routing for ExplicitUpDown
 		}
 		//println!("candidates={:?} current_router={current_router} target_router={target_router} up_down_distance={up_down_distance} down_distance={down_distance:?}",r.iter().map(|x|x.port).collect::<Vec<_>>());
 		//println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
-		Ok(RoutingNextCandidates{candidates:r,idempotent:true})
+		Ok(RoutingNextCandidates{candidates:self.failed_links.filter(current_router,r),idempotent:true})
+	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, _rng: &mut StdRng)
+	{
+		self.failed_links.update(topology,router,port,available);
 	}
 	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
 	{
 		let n = topology.num_routers();
+		if let Some(selection) = self.root_selection
+		{
+			self.root = Some(selection.select(topology));
+		}
 		if let Some(root) = self.root
 		{
-			self.up_down_distances = Matrix::constant(None,n,n);
-			self.down_distances = Matrix::constant(None,n,n);
-			//First perform a single BFS at root.
-			let mut distance_to_root=vec![None;n];
-			distance_to_root[root]=Some(0);
-			//A BFS from the root.
-			let mut downwards = Vec::with_capacity(n);
-			let mut read_index = 0;
-			downwards.push(root);
-			//for current in 0..n
-			while read_index < downwards.len()
+			match self.tree
 			{
-				let current = downwards[read_index];
-				read_index+=1;
-				if let Some(current_distance) = distance_to_root[current]
+				SpanningTree::BFS => self.compute_bfs_distances(topology,root),
+				SpanningTree::DFS =>
 				{
-					let alternate_distance = current_distance + 1;
-					for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(current)
-					{
-						if distance_to_root[neighbour].is_none()
-						{
-							distance_to_root[neighbour]=Some(alternate_distance);
-							downwards.push(neighbour);
-						}
-					}
-				}
+					self.distance_to_root = levels_to_u8(&bfs_levels(topology,root));
+					self.rank = dfs_ranks(topology,root);
+					self.compute_rank_distances(topology);
+				},
 			}
-			self.distance_to_root = distance_to_root.into_iter().map(|d|d.unwrap()).collect();
-			//dbg!(&distance_to_root);
-			//Second fill assuming going through root
-			//dbg!(root,"fill");
-			for origin in 0..n
+		}
+		if n!=self.up_down_distances.get_columns()
+		{
+			panic!("ExplicitUpDown has not being properly initialized");
+		}
+		//The packets follow shortest up/down paths, so these are the lengths of their routes.
+		self.minimal_fraction = minimal_fraction(topology,|origin,destination|*self.up_down_distances.get(origin,destination));
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		let mut content = vec![(String::from("minimal_fraction"),ConfigurationValue::Number(self.minimal_fraction))];
+		if let Some(root) = self.root
+		{
+			content.push((String::from("root"),ConfigurationValue::Number(root as f64)));
+		}
+		Some(ConfigurationValue::Object(String::from("UpDownStatistics"),content))
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections=Some(vec![0]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if let (Location::RouterPort{router_index:previous,..},_) = topology.neighbour(current_router,current_port)
+		{
+			if self.rank[current_router]>self.rank[previous]
 			{
-				let origin_to_root = self.distance_to_root[origin];
-				for target in 0..n
+				routing_info.borrow_mut().selections=Some(vec![1]);
+			}
+		}
+	}
+}
+
+///The distance of each router to `root`, by a BFS.
+fn bfs_levels(topology:&dyn Topology, root:usize) -> Vec<usize>
+{
+	let n = topology.num_routers();
+	let mut distance_to_root = vec![None;n];
+	distance_to_root[root] = Some(0);
+	let mut queue = VecDeque::new();
+	queue.push_back(root);
+	while let Some(current) = queue.pop_front()
+	{
+		let alternate_distance = distance_to_root[current].unwrap() + 1;
+		for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(current)
+		{
+			if distance_to_root[neighbour].is_none()
+			{
+				distance_to_root[neighbour] = Some(alternate_distance);
+				queue.push_back(neighbour);
+			}
+		}
+	}
+	distance_to_root.into_iter().enumerate().map(|(router,d)|d.unwrap_or_else(||panic!("router {} is not connected to the root {}",router,root))).collect()
+}
+
+///The order in which each router is first visited by a DFS from `root`, trying the neighbours in port order.
+fn dfs_ranks(topology:&dyn Topology, root:usize) -> Vec<usize>
+{
+	let n = topology.num_routers();
+	let mut order = vec![None;n];
+	let mut visited = 0;
+	order[root] = Some(visited);
+	visited += 1;
+	//The stack has the routers of the current branch with their neighbours yet to be tried.
+	let mut stack = vec![(root,topology.neighbour_router_iter(root))];
+	while let Some((_router,neighbours)) = stack.last_mut()
+	{
+		match neighbours.next()
+		{
+			Some(NeighbourRouterIteratorItem{neighbour_router:neighbour,..}) =>
+			{
+				if order[neighbour].is_none()
 				{
-					let target_to_root = self.distance_to_root[target];
-					*self.up_down_distances.get_mut(origin,target) = Some(origin_to_root+target_to_root);
+					order[neighbour] = Some(visited);
+					visited += 1;
+					stack.push((neighbour,topology.neighbour_router_iter(neighbour)));
+				}
+			},
+			None =>
+			{
+				stack.pop();
+			},
+		}
+	}
+	order.into_iter().enumerate().map(|(router,o)|o.unwrap_or_else(||panic!("router {} is not connected to the root {}",router,root))).collect()
+}
+
+///Converts the distances to the root into the `u8` of `ExplicitUpDown::distance_to_root`.
+fn levels_to_u8(levels:&[usize]) -> Vec<u8>
+{
+	levels.iter().enumerate().map(|(router,&level)|u8::try_from(level).unwrap_or_else(|_|panic!("The router {} is at distance {} from the root, which does not fit in distance_to_root",router,level))).collect()
+}
+
+impl ExplicitUpDown
+{
+	///Fills `distance_to_root`, `rank`, `up_down_distances` and `down_distances` for the BFS tree from `root`.
+	fn compute_bfs_distances(&mut self, topology:&dyn Topology, root:usize)
+	{
+		let n = topology.num_routers();
+		self.up_down_distances = Matrix::constant(None,n,n);
+		self.down_distances = Matrix::constant(None,n,n);
+		//First perform a single BFS at root.
+		let mut distance_to_root=vec![None;n];
+		distance_to_root[root]=Some(0);
+		//A BFS from the root.
+		let mut downwards = Vec::with_capacity(n);
+		let mut read_index = 0;
+		downwards.push(root);
+		while read_index < downwards.len()
+		{
+			let current = downwards[read_index];
+			read_index+=1;
+			if let Some(current_distance) = distance_to_root[current]
+			{
+				let alternate_distance = current_distance + 1;
+				for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(current)
+				{
+					if distance_to_root[neighbour].is_none()
+					{
+						distance_to_root[neighbour]=Some(alternate_distance);
+						downwards.push(neighbour);
+					}
 				}
-				*self.down_distances.get_mut(root,origin) = Some(origin_to_root);
 			}
-			//Update the distances considering not reaching the root.
-			for origin in 0..n
+		}
+		self.rank = distance_to_root.into_iter().enumerate().map(|(router,d)|d.unwrap_or_else(||panic!("router {} is not connected to the root {}",router,root))).collect();
+		self.distance_to_root = levels_to_u8(&self.rank);
+		//Second fill assuming going through root
+		for origin in 0..n
+		{
+			let origin_to_root = self.rank[origin];
+			for target in 0..n
 			{
-				*self.up_down_distances.get_mut(origin,origin) = Some(0);
-				*self.down_distances.get_mut(origin,origin) = Some(0);
+				let target_to_root = self.rank[target];
+				*self.up_down_distances.get_mut(origin,target) = Some(origin_to_root+target_to_root);
 			}
-			//dbg!(root,"segments");
-			//As invariant: fully computed the higher part (closer to the root).
-			for (low_index,&low) in downwards.iter().enumerate()
+			*self.down_distances.get_mut(root,origin) = Some(origin_to_root);
+		}
+		//Update the distances considering not reaching the root.
+		for origin in 0..n
+		{
+			*self.up_down_distances.get_mut(origin,origin) = Some(0);
+			*self.down_distances.get_mut(origin,origin) = Some(0);
+		}
+		//As invariant: fully computed the higher part (closer to the root).
+		for (low_index,&low) in downwards.iter().enumerate()
+		{
+			for &high in downwards[0..low_index].iter()
 			{
-				for &high in downwards[0..low_index].iter()
+				for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(low)
 				{
-					for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(low)
+					if self.rank[neighbour]+1==self.rank[low]
 					{
-						if self.distance_to_root[neighbour]+1==self.distance_to_root[low]
+						//neighbour is upwards
+						let neighbour_up_down = self.up_down_distances.get(neighbour,high).unwrap();
+						let origin_up_down = self.up_down_distances.get(low,high).unwrap();
+						if neighbour_up_down+1 < origin_up_down
 						{
-							//neighbour is upwards
-							let neighbour_up_down = self.up_down_distances.get(neighbour,high).unwrap();
-							let origin_up_down = self.up_down_distances.get(low,high).unwrap();
-							if neighbour_up_down+1 < origin_up_down
-							{
-								*self.up_down_distances.get_mut(low,high) = Some(neighbour_up_down+1);
-								*self.up_down_distances.get_mut(high,low) = Some(neighbour_up_down+1);
-							}
-							if let Some(neighbour_down) = self.down_distances.get(high,neighbour)
+							*self.up_down_distances.get_mut(low,high) = Some(neighbour_up_down+1);
+							*self.up_down_distances.get_mut(high,low) = Some(neighbour_up_down+1);
+						}
+						if let Some(neighbour_down) = self.down_distances.get(high,neighbour)
+						{
+							if self.down_distances.get(high,low).map(|origin_down|neighbour_down+1<origin_down).unwrap_or(true)
 							{
-								if self.down_distances.get(high,low).map(|origin_down|neighbour_down+1<origin_down).unwrap_or(true)
-								{
-									//println!("high={high} neighbour={neighbour} low={low} distance={}",neighbour_down+1);
-									*self.down_distances.get_mut(high,low) = Some(neighbour_down+1);
-								}
+								*self.down_distances.get_mut(high,low) = Some(neighbour_down+1);
 							}
 						}
 					}
 				}
 			}
-			//dbg!(&self.up_down_distances);
-			//for origin in 0..n
-			//{
-			//	//Start towards root annotating those that require only upwards.
-			//	//let _origin_to_root) = distance_to_root[origin];
-			//	let mut upwards=Vec::with_capacity(n);
-			//	upwards.push((origin,0));
-			//	let mut read_index = 0;
-			//	while read_index < upwards.len()
-			//	{
-			//		let (current,distance) = upwards[read_index];
-			//		let current_to_root = distance_to_root[current];
-			//		read_index+=1;
-			//		*self.up_down_distances.get_mut(origin,current)=Some((distance,0));
-			//		*self.up_down_distances.get_mut(current,origin)=Some((0,distance));
-			//		for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(current)
-			//		{
-			//			let neighbour_to_root = distance_to_root[neighbour];
-			//			if neighbour_to_root +1 == current_to_root
-			//			{
-			//				upwards.push((neighbour,distance+1));
-			//			}
-			//		}
-			//	}
-			//}
-			//dbg!(root,"finished table");
 		}
-		if n!=self.up_down_distances.get_columns()
+	}
+	///Fills `up_down_distances` and `down_distances` from the order given by `rank`. A link is up when it goes to a router with lower rank and down when it goes to a higher one. Links between routers with the same rank are not used.
+	fn compute_rank_distances(&mut self, topology:&dyn Topology)
+	{
+		let n = topology.num_routers();
+		self.up_down_distances = Matrix::constant(None,n,n);
+		self.down_distances = Matrix::constant(None,n,n);
+		for origin in 0..n
 		{
-			panic!("ExplicitUpDown has not being properly initialized");
+			//A BFS over the states (router,going_down). Starting in the down phase gives the down distances.
+			for &start_down in [true,false].iter()
+			{
+				let mut reached = vec![[false;2];n];
+				let mut queue = VecDeque::new();
+				reached[origin][start_down as usize]=true;
+				queue.push_back((origin,start_down,0));
+				while let Some((current,going_down,distance)) = queue.pop_front()
+				{
+					let table = if start_down { &mut self.down_distances } else { &mut self.up_down_distances };
+					if table.get(origin,current).is_none()
+					{
+						*table.get_mut(origin,current) = Some(distance);
+					}
+					for NeighbourRouterIteratorItem{neighbour_router:neighbour,..} in topology.neighbour_router_iter(current)
+					{
+						let next_down = if self.rank[neighbour] > self.rank[current] {
+							true
+						} else if self.rank[neighbour] < self.rank[current] && !going_down {
+							false
+						} else {
+							continue;
+						};
+						if !reached[neighbour][next_down as usize]
+						{
+							reached[neighbour][next_down as usize]=true;
+							queue.push_back((neighbour,next_down,distance+1));
+						}
+					}
+				}
+			}
 		}
 	}
-	//fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_server:usize, _rng: &mut StdRng)
-	//{
-	//	routing_info.borrow_mut().selections=Some(Vec::new());
-	//}
-	//fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, _current_port:usize, _target_server:usize, _rng: &mut StdRng)
-	//{
-	//	let mut bri = routing_info.borrow_mut();
-	//	let v = bri.selections.as_mut().unwrap();
-	//	let root = *self.root.as_ref().unwrap();
-	//	let distance = topology.distance(root,current_router);
-	//	v.push(distance as i32);
-	//	println!("distances={v:?} current_router={current_router}");
-	//}
-}
-
-impl ExplicitUpDown
-{
 	pub fn new(arg: RoutingBuilderArgument) -> ExplicitUpDown
 	{
 		let mut root = None;
+		let mut root_selection = None;
+		let mut tree = SpanningTree::BFS;
 		let mut branch_crossings_downwards = false;
 		let mut branch_crossings_upwards = false;
 		let mut label_down = 0i32;
@@ -494,6 +707,13 @@ impl ExplicitUpDown
 		let mut label_horizontal_otherwise = 0i32;
 		match_object_panic!(arg.cv,"UpDownStar",value,
 			"root" => root=Some(value.as_f64().expect("bad value for root") as usize),
+			"root_selection" => root_selection=Some(RootSelection::new(value)),
+			"tree" => tree=match value
+			{
+				ConfigurationValue::Object(name,_) if name=="BFS" => SpanningTree::BFS,
+				ConfigurationValue::Object(name,_) if name=="DFS" => SpanningTree::DFS,
+				_ => panic!("bad value for tree, it must be BFS or DFS"),
+			},
 			"branch_crossings" => {
 				branch_crossings_upwards = value.as_bool().expect("bad value for branch_crossings");
 				branch_crossings_downwards = branch_crossings_upwards;
@@ -507,17 +727,320 @@ impl ExplicitUpDown
 			}).collect(),
 			"label_horizontal_otherwise" => label_horizontal_otherwise = value.as_i32().expect("bad value for label_horizontal_otherwise"),
 		);
+		if root.is_some() && root_selection.is_some()
+		{
+			panic!("UpDownStar cannot have both root and root_selection");
+		}
 		ExplicitUpDown{
 			root,
+			root_selection,
+			tree,
 			up_down_distances: Matrix::constant(None,0,0),
 			down_distances: Matrix::constant(None,0,0),
 			distance_to_root: Vec::new(),
+			rank: Vec::new(),
+			minimal_fraction: 0.0,
 			branch_crossings_downwards,
 			branch_crossings_upwards,
 			label_down,
 			label_up,
 			label_horizontal_vec,
 			label_horizontal_otherwise,
+			failed_links: FailedLinks::default(),
+		}
+	}
+}
+
+/**
+Combines several [UpDownStar](ExplicitUpDown) spanning trees, each one using its own virtual channels. At injection each packet takes the tree with the shortest up/down route to its destination, with ties broken randomly. As each tree is deadlock-free in its own virtual channels, the combination is also deadlock-free.
+
+```ignore
+MultipleUpDown{
+	trees: [
+		UpDownStar{root_selection: MinimumEccentricity},
+		UpDownStar{root_selection: MaximumDegree, tree: DFS},
+	],
+	//virtual_channels: [[0],[1]],//optional, defaults to the virtual channel i for the tree i
+	legend_name: "two roots",
+}
+```
+It panics when routing through a tree whose virtual channels are not in the router, as with less virtual channels than trees by default.
+The routing statistics include the fraction of pairs of routers joined by a minimal route in some tree, as `minimal_fraction`, and that fraction for each tree, as `tree_minimal_fractions`.
+**/
+#[derive(Debug)]
+pub struct MultipleUpDown
+{
+	trees: Vec<ExplicitUpDown>,
+	///The virtual channels used by each tree.
+	virtual_channels: Vec<Vec<usize>>,
+	minimal_fraction: f64,
+}
+
+//routing_info.selections has the index of the selected tree and routing_info.meta the routing info of that tree.
+impl Routing for MultipleUpDown
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let tree = routing_info.selections.as_ref().expect("MultipleUpDown requires a selected tree")[0] as usize;
+		let virtual_channels = &self.virtual_channels[tree];
+		if let Some(&vc) = virtual_channels.iter().find(|&&vc|vc>=num_virtual_channels)
+		{
+			panic!("MultipleUpDown uses the virtual channel {} for the tree {}, but there are only {} virtual channels. Each of the {} trees requires its own virtual channels.",vc,tree,num_virtual_channels,self.trees.len());
+		}
+		let meta = routing_info.meta.as_ref().unwrap();
+		let base = self.trees[tree].next(&meta[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		let idempotent = base.idempotent;
+		let candidates = base.into_iter().filter(|egress|virtual_channels.contains(&egress.virtual_channel)).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let lengths:Vec<usize> = self.trees.iter().map(|tree|tree.up_down_distances.get(current_router,target_router).unwrap_or_else(||panic!("Missing up/down path from {} to {}",current_router,target_router))).collect();
+		let shortest = *lengths.iter().min().expect("there are no trees");
+		let best:Vec<usize> = (0..lengths.len()).filter(|&index|lengths[index]==shortest).collect();
+		let tree = *best.choose(rng).unwrap();
+		let mut bri = routing_info.borrow_mut();
+		bri.selections=Some(vec![tree as i32]);
+		bri.meta=Some(vec![RefCell::new(RoutingInfo::new())]);
+		self.trees[tree].initialize_routing_info(&bri.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,rng);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let bri = routing_info.borrow();
+		let tree = bri.selections.as_ref().expect("MultipleUpDown requires a selected tree")[0] as usize;
+		let meta = bri.meta.as_ref().unwrap();
+		meta[0].borrow_mut().hops+=1;
+		self.trees[tree].update_routing_info(&meta[0],topology,current_router,current_port,target_router,target_server,rng);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		for tree in self.trees.iter_mut()
+		{
+			tree.initialize(topology,rng);
+		}
+		let trees = &self.trees;
+		self.minimal_fraction = minimal_fraction(topology,|origin,destination|trees.iter().filter_map(|tree|*tree.up_down_distances.get(origin,destination)).min());
+	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		for tree in self.trees.iter_mut()
+		{
+			tree.link_status_change(topology,router,port,available,rng);
+		}
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		for tree in self.trees.iter_mut()
+		{
+			tree.update_global_status(routers,topology,cycle);
+		}
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		Some(ConfigurationValue::Object(String::from("MultipleUpDownStatistics"),vec![
+			(String::from("minimal_fraction"),ConfigurationValue::Number(self.minimal_fraction)),
+			(String::from("tree_minimal_fractions"),ConfigurationValue::Array(self.trees.iter().map(|tree|ConfigurationValue::Number(tree.minimal_fraction)).collect())),
+		]))
+	}
+}
+
+impl MultipleUpDown
+{
+	pub fn new(arg: RoutingBuilderArgument) -> MultipleUpDown
+	{
+		let mut trees = None;
+		let mut virtual_channels = None;
+		match_object_panic!(arg.cv,"MultipleUpDown",value,
+			"trees" => trees=Some(value.as_array().expect("bad value for trees").iter().map(|tree|ExplicitUpDown::new(RoutingBuilderArgument{cv:tree,..arg})).collect::<Vec<_>>()),
+			"virtual_channels" => virtual_channels=Some(value.as_array().expect("bad value for virtual_channels").iter().map(|list|{
+				list.as_array().expect("bad value for virtual_channels").iter().map(|vc|vc.as_usize().expect("bad value in virtual_channels")).collect()
+			}).collect::<Vec<Vec<usize>>>()),
+		);
+		let trees = trees.expect("There were no trees");
+		let virtual_channels = virtual_channels.unwrap_or_else(||(0..trees.len()).map(|index|vec![index]).collect());
+		if virtual_channels.len()!=trees.len()
+		{
+			panic!("MultipleUpDown requires a list of virtual channels for each tree");
+		}
+		MultipleUpDown{
+			trees,
+			virtual_channels,
+			minimal_fraction: 0.0,
+		}
+	}
+}
+
+///How `FatTreeRouting` selects the up port.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum UpPortSelection
+{
+	///The up ports with the least phits in their queues, as known by the credits of the current router.
+	Adaptive,
+	///The deterministic d-mod-k. At each up hop it takes the port given by the target server modulo the number of up ports, after dividing it by the number of up ports of the previous hops.
+	DModK,
+}
+
+/**
+Minimal routing for fat-trees and other `MultiStage` topologies. The packet goes up until its up distance, as given by `Topology::up_down_distance`, becomes zero, and then it switches to the down phase, following the down ports that reduce the down distance.
+With `selection: Adaptive` the up port is selected by the occupation of its queues, as known from the credits of the current router, which is read every `update_period` cycles. All the up ports with the least occupation are offered. With `selection: DModK` the up port is deterministic, to compare against the adaptive selection.
+
+```ignore
+FatTreeRouting{
+	selection: Adaptive,//or DModK
+	//update_period: 1,//optional, for Adaptive
+	legend_name: "adaptive fat-tree",
+}
+```
+**/
+#[derive(Debug)]
+pub struct FatTreeRouting
+{
+	selection: UpPortSelection,
+	///Cycles between reads of the credits for `Adaptive`.
+	update_period: Time,
+	///For `Adaptive`, the phits occupying the queues beyond each port, as `occupation[router][port]`.
+	occupation: Vec<Vec<usize>>,
+}
+
+//routing_info.selections has the divisor of the target server in DModK, the product of the up ports of the previous hops.
+impl Routing for FatTreeRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let (up_distance, down_distance) = topology.up_down_distance(current_router,target_router).unwrap_or_else(||panic!("FatTreeRouting requires up/down paths, but there is no up/down path from {} to {}",current_router,target_router));
+		if up_distance + down_distance == 0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let remaining = up_distance + down_distance - 1;
+		let (ports, idempotent) = if up_distance > 0
+		{
+			let up_ports = up_ports(topology,current_router,target_router);
+			match self.selection
+			{
+				UpPortSelection::Adaptive =>
+				{
+					let least = up_ports.iter().map(|&port|self.occupation[current_router][port]).min().expect("there are no up ports");
+					(up_ports.into_iter().filter(|&port|self.occupation[current_router][port]==least).collect(),false)
+				},
+				UpPortSelection::DModK =>
+				{
+					let divisor = routing_info.selections.as_ref().expect("DModK requires the selections")[0] as usize;
+					let key = target_server.unwrap_or(target_router);
+					(vec![up_ports[(key/divisor)%up_ports.len()]],true)
+				},
+			}
+		}
+		else
+		{
+			let mut ports = vec![];
+			for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(current_router)
+			{
+				if let Some((0,new_d)) = topology.up_down_distance(neighbour_router,target_router)
+				{
+					if new_d<down_distance
+					{
+						ports.push(port_index);
+					}
+				}
+			}
+			(ports,true)
+		};
+		let candidates = ports.into_iter().flat_map(|port|(0..num_virtual_channels).map(move |vc|{
+			let mut egress = CandidateEgress::new(port,vc);
+			egress.estimated_remaining_hops = Some(remaining);
+			egress
+		})).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if self.selection == UpPortSelection::DModK
+		{
+			routing_info.borrow_mut().selections=Some(vec![1]);
+		}
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if self.selection != UpPortSelection::DModK
+		{
+			return;
+		}
+		if let (Location::RouterPort{router_index: previous_router,..},_link_class)=topology.neighbour(current_router,current_port)
+		{
+			let previous_up = topology.up_down_distance(previous_router,target_router).map(|(u,_d)|u).unwrap_or(0);
+			if previous_up > 0
+			{
+				let amount = up_ports(topology,previous_router,target_router).len() as i32;
+				let mut bri = routing_info.borrow_mut();
+				let selections = bri.selections.as_mut().expect("DModK requires the selections");
+				selections[0] *= amount;
+			}
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		if self.selection == UpPortSelection::Adaptive
+		{
+			self.occupation=(0..topology.num_routers()).map(|router|vec![0;topology.ports(router)]).collect();
+		}
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		if self.selection == UpPortSelection::Adaptive && cycle%self.update_period==0
+		{
+			read_port_occupation(&mut self.occupation,routers,topology);
+		}
+	}
+}
+
+///The ports of `router` going up towards `target`, which reduce the up distance without increasing the down distance.
+fn up_ports(topology:&dyn Topology, router:usize, target:usize) -> Vec<usize>
+{
+	let (up_distance, down_distance) = topology.up_down_distance(router,target).unwrap_or_else(||panic!("There is no up/down path from {} to {}",router,target));
+	topology.neighbour_router_iter(router).filter_map(|NeighbourRouterIteratorItem{port_index,neighbour_router,..}|{
+		match topology.up_down_distance(neighbour_router,target)
+		{
+			Some((new_u,new_d)) if new_u<up_distance && new_d<=down_distance => Some(port_index),
+			_ => None,
+		}
+	}).collect()
+}
+
+impl FatTreeRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> FatTreeRouting
+	{
+		let mut selection = None;
+		let mut update_period = 1;
+		match_object_panic!(arg.cv,"FatTreeRouting",value,
+			"selection" => selection=Some(match value
+			{
+				ConfigurationValue::Object(name,_) if name=="Adaptive" => UpPortSelection::Adaptive,
+				ConfigurationValue::Object(name,_) if name=="DModK" => UpPortSelection::DModK,
+				_ => panic!("bad value for selection, it must be Adaptive or DModK"),
+			}),
+			"update_period" => update_period=value.as_time().expect("bad value for update_period"),
+		);
+		if update_period==0
+		{
+			panic!("update_period must be positive");
+		}
+		FatTreeRouting{
+			selection: selection.expect("There were no selection"),
+			update_period,
+			occupation: vec![],
 		}
 	}
 }
@@ -528,6 +1051,8 @@ mod tests {
 	use crate::Plugs;
 	use rand::SeedableRng;
 	use crate::topology::cartesian::Hamming;
+	use crate::topology::TopologyBuilderArgument;
+	use crate::topology::multistage::MultiStage;
 	#[test]
 	fn up_down_star()
 	{
@@ -562,5 +1087,192 @@ mod tests {
 			}
 		}
 	}
+	fn fat_tree(selection:&str) -> (FatTreeRouting,MultiStage,StdRng)
+	{
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let sizes = |list:&[f64]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x)).collect());
+		let xgft_cv = ConfigurationValue::Object("XGFT".to_string(),vec![
+			("height".to_string(),ConfigurationValue::Number(2.0)),
+			("down".to_string(),sizes(&[4.0,4.0])),
+			("up".to_string(),sizes(&[4.0,4.0])),
+			("servers_per_leaf".to_string(),ConfigurationValue::Number(4.0)),
+		]);
+		let topology = MultiStage::new(TopologyBuilderArgument{cv:&xgft_cv,plugs:&plugs,rng:&mut rng});
+		let routing_cv = ConfigurationValue::Object("FatTreeRouting".to_string(),vec![("selection".to_string(),ConfigurationValue::Object(selection.to_string(),vec![]))]);
+		let mut routing = FatTreeRouting::new(RoutingBuilderArgument{cv:&routing_cv,plugs:&plugs});
+		routing.initialize(&topology,&mut rng);
+		(routing,topology,rng)
+	}
+	#[test]
+	fn fat_tree_d_mod_k()
+	{
+		let (routing,topology,mut rng) = fat_tree("DModK");
+		let leafs = 16;
+		for source in 0..leafs
+		{
+			let mut first_ports = vec![];
+			for target_server in 0..leafs*4
+			{
+				let target = target_server/4;
+				if target == source
+				{
+					continue;
+				}
+				let (up,down) = topology.up_down_distance(source,target).unwrap();
+				let info = RefCell::new(RoutingInfo::new());
+				routing.initialize_routing_info(&info,&topology,source,target,Some(target_server),&mut rng);
+				let mut current = source;
+				let mut hops = 0;
+				while current != target
+				{
+					let candidates = routing.next(&info.borrow(),&topology,current,target,Some(target_server),1,&mut rng).unwrap();
+					assert!(candidates.idempotent);
+					assert_eq!(candidates.candidates.len(),1,"d-mod-k must be deterministic");
+					let port = candidates.candidates[0].port;
+					if hops == 0
+					{
+						first_ports.push(port);
+					}
+					match topology.neighbour(current,port).0
+					{
+						Location::RouterPort{router_index,router_port} =>
+						{
+							current = router_index;
+							hops += 1;
+							info.borrow_mut().hops = hops;
+							routing.update_routing_info(&info,&topology,current,router_port,target,Some(target_server),&mut rng);
+						},
+						_ => panic!("reached a server before the target"),
+					}
+				}
+				assert_eq!(hops,up+down);
+			}
+			// The targets are spread over the up ports.
+			for port in 0..4
+			{
+				assert_eq!(first_ports.iter().filter(|&&p|p==port).count(),15,"source={} ports={:?}",source,first_ports);
+			}
+		}
+	}
+	#[test]
+	fn fat_tree_adaptive()
+	{
+		let (mut routing,topology,mut rng) = fat_tree("Adaptive");
+		// From leaf 0 to leaf 15 all the up ports are minimal.
+		let info = RoutingInfo::new();
+		let next = routing.next(&info,&topology,0,15,Some(60),2,&mut rng).unwrap();
+		assert!(!next.idempotent);
+		assert_eq!(next.candidates.len(),8);
+		for port in [0,1,3]
+		{
+			routing.occupation[0][port] = 10;
+		}
+		let next = routing.next(&info,&topology,0,15,Some(60),2,&mut rng).unwrap();
+		assert_eq!(next.candidates.iter().map(|c|c.port).collect::<Vec<_>>(),vec![2,2]);
+		// Once at the top the way down is deterministic.
+		let middle = match topology.neighbour(0,2).0 { Location::RouterPort{router_index,..} => router_index, _ => unreachable!() };
+		let top = match topology.neighbour(middle,0).0 { Location::RouterPort{router_index,..} => router_index, _ => unreachable!() };
+		assert_eq!(topology.up_down_distance(top,15),Some((0,2)));
+		let next = routing.next(&info,&topology,top,15,Some(60),1,&mut rng).unwrap();
+		assert!(next.idempotent);
+		assert_eq!(next.candidates.len(),1);
+	}
+	#[test]
+	fn root_selection_and_trees()
+	{
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let mesh_cv = ConfigurationValue::Object("Mesh".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![
+			ConfigurationValue::Number(5.0),
+			ConfigurationValue::Number(5.0),
+		])),("servers_per_router".to_string(),ConfigurationValue::Number(1.0))]);
+		let topology = crate::topology::cartesian::Mesh::new(&mesh_cv);
+		// The center of the mesh has both the minimum eccentricity and the maximum degree, reached first among the routers of degree 4.
+		assert_eq!(RootSelection::MinimumEccentricity.select(&topology),12);
+		assert_eq!(RootSelection::MaximumDegree.select(&topology),12);
+		for tree in ["BFS","DFS"]
+		{
+			let cv = ConfigurationValue::Object("UpDownStar".to_string(),vec![
+				("root_selection".to_string(),ConfigurationValue::Object("MaximumDegree".to_string(),vec![])),
+				("tree".to_string(),ConfigurationValue::Object(tree.to_string(),vec![])),
+			]);
+			let mut uds = ExplicitUpDown::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs});
+			uds.initialize(&topology,&mut rng);
+			assert_eq!(uds.root,Some(12));
+			let n = topology.num_routers();
+			for origin in 0..n
+			{
+				for destination in 0..n
+				{
+					let length = uds.up_down_distances.get(origin,destination).expect("missing an up/down distance");
+					assert!(length >= topology.distance(origin,destination));
+				}
+			}
+			// Every minimal path in the mesh is an up/down path of the BFS tree from its center, but not of the DFS tree.
+			if tree=="BFS"
+			{
+				assert_eq!(uds.minimal_fraction,1.0);
+			}
+			else
+			{
+				assert!(uds.minimal_fraction>0.0 && uds.minimal_fraction<1.0,"minimal_fraction={}",uds.minimal_fraction);
+			}
+		}
+	}
+	#[test]
+	fn up_down_star_dfs_ring()
+	{
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let torus_cv = ConfigurationValue::Object("Torus".to_string(),vec![("sides".to_string(),ConfigurationValue::Array(vec![
+			ConfigurationValue::Number(20.0),
+		])),("servers_per_router".to_string(),ConfigurationValue::Number(1.0))]);
+		let topology = crate::topology::cartesian::Torus::new(&torus_cv);
+		let cv = ConfigurationValue::Object("UpDownStar".to_string(),vec![
+			("root".to_string(),ConfigurationValue::Number(10.0)),
+			("tree".to_string(),ConfigurationValue::Object("DFS".to_string(),vec![])),
+		]);
+		let mut uds = ExplicitUpDown::new(RoutingBuilderArgument{cv:&cv,plugs:&plugs});
+		uds.initialize(&topology,&mut rng);
+		assert_eq!(uds.distance_to_root[0],10);
+		let n = topology.num_routers();
+		let route_length = |origin:usize,target:usize,rng:&mut StdRng| {
+			let info = RefCell::new(RoutingInfo::new());
+			uds.initialize_routing_info(&info,&topology,origin,target,Some(target),rng);
+			let mut current = origin;
+			let mut hops = 0;
+			let mut gone_down = false;
+			while current != target
+			{
+				let candidates = uds.next(&info.borrow(),&topology,current,target,Some(target),1,rng).unwrap();
+				let port = candidates.candidates.first().unwrap_or_else(||panic!("no candidates from {} towards {}",current,target)).port;
+				match topology.neighbour(current,port).0
+				{
+					Location::RouterPort{router_index,router_port} =>
+					{
+						// Never an up-link after a down-link.
+						let down = uds.rank[router_index]>uds.rank[current];
+						assert!(down || !gone_down,"an up-link after a down-link from {} to {}",origin,target);
+						gone_down |= down;
+						current = router_index;
+						hops += 1;
+						info.borrow_mut().hops = hops;
+						uds.update_routing_info(&info,&topology,current,router_port,target,Some(target),rng);
+					},
+					_ => panic!("reached a server before the target"),
+				}
+			}
+			hops
+		};
+		// Going down from 9 towards 11 would go all around the ring.
+		assert_eq!(route_length(9,11,&mut rng),2);
+		for origin in 0..n
+		{
+			for target in 0..n
+			{
+				assert_eq!(Some(route_length(origin,target,&mut rng)),*uds.up_down_distances.get(origin,target),"origin={} target={}",origin,target);
+			}
+		}
+	}
 }
-
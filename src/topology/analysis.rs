@@ -0,0 +1,353 @@
+/*!
+
+Characterisation of a topology without simulating it.
+
+[topology_analysis] builds a `TopologyAnalysis` object with graph measures of the network, and [analyze_experiment] does the same for the topology of an experiment configuration, so that the `analyze` action can store it in the `local.analysis` file of each experiment.
+
+*/
+
+use rand::{Rng,SeedableRng};
+use rand::seq::SliceRandom;
+use super::prelude::*;
+use super::new_topology;
+use crate::config_parser::ConfigurationValue;
+use crate::Plugs;
+
+///Number of random starting partitions tried by the bisection heuristic, besides the one following a BFS order.
+const BISECTION_RANDOM_STARTS: usize = 4;
+///Maximum number of iterations in the computation of the algebraic connectivity.
+const SPECTRAL_ITERATIONS: usize = 20000;
+
+/**
+Analyze a topology, returning an object with the following fields. Only the links between routers are considered for the graph measures.
+* `num_routers`, `num_servers`, `num_links` (links between routers, each one counted once), `maximum_degree` and `minimum_degree`.
+* `connected`, whether every router can reach every other one. When false the distance measures only consider reachable pairs.
+* `diameter` and `average_distance`, the average over the pairs of different routers.
+* `distance_distribution`, where position `d` is the number of ordered pairs of routers at distance `d`.
+* `average_shortest_paths` and `minimum_shortest_paths`, the average and minimum amount of shortest paths among pairs of different routers, which measures path diversity.
+* `bisection_width`, the smallest number of links found between two halves of the routers, using a Kernighan–Lin heuristic. It is thus an upper bound of the real bisection width.
+* `algebraic_connectivity`, the second smallest eigenvalue of the Laplacian matrix of the router graph.
+* `link_class_counts`, where position `c` is the number of links of class `c`, including the links to servers.
+**/
+pub fn topology_analysis(topology:&dyn Topology, rng:&mut StdRng) -> ConfigurationValue
+{
+	let n=topology.num_routers();
+	let adjacency:Vec<Vec<usize>>=(0..n).map(|router|topology.neighbour_router_iter(router).map(|item|item.neighbour_router).collect()).collect();
+	let num_links=adjacency.iter().map(|list|list.len()).sum::<usize>()/2;
+	//Distances and amount of shortest paths by BFS from each router.
+	let mut distance_distribution:Vec<usize>=vec![0];
+	let mut connected=true;
+	let mut distance_sum=0usize;
+	let mut pair_count=0usize;
+	let mut paths_sum=0f64;
+	let mut minimum_paths:Option<usize>=None;
+	for origin in 0..n
+	{
+		let mut distance=vec![None;n];
+		let mut amount=vec![0usize;n];
+		distance[origin]=Some(0);
+		amount[origin]=1;
+		let mut queue=std::collections::VecDeque::from(vec![origin]);
+		while let Some(current)=queue.pop_front()
+		{
+			let next_distance=distance[current].unwrap()+1;
+			for &neighbour in adjacency[current].iter()
+			{
+				match distance[neighbour]
+				{
+					None =>
+					{
+						distance[neighbour]=Some(next_distance);
+						amount[neighbour]=amount[current];
+						queue.push_back(neighbour);
+					},
+					Some(d) if d==next_distance => amount[neighbour]+=amount[current],
+					_ => (),
+				}
+			}
+		}
+		for target in (0..n).filter(|&target|target!=origin)
+		{
+			match distance[target]
+			{
+				Some(d) =>
+				{
+					if distance_distribution.len()<=d
+					{
+						distance_distribution.resize(d+1,0);
+					}
+					distance_distribution[d]+=1;
+					distance_sum+=d;
+					pair_count+=1;
+					paths_sum+=amount[target] as f64;
+					minimum_paths=Some(minimum_paths.map_or(amount[target],|m|m.min(amount[target])));
+				},
+				None => connected=false,
+			}
+		}
+	}
+	let average = |total:f64| if pair_count>0 { total/pair_count as f64 } else { 0.0 };
+	let mut link_class_counts:Vec<usize>=vec![];
+	let mut count_class=|link_class:usize|
+	{
+		if link_class_counts.len()<=link_class
+		{
+			link_class_counts.resize(link_class+1,0);
+		}
+		link_class_counts[link_class]+=1;
+	};
+	for router in 0..n
+	{
+		for item in topology.neighbour_router_iter(router)
+		{
+			if (router,item.port_index)<(item.neighbour_router,item.neighbour_port)
+			{
+				count_class(item.link_class);
+			}
+		}
+	}
+	for server in 0..topology.num_servers()
+	{
+		count_class(topology.server_neighbour(server).1);
+	}
+	let to_cv_array = |list:&[usize]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+	ConfigurationValue::Object(String::from("TopologyAnalysis"),vec![
+		(String::from("num_routers"),ConfigurationValue::Number(n as f64)),
+		(String::from("num_servers"),ConfigurationValue::Number(topology.num_servers() as f64)),
+		(String::from("num_links"),ConfigurationValue::Number(num_links as f64)),
+		(String::from("maximum_degree"),ConfigurationValue::Number(adjacency.iter().map(|list|list.len()).max().unwrap_or(0) as f64)),
+		(String::from("minimum_degree"),ConfigurationValue::Number(adjacency.iter().map(|list|list.len()).min().unwrap_or(0) as f64)),
+		(String::from("connected"),if connected {ConfigurationValue::True} else {ConfigurationValue::False}),
+		(String::from("diameter"),ConfigurationValue::Number((distance_distribution.len()-1) as f64)),
+		(String::from("average_distance"),ConfigurationValue::Number(average(distance_sum as f64))),
+		(String::from("distance_distribution"),to_cv_array(&distance_distribution)),
+		(String::from("average_shortest_paths"),ConfigurationValue::Number(average(paths_sum))),
+		(String::from("minimum_shortest_paths"),ConfigurationValue::Number(minimum_paths.unwrap_or(0) as f64)),
+		(String::from("bisection_width"),ConfigurationValue::Number(bisection_width(&adjacency,rng) as f64)),
+		(String::from("algebraic_connectivity"),ConfigurationValue::Number(algebraic_connectivity(&adjacency,rng))),
+		(String::from("link_class_counts"),to_cv_array(&link_class_counts)),
+	])
+}
+
+/**
+Analyze the topology of an experiment, given as a `Configuration` object. The topology is built with the `random_seed` of the experiment, so randomized topologies are the same as in the simulation.
+**/
+pub fn analyze_experiment(configuration:&ConfigurationValue, plugs:&Plugs) -> ConfigurationValue
+{
+	let mut topology_cfg=None;
+	let mut seed=None;
+	if let ConfigurationValue::Object(_,pairs)=configuration
+	{
+		for (name,value) in pairs.iter()
+		{
+			match name.as_ref()
+			{
+				"topology" => topology_cfg=Some(value),
+				"random_seed" => seed=Some(value.as_usize().expect("bad value for random_seed")),
+				_ => (),
+			}
+		}
+	}
+	else
+	{
+		panic!("Trying to analyze an experiment from a non-Object");
+	}
+	let topology_cfg=topology_cfg.expect("There were no topology");
+	let seed=seed.expect("There were no random_seed");
+	//Same seeding as in the simulation.
+	let mut rng=StdRng::seed_from_u64(seed as u64);
+	let topology=new_topology(TopologyBuilderArgument{cv:topology_cfg,plugs,rng:&mut rng});
+	topology_analysis(topology.as_ref(),&mut rng)
+}
+
+///Number of links crossing the partition.
+fn cut_size(adjacency:&[Vec<usize>], side:&[bool]) -> usize
+{
+	adjacency.iter().enumerate().map(|(router,list)|list.iter().filter(|&&neighbour|side[router] && !side[neighbour]).count()).sum()
+}
+
+///Estimate the bisection width by Kernighan–Lin refinements of several starting partitions.
+fn bisection_width(adjacency:&[Vec<usize>], rng:&mut StdRng) -> usize
+{
+	let n=adjacency.len();
+	if n<2
+	{
+		return 0;
+	}
+	//The first start takes the routers in BFS order from router 0, which keeps close routers together.
+	let mut order=Vec::with_capacity(n);
+	let mut visited=vec![false;n];
+	for root in 0..n
+	{
+		if visited[root] { continue; }
+		visited[root]=true;
+		let mut index=order.len();
+		order.push(root);
+		while index<order.len()
+		{
+			let current=order[index];
+			index+=1;
+			for &neighbour in adjacency[current].iter()
+			{
+				if !visited[neighbour]
+				{
+					visited[neighbour]=true;
+					order.push(neighbour);
+				}
+			}
+		}
+	}
+	let mut best=usize::MAX;
+	for start in 0..=BISECTION_RANDOM_STARTS
+	{
+		if start>0
+		{
+			order.shuffle(rng);
+		}
+		let mut side=vec![false;n];
+		for &router in order.iter().take(n/2)
+		{
+			side[router]=true;
+		}
+		kernighan_lin(adjacency,&mut side);
+		best=best.min(cut_size(adjacency,&side));
+	}
+	best
+}
+
+///Improve a partition by passes of Kernighan–Lin swaps until no pass reduces the cut.
+fn kernighan_lin(adjacency:&[Vec<usize>], side:&mut [bool])
+{
+	let n=adjacency.len();
+	loop
+	{
+		//gain[v] = external links - internal links of v.
+		let mut gain:Vec<i64>=(0..n).map(|v|adjacency[v].iter().map(|&w|if side[v]!=side[w] {1} else {-1}).sum()).collect();
+		let mut locked=vec![false;n];
+		let mut swaps=vec![];
+		let mut total=0i64;
+		let mut best_total=0i64;
+		let mut best_length=0;
+		loop
+		{
+			let best_in=|in_side:bool, locked:&[bool], gain:&[i64]| (0..n).filter(|&v|side[v]==in_side && !locked[v]).max_by_key(|&v|gain[v]);
+			let (a,b)=match (best_in(true,&locked,&gain),best_in(false,&locked,&gain))
+			{
+				(Some(a),Some(b)) => (a,b),
+				_ => break,
+			};
+			let common=adjacency[a].iter().filter(|&&w|w==b).count() as i64;
+			total+=gain[a]+gain[b]-2*common;
+			locked[a]=true;
+			locked[b]=true;
+			swaps.push((a,b));
+			//Update the gains as if a and b were swapped.
+			side[a]^=true;
+			side[b]^=true;
+			for &v in [a,b].iter()
+			{
+				for &w in adjacency[v].iter()
+				{
+					//The link v-w changed from internal to external or conversely.
+					gain[w] += if side[v]!=side[w] { 2 } else { -2 };
+				}
+			}
+			if total>best_total
+			{
+				best_total=total;
+				best_length=swaps.len();
+			}
+		}
+		//Undo the swaps after the best prefix.
+		for &(a,b) in swaps[best_length..].iter()
+		{
+			side[a]^=true;
+			side[b]^=true;
+		}
+		if best_total<=0
+		{
+			break;
+		}
+	}
+}
+
+///Second smallest eigenvalue of the Laplacian, by power iteration of `c*I-L` orthogonally to the constant vector.
+fn algebraic_connectivity(adjacency:&[Vec<usize>], rng:&mut StdRng) -> f64
+{
+	let n=adjacency.len();
+	if n<2
+	{
+		return 0.0;
+	}
+	let maximum_degree=adjacency.iter().map(|list|list.len()).max().unwrap_or(0) as f64;
+	let shift=2.0*maximum_degree+1.0;
+	let normalize=|x:&mut Vec<f64>|
+	{
+		let mean=x.iter().sum::<f64>()/n as f64;
+		x.iter_mut().for_each(|value|*value-=mean);
+		let norm=x.iter().map(|value|value*value).sum::<f64>().sqrt();
+		x.iter_mut().for_each(|value|*value/=norm);
+	};
+	let mut x:Vec<f64>=(0..n).map(|_|rng.gen::<f64>()-0.5).collect();
+	normalize(&mut x);
+	let mut eigenvalue=0.0;
+	for _ in 0..SPECTRAL_ITERATIONS
+	{
+		//y = (shift*I - L) x, with L x = degree*x - sum of neighbours.
+		let mut y:Vec<f64>=(0..n).map(|v|(shift-adjacency[v].len() as f64)*x[v]+adjacency[v].iter().map(|&w|x[w]).sum::<f64>()).collect();
+		let rayleigh:f64=x.iter().zip(y.iter()).map(|(a,b)|a*b).sum();
+		normalize(&mut y);
+		x=y;
+		if (rayleigh-eigenvalue).abs()<1e-12*shift
+		{
+			eigenvalue=rayleigh;
+			break;
+		}
+		eigenvalue=rayleigh;
+	}
+	(shift-eigenvalue).max(0.0)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::file_topology::{FileTopology,parse_edge_list};
+	fn field<'a>(analysis:&'a ConfigurationValue, name:&str) -> &'a ConfigurationValue
+	{
+		match analysis
+		{
+			ConfigurationValue::Object(_,pairs) => &pairs.iter().find(|(key,_)|key==name).expect("missing field").1,
+			_ => panic!("not an object"),
+		}
+	}
+	#[test]
+	fn ring_analysis()
+	{
+		//A ring of 8 routers with a server each.
+		let edges:String=(0..8).map(|i|format!("r{} * r{} *\n",i,(i+1)%8)).collect();
+		let topology=FileTopology::from_graph(&parse_edge_list(&edges),1);
+		let mut rng=StdRng::seed_from_u64(3);
+		let analysis=topology_analysis(&topology,&mut rng);
+		assert_eq!(field(&analysis,"diameter").as_f64().unwrap(),4.0);
+		assert_eq!(field(&analysis,"num_links").as_f64().unwrap(),8.0);
+		//Each router has two routers at distances 1,2,3 and one at distance 4.
+		assert_eq!(field(&analysis,"average_distance").as_f64().unwrap(),16.0/7.0);
+		assert_eq!(field(&analysis,"distance_distribution").as_array().unwrap().len(),5);
+		//The opposite router is reached by two shortest paths.
+		assert_eq!(field(&analysis,"average_shortest_paths").as_f64().unwrap(),8.0/7.0);
+		assert_eq!(field(&analysis,"bisection_width").as_f64().unwrap(),2.0);
+		//The Laplacian of the cycle C_n has λ2 = 2-2cos(2π/n).
+		let expected=2.0-2.0*(2.0*std::f64::consts::PI/8.0).cos();
+		assert!((field(&analysis,"algebraic_connectivity").as_f64().unwrap()-expected).abs()<1e-6);
+		let classes=field(&analysis,"link_class_counts").as_array().unwrap();
+		assert_eq!(classes[0].as_f64().unwrap(),8.0);
+		assert_eq!(classes[1].as_f64().unwrap(),8.0);
+		//It must be readable as a result file.
+		match crate::config_parser::parse(&format!("{}",analysis))
+		{
+			Ok(crate::config_parser::Token::Value(value)) => assert_eq!(field(&value,"diameter").as_f64().unwrap(),4.0),
+			_ => panic!("the analysis cannot be parsed back"),
+		}
+	}
+}
@@ -2,6 +2,7 @@
 use crate::general_pattern::pattern::Pattern;
 use crate::general_pattern::pattern::probabilistic::UniformPattern;
 use std::cell::RefCell;
+use std::rc::Rc;
 use ::rand::{Rng, rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
@@ -12,6 +13,7 @@ use crate::routing::prelude::*;
 use crate::matrix::Matrix;
 use crate::{match_object_panic};
 use crate::routing::RoutingAnnotation;
+use crate::router::Router;
 use crate::general_pattern::*; //For Valiant
 
 //extern crate itertools;
@@ -687,6 +689,519 @@ impl Hamming
 	}
 }
 
+/**
+A HyperX network. Like the [Hamming] graph, but each dimension may have several parallel links between each pair of aligned routers, which is known as trunking.
+The ports of the dimension `d` are `(sides[d]-1)*trunking[d]`, placed consecutively from the dimension 0 up, and then the servers.
+Inside a dimension the parallel copies are consecutive blocks of `sides[d]-1` ports, each block reaching every other router of the dimension.
+
+The link class of each link is its dimension, as in the Hamming. Thus, the `link_classes` of the configuration can give a different delay to each dimension, while the servers use the link class `sides.len()`.
+Routings as `DOR`, `DimWAR`, `OmniDimensionalDeroute` or `Valiant4Hamming` will see the parallel links as alternative candidates.
+
+```ignore
+HyperX{
+	sides: [8,8],
+	trunking: [2,1],//optional, one link per pair by default
+	servers_per_router: 8,
+	legend_name: "8x8 HyperX with double links in the first dimension",
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct HyperX
+{
+	cartesian_data: CartesianData,
+	servers_per_router: usize,
+	///Number of parallel links between aligned routers in each dimension.
+	trunking: Vec<usize>,
+	///The first port of each dimension. It has an additional last element with the degree.
+	port_offsets: Vec<usize>,
+}
+
+impl Topology for HyperX
+{
+	fn num_routers(&self) -> usize
+	{
+		self.cartesian_data.size
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.cartesian_data.size*self.servers_per_router
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		let degree=self.port_offsets[m];
+		if port>=degree
+		{
+			return (Location::ServerPort(port-degree + router_index*self.servers_per_router),m);
+		}
+		let mut dimension=0;
+		while port>=self.port_offsets[dimension+1]
+		{
+			dimension+=1;
+		}
+		let side=self.cartesian_data.sides[dimension];
+		let local=port-self.port_offsets[dimension];
+		let copy=local/(side-1);
+		let offset=local%(side-1);
+		let mut coordinates=self.cartesian_data.unpack(router_index);
+		coordinates[dimension]=(coordinates[dimension]+offset+1)%side;
+		(Location::RouterPort{
+			router_index: self.cartesian_data.pack(&coordinates),
+			router_port: self.port_offsets[dimension] + copy*(side-1) + (side-2-offset),
+		},dimension)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		(Location::RouterPort{
+			router_index: server_index/self.servers_per_router,
+			router_port: self.port_offsets[m]+server_index%self.servers_per_router,
+		},m)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.cartesian_data.sides.iter().filter(|&&side|side>1).count()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		let co=self.cartesian_data.unpack(origin);
+		let cd=self.cartesian_data.unpack(destination);
+		co.iter().zip(cd.iter()).filter(|(a,b)|a!=b).count()
+	}
+	///Counts the paths as sequences of links, so each parallel link gives a different path.
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		let co=self.cartesian_data.unpack(origin);
+		let cd=self.cartesian_data.unpack(destination);
+		let mut orderings=1;
+		let mut links=1;
+		let mut unaligned=0;
+		for (dimension,(a,b)) in co.iter().zip(cd.iter()).enumerate()
+		{
+			if a!=b
+			{
+				unaligned+=1;
+				orderings*=unaligned;
+				links*=self.trunking[dimension];
+			}
+		}
+		orderings*links
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		let n=self.num_routers();
+		let mut total=0f64;
+		for origin in 0..n
+		{
+			for destination in 0..n
+			{
+				total+=self.amount_shortest_paths(origin,destination) as f64;
+			}
+		}
+		(total/(n*n) as f64) as f32
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		self.port_offsets[self.cartesian_data.sides.len()]
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		self.maximum_degree()
+	}
+	fn degree(&self, _router_index: usize) -> usize
+	{
+		self.maximum_degree()
+	}
+	fn ports(&self, _router_index: usize) -> usize
+	{
+		self.maximum_degree()+self.servers_per_router
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		Some(&self.cartesian_data)
+	}
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], _rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		//As in Hamming.
+		(0..coordinates_a.len()).map(|i|coordinates_b[i] as i32-coordinates_a[i] as i32).collect()
+	}
+	fn is_direction_change(&self, _router_index:usize, _input_port: usize, _output_port: usize) -> bool
+	{
+		true
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl HyperX
+{
+	pub fn new(cv:&ConfigurationValue) -> HyperX
+	{
+		let mut sides:Option<Vec<usize>>=None;
+		let mut trunking:Option<Vec<usize>>=None;
+		let mut servers_per_router=None;
+		match_object_panic!(cv,"HyperX",value,
+			"sides" => sides=Some(value.as_array().expect("bad value for sides").iter().map(|v|v.as_usize().expect("bad value in sides")).collect()),
+			"trunking" => trunking=Some(value.as_array().expect("bad value for trunking").iter().map(|v|v.as_usize().expect("bad value in trunking")).collect()),
+			"servers_per_router" => servers_per_router=Some(value.as_usize().expect("bad value for servers_per_router")),
+		);
+		let sides=sides.expect("There were no sides");
+		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		let trunking=trunking.unwrap_or_else(||vec![1;sides.len()]);
+		if trunking.len()!=sides.len()
+		{
+			panic!("HyperX: trunking has {} values but there are {} sides",trunking.len(),sides.len());
+		}
+		if trunking.contains(&0)
+		{
+			panic!("HyperX: the trunking must be at least 1 in every dimension");
+		}
+		let mut port_offsets=Vec::with_capacity(sides.len()+1);
+		let mut offset=0;
+		port_offsets.push(offset);
+		for (side,trunk) in sides.iter().zip(trunking.iter())
+		{
+			offset+=(side-1)*trunk;
+			port_offsets.push(offset);
+		}
+		HyperX{
+			cartesian_data: CartesianData::new(&sides),
+			servers_per_router,
+			trunking,
+			port_offsets,
+		}
+	}
+	///Number of parallel links in each dimension.
+	pub fn trunking(&self) -> &[usize]
+	{
+		&self.trunking
+	}
+}
+
+/**
+A Torus or Mesh in which some dimensions have additional express links, that join each router with the routers at a given distance `express` along that dimension. The Cartesian data is the same as the one of the underlying Torus or Mesh, so Cartesian routings can be used with it.
+```ignore
+ExpressCube{
+	sides: [16,16],
+	express: [4,0],//skip distance of the express links in each dimension, 0 for none
+	wrap: true,//optional, true for a Torus (default) and false for a Mesh
+	servers_per_router: 4,
+	legend_name: "16x16 torus with express links of length 4 in the first dimension",
+}
+```
+
+The first `2*n` ports are as in the Torus and Mesh, with port `2*d` going in the negative direction of the dimension `d` and port `2*d+1` in the positive one. Next there are two express ports, negative and positive, for each dimension with express links. The ports to servers come last.
+The regular links of dimension `d` are of class `d` and the links to servers of class `n`, as in the Torus and Mesh. The express links of dimension `d` are of class `n+1+d`, so they can be given their own [LinkClass](crate::LinkClass) delay.
+The `DOR` and `O1TURN` routings have an `express` option to also employ the express links. `GeneralTurn` does not support express links; as it computes the ports of a Hamming graph it panics at initialization over an ExpressCube.
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct ExpressCube
+{
+	cartesian_data: CartesianData,
+	servers_per_router: usize,
+	///Skip distance of the express links in each dimension. A 0 means that dimension has no express links.
+	express: Vec<usize>,
+	///Whether the regular and express links wrap around, as in a Torus.
+	wrap: bool,
+	///The dimensions with express links, in the order of their express ports.
+	express_dimensions: Vec<usize>,
+	///`dimension_distance[d].get(a,b)` is the distance between the coordinates `a` and `b` along the dimension `d`.
+	dimension_distance: Vec<Matrix<usize>>,
+	///`dimension_paths[d].get(a,b)` is the amount of shortest paths between the coordinates `a` and `b` along the dimension `d`.
+	dimension_paths: Vec<Matrix<usize>>,
+}
+
+impl Topology for ExpressCube
+{
+	fn num_routers(&self) -> usize
+	{
+		self.cartesian_data.size
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.cartesian_data.size*self.servers_per_router
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		let degree=2*m+2*self.express_dimensions.len();
+		if port>=degree
+		{
+			return (Location::ServerPort(port-degree + router_index*self.servers_per_router),m);
+		}
+		let (dimension,length,first_port,link_class) = if port<2*m
+		{
+			(port/2,1,2*(port/2),port/2)
+		}
+		else
+		{
+			let dimension=self.express_dimensions[(port-2*m)/2];
+			(dimension,self.express[dimension],port-port%2,m+1+dimension)
+		};
+		let positive = port%2==1;
+		let side=self.cartesian_data.sides[dimension];
+		let mut coordinates=self.cartesian_data.unpack(router_index);
+		let target = if self.wrap
+		{
+			if positive { (coordinates[dimension]+length)%side } else { (coordinates[dimension]+side-length%side)%side }
+		}
+		else
+		{
+			let target = if positive { coordinates[dimension]+length } else { coordinates[dimension].wrapping_sub(length) };
+			if target>=side
+			{
+				return (Location::None,0);
+			}
+			target
+		};
+		coordinates[dimension]=target;
+		//Arrive by the port of the opposite direction.
+		let router_port = if positive { first_port } else { first_port+1 };
+		(Location::RouterPort{router_index:self.cartesian_data.pack(&coordinates),router_port},link_class)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let m=self.cartesian_data.sides.len();
+		(Location::RouterPort{
+			router_index: server_index/self.servers_per_router,
+			router_port: 2*m+2*self.express_dimensions.len()+server_index%self.servers_per_router,
+		},m)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.dimension_distance.iter().map(|matrix|{
+			let side=matrix.get_rows();
+			(0..side).flat_map(|a|(0..side).map(move|b|*matrix.get(a,b))).max().unwrap_or(0)
+		}).sum()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		let coord_origin=self.cartesian_data.unpack(origin);
+		let coord_destination=self.cartesian_data.unpack(destination);
+		(0..coord_origin.len()).map(|d|*self.dimension_distance[d].get(coord_origin[d],coord_destination[d])).sum()
+	}
+	///Counts the paths as sequences of links. A shortest path goes minimally along each dimension, interleaving the hops of the different dimensions in any way.
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		let coord_origin=self.cartesian_data.unpack(origin);
+		let coord_destination=self.cartesian_data.unpack(destination);
+		let mut orderings=1;
+		let mut paths=1;
+		let mut hops=0;
+		for d in 0..coord_origin.len()
+		{
+			let dimension_hops=*self.dimension_distance[d].get(coord_origin[d],coord_destination[d]);
+			//Multiply by the binomial coefficient (hops+dimension_hops choose dimension_hops).
+			for k in 1..=dimension_hops
+			{
+				orderings=orderings*(hops+k)/k;
+			}
+			hops+=dimension_hops;
+			paths*=*self.dimension_paths[d].get(coord_origin[d],coord_destination[d]);
+		}
+		orderings*paths
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		let n=self.num_routers();
+		let mut total=0f64;
+		for origin in 0..n
+		{
+			for destination in 0..n
+			{
+				total+=self.amount_shortest_paths(origin,destination) as f64;
+			}
+		}
+		(total/(n*n) as f64) as f32
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		2*self.cartesian_data.sides.len()+2*self.express_dimensions.len()
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		(0..self.num_routers()).map(|router|self.degree(router)).min().unwrap_or(0)
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		if self.wrap
+		{
+			self.maximum_degree()
+		}
+		else
+		{
+			(0..self.maximum_degree()).filter(|&port|!matches!(self.neighbour(router_index,port).0,Location::None)).count()
+		}
+	}
+	fn ports(&self, _router_index: usize) -> usize
+	{
+		self.maximum_degree()+self.servers_per_router
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		Some(&self.cartesian_data)
+	}
+	///The routing record is the signed displacement in each dimension. When wrapping, it follows the direction of the first hop of a shortest path along the dimension, which may combine express and regular hops in both directions.
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], mut rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		(0..coordinates_a.len()).map(|i|{
+			if !self.wrap
+			{
+				return coordinates_b[i] as i32-coordinates_a[i] as i32;
+			}
+			let side=self.cartesian_data.sides[i];
+			let a=(side+coordinates_b[i]-coordinates_a[i])%side;
+			let b=(side+coordinates_a[i]-coordinates_b[i])%side;
+			let distance=*self.dimension_distance[i].get(coordinates_a[i],coordinates_b[i]);
+			if distance==0
+			{
+				return 0;
+			}
+			let lengths:Vec<usize> = if self.express[i]>0 { vec![1,self.express[i]] } else { vec![1] };
+			let minimal_first_hop=|next:usize| *self.dimension_distance[i].get(next,coordinates_b[i])+1==distance;
+			let positive=lengths.iter().any(|&length|minimal_first_hop((coordinates_a[i]+length)%side));
+			let negative=lengths.iter().any(|&length|minimal_first_hop((coordinates_a[i]+side-length)%side));
+			if positive && negative
+			{
+				if let Some(ref mut rng)=rng
+				{
+					if rng.gen_range(0..2)==0 { a as i32 } else { -(b as i32) }
+				}
+				else
+				{
+					a as i32
+				}
+			}
+			else if positive { a as i32 } else { -(b as i32) }
+		}).collect()
+	}
+	fn is_direction_change(&self, _router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.port_dimension(input_port) != self.port_dimension(output_port)
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl ExpressCube
+{
+	pub fn new(cv:&ConfigurationValue) -> ExpressCube
+	{
+		let mut sides:Option<Vec<usize>>=None;
+		let mut express:Option<Vec<usize>>=None;
+		let mut wrap=true;
+		let mut servers_per_router=None;
+		match_object_panic!(cv,"ExpressCube",value,
+			"sides" => sides=Some(value.as_array().expect("bad value for sides").iter().map(|v|v.as_usize().expect("bad value in sides")).collect()),
+			"express" => express=Some(value.as_array().expect("bad value for express").iter().map(|v|v.as_usize().expect("bad value in express")).collect()),
+			"wrap" => wrap=value.as_bool().expect("bad value for wrap"),
+			"servers_per_router" => servers_per_router=Some(value.as_usize().expect("bad value for servers_per_router")),
+		);
+		let sides=sides.expect("There were no sides");
+		let express=express.expect("There were no express");
+		let servers_per_router=servers_per_router.expect("There were no servers_per_router");
+		if express.len()!=sides.len()
+		{
+			panic!("ExpressCube: express has {} values but there are {} sides",express.len(),sides.len());
+		}
+		for (dimension,(&length,&side)) in express.iter().zip(sides.iter()).enumerate()
+		{
+			if length==1 || length>=side
+			{
+				panic!("ExpressCube: the express length {} of dimension {} must be 0 or between 2 and the side {} minus one",length,dimension,side);
+			}
+		}
+		let express_dimensions:Vec<usize>=(0..sides.len()).filter(|&d|express[d]>0).collect();
+		let (dimension_distance,dimension_paths)=sides.iter().zip(express.iter()).map(|(&side,&length)|{
+			//A breadth first search along the dimension from each coordinate, also counting the shortest paths.
+			let mut matrix=Matrix::constant(usize::MAX,side,side);
+			let mut paths=Matrix::constant(0,side,side);
+			let lengths:Vec<usize> = if length>0 { vec![1,length] } else { vec![1] };
+			for origin in 0..side
+			{
+				*matrix.get_mut(origin,origin)=0;
+				*paths.get_mut(origin,origin)=1;
+				let mut queue=std::collections::VecDeque::from(vec![origin]);
+				while let Some(current)=queue.pop_front()
+				{
+					let current_distance=*matrix.get(origin,current);
+					let current_paths=*paths.get(origin,current);
+					for &step in lengths.iter()
+					{
+						let next = if wrap { vec![(current+step)%side,(current+side-step)%side] } else { vec![current+step,current.wrapping_sub(step)] };
+						for next in next.into_iter().filter(|&next|next<side)
+						{
+							if *matrix.get(origin,next)==usize::MAX
+							{
+								*matrix.get_mut(origin,next)=current_distance+1;
+								queue.push_back(next);
+							}
+							if *matrix.get(origin,next)==current_distance+1
+							{
+								*paths.get_mut(origin,next)+=current_paths;
+							}
+						}
+					}
+				}
+			}
+			(matrix,paths)
+		}).unzip();
+		ExpressCube{
+			cartesian_data: CartesianData::new(&sides),
+			servers_per_router,
+			express,
+			wrap,
+			express_dimensions,
+			dimension_distance,
+			dimension_paths,
+		}
+	}
+	///The dimension of a port to other router, or `None` for ports to servers.
+	pub fn port_dimension(&self, port:usize) -> Option<usize>
+	{
+		let m=self.cartesian_data.sides.len();
+		if port<2*m
+		{
+			Some(port/2)
+		}
+		else if port<2*m+2*self.express_dimensions.len()
+		{
+			Some(self.express_dimensions[(port-2*m)/2])
+		}
+		else
+		{
+			None
+		}
+	}
+	///Skip distance of the express links in each dimension, with 0 for dimensions without express links.
+	pub fn express(&self) -> &[usize]
+	{
+		&self.express
+	}
+}
+
+///The dimension along which goes a link between two routers of a Cartesian topology.
+///Links of class `d` smaller than the number of dimensions are assumed to go along the dimension `d`, as in the [Mesh] and [Torus].
+///For other classes, such as the express links of [ExpressCube], it is the dimension in which the coordinates of the routers differ.
+pub fn link_dimension(cartesian_data:&CartesianData, link_class:usize, router_a:usize, router_b:usize) -> usize
+{
+	let m=cartesian_data.sides.len();
+	if link_class<m
+	{
+		return link_class;
+	}
+	let coordinates_a=cartesian_data.unpack(router_a);
+	let coordinates_b=cartesian_data.unpack(router_b);
+	(0..m).find(|&d|coordinates_a[d]!=coordinates_b[d]).expect("the link does not change any coordinate")
+}
+
 pub trait CompleteGraphWiring : Quantifiable + core::fmt::Debug
 {
 	/// Initialization should be called once before any other of its methods.
@@ -786,6 +1301,8 @@ impl Topology for AsCartesianTopology
 	{ self.topology.up_down_distance(origin,destination) }
 	fn dragonfly_size(&self) -> Option<crate::topology::dragonfly::ArrangementSize>
 	{ self.topology.dragonfly_size() }
+	fn is_server_unreachable(&self, server_index:usize) -> bool
+	{ self.topology.is_server_unreachable(server_index) }
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{ self.topology.bfs(origin,class_weight) }
 	fn compute_distance_matrix(&self, class_weight:Option<&[usize]>) -> Matrix<usize>
@@ -830,10 +1347,13 @@ impl AsCartesianTopology
 ///A shortest routing for Cartesian topologies employing links in a predefined order.
 ///This is, if `order=[0,1]` the packet will go first by links changing the 0-dimension and then it will use the links in the 1-dimension until destination.
 ///The amount of links in each dimension is stored in `routing_info.routing_record` when the packet reaches the first routing and it is updated each hop.
+///With `express=true` it also considers the links whose class is not a dimension, such as the express links of an [ExpressCube], taking in each hop any of them along the dimension that reduces the distance to the target.
 #[derive(Debug)]
 pub struct DOR
 {
 	order: Vec<usize>,
+	///Whether to use also the links of classes other than the dimension being corrected.
+	express: bool,
 }
 
 //impl RoutingInfo for CartesianRoutingRecord
@@ -844,7 +1364,7 @@ pub struct DOR
 impl Routing for DOR
 {
 	//type info=CartesianRoutingRecord;
-	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, _target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//let routing_record=&routing_info.routing_record.expect("DOR requires a routing record");
 		let routing_record=if let Some(ref rr)=routing_info.routing_record
@@ -902,51 +1422,7 @@ impl Routing for DOR
 			//	Location::RouterPort{router_index,router_port:_} =>router_index,
 			//	_ => panic!("The server is not attached to a router"),
 			//};
-			let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
-			let up_current=cartesian_data.unpack(current_router);
-			//let up_target=cartesian_data.unpack(target_router);
-			let mut best=vec![];
-			let mut best_amount=0;
-			let limit=routing_record[i].abs() as usize;
-			let side=cartesian_data.sides[i];
-			for j in 0..topology.ports(current_router)
-			{
-				if let (Location::RouterPort{router_index: next_router, router_port:_},next_link_class)=topology.neighbour(current_router,j)
-				{
-					if next_link_class==i
-					{
-						let up_next=cartesian_data.unpack(next_router);
-						//if up_target[i]==up_next[i]
-						//{
-						// 	return (0..num_virtual_channels).map(|vc|(j,vc)).collect();
-						//}
-						let amount=(if routing_record[i]<0
-						{
-							up_current[i]-up_next[i]
-						}
-						else
-						{
-							up_next[i]-up_current[i]
-						}+side)%side;
-						if amount<=limit
-						{
-							if amount>best_amount
-							{
-								best_amount=amount;
-								best=vec![j];
-							}
-							else if amount==best_amount
-							{
-								best.push(j);
-							}
-						}
-					}
-				}
-			}
-			if best.is_empty()
-			{
-				panic!("No links improving {} dimension\n",i);
-			}
+			let best=dimension_best_ports(topology,current_router,target_router,i,routing_record[i],self.express);
 			//return (0..num_virtual_channels).flat_map(|vc| best.iter().map(|p|(*p,vc)).collect::<Vec<(usize,usize)>>()).collect();
 			let r= (0..num_virtual_channels).flat_map(|vc| best.iter().map(|p|CandidateEgress::new(*p,vc)).collect::<Vec<_>>()).collect();
 			return Ok(RoutingNextCandidates{candidates:r,idempotent:true});
@@ -972,45 +1448,7 @@ impl Routing for DOR
 	}
 	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
 	{
-		//let dimension=current_port/2;
-		//let delta=if current_port%2==0 { -1i32 } else { 1i32 };
-		let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
-		if let (Location::RouterPort{router_index: previous_router, router_port:_},dimension)=topology.neighbour(current_router,current_port)
-		{
-			let up_current=cartesian_data.unpack(current_router);
-			let up_previous=cartesian_data.unpack(previous_router);
-			let side=cartesian_data.sides[dimension] as i32;
-			match routing_info.borrow_mut().routing_record
-			{
-				Some(ref mut rr) =>
-				{
-					let delta:i32=if rr[dimension]<0
-					{
-						(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
-					}
-					else
-					{
-						-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
-					};
-					rr[dimension]+=delta;
-					// --- DEBUG vvv
-					//let (target_location,_link_class)=topology.server_neighbour(target_server);
-					//let target_router=match target_location
-					//{
-					//	Location::RouterPort{router_index,router_port:_} =>router_index,
-					//	_ => panic!("The server is not attached to a router"),
-					//};
-					//let up_target=cartesian_data.unpack(target_router);
-					//println!("new routing record. current_router={}({:?}, current_port={} previous_router={}({:?}), delta={}, rr={:?}, target_server={} target_router={}({:?})",current_router,up_current,current_port,previous_router,up_previous,delta,rr,target_server,target_router,up_target);
-					// --- DEBUG ^^^
-				},
-				None => panic!("trying to update without routing_record"),
-			};
-		}
-		else
-		{
-			panic!("!!");
-		}
+		update_dimension_routing_record(routing_info,topology,current_router,current_port);
 	}
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng)
 	{
@@ -1032,6 +1470,7 @@ impl DOR
 	pub fn new(arg:RoutingBuilderArgument) -> DOR
 	{
 		let mut order=None;
+		let mut express=false;
 		//let mut servers_per_router=None;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
 		{
@@ -1051,6 +1490,7 @@ impl DOR
 						}).collect()),
 						_ => panic!("bad value for order"),
 					}
+					"express" => express=value.as_bool().expect("bad value for express"),
 					//"servers_per_router" => match value
 					//{
 					//	&ConfigurationValue::Number(f) => servers_per_router=Some(f as usize),
@@ -1070,8 +1510,110 @@ impl DOR
 		let order=order.expect("There were no order");
 		DOR{
 			order,
+			express,
+		}
+	}
+}
+
+///Updates the routing record after a hop along any link of a Cartesian topology, as done by [DOR].
+fn update_dimension_routing_record(routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize)
+{
+	//let dimension=current_port/2;
+	//let delta=if current_port%2==0 { -1i32 } else { 1i32 };
+	let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
+	if let (Location::RouterPort{router_index: previous_router, router_port:_},link_class)=topology.neighbour(current_router,current_port)
+	{
+		let dimension=link_dimension(cartesian_data,link_class,current_router,previous_router);
+		let up_current=cartesian_data.unpack(current_router);
+		let up_previous=cartesian_data.unpack(previous_router);
+		let side=cartesian_data.sides[dimension] as i32;
+		match routing_info.borrow_mut().routing_record
+		{
+			Some(ref mut rr) =>
+			{
+				let delta:i32=if rr[dimension]<0
+				{
+					(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
+				}
+				else
+				{
+					-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+				};
+				rr[dimension]+=delta;
+				// --- DEBUG vvv
+				//let (target_location,_link_class)=topology.server_neighbour(target_server);
+				//let target_router=match target_location
+				//{
+				//	Location::RouterPort{router_index,router_port:_} =>router_index,
+				//	_ => panic!("The server is not attached to a router"),
+				//};
+				//let up_target=cartesian_data.unpack(target_router);
+				//println!("new routing record. current_router={}({:?}, current_port={} previous_router={}({:?}), delta={}, rr={:?}, target_server={} target_router={}({:?})",current_router,up_current,current_port,previous_router,up_previous,delta,rr,target_server,target_router,up_target);
+				// --- DEBUG ^^^
+			},
+			None => panic!("trying to update without routing_record"),
+		};
+	}
+	else
+	{
+		panic!("!!");
+	}
+}
+
+///The ports of `current_router` that advance along `dimension` towards `target_router`, with `displacement` being the signed amount remaining in the routing record.
+///If `express` is false only the links of class `dimension` are considered, taking those that advance the most without surpassing the `displacement`.
+///Otherwise any link along `dimension` is considered, as the express links of an [ExpressCube], taking those that reduce the distance to `target_router`.
+fn dimension_best_ports(topology:&dyn Topology, current_router:usize, target_router:usize, dimension:usize, displacement:i32, express:bool) -> Vec<usize>
+{
+	let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
+	let up_current=cartesian_data.unpack(current_router);
+	let mut best=vec![];
+	let mut best_amount=0;
+	let limit=displacement.unsigned_abs() as usize;
+	let side=cartesian_data.sides[dimension];
+	let distance=topology.distance(current_router,target_router);
+	for j in 0..topology.ports(current_router)
+	{
+		if let (Location::RouterPort{router_index: next_router, router_port:_},next_link_class)=topology.neighbour(current_router,j)
+		{
+			if express
+			{
+				if link_dimension(cartesian_data,next_link_class,current_router,next_router)==dimension && topology.distance(next_router,target_router)+1==distance
+				{
+					best.push(j);
+				}
+			}
+			else if next_link_class==dimension
+			{
+				let up_next=cartesian_data.unpack(next_router);
+				let amount=if displacement<0
+				{
+					(up_current[dimension]+side-up_next[dimension])%side
+				}
+				else
+				{
+					(up_next[dimension]+side-up_current[dimension])%side
+				};
+				if amount<=limit
+				{
+					if amount>best_amount
+					{
+						best_amount=amount;
+						best=vec![j];
+					}
+					else if amount==best_amount
+					{
+						best.push(j);
+					}
+				}
+			}
 		}
 	}
+	if best.is_empty()
+	{
+		panic!("No links improving {} dimension\n",dimension);
+	}
+	best
 }
 
 ///A meta routing for Cartesian topologies employing links in a predefined order.
@@ -1155,14 +1697,15 @@ impl Routing for GeneralDOR
 		let cartesian_data = topology.cartesian_data().expect("DOR requires a Cartesian topology");
 		let up_current = cartesian_data.unpack(current_router);
 		let up_target = cartesian_data.unpack(target_router);
-		let (_,link_class)  = topology.neighbour(current_router, current_port);
+		let (previous_location,link_class)  = topology.neighbour(current_router, current_port);
+		//get previous physical router with the port
+		let Location::RouterPort {router_index: previous_physical_router, router_port:_} = previous_location else { panic!("There should be a port")};
+		let link_class = link_dimension(cartesian_data,link_class,current_router,previous_physical_router);
 
 		let bri = routing_info.borrow();
 
 		let current_logical = up_current[link_class];
 		let target_logical = up_target[link_class];
-		//get previous physical router with the port
-		let Location::RouterPort {router_index: previous_physical_router, router_port:_} = topology.neighbour(current_router, current_port).0 else { panic!("There should be a port")};
 		let up_previous = cartesian_data.unpack(previous_physical_router);
 		let previous_logical_router =up_previous[link_class];
 		//now get the logical port iterating the logical neighbours
@@ -1179,6 +1722,20 @@ impl Routing for GeneralDOR
 			routing.initialize(self.region_logical_topology[i].as_ref(), rng);
 		}
 	}
+
+	fn link_status_change(&mut self, topology: &dyn Topology, router: usize, port: usize, available: bool, rng: &mut StdRng) {
+		//Tell the routing of the dimension of the link, with the link in its logical topology.
+		let (location,link_class) = topology.neighbour(router, port);
+		let Location::RouterPort {router_index: neighbour, router_port:_} = location else { return };
+		let cartesian_data = topology.cartesian_data().expect("DOR requires a Cartesian topology");
+		let dim = link_dimension(cartesian_data,link_class,router,neighbour);
+		let logical_router = cartesian_data.unpack(router)[dim];
+		let logical_neighbour = cartesian_data.unpack(neighbour)[dim];
+		if let Some(item) = self.region_logical_topology[dim].neighbour_router_iter(logical_router).find(|item| item.neighbour_router == logical_neighbour)
+		{
+			self.routings[dim].link_status_change(self.region_logical_topology[dim].as_ref(), logical_router, item.port_index, available, rng);
+		}
+	}
 }
 
 impl GeneralDOR
@@ -1647,11 +2204,14 @@ pub struct O1TURN
 	/// Virtual channels reserved exclusively for the 1 before 0 DOR selection.
 	/// Defaults to `[1]`
 	reserved_virtual_channels_order10: Vec<usize>,
+	/// Whether to use the links of any class along the dimension, as the express links of an [ExpressCube], like [DOR] does with `express=true`.
+	/// Defaults to `false`, in which the ports are assumed to be arranged as in a [Mesh] or [Torus].
+	express: bool,
 }
 
 impl Routing for O1TURN
 {
-	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, _target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//let routing_record=&routing_info.routing_record.expect("DOR requires a routing record");
 		let routing_record=if let Some(ref rr)=routing_info.routing_record
@@ -1708,6 +2268,12 @@ impl Routing for O1TURN
 		{
 			i=order[i];
 			//Go in dimension i
+			if self.express
+			{
+				let best=dimension_best_ports(topology,current_router,target_router,i,routing_record[i],true);
+				let r= available_virtual_channels.flat_map(|vc| best.iter().map(move|p|CandidateEgress::new(*p,vc))).collect();
+				return Ok(RoutingNextCandidates{candidates:r,idempotent:true});
+			}
 			//WARNING: This assumes ports in a mesh-like configuration!
 			let p=if routing_record[i]<0
 			{
@@ -1743,8 +2309,13 @@ impl Routing for O1TURN
 			rng.gen_range(0..2)
 		}]);
 	}
-	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
 	{
+		if self.express
+		{
+			update_dimension_routing_record(routing_info,topology,current_router,current_port);
+			return;
+		}
 		let dimension=current_port/2;
 		let delta=if current_port%2==0 { -1i32 } else { 1i32 };
 		match routing_info.borrow_mut().routing_record
@@ -1780,6 +2351,7 @@ impl O1TURN
 		//let mut servers_per_router=None;
 		let mut reserved_virtual_channels_order01: Option<Vec<usize>> = None;
 		let mut reserved_virtual_channels_order10: Option<Vec<usize>> = None;
+		let mut express=false;
 		if let &ConfigurationValue::Object(ref cv_name, ref cv_pairs)=arg.cv
 		{
 			if cv_name!="O1TURN"
@@ -1815,6 +2387,7 @@ impl O1TURN
 						}).collect()),
 						_ => panic!("bad value for reserved_virtual_channels_order10"),
 					}
+					"express" => express=value.as_bool().expect("bad value for express"),
 					"legend_name" => (),
 					_ => panic!("Nothing to do with field {} in O1TURN",name),
 				}
@@ -1830,6 +2403,7 @@ impl O1TURN
 		O1TURN{
 			reserved_virtual_channels_order01,
 			reserved_virtual_channels_order10,
+			express,
 		}
 	}
 }
@@ -1871,6 +2445,8 @@ pub fn new_general_turn_policy(cv: &ConfigurationValue) -> GeneralTurnPolicy
 
 ///Routing adapted from "Near-optimal worst-case throughput routing for two-dimensional mesh networks" by Daeho Seo, et al.
 /// It asigns a dimension order to each virtual channel.
+/// It computes the ports as in a [Hamming] graph with all sides equal, so it panics at initialization for other topologies, such as an [ExpressCube].
+/// Thus, unlike [DOR] and [O1TURN], it has no `express` option to route through the express links of an [ExpressCube].
 #[derive(Debug)]
 pub struct GENERALTURN
 {
@@ -2028,8 +2604,28 @@ impl Routing for GENERALTURN
 		}
 
 	}
-	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng)
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
 	{
+		//The ports are computed assuming the arrangement of a Hamming graph, so reject other topologies, such as an ExpressCube.
+		let cartesian_data=topology.cartesian_data().expect("GENERALTURN requires a Cartesian topology");
+		for router in 0..topology.num_routers()
+		{
+			let coordinates=cartesian_data.unpack(router);
+			for (dimension,&side) in cartesian_data.sides.iter().enumerate()
+			{
+				for offset in 1..side
+				{
+					let port=(side-1)*dimension+offset-1;
+					let mut expected=coordinates.clone();
+					expected[dimension]=(coordinates[dimension]+offset)%side;
+					match topology.neighbour(router,port)
+					{
+						(Location::RouterPort{router_index,..},_) if router_index==cartesian_data.pack(&expected) => (),
+						_ => panic!("GENERALTURN requires the ports of a Hamming graph, but the port {} of router {} does not go to {:?}",port,router,expected),
+					}
+				}
+			}
+		}
 	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
@@ -2402,23 +2998,11 @@ impl Routing for DimWAR
 		}
 		let dimension_exit = dimension_exit.expect("Next DOR dimension should exist");
 
-		let mut port_offset = 0;
-		for j in 0..dimension_exit
-		{
-			port_offset += cartesian_data.sides[j]-1;
-		}
-
-		for i in port_offset..(port_offset + cartesian_data.sides[dimension_exit] -1)
+		//The link class of each port gives its dimension. This includes any parallel link, as in a HyperX with trunking.
+		for NeighbourRouterIteratorItem{port_index:i,link_class,neighbour_router:router_index,..} in topology.neighbour_router_iter(current_router)
 		{
-			//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-			if let (Location::RouterPort{router_index,router_port:_}, link_class)=topology.neighbour(current_router,i)
+			if link_class == dimension_exit
 			{
-				//let up_next=cartesian_data.unpack(router_index);
-				// panic if link_class do not match the exit dimension
-				if link_class != dimension_exit
-				{
-					panic!("The port is not pointing in the right direction, link_class: {} exit_dimension {}", link_class, dimension_exit);
-				}
 				//r.extend((0..num_virtual_channels).map(|vc|(i,vc)));
 				if miss_dim[dimension_exit] == 1 //can missroutte topology.distance(router_index,target_router) >= distance
 				{
@@ -2841,6 +3425,16 @@ impl Routing for Valiant4Hamming
 		self.second.initialize(topology,rng);
 		self.pattern.initialize(topology.num_servers(), topology.num_servers(), Some(topology), rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.first.link_status_change(topology,router,port,available,rng);
+		self.second.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.first.update_global_status(routers,topology,cycle);
+		self.second.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -2917,6 +3511,7 @@ impl Valiant4Hamming
 /**
 	Non-minimal routing of Clos-AD routing from the Flattened Butterfly paper.
 	Similar to Valiant, it missroutes to a switch in a dimension with the queue more empty.
+	In topologies without Cartesian data but with up/down paths, such as a `FatTree`, it selects adaptively among the up ports using the first reserved virtual channels and then goes down using the second ones.
 
 ```ignore
 AdaptiveValiantClos{
@@ -2966,7 +3561,10 @@ impl Routing for AdaptiveValiantClos
 			}
 			unreachable!();
 		}
-		let cartesian_data = topology.cartesian_data().expect("Cartesian topology");
+		if topology.cartesian_data().is_none()
+		{
+			return Ok(self.up_down_next(topology,current_router,target_router));
+		}
 		let selections=routing_info.selections.as_ref().unwrap();
 		let meta=routing_info.meta.as_ref().unwrap();
 		let mut dimension_exit:i32 = -1;
@@ -2984,21 +3582,11 @@ impl Routing for AdaptiveValiantClos
 		{
 			let dimension_exit = dimension_exit as usize;
 
-			let mut port_offset = 0;
-			for j in 0..dimension_exit
+			//The link class of each port gives its dimension. This includes any parallel link, as in a HyperX with trunking.
+			for NeighbourRouterIteratorItem{port_index:i,link_class,..} in topology.neighbour_router_iter(current_router)
 			{
-				port_offset += cartesian_data.sides[j]-1;
-			}
-
-			for i in port_offset..(port_offset + cartesian_data.sides[dimension_exit] -1)
-			{
-				if let (Location::RouterPort{router_index: _,router_port:_},link_class)=topology.neighbour(current_router,i)
+				if link_class == dimension_exit
 				{
-					if link_class != dimension_exit
-					{
-						panic!("The port is not pointing in the right direction, link_class: {} exit_dimension {}", link_class, dimension_exit);
-					}
-
 					r.extend((self.first_reserved_virtual_channels.clone().into_iter()).map(|vc|CandidateEgress::new(i,vc)));
 				}
 			}
@@ -3032,7 +3620,16 @@ impl Routing for AdaptiveValiantClos
 			_ => panic!("The server is not attached to a router"),
 		};
 
-		let cartesian_topology = topology.cartesian_data().expect("Cartesian topology");
+		let cartesian_topology = match topology.cartesian_data()
+		{
+			Some(data) => data,
+			None =>
+			{
+				//Up/down mode: nothing to select.
+				routing_info.borrow_mut().visited_routers=Some(vec![current_router]);
+				return;
+			}
+		};
 		let mut dimension_deroute = vec![0; cartesian_topology.sides.len()];
 		let mut deroutes = 0;
 		let current_coord = cartesian_topology.unpack(current_router);
@@ -3068,7 +3665,18 @@ impl Routing for AdaptiveValiantClos
 			let _hops = bri.hops;
 			// let _middle = bri.selections.as_ref().map(|s| s[0] as usize);
 
-			let cartesian_data = topology.cartesian_data().expect("OmniDimensionalDeroute requires a Cartesian topology");
+			let cartesian_data = match topology.cartesian_data()
+			{
+				Some(data) => data,
+				None =>
+				{
+					if let Some(ref mut v) = bri.visited_routers
+					{
+						v.push(current_router);
+					}
+					return;
+				}
+			};
 			let up_current = cartesian_data.unpack(current_router);
 			let up_previous = cartesian_data.unpack(previous_router);
 			let mut exit_dimension = None;
@@ -3113,6 +3721,16 @@ impl Routing for AdaptiveValiantClos
 		self.second.initialize(topology,rng);
 
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.first.link_status_change(topology,router,port,available,rng);
+		self.second.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.first.update_global_status(routers,topology,cycle);
+		self.second.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>,  _num_virtual_channels:usize, _rng: &mut StdRng)
 	{
 		//TODO: recurse over routings
@@ -3121,6 +3739,29 @@ impl Routing for AdaptiveValiantClos
 
 impl AdaptiveValiantClos
 {
+	///Candidates for topologies without Cartesian data, such as fat-trees, following their up/down paths.
+	///The up ports are all offered through the `first_reserved_virtual_channels`, so that the up port is selected adaptively.
+	///The down phase is deterministic in fat-trees and it is offered through the `second_reserved_virtual_channels`.
+	fn up_down_next(&self, topology:&dyn Topology, current_router:usize, target_router:usize) -> RoutingNextCandidates
+	{
+		let (up_distance, down_distance) = topology.up_down_distance(current_router,target_router).unwrap_or_else(||panic!("AdaptiveValiantClos requires either a Cartesian topology or up/down paths, but there is no up/down path from {} to {}",current_router,target_router));
+		let mut r = vec![];
+		for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(current_router)
+		{
+			if let Some((new_u, new_d)) = topology.up_down_distance(neighbour_router,target_router)
+			{
+				if new_u<up_distance && new_d<=down_distance
+				{
+					r.extend(self.first_reserved_virtual_channels.iter().map(|&vc|CandidateEgress::new(port_index,vc)));
+				}
+				else if new_u<=up_distance && new_d<down_distance
+				{
+					r.extend(self.second_reserved_virtual_channels.iter().map(|&vc|CandidateEgress::new(port_index,vc)));
+				}
+			}
+		}
+		RoutingNextCandidates{candidates:r,idempotent:true}
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> AdaptiveValiantClos
 	{
 		//let mut order=None;
@@ -3162,3 +3803,156 @@ impl AdaptiveValiantClos
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::routing::RoutingInfo;
+	fn hyperx(sides:&[usize], trunking:&[usize], servers_per_router:usize) -> HyperX
+	{
+		let to_array = |list:&[usize]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+		let cv = ConfigurationValue::Object("HyperX".to_string(),vec![
+			("sides".to_string(),to_array(sides)),
+			("trunking".to_string(),to_array(trunking)),
+			("servers_per_router".to_string(),ConfigurationValue::Number(servers_per_router as f64)),
+		]);
+		HyperX::new(&cv)
+	}
+	#[test]
+	fn hyperx_consistency()
+	{
+		let topology = hyperx(&[4,3],&[2,3],2);
+		topology.check_adjacency_consistency(Some(3));
+		assert_eq!(topology.maximum_degree(), 3*2+2*3);
+		assert_eq!(topology.diameter(), 2);
+		let (distances,amounts) = topology.compute_amount_shortest_paths();
+		for origin in 0..topology.num_routers()
+		{
+			for destination in 0..topology.num_routers()
+			{
+				assert_eq!(*distances.get(origin,destination), topology.distance(origin,destination));
+				if origin!=destination
+				{
+					assert_eq!(*amounts.get(origin,destination), topology.amount_shortest_paths(origin,destination));
+				}
+			}
+		}
+	}
+	#[test]
+	fn hyperx_dor_uses_trunks()
+	{
+		let topology = hyperx(&[4,4],&[3,1],1);
+		let dor = DOR{order:vec![0,1],express:false};
+		let mut rng = StdRng::seed_from_u64(0);
+		let (origin,target) = (0,topology.cartesian_data.pack(&[2,1]));
+		let routing_info = RefCell::new(RoutingInfo::new());
+		dor.initialize_routing_info(&routing_info,&topology,origin,target,None,&mut rng);
+		let candidates = dor.next(&routing_info.borrow(),&topology,origin,target,None,1,&mut rng).expect("DOR failed").candidates;
+		assert_eq!(candidates.len(),3);
+		for candidate in candidates
+		{
+			match topology.neighbour(origin,candidate.port)
+			{
+				(Location::RouterPort{router_index,..},link_class) =>
+				{
+					assert_eq!(link_class,0);
+					assert_eq!(topology.cartesian_data.unpack(router_index),vec![2,0]);
+				},
+				_ => panic!("DOR should go to a router"),
+			}
+		}
+	}
+	fn express_cube(sides:&[usize], express:&[usize], wrap:bool) -> ExpressCube
+	{
+		let to_array = |list:&[usize]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+		let cv = ConfigurationValue::Object("ExpressCube".to_string(),vec![
+			("sides".to_string(),to_array(sides)),
+			("express".to_string(),to_array(express)),
+			("wrap".to_string(),if wrap {ConfigurationValue::True} else {ConfigurationValue::False}),
+			("servers_per_router".to_string(),ConfigurationValue::Number(2.0)),
+		]);
+		ExpressCube::new(&cv)
+	}
+	#[test]
+	fn express_cube_consistency()
+	{
+		for wrap in [true,false]
+		{
+			let topology = express_cube(&[8,5],&[3,0],wrap);
+			topology.check_adjacency_consistency(None);
+			let (distances,amounts) = topology.compute_amount_shortest_paths();
+			let mut diameter = 0;
+			for origin in 0..topology.num_routers()
+			{
+				for destination in 0..topology.num_routers()
+				{
+					assert_eq!(*distances.get(origin,destination), topology.distance(origin,destination));
+					assert_eq!(*amounts.get(origin,destination), topology.amount_shortest_paths(origin,destination));
+					diameter = diameter.max(topology.distance(origin,destination));
+				}
+				for port in 0..topology.degree(origin)
+				{
+					if let (Location::RouterPort{..},link_class) = topology.neighbour(origin,port)
+					{
+						assert!(link_class==0 || link_class==1 || link_class==3, "unexpected link class {}",link_class);
+					}
+				}
+			}
+			assert_eq!(topology.diameter(),diameter);
+		}
+	}
+	#[test]
+	fn express_cube_dor()
+	{
+		let topology = express_cube(&[16,4],&[4,0],false);
+		let dor = DOR{order:vec![0,1],express:true};
+		let mut rng = StdRng::seed_from_u64(0);
+		let (origin,target) = (topology.cartesian_data.pack(&[1,0]),topology.cartesian_data.pack(&[11,3]));
+		let routing_info = RefCell::new(RoutingInfo::new());
+		dor.initialize_routing_info(&routing_info,&topology,origin,target,None,&mut rng);
+		let mut current = origin;
+		let mut hops = 0;
+		let mut express_hops = 0;
+		while current!=target
+		{
+			let candidates = dor.next(&routing_info.borrow(),&topology,current,target,None,1,&mut rng).expect("DOR failed").candidates;
+			let (location,link_class) = topology.neighbour(current,candidates[0].port);
+			let Location::RouterPort{router_index,router_port} = location else { panic!("DOR should go to a router") };
+			if link_class==3 { express_hops+=1; }
+			dor.update_routing_info(&routing_info,&topology,router_index,router_port,target,None,&mut rng);
+			current = router_index;
+			hops += 1;
+		}
+		//Two express hops and two regular hops in the first dimension, then three hops in the second.
+		assert_eq!((hops,express_hops),(7,2));
+	}
+	#[test]
+	fn express_cube_minimal_dor()
+	{
+		//Advancing 7 with express links of length 4 takes 3 hops going beyond and back, instead of the 4 hops of 4+1+1+1.
+		for wrap in [true,false]
+		{
+			let topology = express_cube(&[16],&[4],wrap);
+			let dor = DOR{order:vec![0],express:true};
+			let mut rng = StdRng::seed_from_u64(0);
+			let (origin,target) = (0,7);
+			assert_eq!(topology.distance(origin,target),3);
+			let routing_info = RefCell::new(RoutingInfo::new());
+			dor.initialize_routing_info(&routing_info,&topology,origin,target,None,&mut rng);
+			let mut current = origin;
+			let mut hops = 0;
+			while current!=target
+			{
+				let candidates = dor.next(&routing_info.borrow(),&topology,current,target,None,1,&mut rng).expect("DOR failed").candidates;
+				let (location,_link_class) = topology.neighbour(current,candidates[0].port);
+				let Location::RouterPort{router_index,router_port} = location else { panic!("DOR should go to a router") };
+				dor.update_routing_info(&routing_info,&topology,router_index,router_port,target,None,&mut rng);
+				current = router_index;
+				hops += 1;
+			}
+			assert_eq!(hops,3);
+			assert_eq!(routing_info.borrow().routing_record,Some(vec![0]));
+		}
+	}
+}
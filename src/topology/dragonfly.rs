@@ -558,6 +558,8 @@ pub fn new_arrangement(arg:ArrangementBuilderArgument) -> Box<dyn Arrangement>
 
 use crate::routing::prelude::*;
 use crate::routing::{RoutingAnnotation};
+use crate::router::Router;
+use std::rc::Rc;
 
 /**
 With the switches colored in {0,1} with a global arrangement such that global links connect only switches of the same color, the global link is labelled by that color.
@@ -983,6 +985,16 @@ impl Routing for Valiant4Dragonfly
 			pattern.initialize(size,size,Some(topology),rng);
 		}
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.first.link_status_change(topology,router,port,available,rng);
+		self.second.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.first.update_global_status(routers,topology,cycle);
+		self.second.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -1250,6 +1262,16 @@ impl Routing for PAR
 		self.first.initialize(topology,rng);
 		self.second.initialize(topology,rng);
 	}
+	fn link_status_change(&mut self, topology:&dyn Topology, router:usize, port:usize, available:bool, rng: &mut StdRng)
+	{
+		self.first.link_status_change(topology,router,port,available,rng);
+		self.second.link_status_change(topology,router,port,available,rng);
+	}
+	fn update_global_status(&mut self, routers:&[Rc<RefCell<dyn Router>>], topology:&dyn Topology, cycle:Time)
+	{
+		self.first.update_global_status(routers,topology,cycle);
+		self.second.update_global_status(routers,topology,cycle);
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -1359,13 +1381,15 @@ impl Routing for DragonflyDirect
 		{
 			if let (Location::RouterPort{router_index,router_port:_},link_class)=topology.neighbour(current_router,i)
 			{
-				if link_class == 0 && selections[0] != 0 && selections[1] == 0 //AVOID DEADLOCK
+				let link_weight = self.class_weight[link_class];
+				let new_distance = *self.distance_matrix.get(router_index, target_router);
+				let minimal = new_distance + link_weight == distance;
+				//Minimal local hops are kept, as groups without complete local graph (as in the Dragonfly+) need several of them.
+				if !minimal && link_class == 0 && selections[0] != 0 && selections[1] == 0 //AVOID DEADLOCK
 				{
 					continue
 				}
-				let link_weight = self.class_weight[link_class];
-				let new_distance = *self.distance_matrix.get(router_index, target_router);
-				if new_distance + link_weight == distance
+				if minimal
 					|| (selections[0] == 0 && selections[1] == 0 && distance != self.class_weight[0] && distance != self.class_weight[1] && *self.group_matrix.get(router_index,target_router) == self.class_weight[1] &&(link_weight+new_distance) <= self.total_max_weight_distance) //This is adapted to DF
 				{
 					r.extend((0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)));
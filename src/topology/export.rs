@@ -0,0 +1,214 @@
+/*!
+
+Writing topologies into files for other tools.
+
+The numeric formats are handled by [Topology::write_adjacencies_to_file]. The DOT and GraphML outputs can be read back with [FileTopology](super::file_topology::FileTopology), keeping ports, link classes and servers.
+
+*/
+
+use std::io::Write;
+use super::prelude::*;
+
+///The formats in which a topology can be exported.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ExportFormat
+{
+	///Graphviz DOT, with the links coloured by link class.
+	DOT,
+	///GraphML, with the ports and link classes as data of the edges.
+	GraphML,
+	///JSON object with the adjacency of each router port and the attachment of each server.
+	JSON,
+}
+
+impl ExportFormat
+{
+	///Get the format from its name, ignoring case.
+	pub fn from_name(name:&str) -> Option<ExportFormat>
+	{
+		match name.to_lowercase().as_ref()
+		{
+			"dot" | "graphviz" => Some(ExportFormat::DOT),
+			"graphml" => Some(ExportFormat::GraphML),
+			"json" => Some(ExportFormat::JSON),
+			_ => None,
+		}
+	}
+}
+
+///Colours given to the link classes in DOT files. Classes beyond the list repeat it.
+const DOT_COLOURS: [&str;10] = ["black","blue","red","darkgreen","orange","purple","brown","cyan3","magenta","gold3"];
+
+///Write the topology in the given format.
+pub fn export_topology<W:Write>(topology:&dyn Topology, format:ExportFormat, writer:&mut W) -> Result<(),std::io::Error>
+{
+	match format
+	{
+		ExportFormat::DOT => write_dot(topology,writer),
+		ExportFormat::GraphML => write_graphml(topology,writer),
+		ExportFormat::JSON => write_json(topology,writer),
+	}
+}
+
+///Each link between routers once, as `(router,port,neighbour,neighbour_port,link_class)`.
+fn router_links(topology:&dyn Topology) -> Vec<(usize,usize,usize,usize,usize)>
+{
+	let mut links=vec![];
+	for router in 0..topology.num_routers()
+	{
+		for port in 0..topology.ports(router)
+		{
+			if let (Location::RouterPort{router_index,router_port},link_class)=topology.neighbour(router,port)
+			{
+				if (router,port)<(router_index,router_port)
+				{
+					links.push((router,port,router_index,router_port,link_class));
+				}
+			}
+		}
+	}
+	links
+}
+
+fn write_dot<W:Write>(topology:&dyn Topology, writer:&mut W) -> Result<(),std::io::Error>
+{
+	writeln!(writer,"graph topology {{")?;
+	writeln!(writer,"\tnode [shape=circle];")?;
+	for server in 0..topology.num_servers()
+	{
+		writeln!(writer,"\ts{} [type=server, shape=box];",server)?;
+	}
+	for router in 0..topology.num_routers()
+	{
+		writeln!(writer,"\tr{};",router)?;
+	}
+	for (router,port,neighbour,neighbour_port,link_class) in router_links(topology)
+	{
+		writeln!(writer,"\tr{}:{} -- r{}:{} [class={}, color={}];",router,port,neighbour,neighbour_port,link_class,DOT_COLOURS[link_class%DOT_COLOURS.len()])?;
+	}
+	for server in 0..topology.num_servers()
+	{
+		if let (Location::RouterPort{router_index,router_port},link_class)=topology.server_neighbour(server)
+		{
+			writeln!(writer,"\ts{} -- r{}:{} [class={}, color={}];",server,router_index,router_port,link_class,DOT_COLOURS[link_class%DOT_COLOURS.len()])?;
+		}
+	}
+	writeln!(writer,"}}")?;
+	Ok(())
+}
+
+fn write_graphml<W:Write>(topology:&dyn Topology, writer:&mut W) -> Result<(),std::io::Error>
+{
+	writeln!(writer,"<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+	writeln!(writer,"<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+	writeln!(writer,"\t<key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>")?;
+	writeln!(writer,"\t<key id=\"class\" for=\"edge\" attr.name=\"class\" attr.type=\"int\"/>")?;
+	writeln!(writer,"\t<key id=\"source_port\" for=\"edge\" attr.name=\"source_port\" attr.type=\"int\"/>")?;
+	writeln!(writer,"\t<key id=\"target_port\" for=\"edge\" attr.name=\"target_port\" attr.type=\"int\"/>")?;
+	writeln!(writer,"\t<graph id=\"topology\" edgedefault=\"undirected\">")?;
+	for router in 0..topology.num_routers()
+	{
+		writeln!(writer,"\t\t<node id=\"r{}\"><data key=\"type\">router</data></node>",router)?;
+	}
+	for server in 0..topology.num_servers()
+	{
+		writeln!(writer,"\t\t<node id=\"s{}\"><data key=\"type\">server</data></node>",server)?;
+	}
+	for (router,port,neighbour,neighbour_port,link_class) in router_links(topology)
+	{
+		writeln!(writer,"\t\t<edge source=\"r{}\" target=\"r{}\"><data key=\"class\">{}</data><data key=\"source_port\">{}</data><data key=\"target_port\">{}</data></edge>",router,neighbour,link_class,port,neighbour_port)?;
+	}
+	for server in 0..topology.num_servers()
+	{
+		if let (Location::RouterPort{router_index,router_port},link_class)=topology.server_neighbour(server)
+		{
+			writeln!(writer,"\t\t<edge source=\"s{}\" target=\"r{}\"><data key=\"class\">{}</data><data key=\"target_port\">{}</data></edge>",server,router_index,link_class,router_port)?;
+		}
+	}
+	writeln!(writer,"\t</graph>")?;
+	writeln!(writer,"</graphml>")?;
+	Ok(())
+}
+
+fn write_json<W:Write>(topology:&dyn Topology, writer:&mut W) -> Result<(),std::io::Error>
+{
+	writeln!(writer,"{{")?;
+	writeln!(writer,"\t\"num_routers\": {},",topology.num_routers())?;
+	writeln!(writer,"\t\"num_servers\": {},",topology.num_servers())?;
+	writeln!(writer,"\t\"routers\": [")?;
+	let n=topology.num_routers();
+	for router in 0..n
+	{
+		let ports:Vec<String>=(0..topology.ports(router)).map(|port|{
+			match topology.neighbour(router,port)
+			{
+				(Location::RouterPort{router_index,router_port},link_class) => format!("{{\"router\": {}, \"port\": {}, \"class\": {}}}",router_index,router_port,link_class),
+				(Location::ServerPort(server),link_class) => format!("{{\"server\": {}, \"class\": {}}}",server,link_class),
+				(Location::None,_) => "null".to_string(),
+			}
+		}).collect();
+		writeln!(writer,"\t\t[{}]{}",ports.join(", "),if router+1<n {","} else {""})?;
+	}
+	writeln!(writer,"\t],")?;
+	writeln!(writer,"\t\"servers\": [")?;
+	let m=topology.num_servers();
+	for server in 0..m
+	{
+		let attachment=match topology.server_neighbour(server)
+		{
+			(Location::RouterPort{router_index,router_port},link_class) => format!("{{\"router\": {}, \"port\": {}, \"class\": {}}}",router_index,router_port,link_class),
+			_ => "null".to_string(),
+		};
+		writeln!(writer,"\t\t{}{}",attachment,if server+1<m {","} else {""})?;
+	}
+	writeln!(writer,"\t]")?;
+	writeln!(writer,"}}")?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::file_topology::{FileTopology,parse_dot,parse_graphml,parse_edge_list};
+	///Exports and reads back the topology, checking that everything is kept.
+	fn round_trip(topology:&dyn Topology, format:ExportFormat)
+	{
+		let mut output=vec![];
+		export_topology(topology,format,&mut output).expect("export failed");
+		let text=String::from_utf8(output).expect("non UTF-8 output");
+		let graph = match format
+		{
+			ExportFormat::DOT => parse_dot(&text),
+			ExportFormat::GraphML => parse_graphml(&text),
+			_ => unreachable!(),
+		};
+		let copy=FileTopology::from_graph(&graph,0);
+		assert_eq!(copy.num_routers(),topology.num_routers());
+		assert_eq!(copy.num_servers(),topology.num_servers());
+		for router in 0..topology.num_routers()
+		{
+			assert_eq!(copy.ports(router),topology.ports(router));
+			for port in 0..topology.ports(router)
+			{
+				assert_eq!(copy.neighbour(router,port),topology.neighbour(router,port),"{:?} router {} port {}",format,router,port);
+			}
+		}
+		for server in 0..topology.num_servers()
+		{
+			assert_eq!(copy.server_neighbour(server),topology.server_neighbour(server));
+		}
+	}
+	#[test]
+	fn export_and_read()
+	{
+		let topology=FileTopology::from_graph(&parse_edge_list("a 1 b 0 2\nb 1 c 0\nc 1 a 0 4\nserver a 3\nserver c 2 7\n"),1);
+		round_trip(&topology,ExportFormat::DOT);
+		round_trip(&topology,ExportFormat::GraphML);
+		let mut output=vec![];
+		export_topology(&topology,ExportFormat::JSON,&mut output).expect("export failed");
+		let text=String::from_utf8(output).expect("non UTF-8 output");
+		assert!(text.contains("{\"router\": 1, \"port\": 0, \"class\": 2}"));
+		assert!(text.contains("{\"router\": 2, \"port\": 2, \"class\": 7}"));
+	}
+}
@@ -0,0 +1,329 @@
+/*!
+
+Fat-trees built as a particular case of [MultiStage].
+
+* FatTree: the three-level (or `levels`-level) datacenter fat-tree with `k`-port switches, organized in pods.
+* KAryNTree: the classic k-ary n-tree, in which every switch has `k` ports upwards and `k` ports downwards.
+
+*/
+
+use quantifiable_derive::Quantifiable;//the derive macro
+use super::prelude::*;
+use super::multistage::MultiStage;
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+/**
+A fat-tree given by the radix `k` and the number of switch `levels`. It is built internally as a [MultiStage] of [FatStage](super::multistage::FatStage)s, so it
+provides `up_down_distance` and can be used directly with `UpDown`, `UpDownStar` and similar routings.
+
+With `FatTree` the switches have `k` ports. Switches below the top level use `k/2` ports upwards and `k/2` ports downwards, while the top level (the core) uses its `k` ports downwards. There are `k` pods for any number of levels, each pod being a subnetwork below the core. With `levels=3` this is the datacenter fat-tree of Al-Fares et al., with `k` pods of `k/2` edge and `k/2` aggregation switches, and `(k/2)^2` core switches. By default each leaf (edge switch) has `k/2` servers.
+```ignore
+FatTree{
+	k: 8,
+	levels: 3,
+	//servers_per_leaf: 4,//optional, defaults to k/2
+	legend_name: "datacenter fat-tree with 8-port switches",
+}
+```
+
+With `KAryNTree` there are `levels` levels of `k^(levels-1)` switches each. Switches below the top level have `k` ports upwards and `k` ports downwards, and top switches use only `k` ports downwards. By default each leaf has `k` servers. The pods are the `k` subtrees below the top level.
+```ignore
+KAryNTree{
+	k: 4,
+	levels: 3,
+	legend_name: "4-ary 3-tree",
+}
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct FatTree
+{
+	///The underlying multistage network.
+	base: MultiStage,
+	///The radix parameter used to build the network.
+	k: usize,
+	///Number of levels of switches. One more than the number of stages.
+	levels: usize,
+	///Number of pods. The pods partition the routers of every level except the top one.
+	pods: usize,
+}
+
+impl Topology for FatTree
+{
+	fn num_routers(&self) -> usize
+	{
+		self.base.num_routers()
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.base.num_servers()
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		self.base.neighbour(router_index,port)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.base.server_neighbour(server_index)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.base.diameter()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		self.base.distance(origin,destination)
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		self.base.amount_shortest_paths(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		self.base.average_amount_shortest_paths()
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		self.base.maximum_degree()
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		self.base.minimum_degree()
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		self.base.degree(router_index)
+	}
+	fn ports(&self, router_index: usize) -> usize
+	{
+		self.base.ports(router_index)
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		None
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.base.is_direction_change(router_index,input_port,output_port)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.base.up_down_distance(origin,destination)
+	}
+}
+
+impl FatTree
+{
+	pub fn new(arg:TopologyBuilderArgument) -> FatTree
+	{
+		let mut k=None;
+		let mut levels=None;
+		let mut servers_per_leaf=None;
+		let cv_name = match arg.cv
+		{
+			ConfigurationValue::Object(cv_name,_) => cv_name.clone(),
+			_ => panic!("Trying to create a FatTree from a non-Object"),
+		};
+		match_object_panic!(arg.cv,["FatTree","KAryNTree"],value,
+			"k" => k=Some(value.as_usize().expect("bad value for k")),
+			"levels" => levels=Some(value.as_usize().expect("bad value for levels")),
+			"servers_per_leaf" => servers_per_leaf=Some(value.as_usize().expect("bad value for servers_per_leaf")),
+		);
+		let k=k.expect("There were no k");
+		let levels=levels.expect("There were no levels");
+		if levels<2
+		{
+			panic!("A {} requires at least 2 levels, but {} were given",cv_name,levels);
+		}
+		let height = levels-1;
+		let (down,up,default_servers):(Vec<usize>,Vec<usize>,usize) = match cv_name.as_ref()
+		{
+			"FatTree" =>
+			{
+				if k%2!=0
+				{
+					panic!("A FatTree requires an even k, but k={} was given",k);
+				}
+				let half=k/2;
+				let mut down=vec![half;height];
+				down[height-1]=k;
+				(down,vec![half;height],half)
+			},
+			"KAryNTree" => (vec![k;height],vec![k;height],k),
+			_ => unreachable!(),
+		};
+		let servers_per_leaf = servers_per_leaf.unwrap_or(default_servers);
+		//Each top router has a down link to each of the subtrees below the top level, so these are the pods for any number of levels.
+		let pods = down[height-1];
+		let to_cv_array = |list:&[usize]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+		let xgft = ConfigurationValue::Object("XGFT".to_string(),vec![
+			("height".to_string(),ConfigurationValue::Number(height as f64)),
+			("down".to_string(),to_cv_array(&down)),
+			("up".to_string(),to_cv_array(&up)),
+			("servers_per_leaf".to_string(),ConfigurationValue::Number(servers_per_leaf as f64)),
+		]);
+		let base = MultiStage::new(TopologyBuilderArgument{cv:&xgft,plugs:arg.plugs,rng:arg.rng});
+		FatTree{
+			base,
+			k,
+			levels,
+			pods,
+		}
+	}
+	///The radix parameter `k` used to build the network.
+	pub fn k(&self) -> usize
+	{
+		self.k
+	}
+	///The number of levels of switches.
+	pub fn levels(&self) -> usize
+	{
+		self.levels
+	}
+	///The number of pods.
+	pub fn pods(&self) -> usize
+	{
+		self.pods
+	}
+	///Number of routers in a level. Level 0 are the leaves.
+	pub fn routers_in_level(&self, level:usize) -> usize
+	{
+		self.base.routers_per_level[level]
+	}
+	///The level of a router, with 0 for the leaves and `levels-1` for the top routers.
+	pub fn level(&self, router_index:usize) -> usize
+	{
+		self.base.unpack(router_index).0
+	}
+	///The pod containing a router. Top-level routers do not belong to any pod and return `None`.
+	pub fn pod(&self, router_index:usize) -> Option<usize>
+	{
+		let (level,offset) = self.base.unpack(router_index);
+		if level+1 == self.levels
+		{
+			None
+		}
+		else
+		{
+			Some(offset / self.pod_size(level))
+		}
+	}
+	///Number of routers of a given level in each pod.
+	pub fn pod_size(&self, level:usize) -> usize
+	{
+		self.base.routers_per_level[level] / self.pods
+	}
+	///Gives the `(level,pod,offset inside the pod)` of a router. For top routers the pod is `None` and the offset is the one inside the level.
+	pub fn unpack(&self, router_index:usize) -> (usize,Option<usize>,usize)
+	{
+		let (level,offset) = self.base.unpack(router_index);
+		if level+1 == self.levels
+		{
+			(level,None,offset)
+		}
+		else
+		{
+			let size = self.pod_size(level);
+			(level,Some(offset/size),offset%size)
+		}
+	}
+	///Inverse of `unpack`.
+	pub fn pack(&self, level:usize, pod:Option<usize>, offset:usize) -> usize
+	{
+		match pod
+		{
+			Some(pod) => self.base.pack(level,pod*self.pod_size(level)+offset),
+			None => self.base.pack(level,offset),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::Plugs;
+	fn build(name:&str, k:usize, levels:usize) -> FatTree
+	{
+		let cv = ConfigurationValue::Object(name.to_string(),vec![
+			("k".to_string(),ConfigurationValue::Number(k as f64)),
+			("levels".to_string(),ConfigurationValue::Number(levels as f64)),
+		]);
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		FatTree::new(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng})
+	}
+	#[test]
+	fn datacenter_sizes()
+	{
+		let k=4;
+		let topo = build("FatTree",k,3);
+		topo.check_adjacency_consistency(Some(3));
+		assert_eq!(topo.num_routers(), 5*k*k/4);
+		assert_eq!(topo.num_servers(), k*k*k/4);
+		assert_eq!(topo.routers_in_level(2), k*k/4);
+		assert_eq!(topo.diameter(), 4);
+		for router in 0..topo.num_routers()
+		{
+			let (level,pod,offset) = topo.unpack(router);
+			assert_eq!(topo.pack(level,pod,offset),router);
+			assert!(topo.ports(router)<=k, "router {} has {} ports", router, topo.ports(router));
+			//Leaves of the same pod are connected through an aggregation switch.
+			if level==0
+			{
+				for other in 0..topo.routers_in_level(0)
+				{
+					let (u,d) = topo.up_down_distance(router,other).expect("missing up/down path");
+					if other==router { assert_eq!((u,d),(0,0)); }
+					else if topo.pod(other)==pod { assert_eq!((u,d),(1,1)); }
+					else { assert_eq!((u,d),(2,2)); }
+				}
+			}
+		}
+	}
+	#[test]
+	fn pods_are_subtrees()
+	{
+		//The pods are the components left when removing the top level, whatever the number of levels.
+		for (name,k,levels) in [("FatTree",4,2),("FatTree",4,3),("FatTree",4,4),("KAryNTree",2,4),("KAryNTree",3,2)]
+		{
+			let topo = build(name,k,levels);
+			let mut pods_seen = vec![false;topo.pods()];
+			for router in 0..topo.num_routers()
+			{
+				let pod = match topo.pod(router)
+				{
+					Some(pod) => pod,
+					None => continue,
+				};
+				pods_seen[pod] = true;
+				for port in 0..topo.ports(router)
+				{
+					if let (Location::RouterPort{router_index,..},_) = topo.neighbour(router,port)
+					{
+						if topo.level(router_index)+1 < levels
+						{
+							assert_eq!(topo.pod(router_index),Some(pod),"{} k={} levels={} joins pods through router {}",name,k,levels,router);
+						}
+					}
+				}
+			}
+			assert!(pods_seen.iter().all(|&seen|seen),"{} k={} levels={} has empty pods",name,k,levels);
+		}
+	}
+	#[test]
+	fn kary_ntree_sizes()
+	{
+		let (k,n)=(2,4);
+		let topo = build("KAryNTree",k,n);
+		topo.check_adjacency_consistency(Some(n));
+		assert_eq!(topo.num_routers(), n*k.pow(n as u32-1));
+		assert_eq!(topo.num_servers(), k.pow(n as u32));
+		assert_eq!(topo.maximum_degree(), 2*k);
+		assert_eq!(topo.pod(topo.num_routers()-1), None);
+		assert_eq!(topo.pod_size(0), k.pow(n as u32-2));
+	}
+}
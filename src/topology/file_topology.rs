@@ -0,0 +1,857 @@
+/*!
+
+Topologies read from files in standard graph formats.
+
+* GraphML, the XML format used by Gephi, yEd and NetworkX.
+* DOT, the language of Graphviz.
+* EdgeList, a plain text list of links annotated with ports.
+
+*/
+
+use std::collections::HashMap;
+use quantifiable_derive::Quantifiable;//the derive macro
+use super::prelude::*;
+use crate::config_parser::ConfigurationValue;
+use crate::matrix::Matrix;
+use crate::match_object_panic;
+
+///The formats that can be read by [FileTopology].
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum GraphFormat
+{
+	GraphML,
+	DOT,
+	EdgeList,
+}
+
+impl GraphFormat
+{
+	///Get the format from its name, ignoring case.
+	pub fn from_name(name:&str) -> Option<GraphFormat>
+	{
+		match name.to_lowercase().as_ref()
+		{
+			"graphml" => Some(GraphFormat::GraphML),
+			"dot" | "graphviz" => Some(GraphFormat::DOT),
+			"edgelist" | "edge_list" | "edges" => Some(GraphFormat::EdgeList),
+			_ => None,
+		}
+	}
+	///Guess the format from the extension of a file name. Unknown extensions are taken as edge lists.
+	pub fn from_filename(filename:&str) -> GraphFormat
+	{
+		let extension = std::path::Path::new(filename).extension().and_then(|e|e.to_str()).unwrap_or("").to_lowercase();
+		match extension.as_ref()
+		{
+			"graphml" | "xml" => GraphFormat::GraphML,
+			"dot" | "gv" => GraphFormat::DOT,
+			_ => GraphFormat::EdgeList,
+		}
+	}
+}
+
+///A node read from a graph file.
+#[derive(Clone,Debug,Default)]
+pub struct ParsedNode
+{
+	pub name: String,
+	pub is_server: bool,
+}
+
+///A link read from a graph file. Ports and link class are optional.
+#[derive(Clone,Debug,Default)]
+pub struct ParsedEdge
+{
+	pub source: String,
+	pub source_port: Option<usize>,
+	pub target: String,
+	pub target_port: Option<usize>,
+	pub link_class: Option<usize>,
+}
+
+///The nodes and edges of a graph file, before building the topology.
+#[derive(Clone,Debug,Default)]
+pub struct ParsedGraph
+{
+	pub nodes: Vec<ParsedNode>,
+	pub edges: Vec<ParsedEdge>,
+}
+
+impl ParsedGraph
+{
+	///Index of the node with the given name, inserting a router if it did not exist.
+	fn node(&mut self, name:&str) -> usize
+	{
+		match self.nodes.iter().position(|node|node.name==name)
+		{
+			Some(index) => index,
+			None =>
+			{
+				self.nodes.push(ParsedNode{name:name.to_string(),is_server:false});
+				self.nodes.len()-1
+			},
+		}
+	}
+}
+
+/**
+A topology read from a file in GraphML, DOT, or edge list format. Explicit port numbers, link classes and server attachments are kept.
+```ignore
+FileTopology{
+	filename: "/path/to/fabric.graphml",
+	//format: "GraphML",//optional, one of "GraphML", "DOT", or "EdgeList". By default it is deduced from the file extension.
+	//servers_per_router: 0,//optional, servers added to every router after the ones in the file.
+	legend_name: "the fabric in the file",
+}
+```
+
+The nodes are routers unless marked as servers. Routers are numbered in order of appearance in the file, and so are servers. Links are bidirectional, also in directed graphs.
+Each link end may give its port. The links without explicit port take the lowest ports of the router not given explicitly to any link, first the links to routers and then the links to servers.
+Links between routers have link class 0 and links to servers link class 1, unless other class is given.
+
+* GraphML. Nodes with a `type` (or `kind`) data equal to `server` are servers. Edges take the ports from the `sourceport` and `targetport` attributes or from `source_port` and `target_port` data, and the link class from a `class` (or `link_class`) data. The data keys are matched by their `attr.name`.
+```ignore
+<key id="t" for="node" attr.name="type" attr.type="string"/>
+<key id="c" for="edge" attr.name="class" attr.type="int"/>
+<node id="r0"/> <node id="r1"/> <node id="s0"><data key="t">server</data></node>
+<edge source="r0" target="r1" sourceport="0" targetport="0"><data key="c">0</data></edge>
+<edge source="s0" target="r0" targetport="1"/>
+```
+* DOT. Nodes with attribute `type=server` (or `kind=server`) are servers. Ports are given with the `node:port` syntax or with the `tailport` and `headport` attributes, and the link class with a `class` (or `link_class`) attribute. Attribute statements (`graph`, `node`, `edge`) and subgraph structure are ignored.
+```ignore
+graph fabric {
+	s0 [type=server];
+	r0:0 -- r1:0 [class=0];
+	s0 -- r0:1;
+}
+```
+* EdgeList. One link per line. `#` begins a comment. The lines `router port router port [class]` join two routers and the lines `server router port [class]` attach a new server to a router. A `*` can be used for ports without explicit value.
+```ignore
+r0 0 r1 0
+r1 1 r2 * 0
+server r0 1
+```
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct FileTopology
+{
+	///`ports[router][port]` is the neighbour by that port and the link class.
+	ports: Vec<Vec<(Location,usize)>>,
+	///`servers[server]` is the router and port the server is attached to, and the link class.
+	servers: Vec<(usize,usize,usize)>,
+	///The names of the routers in the file.
+	router_names: Vec<String>,
+	///`distance_matrix.get(i,j)` = distance from router i to router j.
+	distance_matrix:Matrix<usize>,
+	///`amount_matrix.get(i,j)` = amount of shortest paths from router i to router j.
+	amount_matrix:Matrix<usize>,
+}
+
+impl Topology for FileTopology
+{
+	fn num_routers(&self) -> usize
+	{
+		self.ports.len()
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.servers.len()
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		self.ports[router_index][port].clone()
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let (router_index,router_port,link_class) = self.servers[server_index];
+		(Location::RouterPort{router_index,router_port},link_class)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.compute_diameter()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		*self.distance_matrix.get(origin,destination)
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		*self.amount_matrix.get(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		let n=self.num_routers();
+		if n<2
+		{
+			//There are no pairs of distinct routers.
+			return 0f32;
+		}
+		let total:usize=(0..n).flat_map(|i|(0..n).filter(move|&j|j!=i).map(move|j|(i,j))).map(|(i,j)|self.amount_shortest_paths(i,j)).sum();
+		total as f32/(n*(n-1)) as f32
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		(0..self.num_routers()).map(|router|self.degree(router)).max().expect("calling maximum_degree without routers")
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		(0..self.num_routers()).map(|router|self.degree(router)).min().expect("calling minimum_degree without routers")
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		self.ports[router_index].iter().filter(|(location,_)|matches!(location,Location::RouterPort{..})).count()
+	}
+	fn ports(&self, router_index: usize) -> usize
+	{
+		self.ports[router_index].len()
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		None
+	}
+	fn coordinated_routing_record(&self, _coordinates_a:&[usize], _coordinates_b:&[usize], _rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		unimplemented!();
+	}
+	fn is_direction_change(&self, _router_index:usize, _input_port: usize, _output_port: usize) -> bool
+	{
+		true
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl FileTopology
+{
+	pub fn new(arg:TopologyBuilderArgument) -> FileTopology
+	{
+		let mut filename=None;
+		let mut format=None;
+		let mut servers_per_router=0;
+		match_object_panic!(arg.cv,"FileTopology",value,
+			"filename" => filename=Some(value.as_str().expect("bad value for filename").to_string()),
+			"format" => format=Some(GraphFormat::from_name(value.as_str().expect("bad value for format")).unwrap_or_else(||panic!("Unknown graph format {}",value))),
+			"servers_per_router" => servers_per_router=value.as_usize().expect("bad value for servers_per_router"),
+		);
+		let filename=filename.expect("There were no filename");
+		let format=format.unwrap_or_else(||GraphFormat::from_filename(&filename));
+		let contents=std::fs::read_to_string(&filename).unwrap_or_else(|err|panic!("could not read topology file {}: {}",filename,err));
+		let graph=match format
+		{
+			GraphFormat::GraphML => parse_graphml(&contents),
+			GraphFormat::DOT => parse_dot(&contents),
+			GraphFormat::EdgeList => parse_edge_list(&contents),
+		};
+		FileTopology::from_graph(&graph,servers_per_router)
+	}
+	///Build the topology from a parsed graph, adding `servers_per_router` servers to each router after the ones in the graph.
+	pub fn from_graph(graph:&ParsedGraph, servers_per_router:usize) -> FileTopology
+	{
+		//Index of each node as router or server.
+		let mut router_names=vec![];
+		let mut server_count=0;
+		let mut node_index:HashMap<&str,(bool,usize)>=HashMap::new();
+		for node in graph.nodes.iter()
+		{
+			if node_index.contains_key(node.name.as_str())
+			{
+				panic!("Node {} is defined twice",node.name);
+			}
+			if node.is_server
+			{
+				node_index.insert(&node.name,(true,server_count));
+				server_count+=1;
+			}
+			else
+			{
+				node_index.insert(&node.name,(false,router_names.len()));
+				router_names.push(node.name.clone());
+			}
+		}
+		let n=router_names.len();
+		let mut ports:Vec<Vec<(Location,usize)>>=vec![vec![];n];
+		let mut servers:Vec<Option<(usize,usize,usize)>>=vec![None;server_count];
+		//Links whose ports are to be assigned after the explicit ones.
+		let mut pending_router_links=vec![];
+		let mut pending_server_links=vec![];
+		let set_port=|ports:&mut Vec<Vec<(Location,usize)>>, router:usize, port:usize, location:Location, link_class:usize|
+		{
+			if ports[router].len()<=port
+			{
+				ports[router].resize(port+1,(Location::None,0));
+			}
+			if ports[router][port].0!=Location::None
+			{
+				panic!("Port {} of router {} is used twice",port,router);
+			}
+			ports[router][port]=(location,link_class);
+		};
+		for edge in graph.edges.iter()
+		{
+			let source=*node_index.get(edge.source.as_str()).unwrap_or_else(||panic!("Unknown node {}",edge.source));
+			let target=*node_index.get(edge.target.as_str()).unwrap_or_else(||panic!("Unknown node {}",edge.target));
+			match (source,target)
+			{
+				((false,a),(false,b)) =>
+				{
+					if a==b
+					{
+						panic!("Loop at router {}",edge.source);
+					}
+					let link_class=edge.link_class.unwrap_or(0);
+					match (edge.source_port,edge.target_port)
+					{
+						(Some(pa),Some(pb)) =>
+						{
+							set_port(&mut ports,a,pa,Location::RouterPort{router_index:b,router_port:pb},link_class);
+							set_port(&mut ports,b,pb,Location::RouterPort{router_index:a,router_port:pa},link_class);
+						},
+						_ => pending_router_links.push((a,edge.source_port,b,edge.target_port,link_class)),
+					}
+				},
+				((true,server),(false,router)) | ((false,router),(true,server)) =>
+				{
+					let port = if source.0 { edge.target_port } else { edge.source_port };
+					let link_class=edge.link_class.unwrap_or(1);
+					if servers[server].is_some()
+					{
+						panic!("Server {} is attached to more than one router",server);
+					}
+					match port
+					{
+						Some(port) =>
+						{
+							set_port(&mut ports,router,port,Location::ServerPort(server),link_class);
+							servers[server]=Some((router,port,link_class));
+						},
+						None => pending_server_links.push((server,router,link_class)),
+					}
+				},
+				((true,_),(true,_)) => panic!("Link between the servers {} and {}",edge.source,edge.target),
+			}
+		}
+		//Reserve the explicit ends of the pending links before any implicit port is assigned.
+		for &(a,pa,b,pb,link_class) in pending_router_links.iter()
+		{
+			if let Some(pa)=pa
+			{
+				set_port(&mut ports,a,pa,Location::RouterPort{router_index:b,router_port:0},link_class);
+			}
+			if let Some(pb)=pb
+			{
+				set_port(&mut ports,b,pb,Location::RouterPort{router_index:a,router_port:0},link_class);
+			}
+		}
+		let free_port=|ports:&Vec<Vec<(Location,usize)>>, router:usize| ports[router].iter().position(|(location,_)|*location==Location::None).unwrap_or(ports[router].len());
+		for (a,pa,b,pb,link_class) in pending_router_links
+		{
+			let pa=pa.unwrap_or_else(||{
+				let pa=free_port(&ports,a);
+				set_port(&mut ports,a,pa,Location::RouterPort{router_index:b,router_port:0},link_class);
+				pa
+			});
+			let pb=pb.unwrap_or_else(||{
+				let pb=free_port(&ports,b);
+				set_port(&mut ports,b,pb,Location::RouterPort{router_index:a,router_port:0},link_class);
+				pb
+			});
+			ports[a][pa].0=Location::RouterPort{router_index:b,router_port:pb};
+			ports[b][pb].0=Location::RouterPort{router_index:a,router_port:pa};
+		}
+		for (server,router,link_class) in pending_server_links
+		{
+			let port=free_port(&ports,router);
+			set_port(&mut ports,router,port,Location::ServerPort(server),link_class);
+			servers[server]=Some((router,port,link_class));
+		}
+		let mut servers:Vec<(usize,usize,usize)>=servers.into_iter().enumerate().map(|(index,s)|s.unwrap_or_else(||panic!("Server {} is not attached to any router",index))).collect();
+		for router in 0..n
+		{
+			for _ in 0..servers_per_router
+			{
+				let port=free_port(&ports,router);
+				set_port(&mut ports,router,port,Location::ServerPort(servers.len()),1);
+				servers.push((router,port,1));
+			}
+		}
+		let mut topology=FileTopology{
+			ports,
+			servers,
+			router_names,
+			distance_matrix:Matrix::constant(0,0,0),
+			amount_matrix:Matrix::constant(0,0,0),
+		};
+		let (distance_matrix,amount_matrix)=topology.compute_amount_shortest_paths();
+		topology.distance_matrix=distance_matrix;
+		topology.amount_matrix=amount_matrix;
+		topology
+	}
+	///The name of a router in the file.
+	pub fn router_name(&self, router_index:usize) -> &str
+	{
+		&self.router_names[router_index]
+	}
+}
+
+///Parse a port or link class, with `*` or an empty string meaning no value.
+fn parse_optional_number(text:&str, what:&str) -> Option<usize>
+{
+	let text=text.trim();
+	if text.is_empty() || text=="*"
+	{
+		None
+	}
+	else
+	{
+		Some(text.parse::<usize>().unwrap_or_else(|_|panic!("bad value {} for {}",text,what)))
+	}
+}
+
+///Parse the edge list format. See [FileTopology].
+pub fn parse_edge_list(contents:&str) -> ParsedGraph
+{
+	let mut graph=ParsedGraph::default();
+	let mut server_count=0;
+	for (line_index,line) in contents.lines().enumerate()
+	{
+		let line=match line.find('#') { Some(index) => &line[..index], None => line };
+		let words:Vec<&str>=line.split_whitespace().collect();
+		if words.is_empty()
+		{
+			continue;
+		}
+		if words[0]=="server"
+		{
+			if words.len()<3 || words.len()>4
+			{
+				panic!("line {}: a server line must be `server router port [class]`",line_index+1);
+			}
+			let name=format!("server {}",server_count);
+			server_count+=1;
+			graph.nodes.push(ParsedNode{name:name.clone(),is_server:true});
+			graph.node(words[1]);
+			graph.edges.push(ParsedEdge{
+				source: name,
+				source_port: None,
+				target: words[1].to_string(),
+				target_port: parse_optional_number(words[2],"port"),
+				link_class: words.get(3).and_then(|w|parse_optional_number(w,"link class")),
+			});
+		}
+		else
+		{
+			if words.len()<4 || words.len()>5
+			{
+				panic!("line {}: a link line must be `router port router port [class]`",line_index+1);
+			}
+			graph.node(words[0]);
+			graph.node(words[2]);
+			graph.edges.push(ParsedEdge{
+				source: words[0].to_string(),
+				source_port: parse_optional_number(words[1],"port"),
+				target: words[2].to_string(),
+				target_port: parse_optional_number(words[3],"port"),
+				link_class: words.get(4).and_then(|w|parse_optional_number(w,"link class")),
+			});
+		}
+	}
+	graph
+}
+
+///Whether an attribute value marks a server node.
+fn is_server_attribute(name:&str, value:&str) -> bool
+{
+	(name=="type" || name=="kind") && value.eq_ignore_ascii_case("server")
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum DotToken
+{
+	Id(String),
+	Symbol(String),
+}
+
+fn dot_tokens(contents:&str) -> Vec<DotToken>
+{
+	let chars:Vec<char>=contents.chars().collect();
+	let mut tokens=vec![];
+	let mut index=0;
+	while index<chars.len()
+	{
+		let c=chars[index];
+		if c.is_whitespace()
+		{
+			index+=1;
+		}
+		else if c=='/' && chars.get(index+1)==Some(&'/') || c=='#'
+		{
+			while index<chars.len() && chars[index]!='\n' { index+=1; }
+		}
+		else if c=='/' && chars.get(index+1)==Some(&'*')
+		{
+			index+=2;
+			while index+1<chars.len() && !(chars[index]=='*' && chars[index+1]=='/') { index+=1; }
+			index+=2;
+		}
+		else if c=='"'
+		{
+			let mut text=String::new();
+			index+=1;
+			while index<chars.len() && chars[index]!='"'
+			{
+				if chars[index]=='\\' && index+1<chars.len() { index+=1; }
+				text.push(chars[index]);
+				index+=1;
+			}
+			index+=1;
+			tokens.push(DotToken::Id(text));
+		}
+		else if c=='-' && (chars.get(index+1)==Some(&'-') || chars.get(index+1)==Some(&'>'))
+		{
+			tokens.push(DotToken::Symbol(chars[index..index+2].iter().collect()));
+			index+=2;
+		}
+		else if c.is_alphanumeric() || c=='_' || c=='.' || c=='-'
+		{
+			let start=index;
+			while index<chars.len() && (chars[index].is_alphanumeric() || chars[index]=='_' || chars[index]=='.' || (chars[index]=='-' && index==start))
+			{
+				index+=1;
+			}
+			tokens.push(DotToken::Id(chars[start..index].iter().collect()));
+		}
+		else
+		{
+			tokens.push(DotToken::Symbol(c.to_string()));
+			index+=1;
+		}
+	}
+	tokens
+}
+
+///Parse a Graphviz DOT graph. See [FileTopology].
+pub fn parse_dot(contents:&str) -> ParsedGraph
+{
+	let tokens=dot_tokens(contents);
+	let symbol=|index:usize, s:&str| tokens.get(index)==Some(&DotToken::Symbol(s.to_string()));
+	let mut graph=ParsedGraph::default();
+	let mut index=0;
+	//Skip the header until the first brace.
+	while index<tokens.len() && !symbol(index,"{") { index+=1; }
+	index+=1;
+	while index<tokens.len()
+	{
+		match &tokens[index]
+		{
+			DotToken::Symbol(s) if s=="{" || s=="}" || s==";" || s=="," => index+=1,
+			DotToken::Symbol(s) => panic!("Unexpected symbol {} in DOT file",s),
+			DotToken::Id(id) if (id=="graph" || id=="node" || id=="edge") && symbol(index+1,"[") =>
+			{
+				index+=1;
+				let (_attributes,next)=dot_attributes(&tokens,index);
+				index=next;
+			},
+			DotToken::Id(id) if id=="subgraph" =>
+			{
+				index+=1;
+				if let Some(DotToken::Id(_))=tokens.get(index) { index+=1; }
+			},
+			DotToken::Id(_) if symbol(index+1,"=") => index+=3,
+			DotToken::Id(_) =>
+			{
+				//A node or a chain of edges.
+				let mut ends=vec![];
+				loop
+				{
+					let name = match tokens.get(index)
+					{
+						Some(DotToken::Id(name)) => name,
+						_ => panic!("Expected a node in DOT file"),
+					};
+					index+=1;
+					let mut port=None;
+					if symbol(index,":")
+					{
+						if let Some(DotToken::Id(p))=tokens.get(index+1)
+						{
+							port=parse_optional_number(p,"port");
+						}
+						index+=2;
+					}
+					ends.push((name.clone(),port));
+					if symbol(index,"--") || symbol(index,"->")
+					{
+						index+=1;
+					}
+					else
+					{
+						break;
+					}
+				}
+				let (attributes,next)=dot_attributes(&tokens,index);
+				index=next;
+				for (name,_) in ends.iter()
+				{
+					graph.node(name);
+				}
+				if ends.len()==1
+				{
+					let node=graph.node(&ends[0].0);
+					if attributes.iter().any(|(name,value)|is_server_attribute(name,value))
+					{
+						graph.nodes[node].is_server=true;
+					}
+				}
+				let attribute=|key:&str| attributes.iter().find(|(name,_)|name==key).map(|(_,value)|value.as_str());
+				let link_class=attribute("class").or_else(||attribute("link_class")).and_then(|v|parse_optional_number(v,"link class"));
+				for pair in ends.windows(2)
+				{
+					graph.edges.push(ParsedEdge{
+						source: pair[0].0.clone(),
+						source_port: pair[0].1.or_else(||attribute("tailport").and_then(|v|parse_optional_number(v,"port"))),
+						target: pair[1].0.clone(),
+						target_port: pair[1].1.or_else(||attribute("headport").and_then(|v|parse_optional_number(v,"port"))),
+						link_class,
+					});
+				}
+			},
+		}
+	}
+	graph
+}
+
+///Read the attribute lists beginning at `index`, if any. Returns the attributes and the index after them.
+fn dot_attributes(tokens:&[DotToken], mut index:usize) -> (Vec<(String,String)>,usize)
+{
+	let mut attributes=vec![];
+	while tokens.get(index)==Some(&DotToken::Symbol("[".to_string()))
+	{
+		index+=1;
+		while index<tokens.len() && tokens[index]!=DotToken::Symbol("]".to_string())
+		{
+			match (&tokens[index],tokens.get(index+1),tokens.get(index+2))
+			{
+				(DotToken::Id(name),Some(DotToken::Symbol(eq)),Some(DotToken::Id(value))) if eq=="=" =>
+				{
+					attributes.push((name.clone(),value.clone()));
+					index+=3;
+				},
+				_ => index+=1,
+			}
+		}
+		index+=1;
+	}
+	(attributes,index)
+}
+
+///A tag of a XML document, with its attributes and the text following it.
+#[derive(Debug)]
+struct XmlTag
+{
+	name: String,
+	closing: bool,
+	self_closing: bool,
+	attributes: Vec<(String,String)>,
+	text: String,
+}
+
+fn xml_unescape(text:&str) -> String
+{
+	text.replace("&lt;","<").replace("&gt;",">").replace("&quot;","\"").replace("&apos;","'").replace("&amp;","&")
+}
+
+fn xml_tags(contents:&str) -> Vec<XmlTag>
+{
+	let mut tags=vec![];
+	let mut rest=contents;
+	while let Some(start)=rest.find('<')
+	{
+		rest=&rest[start..];
+		if rest.starts_with("<!--")
+		{
+			let end=rest.find("-->").expect("unterminated XML comment");
+			rest=&rest[end+3..];
+			continue;
+		}
+		let end=rest.find('>').expect("unterminated XML tag");
+		let inner=&rest[1..end];
+		rest=&rest[end+1..];
+		if inner.starts_with('?') || inner.starts_with('!')
+		{
+			continue;
+		}
+		let closing=inner.starts_with('/');
+		let self_closing=inner.ends_with('/');
+		let inner=inner.trim_start_matches('/').trim_end_matches('/');
+		let name_end=inner.find(char::is_whitespace).unwrap_or(inner.len());
+		let name=inner[..name_end].to_string();
+		let mut attributes=vec![];
+		let mut attribute_text=&inner[name_end..];
+		while let Some(eq)=attribute_text.find('=')
+		{
+			let key=attribute_text[..eq].trim().to_string();
+			let after=attribute_text[eq+1..].trim_start();
+			let quote=after.chars().next().expect("missing attribute value");
+			let value_end=after[1..].find(quote).expect("unterminated attribute value");
+			attributes.push((key,xml_unescape(&after[1..1+value_end])));
+			attribute_text=&after[value_end+2..];
+		}
+		let text_end=rest.find('<').unwrap_or(rest.len());
+		tags.push(XmlTag{name,closing,self_closing,attributes,text:xml_unescape(rest[..text_end].trim())});
+	}
+	tags
+}
+
+///Parse a GraphML document. See [FileTopology].
+pub fn parse_graphml(contents:&str) -> ParsedGraph
+{
+	let mut graph=ParsedGraph::default();
+	//`attr.name` of each key.
+	let mut keys:HashMap<String,String>=HashMap::new();
+	//The element receiving the data, either a node or an edge index.
+	enum Current { Nothing, Node(usize), Edge(usize) }
+	let mut current=Current::Nothing;
+	for tag in xml_tags(contents)
+	{
+		let attribute=|key:&str| tag.attributes.iter().find(|(name,_)|name==key).map(|(_,value)|value.as_str());
+		match (tag.name.as_ref(),tag.closing)
+		{
+			("key",false) =>
+			{
+				let id=attribute("id").expect("GraphML key without id");
+				let name=attribute("attr.name").unwrap_or(id);
+				keys.insert(id.to_string(),name.to_string());
+			},
+			("node",false) =>
+			{
+				let node=graph.node(attribute("id").expect("GraphML node without id"));
+				current = if tag.self_closing { Current::Nothing } else { Current::Node(node) };
+			},
+			("edge",false) =>
+			{
+				let source=attribute("source").expect("GraphML edge without source").to_string();
+				let target=attribute("target").expect("GraphML edge without target").to_string();
+				graph.node(&source);
+				graph.node(&target);
+				graph.edges.push(ParsedEdge{
+					source,
+					source_port: attribute("sourceport").and_then(|v|parse_optional_number(v,"port")),
+					target,
+					target_port: attribute("targetport").and_then(|v|parse_optional_number(v,"port")),
+					link_class: None,
+				});
+				current = if tag.self_closing { Current::Nothing } else { Current::Edge(graph.edges.len()-1) };
+			},
+			("node",true) | ("edge",true) => current=Current::Nothing,
+			("data",false) =>
+			{
+				let key=attribute("key").expect("GraphML data without key");
+				let name=keys.get(key).map(|s|s.as_str()).unwrap_or(key);
+				let value=tag.text.as_str();
+				match current
+				{
+					Current::Node(node) => if is_server_attribute(name,value)
+					{
+						graph.nodes[node].is_server=true;
+					},
+					Current::Edge(edge) => match name
+					{
+						"class" | "link_class" => graph.edges[edge].link_class=parse_optional_number(value,"link class"),
+						"source_port" => graph.edges[edge].source_port=parse_optional_number(value,"port"),
+						"target_port" => graph.edges[edge].target_port=parse_optional_number(value,"port"),
+						_ => (),
+					},
+					Current::Nothing => (),
+				}
+			},
+			_ => (),
+		}
+	}
+	graph
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	///Checks the graph r0 -- r1 -- r2 with a server on r0 and another on r2, which all the test files describe.
+	fn check_path(topology:&FileTopology)
+	{
+		topology.check_adjacency_consistency(None);
+		assert_eq!(topology.num_routers(),3);
+		assert_eq!(topology.num_servers(),2);
+		assert_eq!(topology.neighbour(0,0),(Location::RouterPort{router_index:1,router_port:1},0));
+		assert_eq!(topology.neighbour(1,0),(Location::RouterPort{router_index:2,router_port:0},3));
+		assert_eq!(topology.server_neighbour(0),(Location::RouterPort{router_index:0,router_port:2},1));
+		assert_eq!(topology.server_neighbour(1),(Location::RouterPort{router_index:2,router_port:1},1));
+		assert_eq!(topology.ports(0),3);
+		assert_eq!(topology.neighbour(0,1).0,Location::None);
+		assert_eq!(topology.distance(0,2),2);
+		assert_eq!(topology.router_name(2),"r2");
+	}
+	#[test]
+	fn edge_list()
+	{
+		let contents="# a path\nr0 0 r1 1\nr1 0 r2 * 3\nserver r0 2\nserver r2 *\n";
+		check_path(&FileTopology::from_graph(&parse_edge_list(contents),0));
+	}
+	#[test]
+	fn dot()
+	{
+		let contents="graph fabric {\n\tnode [shape=box];\n\ts0 [type=server];\n\tr0:0 -- r1:1;\n\tr1:0 -- r2 [class=3];\n\ts0 -- r0:2\n\tr2 -- s1;\n\ts1 [type=\"server\"]\n}\n";
+		check_path(&FileTopology::from_graph(&parse_dot(contents),0));
+	}
+	#[test]
+	fn graphml()
+	{
+		let contents=r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+	<key id="d0" for="node" attr.name="type" attr.type="string"/>
+	<key id="d1" for="edge" attr.name="class" attr.type="int"/>
+	<graph id="G" edgedefault="undirected">
+		<node id="s0"><data key="d0">server</data></node>
+		<node id="r0"/>
+		<node id="r1"/>
+		<!-- the last router -->
+		<node id="r2"/>
+		<node id="s1"><data key="d0">server</data></node>
+		<edge source="r0" target="r1" sourceport="0" targetport="1"/>
+		<edge source="r1" target="r2" sourceport="0"><data key="d1">3</data></edge>
+		<edge source="s0" target="r0" targetport="2"/>
+		<edge source="s1" target="r2"/>
+	</graph>
+</graphml>
+"#;
+		check_path(&FileTopology::from_graph(&parse_graphml(contents),0));
+	}
+	#[test]
+	fn extra_servers()
+	{
+		let topology=FileTopology::from_graph(&parse_edge_list("a * b *\n"),2);
+		topology.check_adjacency_consistency(None);
+		assert_eq!(topology.num_servers(),4);
+		assert_eq!(topology.server_neighbour(3),(Location::RouterPort{router_index:1,router_port:2},1));
+	}
+	#[test]
+	fn explicit_port_of_later_link()
+	{
+		//The port 0 of r0 is given in the second link, but the first link must not take it.
+		let topology=FileTopology::from_graph(&parse_edge_list("r1 * r0 *\nr0 0 r2 *\n"),0);
+		topology.check_adjacency_consistency(None);
+		//The routers are numbered as they appear: r1, r0, r2.
+		assert_eq!(topology.router_name(1),"r0");
+		assert_eq!(topology.neighbour(0,0),(Location::RouterPort{router_index:1,router_port:1},0));
+		assert_eq!(topology.neighbour(1,0),(Location::RouterPort{router_index:2,router_port:0},0));
+	}
+	#[test]
+	fn single_router()
+	{
+		//There are no pairs of routers to average over.
+		let topology=FileTopology::from_graph(&parse_edge_list("server r0 *\n"),0);
+		assert_eq!(topology.num_routers(),1);
+		assert_eq!(topology.average_amount_shortest_paths(),0f32);
+	}
+}
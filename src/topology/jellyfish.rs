@@ -0,0 +1,350 @@
+/*!
+
+The Jellyfish topology, a random graph among switches of possibly different radix.
+
+Reference: Singla, A., Hong, C. Y., Popa, L., & Godfrey, P. B. (2012). Jellyfish: Networking data centers randomly. In 9th USENIX Symposium on Networked Systems Design and Implementation (NSDI 12) (pp. 225-238).
+
+*/
+
+use std::collections::BTreeSet;
+use ::rand::{Rng,rngs::StdRng};
+use quantifiable_derive::Quantifiable;//the derive macro
+use super::prelude::*;
+use super::neighbourslists::NeighboursLists;
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+///A kind of switch in a [Jellyfish].
+#[derive(Clone,Copy,Debug)]
+pub struct SwitchClass
+{
+	///Number of switches of this class.
+	pub count: usize,
+	///Number of ports of each switch intended to connect to other switches.
+	pub network_ports: usize,
+	///Average number of servers of each switch. A fractional value spreads `round(count*server_ports)` servers as evenly as possible among the switches of the class.
+	pub server_ports: f64,
+}
+
+impl SwitchClass
+{
+	pub fn new(cv:&ConfigurationValue) -> SwitchClass
+	{
+		let mut count=None;
+		let mut network_ports=None;
+		let mut server_ports=None;
+		match_object_panic!(cv,"SwitchClass",value,
+			"count" => count=Some(value.as_usize().expect("bad value for count")),
+			"network_ports" => network_ports=Some(value.as_usize().expect("bad value for network_ports")),
+			"server_ports" => server_ports=Some(value.as_f64().expect("bad value for server_ports")),
+		);
+		SwitchClass{
+			count: count.expect("There were no count"),
+			network_ports: network_ports.expect("There were no network_ports"),
+			server_ports: server_ports.expect("There were no server_ports"),
+		}
+	}
+	///The number of servers of each of the switches in the class.
+	fn servers(&self) -> Vec<usize>
+	{
+		let total=(self.count as f64*self.server_ports).round() as usize;
+		(0..self.count).map(|i|(i+1)*total/self.count - i*total/self.count).collect()
+	}
+}
+
+/**
+A Jellyfish network: a random graph among switches that may have different number of ports.
+The switches are given by a list of [SwitchClass]es. The links are built by joining random pairs of free ports of non-adjacent switches. When no such pair remains but some switch has at least two free ports, a random link is broken and its endpoints are joined to that switch.
+Then, the switches of the optional `expansion` classes are added one by one, each one breaking random links of the existing network and joining their endpoints to the new switch, as in the incremental expansion of the original paper.
+Some network ports may remain unconnected; they are not included in the ports of the router.
+The random choices use the topology random number generator, so the network is reproducible from the simulation seed.
+```ignore
+Jellyfish{
+	switch_classes: [
+		SwitchClass{count: 40, network_ports: 8, server_ports: 4},
+		SwitchClass{count: 20, network_ports: 12, server_ports: 5.5},
+	],
+	//expansion: [SwitchClass{count: 10, network_ports: 12, server_ports: 6}],//optional switches added by breaking links.
+	legend_name: "Jellyfish with two switch classes",
+}
+```
+Links between switches are of class 0 and links to servers of class 1, as in other random topologies.
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct Jellyfish
+{
+	///The actual graph.
+	base: NeighboursLists,
+	///`router_class[router]` is the index of the class of `router`, counting first `switch_classes` and then the `expansion` classes.
+	router_class: Vec<usize>,
+	///The number of network ports of each router, including the unconnected ones.
+	network_ports: Vec<usize>,
+}
+
+impl Topology for Jellyfish
+{
+	fn num_routers(&self) -> usize
+	{
+		self.base.num_routers()
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.base.num_servers()
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		self.base.neighbour(router_index,port)
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.base.server_neighbour(server_index)
+	}
+	fn diameter(&self) -> usize
+	{
+		self.base.diameter()
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		self.base.distance(origin,destination)
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		self.base.amount_shortest_paths(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		self.base.average_amount_shortest_paths()
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		self.base.maximum_degree()
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		self.base.minimum_degree()
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		self.base.degree(router_index)
+	}
+	fn ports(&self, router_index: usize) -> usize
+	{
+		self.base.ports(router_index)
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		None
+	}
+	fn coordinated_routing_record(&self, _coordinates_a:&[usize], _coordinates_b:&[usize], _rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		unimplemented!();
+	}
+	fn is_direction_change(&self, _router_index:usize, _input_port: usize, _output_port: usize) -> bool
+	{
+		true
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+}
+
+impl Jellyfish
+{
+	pub fn new(arg:TopologyBuilderArgument) -> Jellyfish
+	{
+		let mut switch_classes=None;
+		let mut expansion=vec![];
+		match_object_panic!(arg.cv,"Jellyfish",value,
+			"switch_classes" => switch_classes=Some(value.as_array().expect("bad value for switch_classes").iter().map(SwitchClass::new).collect::<Vec<_>>()),
+			"expansion" => expansion=value.as_array().expect("bad value for expansion").iter().map(SwitchClass::new).collect(),
+		);
+		let switch_classes=switch_classes.expect("There were no switch_classes");
+		let mut router_class=vec![];
+		let mut network_ports=vec![];
+		let mut servers=vec![];
+		for (class_index,class) in switch_classes.iter().chain(expansion.iter()).enumerate()
+		{
+			router_class.extend(std::iter::repeat(class_index).take(class.count));
+			network_ports.extend(std::iter::repeat(class.network_ports).take(class.count));
+			servers.extend(class.servers());
+		}
+		let initial:usize=switch_classes.iter().map(|class|class.count).sum();
+		let mut adj=random_adjacencies(&network_ports[..initial],arg.rng);
+		for &ports in network_ports[initial..].iter()
+		{
+			expand(&mut adj,ports,arg.rng);
+		}
+		let list=adj.iter().enumerate().map(|(current,neighbours)|
+			neighbours.iter().map(|&neighbour|(neighbour,adj[neighbour].iter().position(|&v|v==current).expect("asymmetric adjacency"))).collect()
+		).collect();
+		Jellyfish{
+			base: NeighboursLists::new(list,servers),
+			router_class,
+			network_ports,
+		}
+	}
+	///The index of the switch class of a router. The classes in `expansion` are numbered after the ones in `switch_classes`.
+	pub fn switch_class(&self, router_index:usize) -> usize
+	{
+		self.router_class[router_index]
+	}
+	///The number of network ports of a router that have been left unconnected.
+	pub fn free_ports(&self, router_index:usize) -> usize
+	{
+		self.network_ports[router_index] - self.base.degree(router_index)
+	}
+}
+
+///Build random adjacencies with the given number of ports in each router, without loops nor multiple links.
+fn random_adjacencies(ports:&[usize], rng:&mut StdRng) -> Vec<Vec<usize>>
+{
+	let n=ports.len();
+	let mut adj:Vec<Vec<usize>>=ports.iter().map(|&p|Vec::with_capacity(p)).collect();
+	//Each free port is represented by its router.
+	let mut stubs:Vec<usize>=(0..n).flat_map(|router|std::iter::repeat(router).take(ports[router])).collect();
+	let mut failures=0;
+	while stubs.len()>=2
+	{
+		//Random pairs while it is easy to find a valid one.
+		if failures<100
+		{
+			let a=rng.gen_range(0..stubs.len());
+			let b=rng.gen_range(0..stubs.len());
+			let (u,v)=(stubs[a],stubs[b]);
+			if u==v || adj[u].contains(&v)
+			{
+				failures+=1;
+				continue;
+			}
+			failures=0;
+			adj[u].push(v);
+			adj[v].push(u);
+			let (first,second)=if a>b {(a,b)} else {(b,a)};
+			stubs.swap_remove(first);
+			stubs.swap_remove(second);
+			continue;
+		}
+		//Otherwise check all the remaining pairs.
+		let free:BTreeSet<usize>=stubs.iter().cloned().collect();
+		let pairs:Vec<(usize,usize)>=free.iter().flat_map(|&u|free.iter().filter(move|&&v|u<v).map(move|&v|(u,v))).filter(|&(u,v)|!adj[u].contains(&v)).collect();
+		if !pairs.is_empty()
+		{
+			let (u,v)=pairs[rng.gen_range(0..pairs.len())];
+			adj[u].push(v);
+			adj[v].push(u);
+			stubs.swap_remove(stubs.iter().position(|&x|x==u).unwrap());
+			stubs.swap_remove(stubs.iter().position(|&x|x==v).unwrap());
+			continue;
+		}
+		//No pair can be joined. Break links to use the routers with at least two free ports.
+		let Some(&u)=free.iter().find(|&&u|stubs.iter().filter(|&&x|x==u).count()>=2) else { break };
+		if !break_link_into(&mut adj,u,rng)
+		{
+			break;
+		}
+		for _ in 0..2
+		{
+			stubs.swap_remove(stubs.iter().position(|&x|x==u).unwrap());
+		}
+	}
+	adj
+}
+
+///Remove a random link `x--y` with `x` and `y` not adjacent to `u` and put the links `u--x` and `u--y`. Returns false if there is no such link.
+fn break_link_into(adj:&mut [Vec<usize>], u:usize, rng:&mut StdRng) -> bool
+{
+	let candidates:Vec<(usize,usize)>=adj.iter().enumerate().flat_map(|(x,neighbours)|neighbours.iter().filter(move|&&y|x<y).map(move|&y|(x,y)))
+		.filter(|&(x,y)|x!=u && y!=u && !adj[u].contains(&x) && !adj[u].contains(&y)).collect();
+	if candidates.is_empty()
+	{
+		return false;
+	}
+	let (x,y)=candidates[rng.gen_range(0..candidates.len())];
+	adj[x].retain(|&w|w!=y);
+	adj[y].retain(|&w|w!=x);
+	adj[x].push(u);
+	adj[y].push(u);
+	adj[u].push(x);
+	adj[u].push(y);
+	true
+}
+
+///Add a new router with the given number of ports, breaking random links of the existing ones.
+fn expand(adj:&mut Vec<Vec<usize>>, ports:usize, rng:&mut StdRng)
+{
+	let u=adj.len();
+	adj.push(Vec::with_capacity(ports));
+	while ports-adj[u].len()>=2
+	{
+		if !break_link_into(adj,u,rng)
+		{
+			break;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::Plugs;
+	fn switch_class(count:usize, network_ports:usize, server_ports:f64) -> ConfigurationValue
+	{
+		ConfigurationValue::Object("SwitchClass".to_string(),vec![
+			("count".to_string(),ConfigurationValue::Number(count as f64)),
+			("network_ports".to_string(),ConfigurationValue::Number(network_ports as f64)),
+			("server_ports".to_string(),ConfigurationValue::Number(server_ports)),
+		])
+	}
+	fn build(seed:u64) -> Jellyfish
+	{
+		let cv = ConfigurationValue::Object("Jellyfish".to_string(),vec![
+			("switch_classes".to_string(),ConfigurationValue::Array(vec![switch_class(20,4,2.0),switch_class(10,7,2.5)])),
+			("expansion".to_string(),ConfigurationValue::Array(vec![switch_class(5,6,3.0)])),
+		]);
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(seed);
+		Jellyfish::new(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng})
+	}
+	#[test]
+	fn jellyfish_structure()
+	{
+		let topology = build(3);
+		topology.check_adjacency_consistency(Some(2));
+		assert_eq!(topology.num_routers(), 35);
+		assert_eq!(topology.num_servers(), 20*2 + 25 + 5*3);
+		let free:usize = (0..topology.num_routers()).map(|router|topology.free_ports(router)).sum();
+		assert!(free<=2, "too many free ports: {}", free);
+		for router in 0..topology.num_routers()
+		{
+			let servers = topology.ports(router) - topology.degree(router);
+			match topology.switch_class(router)
+			{
+				0 => assert_eq!(servers,2),
+				1 => assert!(servers==2 || servers==3),
+				2 => assert_eq!(servers,3),
+				_ => unreachable!(),
+			}
+			for NeighbourRouterIteratorItem{neighbour_router,..} in topology.neighbour_router_iter(router)
+			{
+				assert_ne!(neighbour_router,router);
+			}
+		}
+		assert!(topology.diameter() < 35, "the network should be connected");
+	}
+	#[test]
+	fn jellyfish_reproducible()
+	{
+		let a = build(7);
+		let b = build(7);
+		for router in 0..a.num_routers()
+		{
+			let na:Vec<usize> = a.neighbour_router_iter(router).map(|item|item.neighbour_router).collect();
+			let nb:Vec<usize> = b.neighbour_router_iter(router).map(|item|item.neighbour_router).collect();
+			assert_eq!(na,nb);
+		}
+	}
+}
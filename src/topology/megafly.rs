@@ -269,6 +269,270 @@ impl Megafly
 	}
 }
 
+/**
+The Dragonfly+ topology of Shpiner et al., in which each group is a complete bipartite graph between `leaves_per_group` leaf switches and `spines_per_group` spine switches.
+Servers are attached only to the leaves and the global links are attached only to the spines, following the given global arrangement (see [Arrangement]). By default it uses the palm-tree arrangement.
+In contrast with [Megafly], the number of leaves and spines in a group may differ.
+```ignore
+DragonflyPlus{
+	leaves_per_group: 4,
+	spines_per_group: 4,
+	servers_per_leaf: 4,
+	global_ports_per_spine: 4,
+	/// Defaults to the maximum with a single global link between each pair of groups, `spines_per_group*global_ports_per_spine/global_lag + 1`.
+	//number_of_groups: 17,
+	//global_arrangement: Random,
+	//global_lag: 1,
+	legend_name: "Dragonfly+ with 4 leaves and 4 spines per group",
+}
+```
+
+Links inside a group are of class 0, global links of class 1, and links to servers of class 2, as in the [Dragonfly](super::dragonfly::Dragonfly) and [Megafly].
+The routers are numbered group by group, with the leaves of a group before its spines. Thus the cartesian data is `[leaves_per_group+spines_per_group, number_of_groups]`, where the second coordinate is the group of any router.
+This allows to use it with routings such as `DragonflyDirect`, `MegaflyAD`, `Valiant4Dragonfly` and `PAR`.
+The `dragonfly_size` reported has all the switches of a group, `leaves_per_group+spines_per_group`, as `group_size`, while the arrangement of global links is built over the spines alone.
+**/
+#[derive(Quantifiable)]
+#[derive(Debug)]
+pub struct DragonflyPlus
+{
+	/// Number of leaf switches in each group. Only leaf switches have servers.
+	leaves_per_group: usize,
+	/// Number of spine switches in each group. Only spine switches have global links.
+	spines_per_group: usize,
+	/// Number of ports per leaf switch that connect to servers.
+	servers_per_leaf: usize,
+	/// Number of ports per spine switch that connect to switches in a different group.
+	global_ports_per_spine: usize,
+	/// Number of groups in the whole network.
+	number_of_groups: usize,
+	/// Parallel global links.
+	lag: usize,
+	/// Configuration of the global links.
+	global_arrangement: Box<dyn Arrangement>,
+	///`distance_matrix.get(i,j)` = distance from router i to router j.
+	distance_matrix:Matrix<u8>,
+	///`amount_matrix.get(i,j)` = number of shortest paths from router i to router j.
+	amount_matrix:Matrix<usize>,
+	// Cartesian data [router_in_group, group_index]
+	cartesian_data: CartesianData,
+}
+
+impl Topology for DragonflyPlus
+{
+	fn num_routers(&self) -> usize
+	{
+		self.number_of_groups * (self.leaves_per_group + self.spines_per_group)
+	}
+	fn num_servers(&self) -> usize
+	{
+		self.number_of_groups * self.leaves_per_group * self.servers_per_leaf
+	}
+	fn neighbour(&self, router_index:usize, port: usize) -> (Location,usize)
+	{
+		// link class 0 : local link between a leaf and a spine of the same group.
+		// link class 1 : global link between spines of different groups, following the arrangement.
+		// link class 2 : server to leaf switch.
+		let (router_local,router_global,level_index)=self.unpack(router_index);
+		match level_index
+		{
+			0 => {
+				if port < self.spines_per_group
+				{
+					// Upwards link to the spine `port`, arriving by the port numbered as the leaf.
+					(Location::RouterPort{router_index:self.pack((port,router_global,1)),router_port:router_local},0)
+				}
+				else
+				{
+					let port_offset = port - self.spines_per_group;
+					let leaf_index = router_global*self.leaves_per_group + router_local;
+					(Location::ServerPort(leaf_index*self.servers_per_leaf + port_offset),2)
+				}
+			},
+			1 => {
+				if port < self.leaves_per_group
+				{
+					// Downwards link to the leaf `port`, arriving by the port numbered as the spine.
+					(Location::RouterPort{router_index:self.pack((port,router_global,0)),router_port:router_local},0)
+				}
+				else
+				{
+					let point = ArrangementPoint {
+						group_index: router_global,
+						group_offset: router_local,
+						port_index: port - self.leaves_per_group,
+					};
+					let target_point = self.global_arrangement.map(point);
+					let target_router = self.pack((target_point.group_offset,target_point.group_index,1));
+					(Location::RouterPort{router_index:target_router,router_port:self.leaves_per_group + target_point.port_index},1)
+				}
+			},
+			_ => unreachable!("level must be 0 or 1"),
+		}
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		let leaf_index = server_index/self.servers_per_leaf;
+		let router_index = self.pack((leaf_index%self.leaves_per_group,leaf_index/self.leaves_per_group,0));
+		(Location::RouterPort{
+			router_index,
+			router_port: self.spines_per_group + server_index%self.servers_per_leaf,
+		},2)
+	}
+	fn diameter(&self) -> usize
+	{
+		let n = self.num_routers();
+		(0..n).flat_map(|i|(0..n).map(move|j|(i,j))).map(|(i,j)|self.distance(i,j)).max().unwrap_or(0)
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize
+	{
+		(*self.distance_matrix.get(origin,destination)).into()
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		*self.amount_matrix.get(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		let n = self.num_routers();
+		let total:usize = (0..n).flat_map(|i|(0..n).map(move|j|(i,j))).map(|(i,j)|self.amount_shortest_paths(i,j)).sum();
+		total as f32 / (n*n) as f32
+	}
+	fn maximum_degree(&self) -> usize
+	{
+		self.spines_per_group.max(self.leaves_per_group + self.global_ports_per_spine)
+	}
+	fn minimum_degree(&self) -> usize
+	{
+		self.spines_per_group.min(self.leaves_per_group + self.global_ports_per_spine)
+	}
+	fn degree(&self, router_index: usize) -> usize
+	{
+		let (_router_local,_router_global,level_index)=self.unpack(router_index);
+		match level_index
+		{
+			0 => self.spines_per_group,
+			1 => self.leaves_per_group + self.global_ports_per_spine,
+			_ => unreachable!(),
+		}
+	}
+	fn ports(&self, router_index: usize) -> usize
+	{
+		let (_router_local,_router_global,level_index)=self.unpack(router_index);
+		match level_index
+		{
+			0 => self.spines_per_group + self.servers_per_leaf,
+			1 => self.leaves_per_group + self.global_ports_per_spine,
+			_ => unreachable!(),
+		}
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData>
+	{
+		Some(&self.cartesian_data)
+	}
+	fn coordinated_routing_record(&self, _coordinates_a:&[usize], _coordinates_b:&[usize], _rng: Option<&mut StdRng>)->Vec<i32>
+	{
+		unimplemented!();
+	}
+	fn is_direction_change(&self, _router_index:usize, _input_port: usize, _output_port: usize) -> bool
+	{
+		true
+	}
+	fn up_down_distance(&self,_origin:usize,_destination:usize) -> Option<(usize,usize)>
+	{
+		None
+	}
+	///The groups are given with all their switches, leaves first, so that the size unpacks any switch into its group.
+	///The `global_arrangement` is built instead over the spines alone.
+	fn dragonfly_size(&self) -> Option<ArrangementSize>
+	{
+		Some(ArrangementSize{
+			number_of_groups: self.number_of_groups,
+			group_size: self.leaves_per_group + self.spines_per_group,
+			number_of_ports: self.global_ports_per_spine,
+			lag: self.lag,
+		})
+	}
+}
+
+impl DragonflyPlus
+{
+	pub fn new(arg:TopologyBuilderArgument) -> DragonflyPlus
+	{
+		let mut leaves_per_group=None;
+		let mut spines_per_group=None;
+		let mut servers_per_leaf=None;
+		let mut global_ports_per_spine=None;
+		let mut number_of_groups=None;
+		let mut global_arrangement=None;
+		let mut lag=1;
+		match_object_panic!(arg.cv,"DragonflyPlus",value,
+			"leaves_per_group" => leaves_per_group=Some(value.as_usize().expect("bad value for leaves_per_group")),
+			"spines_per_group" => spines_per_group=Some(value.as_usize().expect("bad value for spines_per_group")),
+			"servers_per_leaf" => servers_per_leaf=Some(value.as_usize().expect("bad value for servers_per_leaf")),
+			"global_ports_per_spine" => global_ports_per_spine=Some(value.as_usize().expect("bad value for global_ports_per_spine")),
+			"number_of_groups" => number_of_groups=Some(value.as_usize().expect("bad value for number_of_groups")),
+			"global_arrangement" => global_arrangement=Some(new_arrangement(value.into())),
+			"lag" | "global_lag" => lag=value.as_usize().expect("bad value for lag"),
+		);
+		let leaves_per_group=leaves_per_group.expect("There were no leaves_per_group");
+		let spines_per_group=spines_per_group.expect("There were no spines_per_group");
+		let servers_per_leaf=servers_per_leaf.expect("There were no servers_per_leaf");
+		let global_ports_per_spine=global_ports_per_spine.expect("There were no global_ports_per_spine");
+		let number_of_groups=number_of_groups.unwrap_or_else(||spines_per_group*global_ports_per_spine/lag + 1);
+		let mut global_arrangement = global_arrangement.unwrap_or_else(||Box::new(Palmtree::default()));
+		global_arrangement.initialize(ArrangementSize{
+			number_of_groups,
+			group_size: spines_per_group,
+			number_of_ports: global_ports_per_spine,
+			lag,
+		},arg.rng);
+		let mut topo=DragonflyPlus{
+			leaves_per_group,
+			spines_per_group,
+			servers_per_leaf,
+			global_ports_per_spine,
+			number_of_groups,
+			lag,
+			global_arrangement,
+			distance_matrix:Matrix::constant(0,0,0),
+			amount_matrix:Matrix::constant(0,0,0),
+			cartesian_data: CartesianData::new(&[leaves_per_group+spines_per_group, number_of_groups]),
+		};
+		let (distance_matrix,amount_matrix)=topo.compute_amount_shortest_paths();
+		topo.distance_matrix=distance_matrix.map(|x|*x as u8);
+		topo.amount_matrix=amount_matrix;
+		topo
+	}
+	/**
+	 Unpack a switch index into `(offset, group_index, level)` coordinates.
+	 `level=0` for leaf switches and `level=1` for spine switches. The `offset` is the position among the switches of the same level in the group.
+	**/
+	pub fn unpack(&self, router_index: usize) -> (usize,usize,usize)
+	{
+		let size = self.leaves_per_group + self.spines_per_group;
+		let group_index = router_index / size;
+		let local = router_index % size;
+		if local < self.leaves_per_group
+		{
+			(local,group_index,0)
+		}
+		else
+		{
+			(local-self.leaves_per_group,group_index,1)
+		}
+	}
+	/**
+	 Pack coordinates `(offset, group_index, level)` into a whole switch index.
+	**/
+	pub fn pack(&self, coordinates:(usize,usize,usize)) -> usize
+	{
+		let (offset, group_index, level_index) = coordinates;
+		let local = if level_index==0 { offset } else { self.leaves_per_group + offset };
+		local + group_index*(self.leaves_per_group + self.spines_per_group)
+	}
+}
+
 pub fn pack_source_destination_server(source_server: usize, destination_server:usize, total_servers:usize) -> usize
 {
 	source_server*total_servers + destination_server
@@ -728,4 +992,74 @@ impl MegaflyAD
 			cartesian_data_indirect: CartesianData::new(&[0, 0]),
 		}
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::Plugs;
+	#[test]
+	fn dragonfly_plus_structure()
+	{
+		let (leaves,spines,servers,globals) = (3,2,2,3);
+		let cv = ConfigurationValue::Object("DragonflyPlus".to_string(),vec![
+			("leaves_per_group".to_string(),ConfigurationValue::Number(leaves as f64)),
+			("spines_per_group".to_string(),ConfigurationValue::Number(spines as f64)),
+			("servers_per_leaf".to_string(),ConfigurationValue::Number(servers as f64)),
+			("global_ports_per_spine".to_string(),ConfigurationValue::Number(globals as f64)),
+		]);
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let topo = DragonflyPlus::new(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		let groups = spines*globals+1;
+		topo.check_adjacency_consistency(Some(3));
+		assert_eq!(topo.num_routers(), groups*(leaves+spines));
+		assert_eq!(topo.num_servers(), groups*leaves*servers);
+		let size = topo.dragonfly_size().expect("missing dragonfly_size");
+		assert_eq!((size.number_of_groups,size.group_size,size.number_of_ports),(groups,leaves+spines,globals));
+		let cartesian_data = topo.cartesian_data().expect("missing cartesian data");
+		for router in 0..topo.num_routers()
+		{
+			let (offset,group,level) = topo.unpack(router);
+			assert_eq!(topo.pack((offset,group,level)),router);
+			assert_eq!(cartesian_data.unpack(router)[1],group);
+			assert_eq!(router/size.group_size,group);
+			for NeighbourRouterIteratorItem{link_class,neighbour_router,..} in topo.neighbour_router_iter(router)
+			{
+				let (_,neighbour_group,neighbour_level) = topo.unpack(neighbour_router);
+				match link_class
+				{
+					0 => assert!(neighbour_group==group && neighbour_level!=level),
+					1 => assert!(neighbour_group!=group && level==1 && neighbour_level==1),
+					_ => panic!("unexpected link class {}",link_class),
+				}
+			}
+		}
+		//With a single global link between each pair of groups any two leaves are at distance at most 3.
+		for origin in 0..topo.num_routers()
+		{
+			for destination in 0..topo.num_routers()
+			{
+				if topo.unpack(origin).2==0 && topo.unpack(destination).2==0
+				{
+					assert!(topo.distance(origin,destination)<=3);
+				}
+			}
+		}
+		for server in 0..topo.num_servers()
+		{
+			let (location,link_class) = topo.server_neighbour(server);
+			assert_eq!(link_class,2);
+			match location
+			{
+				Location::RouterPort{router_index,router_port} =>
+				{
+					assert_eq!(topo.unpack(router_index).2,0);
+					assert_eq!(topo.neighbour(router_index,router_port).0,Location::ServerPort(server));
+				},
+				_ => panic!("server not attached to a router"),
+			}
+		}
+	}
+}
@@ -15,6 +15,11 @@ pub mod projective;
 pub mod slimfly;
 pub mod multistage;
 pub mod megafly;
+pub mod fattree;
+pub mod jellyfish;
+pub mod file_topology;
+pub mod export;
+pub mod analysis;
 mod tree;
 
 use std::fs::File;
@@ -22,7 +27,7 @@ use ::rand::{rngs::StdRng};
 use std::io::{Write};
 
 use quantifiable_derive::Quantifiable;//the derive macro
-use self::cartesian::{Mesh,Torus,CartesianData,Hamming,AsCartesianTopology};
+use self::cartesian::{Mesh,Torus,CartesianData,Hamming,HyperX,ExpressCube,AsCartesianTopology};
 use self::neighbourslists::NeighboursLists;
 use self::dragonfly::Dragonfly;
 use self::projective::{Projective,LeviProjective};
@@ -165,6 +170,8 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>;
 	/// Information for Dragonfly-like networks.
 	fn dragonfly_size(&self) -> Option<dragonfly::ArrangementSize> { None }
+	///Whether the server can neither reach nor be reached from the other servers, as those attached to a failed router.
+	fn is_server_unreachable(&self, _server_index:usize) -> bool { false }
 
 	///Breadth First Search to compute distances from a router to all others.
 	///It may use weights, but it there are multiple paths with different distances it may give a non-minimal distance, since it is not Dijkstra.
@@ -395,7 +402,7 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 			let mut queue_read_index=0;
 			let mut queue_write_index=1;
 			queue[0]=origin;
-			while queue_read_index<n
+			while queue_read_index<queue_write_index
 			{
 				//vertex_index best=queue[queue_read_index++];
 				let best=queue[queue_read_index];
@@ -750,6 +757,28 @@ Hamming{
 }
 ```
 
+### HyperX example
+A [HyperX] is like a Hamming graph, but allowing several parallel links (trunking) in each dimension. Each dimension uses its own link class, so they can have different delays.
+```ignore
+HyperX{
+	sides: [8,8],
+	trunking: [2,1],
+	servers_per_router: 8,
+	legend_name: "An 8x8 HyperX with trunking 2 in the first dimension",
+}
+```
+
+### ExpressCube example
+An [ExpressCube] is a Torus (or a Mesh with `wrap: false`) in which some dimensions have express links skipping a number of routers. The express links of each dimension have their own link class, after the class of the links to servers. Use `DOR` or `O1TURN` with `express: true` to route through them.
+```ignore
+ExpressCube{
+	sides: [16,16],
+	express: [4,4],
+	servers_per_router: 4,
+	legend_name: "16x16 torus with express links of length 4",
+}
+```
+
 
 ## Topologies given by lists of neighbours.
 
@@ -764,6 +793,19 @@ RandomRegularGraph{
 }
 ```
 
+### Jellyfish example
+A [Jellyfish](jellyfish::Jellyfish) is a random graph among switches of several classes, each with its own number of network and server ports. The server ports may be fractional, spreading the servers among the switches of the class. The `expansion` classes are added incrementally by breaking random links.
+```ignore
+Jellyfish{
+	switch_classes: [
+		SwitchClass{count: 400, network_ports: 16, server_ports: 8},
+		SwitchClass{count: 100, network_ports: 24, server_ports: 10.5},
+	],
+	expansion: [SwitchClass{count: 50, network_ports: 24, server_ports: 12}],
+	legend_name: "Jellyfish with heterogeneous switches",
+}
+```
+
 ### File example
 A [file](NeighboursLists) can be load as topology. This can be useful to keep a specific random graph without need to care about using the same RNG seed. It can also be used to simulate topologies generated by other software.
 ```ignore
@@ -775,6 +817,17 @@ File{
 }
 ```
 
+### FileTopology example
+A [FileTopology](file_topology::FileTopology) reads a graph in GraphML, DOT, or a port-annotated edge list, as produced by other tools. Unlike `File`, it keeps the port numbers, link classes and server attachments given in the file.
+```ignore
+FileTopology{
+	filename: "/path/to/fabric.dot",
+	//format: "DOT",//optional, deduced from the extension by default
+	//servers_per_router: 4,//optional, servers added to each router besides the ones in the file
+	legend_name: "fabric designed elsewhere",
+}
+```
+
 ## Dragonfly networks.
 The `global_ports_per_router` was denotated `h` in the original article of the [Dragonfly].
 The number of servers per router can be varied, but recommended to the same value as `global_ports_per_router`.
@@ -800,6 +853,18 @@ Dragonfly{
 }
 ```
 
+### Dragonfly+
+The [DragonflyPlus](megafly::DragonflyPlus) has groups made of a complete bipartite graph between leaf and spine switches. Servers are attached to the leaves and global links to the spines, using an [Arrangement](dragonfly::Arrangement) as in the Dragonfly.
+```ignore
+DragonflyPlus{
+	leaves_per_group: 4,
+	spines_per_group: 4,
+	servers_per_leaf: 4,
+	global_ports_per_spine: 4,
+	legend_name: "Dragonfly+ with 17 groups",
+}
+```
+
 
 ## Networks built over finite fields. Only prime fields are currently supported.
 
@@ -883,6 +948,23 @@ OFT{
 }
 ```
 
+### FatTree and KAryNTree
+See [FatTree](fattree::FatTree). Fat-trees given by a radix `k` and a number of levels, exposing their levels and pods.
+`FatTree` is the datacenter fat-tree (pods, aggregation and core for `levels=3`) and `KAryNTree` is the k-ary n-tree.
+
+```ignore
+FatTree{
+	k: 8,
+	levels: 3,
+	legend_name: "datacenter fat-tree with 8-port switches",
+}
+KAryNTree{
+	k: 4,
+	levels: 3,
+	legend_name: "4-ary 3-tree",
+}
+```
+
 ### RFC
 See [ExplicitStage](multistage::ExplicitStage) and [MultiStage].
 Random Folded Clos. See "Random Folded Clos Topologies for Datacenter Networks" by C. Camarero et al.
@@ -939,16 +1021,23 @@ pub fn new_topology(arg:TopologyBuilderArgument) -> Box<dyn Topology>
 			"Mesh" => Box::new(Mesh::new(arg.cv)),
 			"Torus" => Box::new(Torus::new(arg.cv)),
 			"RandomRegularGraph" | "File" => Box::new(NeighboursLists::new_cfg(arg.cv,arg.rng)),
+			"Jellyfish" => Box::new(jellyfish::Jellyfish::new(arg)),
+			"FileTopology" => Box::new(file_topology::FileTopology::new(arg)),
 			"Hamming" => Box::new(Hamming::new(arg.cv)),
+			"HyperX" => Box::new(HyperX::new(arg.cv)),
+			"ExpressCube" => Box::new(ExpressCube::new(arg.cv)),
 			"Dragonfly" | "CanonicDragonfly" => Box::new(Dragonfly::new(arg)),
 			"Projective" => Box::new(Projective::new(arg)),
 			"LeviProjective" => Box::new(LeviProjective::new(arg)),
 			"SlimFly" => Box::new(SlimFly::new(arg)),
 			"MultiStage" | "XGFT" | "OFT" | "RFC" => Box::new(MultiStage::new(arg)),
 			"Megafly" => Box::new(megafly::Megafly::new(arg)),
+			"DragonflyPlus" => Box::new(megafly::DragonflyPlus::new(arg)),
+			"FatTree" | "KAryNTree" => Box::new(fattree::FatTree::new(arg)),
 			"RemappedServers" => Box::new(operations::RemappedServersTopology::new(arg)),
 			"AsCartesianTopology" => Box::new(AsCartesianTopology::new(arg)),
 			"RandomLinkFaults" => Box::new(operations::RandomLinkFaults::new(arg)),
+			"StructuredFaults" => Box::new(operations::StructuredFaults::new(arg)),
 			"Tree" => Box::new(Tree::new(arg)),
 			_ => panic!("Unknown topology {}",cv_name),
 		}
@@ -8,6 +8,7 @@ use crate::config_parser::ConfigurationValue;
 use quantifiable_derive::Quantifiable;//the derive macro
 
 use rand::prelude::SliceRandom;
+use rand::Rng;
 use std::collections::{HashMap,HashSet};
 
 /**
@@ -81,6 +82,10 @@ impl Topology for RemappedServersTopology
 	{
 		self.topology.up_down_distance(origin,destination)
 	}
+	fn is_server_unreachable(&self, server_index:usize) -> bool
+	{
+		self.topology.is_server_unreachable(self.into_base_map[server_index])
+	}
 	// Noone really overrides this...
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{
@@ -257,6 +262,10 @@ impl Topology for RandomLinkFaults
 		// XXX what happens with broken links?
 		self.topology.up_down_distance(origin,destination)
 	}
+	fn is_server_unreachable(&self, server_index:usize) -> bool
+	{
+		self.topology.is_server_unreachable(server_index)
+	}
 }
 
 
@@ -361,3 +370,318 @@ impl RandomLinkFaults
 
 
 
+
+/**
+Removes links following structured fault models, as opposed to the independent faults of [RandomLinkFaults]. All the given models are applied together.
+* `routers`: a list of routers that fail completely, losing all their links. Their servers are still attached to them, but they become unreachable from any other server, as told by [Topology::is_server_unreachable]. A simulation excludes those servers: they do not generate messages and the messages towards them are discarded.
+* `detach_servers`: whether to remove the servers of the failed routers from the topology, numbering consecutively the remaining ones. Then the traffic is built only for the remaining servers, so that traffics with a fixed amount of messages can finish. Defaults to false.
+* `groups`: a list of groups whose global links fail. The topology must provide `dragonfly_size`, with the groups numbered consecutively, as in [Dragonfly](super::dragonfly::Dragonfly) or [Megafly](super::megafly::Megafly). A link is global when it joins routers of different groups.
+* `links`: a list of `[router,port]` whose links fail.
+* `link_class_probabilities`: position `c` is the probability of failure for each link of class `c`. Classes beyond the list do not fail.
+
+```ignore
+topology: StructuredFaults{
+	topology: Dragonfly{
+		global_ports_per_router: 4,
+		servers_per_router: 4,
+	},
+	routers: [5],
+	groups: [2],
+	links: [[0,3],[17,6]],
+	link_class_probabilities: [0.01, 0.05],
+	detach_servers: true,//optional, the traffic must then be for the servers of the other routers
+	seed: 0,//optional, to get the same faults for any global RNG.
+},
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct StructuredFaults
+{
+	/// The base topology.
+	topology: Box<dyn Topology>,
+	///`failed_routers[router]` tells whether the router has failed completely.
+	failed_routers: Vec<bool>,
+	///Maps each server index to the one of the base topology. It skips the servers of the failed routers when `detach_servers`.
+	into_base_map: Vec<usize>,
+	///The inverse of `into_base_map`, with `None` for the detached servers.
+	from_base_map: Vec<Option<usize>>,
+	///Both ends of each removed link, as in [RandomLinkFaults].
+	removed_links: HashMap< Location, Location >,
+	///Cached distances. `distance_matrix.get(i,j)` is the distance from router i to router j.
+	distance_matrix:Matrix<usize>,
+	///amount_matrix.get(i,j) = amount of shortest paths from router i to router j
+	amount_matrix:Matrix<usize>,
+	///Average of the amount_matrix entries among routers that did not fail.
+	average_amount: f32,
+}
+
+impl Topology for StructuredFaults
+{
+	fn num_routers(&self) -> usize { self.topology.num_routers() }
+	fn num_servers(&self) -> usize { self.into_base_map.len() }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		if self.removed_links.contains_key( &Location::RouterPort{router_index,router_port:port} ) {
+			return (Location::None,0);
+		}
+		match self.topology.neighbour(router_index,port)
+		{
+			(Location::ServerPort(base_server),link_class) => match self.from_base_map[base_server]
+			{
+				Some(server) => (Location::ServerPort(server),link_class),
+				None => (Location::None,0),
+			},
+			other => other,
+		}
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize)
+	{
+		self.topology.server_neighbour(self.into_base_map[server_index])
+	}
+	fn diameter(&self) -> usize
+	{
+		// The failed routers are at infinite distance, so we only take the others.
+		let n=self.num_routers();
+		(0..n).filter(|&i|!self.failed_routers[i]).flat_map(|i|(0..n).filter(|&j|!self.failed_routers[j]).map(move|j|(i,j))).map(|(i,j)|self.distance(i,j)).max().unwrap_or(0)
+	}
+	fn distance(&self,origin:usize,destination:usize) -> usize {
+		*self.distance_matrix.get(origin,destination)
+	}
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize
+	{
+		*self.amount_matrix.get(origin,destination)
+	}
+	fn average_amount_shortest_paths(&self) -> f32
+	{
+		self.average_amount
+	}
+	fn degree(&self, router_index: usize) -> usize {
+		self.neighbour_router_iter(router_index).count()
+	}
+	fn ports(&self, router_index: usize) -> usize { self.topology.ports(router_index) }
+	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
+	{
+		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.topology.is_direction_change(router_index,input_port,output_port)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.topology.up_down_distance(origin,destination)
+	}
+	fn dragonfly_size(&self) -> Option<super::dragonfly::ArrangementSize>
+	{
+		self.topology.dragonfly_size()
+	}
+	///Whether the server is attached to a failed router, and therefore unreachable from other servers.
+	fn is_server_unreachable(&self, server_index:usize) -> bool
+	{
+		match self.server_neighbour(server_index).0
+		{
+			Location::RouterPort{router_index,..} => self.failed_routers[router_index],
+			_ => true,
+		}
+	}
+}
+
+impl StructuredFaults
+{
+	pub fn new(arg:TopologyBuilderArgument) -> StructuredFaults
+	{
+		let mut topology = None;
+		let mut routers = vec![];
+		let mut groups = vec![];
+		let mut links = vec![];
+		let mut link_class_probabilities = vec![];
+		let mut detach_servers = false;
+		let mut rng = None;
+		match_object_panic!(arg.cv, "StructuredFaults", value,
+			"topology" => topology = Some(new_topology(TopologyBuilderArgument{cv:value,rng:arg.rng,..arg})),
+			"routers" => routers = value.as_array().expect("bad value for routers").iter().map(|v|v.as_usize().expect("bad value in routers")).collect(),
+			"groups" => groups = value.as_array().expect("bad value for groups").iter().map(|v|v.as_usize().expect("bad value in groups")).collect(),
+			"links" => links = value.as_array().expect("bad value for links").iter().map(|v|{
+				let pair = v.as_array().expect("bad value in links");
+				if pair.len()!=2 { panic!("Each entry of links must be [router,port]"); }
+				(pair[0].as_usize().expect("bad router in links"),pair[1].as_usize().expect("bad port in links"))
+			}).collect(),
+			"link_class_probabilities" => link_class_probabilities = value.as_array().expect("bad value for link_class_probabilities").iter().map(|v|v.as_f64().expect("bad value in link_class_probabilities")).collect(),
+			"detach_servers" => detach_servers = value.as_bool().expect("bad value for detach_servers"),
+			"seed" => rng = Some( value.as_rng().expect("bad value for seed") ),
+		);
+		let topology = topology.expect("There were no topology in configuration of StructuredFaults.");
+		let rng = rng.as_mut().unwrap_or(arg.rng);
+		let n = topology.num_routers();
+		let mut failed_routers = vec![false;n];
+		let mut removed_links = HashMap::new();
+		let remove = |removed_links:&mut HashMap<Location,Location>, router_index:usize, router_port:usize|
+		{
+			let left_loc = Location::RouterPort{router_index,router_port};
+			if let (right_loc@Location::RouterPort{..},_) = topology.neighbour(router_index,router_port)
+			{
+				removed_links.insert( left_loc.clone(), right_loc.clone() );
+				removed_links.insert( right_loc, left_loc );
+			}
+		};
+		for router in routers
+		{
+			if router>=n { panic!("Cannot fail router {} of a topology with {} routers",router,n); }
+			failed_routers[router]=true;
+			for port in 0..topology.ports(router)
+			{
+				remove(&mut removed_links,router,port);
+			}
+		}
+		if !groups.is_empty()
+		{
+			let size = topology.dragonfly_size().expect("Failing groups requires a topology with dragonfly_size.");
+			let group_routers = n / size.number_of_groups;
+			for group in groups
+			{
+				if group>=size.number_of_groups { panic!("Cannot fail group {} of a topology with {} groups",group,size.number_of_groups); }
+				for router in group*group_routers..(group+1)*group_routers
+				{
+					for NeighbourRouterIteratorItem{port_index,neighbour_router,..} in topology.neighbour_router_iter(router)
+					{
+						if neighbour_router/group_routers != group
+						{
+							remove(&mut removed_links,router,port_index);
+						}
+					}
+				}
+			}
+		}
+		for (router,port) in links
+		{
+			if router>=n || port>=topology.ports(router) { panic!("There is no port {} in router {}",port,router); }
+			remove(&mut removed_links,router,port);
+		}
+		if !link_class_probabilities.is_empty()
+		{
+			for router in 0..n
+			{
+				for NeighbourRouterIteratorItem{port_index,link_class,neighbour_router,neighbour_port} in topology.neighbour_router_iter(router)
+				{
+					// Each link is considered once, from its lower end.
+					if (router,port_index) < (neighbour_router,neighbour_port)
+					{
+						let probability = link_class_probabilities.get(link_class).copied().unwrap_or(0.0);
+						if probability>0.0 && rng.gen_bool(probability.min(1.0))
+						{
+							remove(&mut removed_links,router,port_index);
+						}
+					}
+				}
+			}
+		}
+		let into_base_map : Vec<usize> = (0..topology.num_servers()).filter(|&server|{
+			!detach_servers || match topology.server_neighbour(server).0
+			{
+				Location::RouterPort{router_index,..} => !failed_routers[router_index],
+				_ => true,
+			}
+		}).collect();
+		let mut from_base_map = vec![None;topology.num_servers()];
+		for (server,&base_server) in into_base_map.iter().enumerate()
+		{
+			from_base_map[base_server]=Some(server);
+		}
+		let mut topo = StructuredFaults{
+			topology,
+			failed_routers,
+			into_base_map,
+			from_base_map,
+			removed_links,
+			distance_matrix:Matrix::constant(0,0,0),
+			amount_matrix:Matrix::constant(0,0,0),
+			average_amount: 0f32,
+		};
+		let (distance_matrix,amount_matrix)=topo.compute_amount_shortest_paths();
+		topo.distance_matrix=distance_matrix;
+		topo.amount_matrix=amount_matrix;
+		topo.average_amount={
+			let alive : Vec<usize> = (0..n).filter(|&i|!topo.failed_routers[i]).collect();
+			let mut r=0;
+			let mut count=0;
+			for &i in alive.iter()
+			{
+				for &j in alive.iter()
+				{
+					if i!=j
+					{
+						r+=topo.amount_shortest_paths(i,j);
+						count+=1;
+					}
+				}
+			}
+			r as f32/count as f32
+		};
+		topo
+	}
+	///Whether the router has failed completely.
+	pub fn is_router_failed(&self, router_index:usize) -> bool
+	{
+		self.failed_routers[router_index]
+	}
+	///Number of links removed, counting each bidirectional link once.
+	pub fn removed_links(&self) -> usize
+	{
+		self.removed_links.len()/2
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::Plugs;
+	fn build(faults:Vec<(&str,ConfigurationValue)>) -> StructuredFaults
+	{
+		let number = |x:f64| ConfigurationValue::Number(x);
+		let dragonfly = ConfigurationValue::Object("Dragonfly".to_string(),vec![
+			("global_ports_per_router".to_string(),number(2.0)),
+			("servers_per_router".to_string(),number(2.0)),
+		]);
+		let mut fields = vec![("topology".to_string(),dragonfly)];
+		fields.extend(faults.into_iter().map(|(name,value)|(name.to_string(),value)));
+		let cv = ConfigurationValue::Object("StructuredFaults".to_string(),fields);
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		StructuredFaults::new(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng})
+	}
+	#[test]
+	fn structured_faults()
+	{
+		let array = |list:&[f64]| ConfigurationValue::Array(list.iter().map(|&x|ConfigurationValue::Number(x)).collect());
+		//A router fails with all its links and servers.
+		let topo = build(vec![("routers",array(&[5.0]))]);
+		topo.check_adjacency_consistency(None);
+		assert!(topo.is_router_failed(5));
+		assert_eq!(topo.degree(5),0);
+		let servers : Vec<usize> = (0..topo.num_servers()).filter(|&s|topo.is_server_unreachable(s)).collect();
+		assert_eq!(servers.len(),2);
+		//Detaching the servers of the failed router leaves the others numbered consecutively.
+		let base_servers = topo.num_servers();
+		let topo = build(vec![("routers",array(&[5.0])),("detach_servers",ConfigurationValue::True)]);
+		topo.check_adjacency_consistency(None);
+		assert_eq!(topo.num_servers(),base_servers-2);
+		assert!((0..topo.num_servers()).all(|s|!topo.is_server_unreachable(s)));
+		assert!(topo.diameter() < usize::MAX/3);
+		//A group loses its global links and becomes isolated.
+		let topo = build(vec![("groups",array(&[1.0]))]);
+		topo.check_adjacency_consistency(None);
+		let size = topo.dragonfly_size().unwrap();
+		let group_routers = topo.num_routers()/size.number_of_groups;
+		assert_eq!(topo.removed_links(), group_routers*2);
+		assert!(topo.distance(0,group_routers) >= usize::MAX/3);
+		//Explicit links and a link class failing surely.
+		let links = ConfigurationValue::Array(vec![array(&[0.0,0.0])]);
+		let topo = build(vec![("links",links),("link_class_probabilities",array(&[0.0,1.0]))]);
+		topo.check_adjacency_consistency(None);
+		let global_links = topo.num_routers()*2/2;
+		assert_eq!(topo.removed_links(), global_links+1);
+	}
+}
@@ -0,0 +1,321 @@
+/*!
+
+Detection of simulations that stop making progress.
+
+A [Watchdog] is given in the optional `watchdog` field of the `Configuration`. It declares a deadlock when no phit has moved nor been consumed for `cycles` cycles while there are phits in the network, and optionally a livelock when phits keep moving but none is consumed for `livelock_cycles` cycles.
+
+```ignore
+watchdog: Watchdog{
+	cycles: 2000,
+	livelock_cycles: 100000,//Optional, no livelock detection by default.
+	file: "stall.txt",//Optional, defaults to stall_diagnostic.txt.
+},
+```
+
+When it triggers, the [Simulation](crate::Simulation) stops. The watchdog takes a snapshot of the input buffers of the routers by [Router::iter_input_phits](crate::router::Router::iter_input_phits) and builds the relation of which buffers each buffer is blocked on. A buffer whose front phit is the head of a packet waits on the buffers of the next routers given by [Routing::next](crate::routing::Routing::next). A buffer whose front phit is in the middle of a packet waits on the buffer holding the previous phit of that packet. A cycle in this relation is searched and a diagnostic file is written with it and with every buffer holding phits, naming the routers, ports, virtual channels and packets. The file is written in the directory of the experiment when run by the `local` action, or in the working directory otherwise.
+
+Instead of the usual statistics, the result of the simulation is then a `Deadlock` or `Livelock` object with the fields
+* `cycle`, when the stall was declared, and `stalled_since`, the last cycle with a phit moving (for a deadlock) or being consumed (for a livelock).
+* `phits_in_flight`, `blocked_buffers` and `packets`, the number of phits in the network, of input buffers holding phits, and of packets with phits in them.
+* `cycle_buffers`, the list of `Buffer{router,port,virtual_channel}` in the found cycle, empty when there is none.
+* `diagnostic_file`, the path of the diagnostic file.
+
+*/
+
+use std::collections::{BTreeMap,HashSet};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path,PathBuf};
+use std::rc::Rc;
+
+use ::rand::rngs::StdRng;
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+use crate::event::Time;
+use crate::packet::{Phit,Packet};
+use crate::routing::Routing;
+use crate::routing::deadlock::find_cycle;
+use crate::topology::Location;
+use crate::Network;
+
+///The kind of stall detected by the watchdog.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Stall
+{
+	///No phit moves.
+	Deadlock,
+	///Phits move but none arrives to its destination.
+	Livelock,
+}
+
+///An input buffer of a router, given by its `(router,port,virtual_channel)`.
+type BufferId=(usize,usize,usize);
+
+///Watches the progress of the simulation. See the [module documentation](self).
+#[derive(Debug)]
+pub struct Watchdog
+{
+	///Cycles without movement to declare a deadlock.
+	cycles: Time,
+	///Cycles without consumption to declare a livelock.
+	livelock_cycles: Option<Time>,
+	///The name of the diagnostic file.
+	file: String,
+	///The directory in which to write the diagnostic file, or the working directory if `None`.
+	directory: Option<PathBuf>,
+	///The last cycle in which some phit moved.
+	last_movement: Time,
+	///The last cycle in which some phit was consumed.
+	last_consumption: Time,
+	///Phits injected into the network and not yet consumed nor dropped.
+	phits_in_flight: usize,
+	///The result to report once a stall has been detected.
+	report: Option<ConfigurationValue>,
+}
+
+impl Watchdog
+{
+	pub fn new(cv:&ConfigurationValue) -> Watchdog
+	{
+		let mut cycles=None;
+		let mut livelock_cycles=None;
+		let mut file=String::from("stall_diagnostic.txt");
+		match_object_panic!(cv,"Watchdog",value,
+			"cycles" => cycles=Some(value.as_time().expect("bad value for cycles")),
+			"livelock_cycles" => livelock_cycles=Some(value.as_time().expect("bad value for livelock_cycles")),
+			"file" => file=value.as_str().expect("bad value for file").to_string(),
+		);
+		Watchdog{
+			cycles: cycles.expect("There were no cycles"),
+			livelock_cycles,
+			file,
+			directory: None,
+			last_movement: 0,
+			last_consumption: 0,
+			phits_in_flight: 0,
+			report: None,
+		}
+	}
+	///Set the directory in which to write the diagnostic file.
+	pub fn set_directory(&mut self, directory:&Path)
+	{
+		self.directory=Some(directory.to_path_buf());
+	}
+	///Account a phit arriving at some location. `injected` is true when it comes from a server.
+	pub fn track_movement(&mut self, cycle:Time, injected:bool)
+	{
+		self.last_movement=cycle;
+		if injected
+		{
+			self.phits_in_flight+=1;
+		}
+	}
+	///Account a phit leaving the network, either consumed by its server or discarded.
+	pub fn track_removal(&mut self, cycle:Time, consumed:bool)
+	{
+		self.phits_in_flight-=1;
+		if consumed
+		{
+			self.last_consumption=cycle;
+		}
+	}
+	///The result of the simulation if a stall has been detected.
+	pub fn report(&self) -> Option<&ConfigurationValue>
+	{
+		self.report.as_ref()
+	}
+	///Check for a stall at the given cycle. When there is one, write the diagnostic and return true, after which the simulation should stop.
+	pub fn check(&mut self, cycle:Time, network:&Network, routing:&dyn Routing, rng:&mut StdRng) -> bool
+	{
+		if self.phits_in_flight==0
+		{
+			self.last_movement=cycle;
+			self.last_consumption=cycle;
+			return false;
+		}
+		let stall = if cycle-self.last_movement>=self.cycles
+		{
+			(Stall::Deadlock,self.last_movement)
+		}
+		else
+		{
+			match self.livelock_cycles
+			{
+				Some(livelock_cycles) if cycle-self.last_consumption>=livelock_cycles => (Stall::Livelock,self.last_consumption),
+				_ => return false,
+			}
+		};
+		self.report=Some(self.diagnose(stall,cycle,network,routing,rng));
+		true
+	}
+	///Build the blocked-on relation, write the diagnostic file and return the result.
+	fn diagnose(&self, (stall,stalled_since):(Stall,Time), cycle:Time, network:&Network, routing:&dyn Routing, rng:&mut StdRng) -> ConfigurationValue
+	{
+		let topology=network.topology.as_ref();
+		//The phits of each buffer and its front phit.
+		let mut buffers:BTreeMap<BufferId,(Vec<Rc<Phit>>,Option<Rc<Phit>>)>=BTreeMap::new();
+		for (router_index,router) in network.routers.iter().enumerate()
+		{
+			for (port,phit,at_front) in router.borrow().iter_input_phits()
+			{
+				let virtual_channel=phit.virtual_channel.borrow().expect("phit without virtual channel in an input buffer");
+				let entry=buffers.entry((router_index,port,virtual_channel)).or_insert_with(||(vec![],None));
+				if at_front
+				{
+					entry.1=Some(phit.clone());
+				}
+				entry.0.push(phit);
+			}
+		}
+		let ids:Vec<BufferId>=buffers.keys().copied().collect();
+		let index_of=|id:&BufferId|ids.binary_search(id).ok();
+		let mut waits:Vec<Vec<usize>>=vec![vec![];ids.len()];
+		let mut reasons:Vec<String>=vec![String::new();ids.len()];
+		for (index,(&(router,_port,_virtual_channel),(phits,front))) in buffers.iter().enumerate()
+		{
+			let front=match front
+			{
+				Some(front) => front,
+				None => match phits.first()
+				{
+					Some(phit) => phit,
+					None => continue,
+				},
+			};
+			if front.is_begin()
+			{
+				let target_server=front.packet.message.destination;
+				let target_router=match topology.server_neighbour(target_server).0
+				{
+					Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("The server {} is not attached to a router",target_server),
+				};
+				let num_virtual_channels=network.routers[router].borrow().num_virtual_channels();
+				match routing.next(&front.packet.routing_info.borrow(),topology,router,target_router,Some(target_server),num_virtual_channels,rng)
+				{
+					Ok(candidates) =>
+					{
+						let mut requested=vec![];
+						for candidate in candidates.candidates
+						{
+							match topology.neighbour(router,candidate.port).0
+							{
+								Location::RouterPort{router_index,router_port} =>
+								{
+									let next=(router_index,router_port,candidate.virtual_channel);
+									requested.push(format!("router {} port {} virtual channel {}",next.0,next.1,next.2));
+									if let Some(next_index)=index_of(&next)
+									{
+										if !waits[index].contains(&next_index)
+										{
+											waits[index].push(next_index);
+										}
+									}
+								},
+								Location::ServerPort(server) => requested.push(format!("server {}",server)),
+								Location::None => (),
+							}
+						}
+						reasons[index]=format!("its head packet requests [{}]",requested.join(", "));
+					},
+					Err(error) => reasons[index]=format!("the routing fails for its head packet: {}",error),
+				}
+			}
+			else
+			{
+				//The rest of a packet waits for the buffer holding its previous phit.
+				let previous=ids.iter().position(|id|buffers[id].0.iter().any(|phit|std::ptr::eq(phit.packet.as_ref(),front.packet.as_ref()) && phit.index+1==front.index));
+				match previous
+				{
+					Some(previous) =>
+					{
+						let (r,p,v)=ids[previous];
+						waits[index].push(previous);
+						reasons[index]=format!("its front phit follows its packet in router {} port {} virtual channel {}",r,p,v);
+					},
+					None => reasons[index]=String::from("its front phit follows its packet, which is not in an input buffer"),
+				}
+			}
+		}
+		let cycle_buffers=find_cycle(&waits);
+		//Write the diagnostic.
+		let mut text=String::new();
+		let kind=match stall { Stall::Deadlock => "Deadlock", Stall::Livelock => "Livelock" };
+		match stall
+		{
+			Stall::Deadlock => writeln!(text,"Deadlock detected at cycle {}. No phit has moved since cycle {}.",cycle,stalled_since).unwrap(),
+			Stall::Livelock => writeln!(text,"Livelock detected at cycle {}. No phit has been consumed since cycle {}.",cycle,stalled_since).unwrap(),
+		}
+		writeln!(text,"There are {} phits in flight, {} of them in {} input buffers.",self.phits_in_flight,buffers.values().map(|(phits,_)|phits.len()).sum::<usize>(),buffers.len()).unwrap();
+		if cycle_buffers.is_empty()
+		{
+			writeln!(text,"\nNo cycle was found among the blocked buffers.").unwrap();
+		}
+		else
+		{
+			writeln!(text,"\nCycle of blocked buffers:").unwrap();
+			for &index in cycle_buffers.iter()
+			{
+				let (router,port,virtual_channel)=ids[index];
+				let front=buffers[&ids[index]].1.as_ref().or_else(||buffers[&ids[index]].0.first()).expect("empty buffer");
+				writeln!(text,"\trouter {} port {} virtual channel {}, front {}, {}",router,port,virtual_channel,describe_phit(front),reasons[index]).unwrap();
+			}
+		}
+		writeln!(text,"\nBuffers holding phits:").unwrap();
+		let mut packets=HashSet::new();
+		for (index,(&(router,port,virtual_channel),(phits,_))) in buffers.iter().enumerate()
+		{
+			writeln!(text,"\trouter {} port {} virtual channel {}: {} phits, {}",router,port,virtual_channel,phits.len(),reasons[index]).unwrap();
+			let mut seen=HashSet::new();
+			for phit in phits.iter()
+			{
+				let packet=phit.packet.as_ref() as *const Packet;
+				packets.insert(packet);
+				if seen.insert(packet)
+				{
+					writeln!(text,"\t\t{}",describe_packet(&phit.packet)).unwrap();
+				}
+			}
+		}
+		let path=match self.directory
+		{
+			Some(ref directory) => directory.join(&self.file),
+			None => PathBuf::from(&self.file),
+		};
+		println!("{} detected at cycle {}, writing diagnostic to {}",kind,cycle,path.display());
+		match File::create(&path)
+		{
+			Ok(mut file) => file.write_all(text.as_bytes()).expect("Could not write the diagnostic file."),
+			Err(error) => eprintln!("WARNING: could not create the diagnostic file {}: {}",path.display(),error),
+		}
+		ConfigurationValue::Object(String::from(kind),vec![
+			(String::from("cycle"),ConfigurationValue::Number(cycle as f64)),
+			(String::from("stalled_since"),ConfigurationValue::Number(stalled_since as f64)),
+			(String::from("phits_in_flight"),ConfigurationValue::Number(self.phits_in_flight as f64)),
+			(String::from("blocked_buffers"),ConfigurationValue::Number(buffers.len() as f64)),
+			(String::from("packets"),ConfigurationValue::Number(packets.len() as f64)),
+			(String::from("cycle_buffers"),ConfigurationValue::Array(cycle_buffers.iter().map(|&index|{
+				let (router,port,virtual_channel)=ids[index];
+				ConfigurationValue::Object(String::from("Buffer"),vec![
+					(String::from("router"),ConfigurationValue::Number(router as f64)),
+					(String::from("port"),ConfigurationValue::Number(port as f64)),
+					(String::from("virtual_channel"),ConfigurationValue::Number(virtual_channel as f64)),
+				])
+			}).collect())),
+			(String::from("diagnostic_file"),ConfigurationValue::Literal(path.display().to_string())),
+		])
+	}
+}
+
+fn describe_packet(packet:&Packet) -> String
+{
+	let message=&packet.message;
+	format!("packet {} of the message from server {} to server {} created at cycle {}, with {} hops",packet.index,message.origin,message.destination,message.creation_cycle,packet.routing_info.borrow().hops)
+}
+
+fn describe_phit(phit:&Phit) -> String
+{
+	format!("phit {} of {}",phit.index,describe_packet(&phit.packet))
+}
@@ -196,6 +196,27 @@ pub fn create_megafly_topology(arg: MegaflyBuilder) -> ConfigurationValue //Box<
                                     ("number_of_groups".to_string(),ConfigurationValue::Number(arg.number_of_groups as f64))])
 }
 
+/// Encapsulates the parameters needed to create a DragonflyPlus topology
+pub struct DragonflyPlusBuilder
+{
+    pub leaves_per_group: usize,
+    pub spines_per_group: usize,
+    pub servers_per_leaf: usize,
+    pub global_ports_per_spine: usize,
+    pub number_of_groups: usize,
+}
+
+/// Creates a Configuration Value with the parameters for the DragonflyPlus topology
+pub fn create_dragonfly_plus_topology(arg: DragonflyPlusBuilder) -> ConfigurationValue
+{
+    ConfigurationValue::Object("DragonflyPlus".to_string(),
+                               vec![("leaves_per_group".to_string(),ConfigurationValue::Number(arg.leaves_per_group as f64)),
+                                    ("spines_per_group".to_string(),ConfigurationValue::Number(arg.spines_per_group as f64)),
+                                    ("servers_per_leaf".to_string(),ConfigurationValue::Number(arg.servers_per_leaf as f64)),
+                                    ("global_ports_per_spine".to_string(),ConfigurationValue::Number(arg.global_ports_per_spine as f64)),
+                                    ("number_of_groups".to_string(),ConfigurationValue::Number(arg.number_of_groups as f64))])
+}
+
 /// Routing MegaflyAD (FPAR) for megafly topology
 pub struct MegaflyAD
 {
@@ -417,3 +438,80 @@ pub fn create_simulation(arg: SimulationBuilder) -> ConfigurationValue
     ])
 
 }
+
+/// Appends the given fields to a configuration object, for the options not covered by the builders.
+pub fn append_fields(cv: ConfigurationValue, extra: Vec<(&str, ConfigurationValue)>) -> ConfigurationValue
+{
+    match cv
+    {
+        ConfigurationValue::Object(name, mut fields) =>
+        {
+            fields.extend(extra.into_iter().map(|(key, value)| (key.to_string(), value)));
+            ConfigurationValue::Object(name, fields)
+        },
+        _ => panic!("only objects can be given more fields"),
+    }
+}
+
+/// Creates a Configuration Value for a policy or other object without parameters
+pub fn create_policy(name: &str) -> ConfigurationValue
+{
+    ConfigurationValue::Object(name.to_string(), vec![])
+}
+
+/// Reads a field of a result object, panicking if it is missing
+pub fn field<'a>(result: &'a ConfigurationValue, name: &str) -> &'a ConfigurationValue
+{
+    match result
+    {
+        ConfigurationValue::Object(_, fields) => &fields.iter().find(|(key, _)| key == name).unwrap_or_else(|| panic!("missing field {}", name)).1,
+        _ => panic!("the result is not an object"),
+    }
+}
+
+/// Encapsulates the parameters of a simulation with Basic routers which change between tests.
+/// The remaining ones are flits and packets of 16 phits, no bubble, and the default link classes.
+pub struct BasicSimulationBuilder
+{
+    pub random_seed: usize,
+    pub warmup: usize,
+    pub measured: usize,
+    pub topology: ConfigurationValue,
+    pub traffic: ConfigurationValue,
+    pub routing: ConfigurationValue,
+    pub virtual_channels: usize,
+    pub policies: Vec<ConfigurationValue>,
+    pub buffer_size: usize,
+    pub output_buffer_size: usize,
+    /// Fields appended to the router, for the options not covered by `BasicRouterBuilder`
+    pub router_fields: Vec<(&'static str, ConfigurationValue)>,
+}
+
+/// Creates a Configuration Value with a simulation using Basic routers
+pub fn create_basic_simulation(arg: BasicSimulationBuilder) -> ConfigurationValue
+{
+    let router_args = BasicRouterBuilder{
+        virtual_channels: arg.virtual_channels,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{ policies: arg.policies }),
+        buffer_size: arg.buffer_size,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: arg.output_buffer_size,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    create_simulation(SimulationBuilder{
+        random_seed: arg.random_seed,
+        warmup: arg.warmup,
+        measured: arg.measured,
+        topology: arg.topology,
+        traffic: arg.traffic,
+        router: append_fields(create_basic_router(router_args), arg.router_fields),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: arg.routing,
+        link_classes: create_link_classes(),
+    })
+}
@@ -0,0 +1,78 @@
+/*!
+    Tests for the ECN marking at routers and the congestion control at servers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Builds a simulation in a 4x4 Hamming graph under uniform traffic, with the Basic router marking at `ecn_threshold` and the servers running `congestion_control`.
+fn congestion_simulation(load: f64, ecn_threshold: Option<usize>, congestion_control: ConfigurationValue) -> ConfigurationValue
+{
+    let cv = create_basic_simulation(BasicSimulationBuilder{
+        random_seed: 7,
+        warmup: 1000,
+        measured: 3000,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 16,
+            load,
+            message_size: 16,
+        }),
+        routing: create_shortest_routing(),
+        virtual_channels: 2,
+        policies: vec![create_policy("EnforceFlowControl"), create_policy("Random")],
+        buffer_size: 32,
+        output_buffer_size: 16,
+        router_fields: ecn_threshold.map(|threshold| ("ecn_threshold", ConfigurationValue::Number(threshold as f64))).into_iter().collect(),
+    });
+    append_fields(cv, vec![
+        ("congestion_control", congestion_control),
+        ("statistics_temporal_step", ConfigurationValue::Number(1000.0)),
+    ])
+}
+
+fn run(cv: &ConfigurationValue) -> ConfigurationValue
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(cv, &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    field(&result, "congestion_control").clone()
+}
+
+/// Under full load the routers mark packets and DCQCN reduces the injection rate, which it records per server along time.
+#[test]
+fn dcqcn_throttles()
+{
+    let result = run(&congestion_simulation(1.0, Some(8), ConfigurationValue::Object("DCQCN".to_string(), vec![])));
+    assert!(field(&result, "marking_rate").as_f64().unwrap() > 0.0);
+    let average_rate = field(&result, "average_rate").as_f64().unwrap();
+    assert!(average_rate < 1.0 && average_rate > 0.0, "average_rate={}", average_rate);
+    assert_eq!(field(&result, "server_rate").as_array().unwrap().len(), 16);
+    let temporal = field(&result, "temporal_server_rate").as_array().unwrap();
+    assert_eq!(temporal.len(), 3);
+    assert!(field(&result, "delay_percentile99").as_f64().unwrap() >= field(&result, "delay_percentile50").as_f64().unwrap());
+}
+
+/// Without marks, or with a low load, the servers keep injecting at full rate.
+#[test]
+fn aimd_without_congestion()
+{
+    let aimd = ConfigurationValue::Object("AIMD".to_string(), vec![]);
+    let result = run(&congestion_simulation(0.0, Some(8), aimd.clone()));
+    assert_eq!(field(&result, "echoed_packets").as_f64().unwrap(), 0.0);
+    assert_eq!(field(&result, "marking_rate").as_f64().unwrap(), 0.0);
+    let result = run(&congestion_simulation(1.0, None, aimd.clone()));
+    assert_eq!(field(&result, "marked_packets").as_f64().unwrap(), 0.0);
+    assert_eq!(field(&result, "average_rate").as_f64().unwrap(), 1.0);
+    let result = run(&congestion_simulation(0.1, Some(24), aimd.clone()));
+    assert!(field(&result, "marking_rate").as_f64().unwrap() < 0.05);
+    let result = run(&congestion_simulation(1.0, Some(8), aimd));
+    assert!(field(&result, "minimum_average_rate").as_f64().unwrap() < 1.0);
+}
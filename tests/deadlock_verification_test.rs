@@ -0,0 +1,79 @@
+/*!
+    Tests for the offline deadlock verifier
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::routing::deadlock::verify_experiment;
+use common::*;
+
+/// Builds an experiment on a 4x4 Hamming graph with the given routing, virtual channels and policies.
+fn hamming_experiment(routing: ConfigurationValue, virtual_channels: usize, policies: Vec<ConfigurationValue>) -> ConfigurationValue
+{
+    create_basic_simulation(BasicSimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 100,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic: create_burst_traffic(BurstTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 16,
+            messages_per_server: 1,
+            message_size: 16,
+        }),
+        routing,
+        virtual_channels,
+        policies,
+        buffer_size: 64,
+        output_buffer_size: 32,
+        router_fields: vec![],
+    })
+}
+
+/// Minimal routing in any order of dimensions with a single virtual channel has cyclic dependencies.
+#[test]
+fn shortest_has_cycle()
+{
+    let experiment = hamming_experiment(create_shortest_routing(), 1, vec![create_policy("EnforceFlowControl"), create_policy("Random")]);
+    let result = verify_experiment(&experiment, &Plugs::default());
+    assert_eq!(*field(&result, "deadlock_free"), ConfigurationValue::False);
+    let cycle = field(&result, "cycle").as_array().expect("cycle is not an array");
+    let examples = field(&result, "examples").as_array().expect("examples is not an array");
+    assert!(cycle.len() >= 2);
+    assert_eq!(cycle.len(), examples.len());
+    // Each example route ends with the dependency between consecutive channels of the cycle.
+    for (index, example) in examples.iter().enumerate()
+    {
+        let channels = field(example, "channels").as_array().expect("channels is not an array");
+        assert!(channels.len() >= 2);
+        assert_eq!(channels[channels.len() - 2], cycle[index]);
+        assert_eq!(channels[channels.len() - 1], cycle[(index + 1) % cycle.len()]);
+    }
+}
+
+/// Dimension order routing is free of deadlock.
+#[test]
+fn dor_is_deadlock_free()
+{
+    let experiment = hamming_experiment(create_dor_routing(vec![0, 1]), 1, vec![create_policy("EnforceFlowControl"), create_policy("Random")]);
+    let result = verify_experiment(&experiment, &Plugs::default());
+    assert_eq!(*field(&result, "deadlock_free"), ConfigurationValue::True);
+    assert_eq!(*field(&result, "cycle"), ConfigurationValue::Array(vec![]));
+    assert_eq!(*field(&result, "truncated_routes"), ConfigurationValue::Number(0.0));
+    assert_eq!(*field(&result, "dead_ends"), ConfigurationValue::Number(0.0));
+}
+
+/// Using the virtual channel given by the number of hops breaks the cycles of minimal routing. The policies from `EnforceFlowControl` on depend on the status and are not applied.
+#[test]
+fn hops_policy_breaks_cycles()
+{
+    let experiment = hamming_experiment(create_shortest_routing(), 2, vec![create_policy("Hops"), create_policy("EnforceFlowControl"), create_policy("Random")]);
+    let result = verify_experiment(&experiment, &Plugs::default());
+    assert_eq!(*field(&result, "deadlock_free"), ConfigurationValue::True);
+    // Both minimal paths of two hops are explored, using the two virtual channels.
+    assert_eq!(*field(&result, "channels"), ConfigurationValue::Number(2.0 * 16.0 * 6.0));
+}
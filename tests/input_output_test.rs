@@ -334,3 +334,216 @@ fn input_output_internal_speedup()
     );
 
 }
+
+/// Runs uniform traffic at full load in a 4x4 Hamming graph of InputOutput routers with each of the standard allocators, giving priority to in-transit packets.
+/// We check that every allocator delivers most of the traffic and that the maximum matching is close to the best of them.
+#[test]
+fn input_output_allocators()
+{
+    let mut accepted_loads = vec![];
+    for allocator in ["SeparableInputFirst", "SeparableOutputFirst", "Wavefront", "AugmentingPath"]
+    {
+        let topology = create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+            servers_per_router: 2,
+        });
+        let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers: 32,
+            load: 1.0,
+            message_size: 16,
+        });
+        let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        });
+        let router_args = InputOutputRouterBuilder{
+            virtual_channels: 2,
+            vcp,
+            crossbar_delay: 1,
+            crossbar_frequency_divisor: 1,
+            allocator: ConfigurationValue::Object(allocator.to_string(), vec![]),
+            buffer_size: 64,
+            bubble: ConfigurationValue::False,
+            flit_size: 16,
+            allow_request_busy_port: ConfigurationValue::True,
+            intransit_priority: ConfigurationValue::True,
+            output_buffer_size: 32,
+            neglect_busy_outport: ConfigurationValue::False,
+        };
+        let simulation_builder = SimulationBuilder{
+            random_seed: 1,
+            warmup: 500,
+            measured: 2000,
+            topology,
+            traffic,
+            router: create_input_output_router(router_args),
+            maximum_packet_size: 16,
+            general_frequency_divisor: 1,
+            routing: create_dor_routing(vec![0, 1]),
+            link_classes: create_link_classes(),
+        };
+        let plugs = Plugs::default();
+        let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+        simulation.run();
+        let results = simulation.get_simulation_results();
+        match_object_panic!( &results, "Result", value,
+            "accepted_load" => accepted_loads.push(value.as_f64().expect("Accepted load data")),
+            _ => (),
+        );
+    }
+    // Every allocator sustains most of the offered load, which is around 0.9 with this configuration.
+    assert!(accepted_loads.iter().all(|&load| load > 0.8 && load <= 1.0), "accepted loads {:?}", accepted_loads);
+    let maximum = accepted_loads[3];
+    let best = accepted_loads.iter().cloned().fold(0.0, f64::max);
+    assert!(best - maximum < 0.05, "accepted loads {:?}", accepted_loads);
+}
+
+/// The virtual channel policies of the pipeline simulations.
+fn pipeline_vcp() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    })
+}
+
+/// Builds a simulation in a 4x4 Hamming graph with two servers per router of InputOutput routers with the additional `pipeline` fields.
+fn pipeline_simulation(load: f64, pipeline: Vec<(&str, ConfigurationValue)>) -> ConfigurationValue
+{
+    let router_args = InputOutputRouterBuilder{
+        virtual_channels: 2,
+        vcp: pipeline_vcp(),
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Islip".to_string(), vec![]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    };
+    hamming_simulation(load, append_fields(create_input_output_router(router_args), pipeline))
+}
+
+/// Builds a simulation in a 4x4 Hamming graph with two servers per router of the given `router`.
+fn hamming_simulation(load: f64, router: ConfigurationValue) -> ConfigurationValue
+{
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    });
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 32,
+        load,
+        message_size: 16,
+    });
+    let simulation_builder = SimulationBuilder{
+        random_seed: 3,
+        warmup: 500,
+        measured: 2000,
+        topology,
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_dor_routing(vec![0, 1]),
+        link_classes: create_link_classes(),
+    };
+    create_simulation(simulation_builder)
+}
+
+/// Runs the simulation and returns its `(accepted_load, average_packet_network_delay, [routing, vc_allocation, switch_allocation] stalls)`.
+/// The stalls are empty for the `OutputQueued` router.
+fn run_pipeline(cv: &ConfigurationValue) -> (f64, f64, Vec<f64>)
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut accepted_load = None;
+    let mut delay = None;
+    let mut stalls = vec![];
+    match_object_panic!( &results, "Result", value,
+        "accepted_load" => accepted_load = value.as_f64().ok(),
+        "average_packet_network_delay" => delay = value.as_f64().ok(),
+        "router_aggregated_statistics" => match_object_panic!( value, ["InputOutput", "OutputQueued"], stat,
+            "average_routing_stalls" | "average_vc_allocation_stalls" | "average_switch_allocation_stalls" => stalls.push(stat.as_f64().expect("stalls")),
+            _ => (),
+        ),
+        _ => (),
+    );
+    (accepted_load.expect("accepted_load"), delay.expect("average_packet_network_delay"), stalls)
+}
+
+/// The pipeline with separate virtual channel and switch allocation delivers traffic and reports the stalls at each stage.
+/// Each stage adds its latency to every hop, and the speculative switch allocation hides the latency of the virtual channel allocation.
+#[test]
+fn input_output_pipeline()
+{
+    let pipeline = |speculative: bool| vec![
+        ("routing_delay", ConfigurationValue::Number(1.0)),
+        ("vc_allocation_delay", ConfigurationValue::Number(1.0)),
+        ("switch_allocator", ConfigurationValue::Object("SeparableInputFirst".to_string(), vec![])),
+        ("speculative_switch_allocation", if speculative { ConfigurationValue::True } else { ConfigurationValue::False }),
+    ];
+    let (_, single_pass_delay, single_pass_stalls) = run_pipeline(&pipeline_simulation(0.2, vec![]));
+    let (_, pipeline_delay, _) = run_pipeline(&pipeline_simulation(0.2, pipeline(false)));
+    let (_, speculative_delay, _) = run_pipeline(&pipeline_simulation(0.2, pipeline(true)));
+    assert_eq!(single_pass_stalls[2], 0.0, "without switch allocator there are no switch allocation stalls");
+    assert!(single_pass_delay < speculative_delay, "delays {} {}", single_pass_delay, speculative_delay);
+    assert!(speculative_delay < pipeline_delay, "delays {} {}", speculative_delay, pipeline_delay);
+    let (accepted_load, _, stalls) = run_pipeline(&pipeline_simulation(1.0, pipeline(false)));
+    assert!(accepted_load > 0.1, "accepted load {}", accepted_load);
+    assert_eq!(stalls.len(), 3);
+    assert!(stalls.iter().all(|&stall| stall > 0.0), "stalls {:?}", stalls);
+}
+
+/// With a switch allocator, a crossbar speedup of 2 lets each output receive two phits per cycle, which cannot reduce the throughput at full load.
+/// The ideal output-queued router is the reference that the input-queued router with speedup approaches.
+#[test]
+fn input_output_crossbar_speedup_and_output_queued()
+{
+    let speedup = |speedup: f64| vec![
+        ("switch_allocator", ConfigurationValue::Object("SeparableInputFirst".to_string(), vec![])),
+        ("crossbar_speedup", ConfigurationValue::Number(speedup)),
+    ];
+    let (accepted_load_1, _, _) = run_pipeline(&pipeline_simulation(1.0, speedup(1.0)));
+    let (accepted_load_2, _, stalls_2) = run_pipeline(&pipeline_simulation(1.0, speedup(2.0)));
+    assert!(accepted_load_2 >= accepted_load_1, "accepted loads {} {}", accepted_load_1, accepted_load_2);
+    //The stalls are counted once per cycle, so they cannot exceed the 8 ports with 2 virtual channels of each router.
+    assert!(stalls_2[2] > 0.0 && stalls_2[2] <= 16.0, "switch allocation stalls {}", stalls_2[2]);
+    let output_queued = ConfigurationValue::Object("OutputQueued".to_string(), vec![
+        ("virtual_channels".to_string(), ConfigurationValue::Number(2.0)),
+        ("virtual_channel_policies".to_string(), pipeline_vcp()),
+        ("buffer_size".to_string(), ConfigurationValue::Number(64.0)),
+        ("output_buffer_size".to_string(), ConfigurationValue::Number(32.0)),
+        ("flit_size".to_string(), ConfigurationValue::Number(16.0)),
+    ]);
+    let (_, low_load_delay, stalls) = run_pipeline(&hamming_simulation(0.2, output_queued.clone()));
+    let (_, input_output_delay, _) = run_pipeline(&pipeline_simulation(0.2, vec![]));
+    assert!(stalls.is_empty());
+    assert!(low_load_delay <= input_output_delay, "delays {} {}", low_load_delay, input_output_delay);
+    let (output_queued_load, _, _) = run_pipeline(&hamming_simulation(1.0, output_queued));
+    assert!(output_queued_load > accepted_load_1, "accepted loads {} {}", output_queued_load, accepted_load_1);
+}
+
+/// A crossbar without any switch traversal per cycle is rejected.
+#[test]
+#[should_panic(expected = "crossbar_speedup must be at least 1")]
+fn input_output_crossbar_speedup_zero()
+{
+    let speedup = vec![
+        ("crossbar_speedup", ConfigurationValue::Number(0.0)),
+    ];
+    run_pipeline(&pipeline_simulation(1.0, speedup));
+}
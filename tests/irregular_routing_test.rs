@@ -0,0 +1,162 @@
+/*!
+    Tests for the routings for irregular networks, checked to be deadlock-free with the offline verifier
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::routing::deadlock::verify_experiment;
+use common::*;
+
+/// Builds an experiment on the given topology and routing with `virtual_channels` virtual channels.
+fn experiment(topology: ConfigurationValue, servers: usize, routing: ConfigurationValue, virtual_channels: usize) -> ConfigurationValue
+{
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers,
+        messages_per_server: 1,
+        message_size: 16,
+    };
+    let router_args = BasicRouterBuilder{
+        virtual_channels,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{ policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]}),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 100,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    })
+}
+
+fn deadlock_free(experiment: &ConfigurationValue) -> bool
+{
+    match verify_experiment(experiment, &Plugs::default())
+    {
+        ConfigurationValue::Object(_, ref fields) => fields.iter().find(|(key, _)| key == "deadlock_free").expect("missing deadlock_free").1 == ConfigurationValue::True,
+        _ => panic!("the verification is not an object"),
+    }
+}
+
+fn cartesian(name: &str, sides: &[usize]) -> ConfigurationValue
+{
+    ConfigurationValue::Object(name.to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(sides.iter().map(|&side| ConfigurationValue::Number(side as f64)).collect())),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ])
+}
+
+fn random_regular_graph() -> ConfigurationValue
+{
+    ConfigurationValue::Object("RandomRegularGraph".to_string(), vec![
+        ("routers".to_string(), ConfigurationValue::Number(20.0)),
+        ("degree".to_string(), ConfigurationValue::Number(4.0)),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ])
+}
+
+fn up_down_star(root_selection: &str, tree: &str) -> ConfigurationValue
+{
+    ConfigurationValue::Object("UpDownStar".to_string(), vec![
+        ("root_selection".to_string(), ConfigurationValue::Object(root_selection.to_string(), vec![])),
+        ("tree".to_string(), ConfigurationValue::Object(tree.to_string(), vec![])),
+    ])
+}
+
+/// Segment-based routing is deadlock-free with a single virtual channel in regular and irregular networks.
+#[test]
+fn segment_based_routing()
+{
+    let routing = ConfigurationValue::Object("SegmentBasedRouting".to_string(), vec![]);
+    assert!(deadlock_free(&experiment(cartesian("Mesh", &[4, 4]), 16, routing.clone(), 1)));
+    assert!(deadlock_free(&experiment(cartesian("Torus", &[4, 4]), 16, routing.clone(), 1)));
+    assert!(deadlock_free(&experiment(random_regular_graph(), 20, routing, 1)));
+}
+
+/// Up/down trees chosen by any root heuristic and built either breadth or depth first are deadlock-free with a single virtual channel.
+#[test]
+fn up_down_star_trees()
+{
+    for root_selection in ["MinimumEccentricity", "MaximumDegree"]
+    {
+        for tree in ["BFS", "DFS"]
+        {
+            assert!(deadlock_free(&experiment(random_regular_graph(), 20, up_down_star(root_selection, tree), 1)), "{} {} has a cyclic dependency", root_selection, tree);
+        }
+    }
+}
+
+/// Several up/down trees, each on its own virtual channel, are deadlock-free.
+#[test]
+fn multiple_up_down()
+{
+    let routing = ConfigurationValue::Object("MultipleUpDown".to_string(), vec![
+        ("trees".to_string(), ConfigurationValue::Array(vec![
+            up_down_star("MinimumEccentricity", "BFS"),
+            up_down_star("MaximumDegree", "DFS"),
+        ])),
+    ]);
+    assert!(deadlock_free(&experiment(random_regular_graph(), 20, routing, 2)));
+}
+
+/// Several up/down trees with less virtual channels than trees are rejected when routing.
+#[test]
+#[should_panic(expected = "Each of the 2 trees requires its own virtual channels")]
+fn multiple_up_down_lacking_virtual_channels()
+{
+    let routing = ConfigurationValue::Object("MultipleUpDown".to_string(), vec![
+        ("trees".to_string(), ConfigurationValue::Array(vec![
+            up_down_star("MinimumEccentricity", "BFS"),
+            up_down_star("MaximumDegree", "DFS"),
+        ])),
+    ]);
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&experiment(random_regular_graph(), 20, routing, 1), &plugs);
+    simulation.run();
+}
+
+/// Runs the experiment and returns the cycle at which it ended.
+fn final_cycle(experiment: &ConfigurationValue) -> f64
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(experiment, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = value.as_f64().ok(),
+        _ => (),
+    );
+    cycle.expect("cycle")
+}
+
+/// The servers of a failed router are excluded from the traffic. When detached from the topology, the remaining servers deliver all their messages.
+#[test]
+fn failed_router_servers_excluded()
+{
+    let faults = |detach: bool| ConfigurationValue::Object("StructuredFaults".to_string(), vec![
+        ("topology".to_string(), cartesian("Hamming", &[4])),
+        ("routers".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+        ("detach_servers".to_string(), if detach { ConfigurationValue::True } else { ConfigurationValue::False }),
+    ]);
+    assert!(final_cycle(&experiment(faults(true), 3, create_shortest_routing(), 1)) < 100.0);
+    // Without detaching them the messages towards the failed router are discarded, so the burst never completes, but the simulation runs.
+    assert_eq!(final_cycle(&experiment(faults(false), 4, create_shortest_routing(), 1)), 100.0);
+}
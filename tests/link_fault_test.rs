@@ -0,0 +1,175 @@
+/*!
+    Tests for the links that fail during the simulation
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Builds a simulation of a Hamming graph of the given `sides` and one server per router, in which each server sends one message to the server shifted by one in every dimension. The link fault schedule is appended.
+fn hamming_simulation(sides: &[usize], policies: Vec<ConfigurationValue>, link_fault_schedule: ConfigurationValue, measured: usize) -> ConfigurationValue
+{
+    let hamming_builder = HammingBuilder{
+        sides: sides.iter().map(|&side| ConfigurationValue::Number(side as f64)).collect(),
+        servers_per_router: 1,
+    };
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: std::iter::once(1).chain(sides.iter().cloned()).map(|side| ConfigurationValue::Number(side as f64)).collect(),
+        shift: std::iter::once(0.0).chain(sides.iter().map(|_| 1.0)).map(ConfigurationValue::Number).collect(),
+    };
+    let message_size = 16;
+    let servers: usize = sides.iter().product();
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers,
+        messages_per_server: 1,
+        message_size,
+    };
+    let vcp = create_vcp(VirtualChannelPoliciesBuilder{
+        policies,
+    });
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    match create_simulation(simulation_builder)
+    {
+        ConfigurationValue::Object(name, mut fields) =>
+        {
+            fields.push(("link_fault_schedule".to_string(), link_fault_schedule));
+            ConfigurationValue::Object(name, fields)
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Builds a simulation of two routers exchanging one message per server, with the given link fault schedule appended.
+fn two_routers_simulation(link_fault_schedule: ConfigurationValue, measured: usize) -> ConfigurationValue
+{
+    let policies = vec![
+        ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+        ConfigurationValue::Object("Random".to_string(), vec![])
+    ];
+    hamming_simulation(&[2], policies, link_fault_schedule, measured)
+}
+
+fn link_fault(cycle: usize, state: &str) -> ConfigurationValue
+{
+    ConfigurationValue::Object("LinkFault".to_string(), vec![
+        ("cycle".to_string(), ConfigurationValue::Number(cycle as f64)),
+        ("router".to_string(), ConfigurationValue::Number(0.0)),
+        ("port".to_string(), ConfigurationValue::Number(0.0)),
+        ("state".to_string(), ConfigurationValue::Object(state.to_string(), vec![])),
+    ])
+}
+
+/// The only link fails before the messages reach it and recovers later. With the `Hold` policy the messages wait and are delivered after the recovery.
+#[test]
+fn link_down_and_up_hold()
+{
+    let recovery = 60;
+    let schedule = ConfigurationValue::Object("LinkFaultSchedule".to_string(), vec![
+        ("events".to_string(), ConfigurationValue::Array(vec![link_fault(1, "Down"), link_fault(recovery, "Up")])),
+    ]);
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&two_routers_simulation(schedule, 200), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut found = false;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => assert!(value.as_f64().expect("Cycle data") > recovery as f64, "The messages could not arrive before the recovery"),
+        "average_packet_hops" => assert_eq!(value.as_f64().expect("Packet hops data"), 1.0, "Total hops"),
+        "link_faults" =>
+        {
+            found = true;
+            match_object_panic!( value, "LinkFaults", faults,
+                "dropped_phits" => assert_eq!(faults.as_f64().expect("dropped phits"), 0.0),
+                "events" =>
+                {
+                    let events = faults.as_array().expect("events");
+                    assert_eq!(events.len(), 2);
+                    match_object_panic!( &events[0], "LinkFault", event,
+                        "accepted_load_before" => assert_eq!(event.as_f64().expect("load"), 0.0),
+                        "accepted_load_after" => assert_eq!(event.as_f64().expect("load"), 0.0, "Nothing can be consumed while the link is down"),
+                        _ => (),
+                    );
+                    match_object_panic!( &events[1], "LinkFault", event,
+                        "accepted_load_after" => assert!(event.as_f64().expect("load") > 0.0, "The messages are consumed after the recovery"),
+                        _ => (),
+                    );
+                },
+            );
+        },
+        _ => (),
+    );
+    assert!(found, "There is no link_faults in the results");
+}
+
+/// The link fails while the head phits are crossing it. With the `Drop` policy the packets are discarded, never reaching their destinations, and counted as dropped.
+#[test]
+fn link_down_drop()
+{
+    let schedule = ConfigurationValue::Object("LinkFaultSchedule".to_string(), vec![
+        ("events".to_string(), ConfigurationValue::Array(vec![link_fault(2, "Down"), link_fault(3, "Up")])),
+        ("policy".to_string(), ConfigurationValue::Object("Drop".to_string(), vec![])),
+    ]);
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&two_routers_simulation(schedule, 200), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    match_object_panic!( &results, "Result", value,
+        "accepted_load" => assert_eq!(value.as_f64().expect("Accepted load data"), 0.0, "Accepted load"),
+        "dropped_phits" => assert_eq!(value.as_f64().expect("dropped phits"), 32.0, "The discarded phits are in the drop statistics"),
+        "dropped_packets" => assert_eq!(value.as_f64().expect("dropped packets"), 2.0),
+        "link_faults" => match_object_panic!( value, "LinkFaults", faults,
+            "dropped_phits" => assert_eq!(faults.as_f64().expect("dropped phits"), 32.0, "Both packets are discarded"),
+            _ => (),
+        ),
+        _ => (),
+    );
+}
+
+/// In a 2x2 Hamming graph the link between routers 0 and 1 fails for the whole simulation. Every message has two shortest routes, and the routing is notified of the failure so it only offers the routes avoiding that link. No policy checks the link status, so any packet sent towards the failed link would be held there forever.
+#[test]
+fn shortest_avoids_failed_link()
+{
+    let measured = 500;
+    let schedule = ConfigurationValue::Object("LinkFaultSchedule".to_string(), vec![
+        ("events".to_string(), ConfigurationValue::Array(vec![link_fault(0, "Down")])),
+    ]);
+    let policies = vec![ConfigurationValue::Object("Random".to_string(), vec![])];
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&hamming_simulation(&[2,2], policies, schedule, measured), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    match_object_panic!( &results, "Result", value,
+        "cycle" => assert!(value.as_f64().expect("Cycle data") < measured as f64, "Some message has not arrived"),
+        "average_packet_hops" => assert_eq!(value.as_f64().expect("Packet hops data"), 2.0, "Total hops"),
+        "link_faults" => match_object_panic!( value, "LinkFaults", faults,
+            "dropped_phits" => assert_eq!(faults.as_f64().expect("dropped phits"), 0.0),
+            _ => (),
+        ),
+        _ => (),
+    );
+}
@@ -0,0 +1,117 @@
+/*!
+    Tests for the lossy transmission mechanism and the retransmission of packets by the servers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Builds a simulation in a 4x4 Hamming graph with a Basic router dropping packets with the given policy.
+fn lossy_simulation(traffic: ConfigurationValue, drop_policy: ConfigurationValue, measured: usize, retransmission_timeout: Option<usize>) -> ConfigurationValue
+{
+    let cv = create_basic_simulation(BasicSimulationBuilder{
+        random_seed: 5,
+        warmup: 0,
+        measured,
+        topology: create_hamming_topology(HammingBuilder{
+            sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+            servers_per_router: 1,
+        }),
+        traffic,
+        routing: create_dor_routing(vec![0, 1]),
+        virtual_channels: 1,
+        policies: vec![create_policy("EnforceFlowControl"), create_policy("Random")],
+        buffer_size: 32,
+        output_buffer_size: 16,
+        router_fields: vec![
+            ("transmission_mechanism", ConfigurationValue::Literal("LossyVirtualChannels".to_string())),
+            ("drop_policy", drop_policy),
+        ],
+    });
+    match retransmission_timeout
+    {
+        Some(timeout) => append_fields(cv, vec![("retransmission_timeout", ConfigurationValue::Number(timeout as f64))]),
+        None => cv,
+    }
+}
+
+/// Reads a numeric field of the results.
+fn number(result: &ConfigurationValue, name: &str) -> f64
+{
+    field(result, name).as_f64().expect("not a number")
+}
+
+fn uniform_traffic(load: f64) -> ConfigurationValue
+{
+    create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load,
+        message_size: 16,
+    })
+}
+
+/// Under full uniform load the tail drop discards whole packets, while a low load goes through without losses.
+#[test]
+fn tail_drop()
+{
+    let plugs = Plugs::default();
+    let tail_drop = ConfigurationValue::Object("TailDrop".to_string(), vec![]);
+    let mut simulation = Simulation::new(&lossy_simulation(uniform_traffic(1.0), tail_drop.clone(), 3000, None), &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    let dropped_packets = number(&result, "dropped_packets");
+    assert!(dropped_packets > 0.0, "full load should drop packets");
+    //Whole packets are dropped, except the last ones still arriving when the simulation ends.
+    let dropped_phits = number(&result, "dropped_phits");
+    assert!(dropped_phits <= 16.0 * dropped_packets && dropped_phits > 15.0 * dropped_packets, "whole packets are dropped");
+    assert_eq!(number(&result, "retransmitted_packets"), 0.0);
+
+    let mut simulation = Simulation::new(&lossy_simulation(uniform_traffic(0.05), tail_drop, 3000, None), &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    assert_eq!(number(&result, "dropped_phits"), 0.0);
+    assert!(number(&result, "accepted_load") > 0.03);
+}
+
+/// The random early detection starts dropping before the buffers are full.
+#[test]
+fn random_early_detection()
+{
+    let plugs = Plugs::default();
+    let red = ConfigurationValue::Object("RandomEarlyDetection".to_string(), vec![
+        ("minimum_threshold".to_string(), ConfigurationValue::Number(4.0)),
+        ("maximum_threshold".to_string(), ConfigurationValue::Number(16.0)),
+        ("maximum_probability".to_string(), ConfigurationValue::Number(0.5)),
+        ("weight".to_string(), ConfigurationValue::Number(0.1)),
+    ]);
+    let mut simulation = Simulation::new(&lossy_simulation(uniform_traffic(1.0), red, 3000, None), &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    assert!(number(&result, "dropped_packets") > 0.0);
+    assert!(number(&result, "accepted_load") > 0.0);
+}
+
+/// With retransmission every message of a burst is eventually delivered despite the drops.
+#[test]
+fn retransmission_completes_burst()
+{
+    let plugs = Plugs::default();
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        messages_per_server: 20,
+        message_size: 16,
+    });
+    let tail_drop = ConfigurationValue::Object("TailDrop".to_string(), vec![]);
+    let measured = 100000;
+    let mut simulation = Simulation::new(&lossy_simulation(traffic, tail_drop, measured, Some(200)), &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    assert!(number(&result, "cycle") < measured as f64, "the burst should finish");
+    assert!(number(&result, "dropped_packets") > 0.0);
+    assert!(number(&result, "retransmitted_packets") >= number(&result, "dropped_packets"));
+    let consumed_phits = number(&result, "accepted_load") * number(&result, "cycle") * 16.0;
+    assert!((consumed_phits - 16.0 * 20.0 * 16.0).abs() < 0.5, "every phit is consumed exactly once");
+}
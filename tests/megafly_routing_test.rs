@@ -140,4 +140,112 @@ fn test_megafly_routing()
     //     _ => (),
     // );
     print!("Test passed\n")
-}
\ No newline at end of file
+}
+/// Simulates uniform traffic with the given routing over a Dragonfly+ with different number of leaves and spines per group.
+/// Returns the accepted load.
+fn dragonfly_plus_accepted_load(routing: ConfigurationValue, virtual_channels: usize) -> f64
+{
+    let dragonfly_plus_builder = DragonflyPlusBuilder{
+        leaves_per_group: 4,
+        spines_per_group: 2,
+        servers_per_leaf: 2,
+        global_ports_per_spine: 2,
+        number_of_groups: 5,
+    };
+    let homogeneous_traffic_builder = HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 40,
+        load: 0.5,
+        message_size: 16,
+    };
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let vcp = create_vcp(vcp_args);
+    let router_args = InputOutputRouterBuilder{
+        virtual_channels,
+        vcp,
+        crossbar_delay: 2,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+        buffer_size: 128,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 64,
+        neglect_busy_outport: ConfigurationValue::False,
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 200,
+        measured: 500,
+        topology: create_dragonfly_plus_topology(dragonfly_plus_builder),
+        traffic: create_homogeneous_traffic(homogeneous_traffic_builder),
+        router: create_input_output_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let simulation_cv = create_simulation(simulation_builder);
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut accepted_load = None;
+    match_object_panic!( &results, "Result", value,
+        "accepted_load" => accepted_load = Some(value.as_f64().expect("Accepted load data")),
+        _ => (),
+    );
+    accepted_load.expect("There were no accepted_load")
+}
+
+/// Uniform traffic with MegaflyAD over a Dragonfly+.
+/// We check that the packets are delivered.
+#[test]
+fn test_dragonfly_plus_megafly_routing()
+{
+    let builder_megafly_ad = MegaflyAD{
+        first_allowed_virtual_channels: vec![0],
+        second_allowed_virtual_channels: vec![1],
+        minimal_to_deroute: vec![1, 1, 0],
+    };
+    assert!(dragonfly_plus_accepted_load(create_megafly_ad(builder_megafly_ad), 2) > 0.0, "No packet was delivered");
+}
+
+/// Uniform traffic with DragonflyDirect over a Dragonfly+.
+/// We check that the packets are delivered.
+#[test]
+fn test_dragonfly_plus_direct_routing()
+{
+    let routing = ConfigurationValue::Object("DragonflyDirect".to_string(), vec![]);
+    assert!(dragonfly_plus_accepted_load(routing, 2) > 0.0, "No packet was delivered");
+}
+
+/// Uniform traffic with Valiant4Dragonfly over a Dragonfly+, with a virtual channel for each phase.
+/// We check that the packets are delivered.
+#[test]
+fn test_dragonfly_plus_valiant_routing()
+{
+    let routing = ConfigurationValue::Object("Valiant4Dragonfly".to_string(), vec![
+        ("first".to_string(), create_shortest_routing()),
+        ("second".to_string(), create_shortest_routing()),
+        ("first_reserved_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+        ("second_reserved_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+    ]);
+    assert!(dragonfly_plus_accepted_load(routing, 2) > 0.0, "No packet was delivered");
+}
+
+/// Uniform traffic with PAR over a Dragonfly+. Its local virtual channel grows with the global hops, so up to two local hops per group require 7 virtual channels.
+/// We check that the packets are delivered.
+#[test]
+fn test_dragonfly_plus_par_routing()
+{
+    let routing = ConfigurationValue::Object("PAR".to_string(), vec![]);
+    assert!(dragonfly_plus_accepted_load(routing, 7) > 0.0, "No packet was delivered");
+}
@@ -0,0 +1,124 @@
+/*!
+    Tests for the shared buffer transmission mechanism
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Builds a simulation in a 4x4 Hamming graph under uniform traffic, with `router` using `SharedBuffer` links between routers.
+fn shared_buffer_simulation(load: f64, router: ConfigurationValue) -> ConfigurationValue
+{
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    });
+    let traffic = create_homogeneous_traffic(HomogeneousTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers: 16,
+        load,
+        message_size: 16,
+    });
+    let router = append_fields(router, vec![
+        ("transmission_mechanism", ConfigurationValue::Literal("SharedBuffer".to_string())),
+        ("shared_buffer_policy", ConfigurationValue::Object("DynamicThreshold".to_string(), vec![
+            ("reserved_size".to_string(), ConfigurationValue::Number(16.0)),
+            ("alpha".to_string(), ConfigurationValue::Number(2.0)),
+        ])),
+    ]);
+    let simulation_builder = SimulationBuilder{
+        random_seed: 11,
+        warmup: 500,
+        measured: 2000,
+        topology,
+        traffic,
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_dor_routing(vec![0, 1]),
+        link_classes: create_link_classes(),
+    };
+    create_simulation(simulation_builder)
+}
+
+fn vcp() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    })
+}
+
+fn basic_router() -> ConfigurationValue
+{
+    create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp: vcp(),
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 16,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    })
+}
+
+fn input_output_router() -> ConfigurationValue
+{
+    create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 2,
+        vcp: vcp(),
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![]),
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    })
+}
+
+/// Runs the simulation and returns its accepted load and the average occupation of the shared pools.
+fn run(cv: &ConfigurationValue) -> (f64, f64)
+{
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut accepted_load = None;
+    let mut shared_occupation = None;
+    match_object_panic!( &results, "Result", value,
+        "accepted_load" => accepted_load = value.as_f64().ok(),
+        "router_aggregated_statistics" => match value
+        {
+            ConfigurationValue::Object(_, fields) => shared_occupation = fields.iter().find(|(key, _)| key == "average_shared_space_occupation").map(|(_, x)| x.as_f64().expect("not a number")),
+            _ => panic!("the router statistics are not an object"),
+        },
+        _ => (),
+    );
+    (accepted_load.expect("accepted_load"), shared_occupation.expect("missing average_shared_space_occupation"))
+}
+
+/// Both routers deliver the traffic through shared buffers, which are only filled beyond the reserved regions under high load.
+/// The receptors panic if a phit arrives without space, so completing the runs checks that the credits of the emissors are consistent.
+#[test]
+fn shared_buffer_occupation()
+{
+    for router in [basic_router(), input_output_router()]
+    {
+        let (accepted_load, low_occupation) = run(&shared_buffer_simulation(0.1, router.clone()));
+        assert!((accepted_load - 0.1).abs() < 0.02, "accepted load {}", accepted_load);
+        let (accepted_load, high_occupation) = run(&shared_buffer_simulation(1.0, router));
+        assert!(accepted_load > 0.3, "accepted load {}", accepted_load);
+        assert!(high_occupation > low_occupation, "shared occupations {} {}", low_occupation, high_occupation);
+        assert!(high_occupation > 0.0);
+    }
+}
@@ -0,0 +1,191 @@
+/*!
+    Tests for the switching techniques of the routers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// The virtual channel policies of the switching simulations.
+fn switching_vcp() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    })
+}
+
+/// A Basic router without output buffers and with flits of `flit_size` phits.
+fn basic_router(buffer_size: usize, flit_size: usize) -> ConfigurationValue
+{
+    create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: switching_vcp(),
+        buffer_size,
+        bubble: ConfigurationValue::False,
+        flit_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 0,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    })
+}
+
+/// An InputOutput router with flits of `flit_size` phits.
+fn input_output_router(flit_size: usize) -> ConfigurationValue
+{
+    create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 1,
+        vcp: switching_vcp(),
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    })
+}
+
+/// Sends a message of `message_size` phits between the servers of a line of `routers` routers, each server to the opposite one, with the given `switching`.
+/// Returns the cycle at which all the messages have been consumed.
+fn completion_cycle(routers: usize, router: ConfigurationValue, switching: &str, message_size: usize) -> f64
+{
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(routers as f64)],
+        servers_per_router: 1,
+    });
+    let pattern = create_shift_pattern(ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(routers as f64)],
+        shift: vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)],
+    });
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern,
+        servers: routers,
+        messages_per_server: 1,
+        message_size,
+    });
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 1000,
+        topology,
+        traffic,
+        router: append_fields(router, vec![("switching", ConfigurationValue::Object(switching.to_string(), vec![]))]),
+        maximum_packet_size: message_size,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = value.as_f64().ok(),
+        _ => (),
+    );
+    cycle.expect("cycle")
+}
+
+/// Without contention the head of a packet never waits for space, so wormhole and virtual cut-through have the same latency.
+/// In store-and-forward each of the two routers in the path waits for the rest of the packet, adding `message_size-1` cycles per router.
+#[test]
+fn switching_latency()
+{
+    let message_size = 16;
+    for router in [basic_router(64, 4), input_output_router(4)]
+    {
+        let wormhole = completion_cycle(2, router.clone(), "Wormhole", message_size);
+        let cut_through = completion_cycle(2, router.clone(), "VirtualCutThrough", message_size);
+        let store_and_forward = completion_cycle(2, router, "StoreAndForward", message_size);
+        assert_eq!(wormhole, cut_through);
+        assert_eq!(store_and_forward, cut_through + (2 * (message_size - 1)) as f64);
+    }
+}
+
+/// Virtual cut-through needs buffers able to hold a whole packet, while wormhole works with smaller ones.
+#[test]
+#[should_panic(expected = "must hold the largest packet")]
+fn switching_cut_through_buffer_size()
+{
+    assert!(completion_cycle(2, basic_router(8, 4), "Wormhole", 16) > 0.0);
+    completion_cycle(2, basic_router(8, 4), "VirtualCutThrough", 16);
+}
+
+/// Every server of a triangle of routers sends `messages` messages of 16 phits to the server 0, so that the packets contend for its ejection port. The routers have input buffers of 24 phits.
+/// Returns how many times a packet spread over the input buffers of several routers has been seen without advancing during a cycle.
+fn blocked_split_packets(switching: &str, messages: usize) -> usize
+{
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(3.0)],
+        servers_per_router: 1,
+    });
+    let pattern = ConfigurationValue::Object("Hotspots".to_string(), vec![
+        ("destinations".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+    ]);
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern,
+        servers: 3,
+        messages_per_server: messages,
+        message_size: 16,
+    });
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 0,
+        topology,
+        traffic,
+        router: append_fields(basic_router(24, 4), vec![("switching", ConfigurationValue::Object(switching.to_string(), vec![]))]),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    let mut previous = BTreeMap::new();
+    let mut blocked = 0;
+    for cycle in 1..=1000
+    {
+        // Advance a single cycle and locate the phits of each packet by the router and input port holding them.
+        simulation.measured = cycle;
+        simulation.run();
+        let mut locations: BTreeMap<(usize, usize), BTreeMap<(usize, usize), usize>> = BTreeMap::new();
+        for (router_index, router) in simulation.shared.network.routers.iter().enumerate()
+        {
+            for (port, phit, _) in router.borrow().iter_input_phits()
+            {
+                let packet = (Rc::as_ptr(&phit.packet.message) as usize, phit.packet.index);
+                *locations.entry(packet).or_default().entry((router_index, port)).or_insert(0) += 1;
+            }
+        }
+        blocked += locations.iter().filter(|&(packet, places)| places.len() > 1 && previous.get(packet) == Some(places)).count();
+        if simulation.shared.traffic.is_finished(Some(&mut simulation.mutable.rng))
+        {
+            return blocked;
+        }
+        previous = locations;
+    }
+    panic!("the traffic was not consumed");
+}
+
+/// Under contention a wormhole packet may be blocked with its phits spread over several routers, while virtual cut-through only advances a head when the next buffer can hold the whole packet.
+/// Thus a blocked packet in virtual cut-through is held entirely in one buffer.
+#[test]
+fn switching_cut_through_contention()
+{
+    assert!(blocked_split_packets("Wormhole", 4) > 0);
+    assert_eq!(blocked_split_packets("VirtualCutThrough", 4), 0);
+}
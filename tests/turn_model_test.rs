@@ -0,0 +1,84 @@
+/*!
+    Tests for the turn model routings, checked to be deadlock-free with the offline verifier
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::routing::deadlock::verify_experiment;
+use common::*;
+
+/// Builds an experiment on a mesh with the given routing and a single virtual channel.
+fn mesh_experiment(sides: &[usize], routing: ConfigurationValue) -> ConfigurationValue
+{
+    let servers = sides.iter().product();
+    let mesh = ConfigurationValue::Object("Mesh".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(sides.iter().map(|&side| ConfigurationValue::Number(side as f64)).collect())),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_uniform_pattern(),
+        servers,
+        messages_per_server: 1,
+        message_size: 16,
+    };
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{ policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![]),
+        ]}),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+    };
+    create_simulation(SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 100,
+        topology: mesh,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    })
+}
+
+fn deadlock_free(sides: &[usize], routing: &str) -> bool
+{
+    let experiment = mesh_experiment(sides, ConfigurationValue::Object(routing.to_string(), vec![]));
+    let result = verify_experiment(&experiment, &Plugs::default());
+    match result
+    {
+        ConfigurationValue::Object(_, ref fields) => fields.iter().find(|(key, _)| key == "deadlock_free").expect("missing deadlock_free").1 == ConfigurationValue::True,
+        _ => panic!("the verification is not an object"),
+    }
+}
+
+/// Every turn model is deadlock-free in a 2D mesh with a single virtual channel, while the fully adaptive minimal routing is not.
+#[test]
+fn turn_models_2d()
+{
+    for routing in ["WestFirst", "NorthLast", "NegativeFirst", "OddEven"]
+    {
+        assert!(deadlock_free(&[4, 4], routing), "{} has a cyclic dependency", routing);
+    }
+    assert!(!deadlock_free(&[4, 4], "Shortest"));
+}
+
+/// The generalizations to more dimensions remain deadlock-free.
+#[test]
+fn turn_models_3d()
+{
+    for routing in ["WestFirst", "NorthLast", "NegativeFirst"]
+    {
+        assert!(deadlock_free(&[3, 3, 3], routing), "{} has a cyclic dependency", routing);
+    }
+}
@@ -0,0 +1,88 @@
+/*!
+    Tests for the watchdog detecting stalled simulations
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Builds a simulation under full uniform load, with a single small virtual channel and the given topology and routing, appending a watchdog.
+fn stalling_simulation(topology: ConfigurationValue, servers: usize, routing: ConfigurationValue, measured: usize, file: &str) -> ConfigurationValue
+{
+    let cv = create_basic_simulation(BasicSimulationBuilder{
+        random_seed: 3,
+        warmup: 0,
+        measured,
+        topology,
+        traffic: create_homogeneous_traffic(HomogeneousTrafficBuilder{
+            pattern: create_uniform_pattern(),
+            servers,
+            load: 1.0,
+            message_size: 16,
+        }),
+        routing,
+        virtual_channels: 1,
+        policies: vec![create_policy("EnforceFlowControl"), create_policy("Random")],
+        buffer_size: 16,
+        output_buffer_size: 16,
+        router_fields: vec![],
+    });
+    let watchdog = ConfigurationValue::Object("Watchdog".to_string(), vec![
+        ("cycles".to_string(), ConfigurationValue::Number(500.0)),
+        ("file".to_string(), ConfigurationValue::Literal(file.to_string())),
+    ]);
+    append_fields(cv, vec![("watchdog", watchdog)])
+}
+
+/// Minimal routing in a ring with a single virtual channel deadlocks under full load. The watchdog stops the simulation and reports the cycle of buffers.
+#[test]
+fn ring_deadlock_detected()
+{
+    let path = std::env::temp_dir().join("caminos_watchdog_deadlock.txt");
+    let ring = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(8.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let cv = stalling_simulation(ring, 8, create_shortest_routing(), 20000, path.to_str().unwrap());
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&cv, &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    match result
+    {
+        ConfigurationValue::Object(ref name, _) => assert_eq!(name, "Deadlock"),
+        _ => panic!("the result is not an object"),
+    }
+    let cycle = field(&result, "cycle").as_f64().unwrap();
+    assert!(cycle < 20000.0, "the simulation should have been stopped");
+    assert!(cycle - field(&result, "stalled_since").as_f64().unwrap() >= 500.0);
+    let buffers = field(&result, "cycle_buffers").as_array().unwrap();
+    assert!(buffers.len() >= 2, "a cycle of blocked buffers should be found");
+    let diagnostic = std::fs::read_to_string(&path).expect("the diagnostic file should be written");
+    assert!(diagnostic.starts_with("Deadlock detected at cycle"));
+    assert!(diagnostic.contains("Cycle of blocked buffers:"));
+    std::fs::remove_file(&path).ok();
+}
+
+/// Dimension order routing in a Hamming graph does not deadlock, so the watchdog does not interfere.
+#[test]
+fn dor_runs_normally()
+{
+    let path = std::env::temp_dir().join("caminos_watchdog_dor.txt");
+    let hamming = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+        servers_per_router: 1,
+    });
+    let cv = stalling_simulation(hamming, 16, create_dor_routing(vec![0, 1]), 3000, path.to_str().unwrap());
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&cv, &plugs);
+    simulation.run();
+    let result = simulation.get_simulation_results();
+    match result
+    {
+        ConfigurationValue::Object(ref name, _) => assert_eq!(name, "Result"),
+        _ => panic!("the result is not an object"),
+    }
+    assert!(!path.exists());
+}